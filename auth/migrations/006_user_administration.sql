@@ -0,0 +1,5 @@
+ALTER TABLE users
+ADD COLUMN created_at DATETIME;
+
+ALTER TABLE users
+ADD COLUMN disabled BOOLEAN NOT NULL DEFAULT 0;
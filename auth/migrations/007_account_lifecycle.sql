@@ -0,0 +1,11 @@
+ALTER TABLE users
+ADD COLUMN deletion_scheduled_at DATETIME;
+
+CREATE TABLE login_history(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    datetime DATETIME NOT NULL,
+    user_agent TEXT,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+CREATE INDEX idx__login_history__user_id__datetime ON login_history (user_id, datetime);
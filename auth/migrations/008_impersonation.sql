@@ -0,0 +1,13 @@
+ALTER TABLE sessions
+ADD COLUMN impersonator_id INTEGER REFERENCES users (id);
+
+CREATE TABLE impersonation_audit_log(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    impersonator_id INTEGER NOT NULL,
+    target_id INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    datetime DATETIME NOT NULL,
+    CHECK (action IN ('start', 'stop'))
+);
+CREATE INDEX idx__impersonation_audit_log__impersonator ON impersonation_audit_log (impersonator_id, datetime);
+CREATE INDEX idx__impersonation_audit_log__target ON impersonation_audit_log (target_id, datetime);
@@ -0,0 +1,16 @@
+CREATE TABLE invitations(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    invitation_token_hash BLOB NOT NULL UNIQUE,
+    email TEXT NOT NULL,
+    permission_group_id INTEGER NOT NULL,
+    invited_by INTEGER,
+    created_at DATETIME NOT NULL,
+    expires_at DATETIME NOT NULL,
+    accepted_at DATETIME,
+    user_id INTEGER,
+    FOREIGN KEY (permission_group_id) REFERENCES permission_groups (id) ON DELETE CASCADE,
+    FOREIGN KEY (invited_by) REFERENCES users (id) ON DELETE SET NULL,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE SET NULL,
+    CHECK (expires_at >= created_at)
+);
+CREATE INDEX idx__invitations__email ON invitations (email);
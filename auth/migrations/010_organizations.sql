@@ -0,0 +1,23 @@
+CREATE TABLE organizations(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    created_at DATETIME NOT NULL
+);
+
+-- the role of a member is a permission group,
+-- whose permissions only apply within the organization
+CREATE TABLE organization_memberships(
+    organization_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    permission_group_id INTEGER NOT NULL,
+    created_at DATETIME NOT NULL,
+    PRIMARY KEY (organization_id, user_id),
+    FOREIGN KEY (organization_id) REFERENCES organizations (id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE,
+    FOREIGN KEY (permission_group_id) REFERENCES permission_groups (id) ON DELETE CASCADE
+);
+CREATE INDEX idx__organization_memberships__user_id ON organization_memberships (user_id);
+
+-- tokens bound to an organization can only be used within that organization
+ALTER TABLE access_tokens
+ADD COLUMN organization_id INTEGER REFERENCES organizations (id) ON DELETE CASCADE;
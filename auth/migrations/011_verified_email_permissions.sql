@@ -0,0 +1,5 @@
+-- permissions that are withheld from users whose email is not verified.
+-- synced from the server configuration on startup
+CREATE TABLE verified_email_permissions(
+    permission TEXT PRIMARY KEY
+);
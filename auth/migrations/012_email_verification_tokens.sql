@@ -0,0 +1,8 @@
+CREATE TABLE email_verification_tokens(
+    jti TEXT PRIMARY KEY,
+    email TEXT NOT NULL,
+    issued_at DATETIME NOT NULL,
+    expires_at DATETIME NOT NULL,
+    CHECK (expires_at >= issued_at)
+);
+CREATE INDEX idx__email_verification_tokens__email ON email_verification_tokens (email);
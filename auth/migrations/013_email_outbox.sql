@@ -0,0 +1,16 @@
+CREATE TABLE email_outbox(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    sender TEXT NOT NULL,
+    recipient TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    plain_text TEXT NOT NULL,
+    html TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    created_at DATETIME NOT NULL,
+    next_attempt_at DATETIME NOT NULL,
+    sent_at DATETIME,
+    CHECK (status IN ('pending', 'sent', 'failed'))
+);
+CREATE INDEX idx__email_outbox__status__next_attempt_at ON email_outbox (status, next_attempt_at);
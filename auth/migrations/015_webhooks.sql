@@ -0,0 +1,34 @@
+CREATE TABLE webhooks(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    url TEXT NOT NULL,
+    -- HMAC-SHA256 key of the `Webhook-Signature` header
+    secret TEXT NOT NULL,
+    created_at DATETIME NOT NULL
+);
+
+-- event filter: a webhook only receives the events it subscribed to
+CREATE TABLE webhook_events(
+    webhook_id INTEGER NOT NULL,
+    event TEXT NOT NULL,
+    PRIMARY KEY (webhook_id, event),
+    FOREIGN KEY (webhook_id) REFERENCES webhooks (id) ON DELETE CASCADE
+);
+CREATE INDEX idx__webhook_events__event ON webhook_events (event);
+
+CREATE TABLE webhook_deliveries(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    webhook_id INTEGER NOT NULL,
+    event TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    response_status INTEGER,
+    created_at DATETIME NOT NULL,
+    next_attempt_at DATETIME NOT NULL,
+    delivered_at DATETIME,
+    FOREIGN KEY (webhook_id) REFERENCES webhooks (id) ON DELETE CASCADE,
+    CHECK (status IN ('pending', 'delivered', 'failed'))
+);
+CREATE INDEX idx__webhook_deliveries__status__next_attempt_at ON webhook_deliveries (status, next_attempt_at);
+CREATE INDEX idx__webhook_deliveries__webhook_id ON webhook_deliveries (webhook_id);
@@ -3,14 +3,49 @@ INSERT INTO permissions (permission, description) VALUES
 ('get:/permissions',                    'Get a list of permissions held by the Principal'),
 ('post:/permissions/assign',            'Assign a permission to an Assignee'),
 ('post:/rotate-key',                    'Rotate the Secret key'),
-('get:/sysinfo',                        'Get system information')
+('get:/admin/secrets',                  'Inspect the versions and rotation status of a secret'),
+('get:/sysinfo',                        'Get system information'),
+('get:/admin/users',                    'List and search users'),
+('post:/admin/users/disable',           'Disable a user account'),
+('post:/admin/users/enable',            'Enable a disabled user account'),
+('post:/admin/users/force-email-verification', 'Require a user to verify their email again'),
+('delete:/admin/users',                 'Permanently delete a user account'),
+('get:/account/export',                 'Export all data held about the own account'),
+('delete:/account',                     'Delete the own account'),
+('post:/admin/impersonate',             'Act as another user for support purposes'),
+('post:/admin/invitations',             'Invite a new user into a permission group'),
+('post:/orgs',                          'Create an organization'),
+('get:/orgs/members',                   'List the members of an organization'),
+('post:/orgs/members',                  'Add a member to an organization or change their role'),
+('delete:/orgs/members',                'Remove a member from an organization'),
+('get:/admin/outbox',                   'Inspect queued, sent and failed outgoing emails'),
+('post:/admin/outbox/retry',            'Retry delivery of a failed outgoing email'),
+('get:/admin/webhooks',                 'List webhook subscriptions'),
+('post:/admin/webhooks',                'Subscribe a URL to identity events'),
+('delete:/admin/webhooks',              'Delete a webhook subscription'),
+('get:/admin/webhooks/deliveries',      'Inspect the webhook delivery log'),
+-- vault secrets; narrower grants use the secret's path, e.g. `get:/kv/data/app/*`
+('get:/kv/*',                           'Read every vault secret and its metadata'),
+('put:/kv/*',                           'Write every vault secret and its metadata'),
+('delete:/kv/*',                        'Soft delete or permanently remove every vault secret'),
+('post:/kv/*',                          'Delete, undelete and destroy versions of every vault secret'),
+-- vault PKI; narrower grants use the role or issuer, e.g. `post:/pki/sign/internal`
+('get:/pki/*',                          'Read PKI roles and issued certificates'),
+('put:/pki/*',                          'Create and update PKI roles'),
+('post:/pki/*',                         'Create CAs, sign CSRs and revoke certificates'),
+-- vault transit; services are granted single operations, e.g. `post:/transit/encrypt/user-pii`
+('get:/transit/*',                      'Read transit keys and their public keys'),
+('put:/transit/*',                      'Configure transit keys'),
+('post:/transit/*',                     'Create and rotate transit keys, and use every key')
 ON CONFLICT (permission) DO NOTHING;
 
 
 INSERT INTO permission_groups ([group], description) VALUES
 ('root',        'for superuser access'),
 ('admin',       'for site administrators'),
-('signup',      'for users that just signed up')
+('signup',      'for users that just signed up'),
+('org-owner',   'organization role :: manages the organization and its members'),
+('org-member',  'organization role :: regular member of the organization')
 ON CONFLICT ([group]) DO NOTHING;
 
 
@@ -19,12 +54,38 @@ WITH mapping([group], permission) AS (
     ('signup',    'post:/access-token/generate'),
     ('signup',    'get:/permissions'),
     ('signup',    'post:/permissions/assign'),
+    ('signup',    'get:/account/export'),
+    ('signup',    'delete:/account'),
+    ('signup',    'post:/orgs'),
 
     ('admin',     'post:/access-token/generate'),
     ('admin',     'get:/permissions'),
     ('admin',     'post:/permissions/assign'),
     ('admin',     'post:/rotate-key'),
-    ('admin',     'get:/sysinfo')
+    ('admin',     'get:/admin/secrets'),
+    ('admin',     'get:/sysinfo'),
+    ('admin',     'get:/admin/users'),
+    ('admin',     'post:/admin/users/disable'),
+    ('admin',     'post:/admin/users/enable'),
+    ('admin',     'post:/admin/users/force-email-verification'),
+    ('admin',     'delete:/admin/users'),
+    ('admin',     'get:/account/export'),
+    ('admin',     'delete:/account'),
+    ('admin',     'post:/admin/impersonate'),
+    ('admin',     'post:/admin/invitations'),
+    ('admin',     'post:/orgs'),
+    ('admin',     'get:/admin/outbox'),
+    ('admin',     'post:/admin/outbox/retry'),
+    ('admin',     'get:/admin/webhooks'),
+    ('admin',     'post:/admin/webhooks'),
+    ('admin',     'delete:/admin/webhooks'),
+    ('admin',     'get:/admin/webhooks/deliveries'),
+
+    ('org-owner',  'get:/orgs/members'),
+    ('org-owner',  'post:/orgs/members'),
+    ('org-owner',  'delete:/orgs/members'),
+
+    ('org-member', 'get:/orgs/members')
 )
 INSERT INTO permission_group_association (permission_id, permission_group_id)
 SELECT p.id, pg.id
@@ -1,7 +1,7 @@
 use std::time::Duration;
 
 use axum::{
-    Form,
+    Form, Json,
     extract::State,
     http::StatusCode,
     response::IntoResponse,
@@ -14,7 +14,10 @@ use time::OffsetDateTime;
 
 use crate::{
     AppState,
-    core::{AccessToken, InsufficientPermissionsError, Principal},
+    core::{
+        AccessToken, ImpersonationRestrictedError, InsufficientPermissionsError, Organization,
+        Principal,
+    },
 };
 
 pub const PATH: &str = "/access-token/generate";
@@ -28,6 +31,10 @@ pub struct Config {
 
     #[cfg_attr(feature = "openapi", schema(example = 3600u64, value_type = u64))]
     ttl_sec: Option<u64>,
+
+    /// binds the token to the organization; the token then only acts within it
+    #[cfg_attr(feature = "openapi", schema(example = "acme"))]
+    organization: Option<String>,
 }
 
 pub fn method_router() -> MethodRouter<AppState> {
@@ -44,7 +51,8 @@ pub fn method_router() -> MethodRouter<AppState> {
     ),
     responses(
         (status = 200, description = "Access token generated successfully", body = String),
-        (status = 403, description = "Insufficient permissions", body = extra::ErrorResponse),
+        (status = 403, description = "Insufficient permissions or impersonating", body = extra::ErrorResponse),
+        (status = 404, description = "Organization not found", body = extra::ErrorResponse),
         (status = 500, description = "Internal server error"),
     ),
     tag = "access_token"
@@ -52,16 +60,54 @@ pub fn method_router() -> MethodRouter<AppState> {
 #[debug_handler]
 #[cfg_attr(feature = "tracing", tracing::instrument(fields(%principal, ?settings), skip_all))]
 pub async fn handler(
-    State(AppState { pool, .. }): State<AppState>,
+    State(AppState {
+        pool,
+
+        #[cfg(feature = "smtp")]
+        smtp,
+        ..
+    }): State<AppState>,
     principal: Principal,
     Form(settings): Form<Config>,
 ) -> Result<(StatusCode, String), Error> {
     principal
         .require_permission::<Error>(&pool, "post:/access-token/generate")
         .await?;
+    principal.forbid_impersonation()?;
 
     let user_id = principal.user_id();
 
+    let organization_id = match &settings.organization {
+        Some(name) => {
+            let organization = Organization::from_name(name, &pool)
+                .await
+                .context("name -> Organization")?
+                .ok_or_else(|| Error::OrganizationNotFound(name.clone()))?;
+
+            // non-members must not learn whether the organization exists
+            let is_member = sqlx::query_scalar!(
+                r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM organization_memberships
+                    WHERE organization_id = ? AND user_id = ?
+                )
+                "#,
+                organization.id,
+                user_id
+            )
+            .fetch_one(&pool)
+            .await
+            .context("is organization member")?;
+
+            if is_member == 0 {
+                return Err(Error::OrganizationNotFound(name.clone()));
+            }
+
+            Some(organization.id)
+        }
+        None => None,
+    };
+
     let access_token = AccessToken::new();
     let access_token_hash = access_token.hash_sha256();
     let created_at = OffsetDateTime::now_utc();
@@ -69,22 +115,58 @@ pub async fn handler(
         .ttl_sec
         .map(|sec| created_at + Duration::from_secs(sec));
 
+    let mut tx = pool
+        .begin_with(crate::core::BEGIN_WRITE)
+        .await
+        .context("begin transaction :: generate access token")?;
+
     sqlx::query!(
         r#"
         INSERT INTO access_tokens
-        (name, access_token_hash, user_id, created_at, expires_at)
-        VALUES (?, ?, ?, ?, ?)
+        (name, access_token_hash, user_id, created_at, expires_at, organization_id)
+        VALUES (?, ?, ?, ?, ?, ?)
         "#,
         settings.name,
         access_token_hash,
         user_id,
         created_at,
         expires_at,
+        organization_id,
     )
-    .execute(&pool)
+    .execute(&mut *tx)
     .await
     .context("insert access token")?;
 
+    #[cfg(feature = "webhooks")]
+    crate::webhook::enqueue(
+        &mut *tx,
+        &crate::webhook::Event::TokenCreated {
+            user_id,
+            token_name: settings.name.clone(),
+            expires_at,
+        },
+    )
+    .await?;
+
+    #[cfg(feature = "smtp")]
+    {
+        let username = sqlx::query_scalar!("SELECT username FROM users WHERE id = ?", user_id)
+            .fetch_one(&mut *tx)
+            .await
+            .context("user_id -> username")?;
+
+        let notification = crate::notification::AccessTokenCreated {
+            username,
+            token_name: settings.name,
+            expires_at: expires_at.map(|expires_at| expires_at.to_string()),
+        };
+        crate::notification::notify(&mut tx, &smtp, user_id, &notification).await?;
+    }
+
+    tx.commit()
+        .await
+        .context("commit transaction :: generate access token")?;
+
     #[cfg(feature = "tracing")]
     tracing::info!(?expires_at, "access_token created");
 
@@ -96,18 +178,72 @@ pub enum Error {
     #[error("{0}")]
     InsufficientPermissions(#[from] InsufficientPermissionsError),
 
+    #[error("{0}")]
+    ImpersonationRestricted(#[from] ImpersonationRestrictedError),
+
+    #[error("organization `{0}` not found")]
+    OrganizationNotFound(String),
+
     #[error("{0}")]
     Sqlx(#[from] contextual::Error<sqlx::Error>),
+
+    #[cfg(feature = "webhooks")]
+    #[error("{0}")]
+    Webhook(#[from] crate::webhook::EnqueueError),
+
+    #[cfg(feature = "smtp")]
+    #[error("{0}")]
+    Notify(#[from] crate::notification::NotifyError),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::InsufficientPermissions(err) => err.kind(),
+            Error::ImpersonationRestricted(err) => err.kind(),
+            Error::OrganizationNotFound(_) => "organization.not-found",
+            Error::Sqlx(_) => "sqlx",
+            #[cfg(feature = "webhooks")]
+            Error::Webhook(_) => "webhook",
+            #[cfg(feature = "smtp")]
+            Error::Notify(_) => "notification",
+        }
+    }
 }
 
 impl IntoResponse for Error {
     fn into_response(self) -> axum::response::Response {
         match self {
             Error::InsufficientPermissions(err) => err.into_response(),
+            Error::ImpersonationRestricted(err) => err.into_response(),
+            Error::OrganizationNotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(extra::ErrorResponse::from(self)),
+                )
+                    .into_response()
+            }
             Error::Sqlx(_err) => {
                 #[cfg(feature = "tracing")]
                 tracing::error!("{:?}", _err);
 
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+            #[cfg(feature = "webhooks")]
+            Error::Webhook(_err) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", _err);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+            #[cfg(feature = "smtp")]
+            Error::Notify(_err) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", _err);
+
                 StatusCode::INTERNAL_SERVER_ERROR.into_response()
             }
         }
@@ -58,7 +58,7 @@ pub async fn handler(
     let access_token_info = sqlx::query_as!(
         AccessTokenInfo,
         r#"
-        SELECT id as "id!", name, user_id, created_at, expires_at
+        SELECT id as "id!", name, user_id, created_at, expires_at, organization_id
         FROM access_tokens
         WHERE user_id = ? AND name = ?
         "#,
@@ -0,0 +1,186 @@
+use axum::{
+    Form, Json,
+    extract::State,
+    response::IntoResponse,
+    routing::{MethodRouter, delete},
+};
+use axum_extra::extract::CookieJar;
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::StatusCode;
+use serde::Deserialize;
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    core::{
+        ImpersonationRestrictedError, InsufficientPermissionsError, Principal, UserInfo,
+        delete_user, expired_session_cookie, log_impersonations_stopped,
+    },
+};
+
+pub const PATH: &str = "/account";
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = account::delete::RequestBody))]
+#[derive(Deserialize)]
+pub struct RequestBody {
+    #[cfg_attr(feature = "openapi", schema(examples("h?P7o]37")))]
+    pub password: String,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    delete(handler)
+}
+
+/// Schedules the account for deletion and logs it out everywhere:
+/// sessions are ended and access tokens and basic credentials are rejected.
+/// The account is purged once the grace period elapses,
+/// logging in again before that cancels the deletion.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    delete,
+    path = PATH,
+    operation_id = PATH,
+    request_body(
+        content = RequestBody,
+        content_type = "application/x-www-form-urlencoded",
+    ),
+    responses(
+        (status = 200, description = "Account deleted"),
+        (status = 202, description = "Account scheduled for deletion"),
+        (status = 401, description = "Not authenticated or wrong password", body = ErrorResponse),
+        (status = 403, description = "Insufficient permissions or impersonating", body = ErrorResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "account"
+))]
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%principal), skip_all, ret))]
+pub async fn handler(
+    State(AppState { pool, account, .. }): State<AppState>,
+    principal: Principal,
+    jar: CookieJar,
+    Form(RequestBody { password }): Form<RequestBody>,
+) -> Result<(StatusCode, CookieJar), Error> {
+    principal
+        .require_permission::<Error>(&pool, "delete:/account")
+        .await?;
+    principal.forbid_impersonation()?;
+
+    let user_id = principal.user_id();
+
+    UserInfo::from_user_id(user_id, &pool)
+        .await
+        .context("user_id -> UserInfo")?
+        .ok_or(Error::InvalidPassword)?
+        .verify_password(&password)
+        .context("verify password hash")?
+        .ok_or(Error::InvalidPassword)?;
+
+    let mut tx = pool
+        .begin_with(crate::core::BEGIN_WRITE)
+        .await
+        .context("begin transaction :: delete account")?;
+
+    let status = match account.deletion_grace_period.is_zero() {
+        true => {
+            delete_user(&mut tx, user_id).await.context("delete user")?;
+
+            #[cfg(feature = "tracing")]
+            tracing::info!("account deleted");
+
+            StatusCode::OK
+        }
+        false => {
+            let deletion_scheduled_at = OffsetDateTime::now_utc() + account.deletion_grace_period;
+
+            sqlx::query!(
+                "UPDATE users SET deletion_scheduled_at = ? WHERE id = ?",
+                deletion_scheduled_at,
+                user_id
+            )
+            .execute(&mut *tx)
+            .await
+            .context("schedule account deletion")?;
+
+            let sessions = sqlx::query!(
+                "DELETE FROM sessions WHERE user_id = ? RETURNING user_id, impersonator_id",
+                user_id
+            )
+            .fetch_all(&mut *tx)
+            .await
+            .context("invalidate sessions")?;
+            log_impersonations_stopped(
+                &mut tx,
+                sessions
+                    .into_iter()
+                    .map(|session| (session.user_id, session.impersonator_id)),
+            )
+            .await
+            .context("write impersonation audit log")?;
+
+            #[cfg(feature = "tracing")]
+            tracing::info!(?deletion_scheduled_at, "account deletion scheduled");
+
+            StatusCode::ACCEPTED
+        }
+    };
+
+    tx.commit()
+        .await
+        .context("commit transaction :: delete account")?;
+
+    Ok((status, jar.add(expired_session_cookie())))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    InsufficientPermissions(#[from] InsufficientPermissionsError),
+
+    #[error("{0}")]
+    ImpersonationRestricted(#[from] ImpersonationRestrictedError),
+
+    #[error("invalid password")]
+    InvalidPassword,
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+
+    #[error("{0}")]
+    Bcrypt(#[from] contextual::Error<bcrypt::BcryptError>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::InsufficientPermissions(err) => err.kind(),
+            Error::ImpersonationRestricted(err) => err.kind(),
+            Error::InvalidPassword => "auth.invalid-credentials",
+            Error::Sqlx(_) => "sqlx",
+            Error::Bcrypt(_) => "bcrypt",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::InsufficientPermissions(err) => err.into_response(),
+            Error::ImpersonationRestricted(err) => err.into_response(),
+            Error::InvalidPassword => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::UNAUTHORIZED, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) | Error::Bcrypt(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,402 @@
+use axum::{
+    Json,
+    extract::State,
+    response::IntoResponse,
+    routing::{MethodRouter, get},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::{StatusCode, header::CONTENT_DISPOSITION};
+use serde::Serialize;
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    core::{
+        ImpersonationRestrictedError, InsufficientPermissionsError, Permission, Principal, UserInfo,
+    },
+};
+
+pub const PATH: &str = "/account/export";
+
+/// Everything held about a user.
+/// When a table referencing `users` is added, it must be included here as well.
+#[derive(Serialize)]
+pub struct Archive {
+    #[serde(with = "time::serde::rfc3339")]
+    pub exported_at: OffsetDateTime,
+    pub profile: UserInfo,
+    pub sessions: Vec<Session>,
+    pub access_tokens: Vec<AccessToken>,
+    pub permissions: Vec<Permission>,
+    pub permissions_audit_log: Vec<AuditEntry>,
+    pub login_history: Vec<Login>,
+    pub impersonation_audit_log: Vec<ImpersonationEntry>,
+    pub invitations: Vec<Invitation>,
+    pub organization_memberships: Vec<OrganizationMembership>,
+    pub email_verification_tokens: Vec<EmailVerificationToken>,
+}
+
+#[derive(Serialize)]
+pub struct Session {
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+    pub user_agent: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct AccessToken {
+    #[serde(skip)]
+    pub id: i64,
+    pub name: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+    pub permissions: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct AuditEntry {
+    pub assigner_type: String,
+    pub assigner_id: i64,
+    pub assignee_type: String,
+    pub assignee_id: i64,
+    pub permission: String,
+    pub action: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub datetime: OffsetDateTime,
+}
+
+#[derive(Serialize)]
+pub struct Login {
+    #[serde(with = "time::serde::rfc3339")]
+    pub datetime: OffsetDateTime,
+    pub user_agent: Option<String>,
+}
+
+/// Either side of an impersonation, as impersonator or as target.
+#[derive(Serialize)]
+pub struct ImpersonationEntry {
+    pub impersonator_id: i64,
+    pub target_id: i64,
+    pub action: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub datetime: OffsetDateTime,
+}
+
+/// An invitation sent by the user or accepted to sign up.
+#[derive(Serialize)]
+pub struct Invitation {
+    pub email: String,
+    pub group: String,
+    pub invited_by: Option<i64>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub accepted_at: Option<OffsetDateTime>,
+}
+
+#[derive(Serialize)]
+pub struct OrganizationMembership {
+    pub organization: String,
+    pub role: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+/// An outstanding verification link sent to the user's email address.
+#[derive(Serialize)]
+pub struct EmailVerificationToken {
+    #[serde(with = "time::serde::rfc3339")]
+    pub issued_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    get(handler)
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = PATH,
+    operation_id = PATH,
+    responses(
+        (status = 200, description = "JSON archive of all data held about the account"),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Insufficient permissions", body = ErrorResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "account"
+))]
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%principal), skip_all))]
+pub async fn handler(
+    State(AppState { pool, .. }): State<AppState>,
+    principal: Principal,
+) -> Result<impl IntoResponse, Error> {
+    principal
+        .require_permission::<Error>(&pool, "get:/account/export")
+        .await?;
+    principal.forbid_impersonation()?;
+
+    let archive = Archive::of(principal.user_id(), &pool)
+        .await?
+        .ok_or(Error::UserNotFound)?;
+
+    Ok((
+        [(
+            CONTENT_DISPOSITION,
+            r#"attachment; filename="account-export.json""#,
+        )],
+        Json(archive),
+    ))
+}
+
+impl Archive {
+    pub async fn of(user_id: i64, pool: &sqlx::Pool<sqlx::Sqlite>) -> Result<Option<Self>, Error> {
+        let Some(profile) = UserInfo::from_user_id(user_id, pool)
+            .await
+            .context("user_id -> UserInfo")?
+        else {
+            return Ok(None);
+        };
+
+        let sessions = sqlx::query_as!(
+            Session,
+            r#"
+            SELECT created_at, expires_at, user_agent
+            FROM sessions WHERE user_id = ?
+            ORDER BY created_at
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await
+        .context("export sessions")?;
+
+        let mut access_tokens = sqlx::query!(
+            r#"
+            SELECT id as "id!", name, created_at, expires_at
+            FROM access_tokens WHERE user_id = ?
+            ORDER BY created_at
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await
+        .context("export access tokens")?
+        .into_iter()
+        .map(|record| AccessToken {
+            id: record.id,
+            name: record.name,
+            created_at: record.created_at,
+            expires_at: record.expires_at,
+            permissions: vec![],
+        })
+        .collect::<Vec<_>>();
+
+        for access_token in &mut access_tokens {
+            access_token.permissions = sqlx::query_scalar!(
+                r#"
+                SELECT p.permission FROM permissions p
+                INNER JOIN access_token_permissions atp ON atp.permission_id = p.id
+                WHERE atp.access_token_id = ?
+                "#,
+                access_token.id
+            )
+            .fetch_all(pool)
+            .await
+            .context("export access token permissions")?;
+        }
+
+        let permissions = sqlx::query_as!(
+            Permission,
+            r#"
+            SELECT p.id as "id!", p.permission, p.description FROM permissions p
+            INNER JOIN user_permissions up ON up.permission_id = p.id
+            WHERE up.user_id = ?
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await
+        .context("export permissions")?;
+
+        let permissions_audit_log = sqlx::query_as!(
+            AuditEntry,
+            r#"
+            SELECT
+                l.assigner_type,
+                l.assigner_id,
+                l.assignee_type,
+                l.assignee_id,
+                p.permission,
+                l.action,
+                l.datetime as "datetime: OffsetDateTime"
+            FROM permissions_audit_log l
+            INNER JOIN permissions p ON p.id = l.permission_id
+            WHERE (l.assignee_type = 'user' AND l.assignee_id = ?1)
+            OR (
+                l.assignee_type = 'access_token'
+                AND l.assignee_id IN (SELECT id FROM access_tokens WHERE user_id = ?1)
+            )
+            ORDER BY l.datetime
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await
+        .context("export permissions audit log")?;
+
+        let login_history = sqlx::query_as!(
+            Login,
+            r#"
+            SELECT datetime as "datetime: OffsetDateTime", user_agent
+            FROM login_history WHERE user_id = ?
+            ORDER BY datetime
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await
+        .context("export login history")?;
+
+        let impersonation_audit_log = sqlx::query_as!(
+            ImpersonationEntry,
+            r#"
+            SELECT impersonator_id, target_id, action, datetime as "datetime: OffsetDateTime"
+            FROM impersonation_audit_log
+            WHERE impersonator_id = ?1 OR target_id = ?1
+            ORDER BY datetime
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await
+        .context("export impersonation audit log")?;
+
+        let invitations = sqlx::query_as!(
+            Invitation,
+            r#"
+            SELECT
+                i.email,
+                pg.[group] as "group",
+                i.invited_by,
+                i.created_at as "created_at: OffsetDateTime",
+                i.expires_at as "expires_at: OffsetDateTime",
+                i.accepted_at as "accepted_at: OffsetDateTime"
+            FROM invitations i
+            INNER JOIN permission_groups pg ON pg.id = i.permission_group_id
+            WHERE i.invited_by = ?1 OR i.user_id = ?1
+            ORDER BY i.created_at
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await
+        .context("export invitations")?;
+
+        let organization_memberships = sqlx::query_as!(
+            OrganizationMembership,
+            r#"
+            SELECT
+                o.name as organization,
+                pg.[group] as role,
+                om.created_at as "created_at: OffsetDateTime"
+            FROM organization_memberships om
+            INNER JOIN organizations o ON o.id = om.organization_id
+            INNER JOIN permission_groups pg ON pg.id = om.permission_group_id
+            WHERE om.user_id = ?
+            ORDER BY om.created_at
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await
+        .context("export organization memberships")?;
+
+        let email_verification_tokens = sqlx::query_as!(
+            EmailVerificationToken,
+            r#"
+            SELECT
+                issued_at as "issued_at: OffsetDateTime",
+                expires_at as "expires_at: OffsetDateTime"
+            FROM email_verification_tokens
+            WHERE email = (SELECT email FROM users WHERE id = ?)
+            ORDER BY issued_at
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await
+        .context("export email verification tokens")?;
+
+        Ok(Some(Self {
+            exported_at: OffsetDateTime::now_utc(),
+            profile,
+            sessions,
+            access_tokens,
+            permissions,
+            permissions_audit_log,
+            login_history,
+            impersonation_audit_log,
+            invitations,
+            organization_memberships,
+            email_verification_tokens,
+        }))
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    InsufficientPermissions(#[from] InsufficientPermissionsError),
+
+    #[error("{0}")]
+    ImpersonationRestricted(#[from] ImpersonationRestrictedError),
+
+    #[error("user not found")]
+    UserNotFound,
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::InsufficientPermissions(err) => err.kind(),
+            Error::ImpersonationRestricted(err) => err.kind(),
+            Error::UserNotFound => "user.not-found",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::InsufficientPermissions(err) => err.into_response(),
+            Error::ImpersonationRestricted(err) => err.into_response(),
+            Error::UserNotFound => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
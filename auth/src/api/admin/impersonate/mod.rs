@@ -0,0 +1,234 @@
+pub mod stop;
+
+use axum::{
+    Form, Json,
+    extract::State,
+    http::{HeaderMap, header::USER_AGENT},
+    response::IntoResponse,
+    routing::{MethodRouter, post},
+};
+use axum_extra::extract::CookieJar;
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::StatusCode;
+use serde::Deserialize;
+use time::{Duration, OffsetDateTime};
+
+use crate::{
+    AppState,
+    core::{
+        ImpersonationAction, ImpersonationRestrictedError, InsufficientPermissionsError, Principal,
+        SessionId, log_impersonation,
+    },
+};
+
+pub const PATH: &str = "/admin/impersonate";
+const COOKIE_DURATION: Duration = Duration::hours(1);
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = admin::impersonate::RequestBody))]
+#[derive(Deserialize)]
+pub struct RequestBody {
+    #[cfg_attr(feature = "openapi", schema(examples("joe")))]
+    pub username: String,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    post(handler)
+}
+
+/// Starts a short lived session as the given user.
+/// The session cookie replaces the caller's own session cookie
+/// and the session is tagged with the caller as the impersonator.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = PATH,
+    operation_id = PATH,
+    request_body(
+        content = RequestBody,
+        content_type = "application/x-www-form-urlencoded",
+    ),
+    responses(
+        (status = 200, description = "Impersonation started, session cookie set"),
+        (status = 400, description = "Cannot impersonate own or disabled account", body = ErrorResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Insufficient permissions, already impersonating or target holds permissions the caller lacks", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin"
+))]
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%principal, %username), skip_all, ret))]
+pub async fn handler(
+    State(AppState { pool, .. }): State<AppState>,
+    principal: Principal,
+    headers: HeaderMap,
+    jar: CookieJar,
+    Form(RequestBody { username }): Form<RequestBody>,
+) -> Result<(CookieJar, StatusCode), Error> {
+    principal
+        .require_permission::<Error>(&pool, "post:/admin/impersonate")
+        .await?;
+    principal.forbid_impersonation()?;
+
+    let impersonator_id = principal.user_id();
+
+    let target = sqlx::query!(
+        r#"SELECT id as "id!", disabled FROM users WHERE username = ?"#,
+        username
+    )
+    .fetch_optional(&pool)
+    .await
+    .context("username -> user")?
+    .ok_or(Error::UserNotFound(username))?;
+
+    if target.id == impersonator_id {
+        return Err(Error::SelfImpersonation);
+    }
+
+    if target.disabled {
+        return Err(Error::TargetDisabled);
+    }
+
+    // impersonation must not grant anything the impersonator could not do already
+    let target_privileged = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM user_permissions t
+            WHERE t.user_id = ?
+            AND t.permission_id NOT IN (
+                SELECT permission_id FROM user_permissions WHERE user_id = ?
+            )
+        )
+        "#,
+        target.id,
+        impersonator_id
+    )
+    .fetch_one(&pool)
+    .await
+    .context("target permissions not held by the impersonator")?;
+
+    if target_privileged != 0 {
+        return Err(Error::TargetPrivileged);
+    }
+
+    let session_id = SessionId::new();
+    let session_id_hash = session_id.hash_sha256();
+    let created_at = OffsetDateTime::now_utc();
+    let expires_at = created_at + COOKIE_DURATION;
+    let user_agent = headers.get(USER_AGENT).and_then(|val| val.to_str().ok());
+
+    let mut tx = pool
+        .begin_with(crate::core::BEGIN_WRITE)
+        .await
+        .context("begin transaction :: start impersonation")?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO sessions
+        (session_id_hash, user_id, created_at, expires_at, user_agent, impersonator_id)
+        VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+        session_id_hash,
+        target.id,
+        created_at,
+        expires_at,
+        user_agent,
+        impersonator_id
+    )
+    .execute(&mut *tx)
+    .await
+    .context("insert impersonation session")?;
+
+    log_impersonation(
+        &mut *tx,
+        impersonator_id,
+        target.id,
+        ImpersonationAction::Start,
+    )
+    .await
+    .context("write impersonation audit log")?;
+
+    tx.commit()
+        .await
+        .context("commit transaction :: start impersonation")?;
+
+    #[cfg(feature = "tracing")]
+    tracing::info!(target_id = target.id, ?expires_at, "impersonation started");
+
+    let session_cookie = session_id.into_cookie(COOKIE_DURATION);
+    Ok((jar.add(session_cookie), StatusCode::OK))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    InsufficientPermissions(#[from] InsufficientPermissionsError),
+
+    #[error("{0}")]
+    ImpersonationRestricted(#[from] ImpersonationRestrictedError),
+
+    #[error("user `{0}` not found")]
+    UserNotFound(String),
+
+    #[error("cannot impersonate your own account")]
+    SelfImpersonation,
+
+    #[error("cannot impersonate a disabled account")]
+    TargetDisabled,
+
+    #[error("cannot impersonate an account holding permissions you lack")]
+    TargetPrivileged,
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::InsufficientPermissions(err) => err.kind(),
+            Error::ImpersonationRestricted(err) => err.kind(),
+            Error::UserNotFound(_) => "user.not-found",
+            Error::SelfImpersonation => "user.self-impersonation",
+            Error::TargetDisabled => "auth.account.disabled",
+            Error::TargetPrivileged => "user.impersonation.privileged",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::InsufficientPermissions(err) => err.into_response(),
+            Error::ImpersonationRestricted(err) => err.into_response(),
+            Error::UserNotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::SelfImpersonation | Error::TargetDisabled => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::TargetPrivileged => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::FORBIDDEN, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use axum::{
+    Json,
+    extract::State,
+    response::IntoResponse,
+    routing::{MethodRouter, post},
+};
+use axum_extra::extract::CookieJar;
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+
+use crate::{
+    AppState,
+    core::{
+        Credentials, ImpersonationAction, Principal, SessionId, expired_session_cookie,
+        log_impersonation,
+    },
+};
+
+pub const PATH: &str = "/admin/impersonate/stop";
+
+pub fn method_router() -> MethodRouter<AppState> {
+    post(handler)
+}
+
+/// Ends the impersonation session and removes its cookie.
+/// The impersonator has to log in again to get their own session back.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = PATH,
+    operation_id = PATH,
+    responses(
+        (status = 200, description = "Impersonation stopped, session cookie removed"),
+        (status = 400, description = "Not impersonating", body = ErrorResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin"
+))]
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%principal), skip_all, ret))]
+pub async fn handler(
+    State(AppState { pool, .. }): State<AppState>,
+    principal: Principal,
+    headers: HeaderMap,
+    jar: CookieJar,
+) -> Result<(CookieJar, StatusCode), Error> {
+    let Some(impersonator_id) = principal.impersonator_id() else {
+        return Err(Error::NotImpersonating);
+    };
+
+    // an impersonation principal can only come from a session cookie
+    let Ok(Some(session_id)) = SessionId::try_from_headers(&headers) else {
+        return Err(Error::NotImpersonating);
+    };
+    let session_id_hash = session_id.hash_sha256();
+
+    let mut tx = pool
+        .begin_with(crate::core::BEGIN_WRITE)
+        .await
+        .context("begin transaction :: stop impersonation")?;
+
+    sqlx::query!(
+        "DELETE FROM sessions WHERE session_id_hash = ?",
+        session_id_hash
+    )
+    .execute(&mut *tx)
+    .await
+    .context("delete impersonation session")?;
+
+    log_impersonation(
+        &mut *tx,
+        impersonator_id,
+        principal.user_id(),
+        ImpersonationAction::Stop,
+    )
+    .await
+    .context("write impersonation audit log")?;
+
+    tx.commit()
+        .await
+        .context("commit transaction :: stop impersonation")?;
+
+    #[cfg(feature = "tracing")]
+    tracing::info!("impersonation stopped");
+
+    Ok((jar.add(expired_session_cookie()), StatusCode::OK))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("not impersonating another user")]
+    NotImpersonating,
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::NotImpersonating => "auth.impersonation.inactive",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::NotImpersonating => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,352 @@
+use std::time::Duration;
+
+use axum::{
+    Form, Json,
+    extract::State,
+    response::IntoResponse,
+    routing::{MethodRouter, post},
+};
+use contextual::Context;
+use email::Email;
+use extra::ErrorResponse;
+use http::StatusCode;
+use serde::Deserialize;
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    core::{
+        ImpersonationRestrictedError, InsufficientPermissionsError, InvitationToken, Principal,
+    },
+};
+
+pub const PATH: &str = "/admin/invitations";
+const DEFAULT_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+const MAX_TTL: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// Audience of invitation links, so that no other token encrypted with the same key passes as one.
+#[cfg(feature = "smtp")]
+pub const INVITATION_AUDIENCE: &str = "invitation";
+
+/// Claims of the encrypted token in an invitation link, which must not show the group to whoever
+/// sees the link.
+#[cfg(feature = "smtp")]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct InvitationClaims {
+    /// the invitation token that is returned to the inviter
+    pub invitation: String,
+    pub group: String,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = admin::invitations::RequestBody))]
+#[derive(Deserialize)]
+pub struct RequestBody {
+    #[cfg_attr(feature = "openapi", schema(examples("joe@smith.com")))]
+    pub email: String,
+
+    /// permission group assigned to the account created from this invitation
+    #[cfg_attr(feature = "openapi", schema(examples("signup")))]
+    pub group: String,
+
+    /// at most one year
+    #[cfg_attr(feature = "openapi", schema(example = 604800u64, value_type = u64))]
+    pub ttl_sec: Option<u64>,
+
+    /// language of the invitation email, e.g. `de`
+    #[cfg(feature = "smtp")]
+    #[cfg_attr(feature = "openapi", schema(examples("en")))]
+    pub locale: Option<String>,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    post(handler)
+}
+
+/// Creates an invitation and returns its token.
+/// The inviter must hold every permission of the invited group.
+/// When email is configured, the invitation link is also sent to the invitee.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = PATH,
+    operation_id = PATH,
+    request_body(
+        content = RequestBody,
+        content_type = "application/x-www-form-urlencoded",
+    ),
+    responses(
+        (status = 201, description = "Invitation created", body = String),
+        (status = 400, description = "Invalid email address or ttl", body = ErrorResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Insufficient permissions or impersonating", body = ErrorResponse),
+        (status = 404, description = "Permission group not found", body = ErrorResponse),
+        (status = 409, description = "Email already linked to an account", body = ErrorResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin"
+))]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%principal, %email, %group), skip_all, ret))]
+pub async fn handler(
+    State(AppState {
+        pool,
+
+        #[cfg(feature = "smtp")]
+        secrets,
+
+        #[cfg(feature = "smtp")]
+        smtp,
+        ..
+    }): State<AppState>,
+    principal: Principal,
+    #[cfg(feature = "smtp")] axum_extra::extract::Host(host): axum_extra::extract::Host,
+    Form(RequestBody {
+        email,
+        group,
+        ttl_sec,
+
+        #[cfg(feature = "smtp")]
+        locale,
+        ..
+    }): Form<RequestBody>,
+) -> Result<(StatusCode, String), Error> {
+    principal
+        .require_permission::<Error>(&pool, "post:/admin/invitations")
+        .await?;
+    principal.forbid_impersonation()?;
+
+    let email = Email::try_from(email).map_err(Error::InvalidEmailFormat)?;
+
+    let ttl = ttl_sec.map(Duration::from_secs).unwrap_or(DEFAULT_TTL);
+    if ttl.is_zero() || ttl > MAX_TTL {
+        return Err(Error::InvalidTtl(MAX_TTL.as_secs()));
+    }
+
+    let permission_group_id = sqlx::query_scalar!(
+        r#"SELECT id as "id!" FROM permission_groups WHERE [group] = ?"#,
+        group
+    )
+    .fetch_optional(&pool)
+    .await
+    .context("group -> permission_group_id")?
+    .ok_or_else(|| Error::GroupNotFound(group.clone()))?;
+
+    // The inviter must have every permission of the group themselves
+    // before they hand it out to others
+    let group_permissions = sqlx::query_scalar!(
+        r#"
+        SELECT p.permission FROM permissions p
+        INNER JOIN permission_group_association pga ON pga.permission_id = p.id
+        WHERE pga.permission_group_id = ?
+        "#,
+        permission_group_id
+    )
+    .fetch_all(&pool)
+    .await
+    .context("permission group permissions")?;
+
+    for permission in &group_permissions {
+        principal
+            .require_permission::<Error>(&pool, permission)
+            .await?;
+    }
+
+    if crate::api::email::exists(&pool, &email)
+        .await
+        .context("email exists")?
+    {
+        return Err(Error::EmailExists(email));
+    }
+
+    let invitation_token = InvitationToken::new();
+    let invitation_token_hash = invitation_token.hash_sha256();
+    let invited_by = principal.user_id();
+    let created_at = OffsetDateTime::now_utc();
+    let expires_at = time::Duration::try_from(ttl)
+        .ok()
+        .and_then(|ttl| created_at.checked_add(ttl))
+        .ok_or(Error::InvalidTtl(MAX_TTL.as_secs()))?;
+
+    let mut tx = pool
+        .begin_with(crate::core::BEGIN_WRITE)
+        .await
+        .context("begin transaction :: invitation")?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO invitations
+        (invitation_token_hash, email, permission_group_id, invited_by, created_at, expires_at)
+        VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+        invitation_token_hash,
+        email,
+        permission_group_id,
+        invited_by,
+        created_at,
+        expires_at
+    )
+    .execute(&mut *tx)
+    .await
+    .context("insert invitation")?;
+
+    #[cfg(feature = "smtp")]
+    {
+        let key = crate::encryption::current(&secrets)?;
+        let invitation_link = invitation_link(&host, &key, &invitation_token, &group, ttl)
+            .context("encrypt invitation link")?;
+        let message = invitation_email(&smtp, locale.as_deref(), &email, &invitation_link)
+            .context("render invitation email")?;
+        let _outbox_id = crate::outbox::enqueue(&mut *tx, &message)
+            .await
+            .context("enqueue invitation email")?;
+
+        #[cfg(feature = "tracing")]
+        tracing::info!(outbox_id = _outbox_id, "invitation email queued");
+    }
+
+    tx.commit()
+        .await
+        .context("commit transaction :: invitation")?;
+
+    #[cfg(feature = "tracing")]
+    tracing::info!(?expires_at, "invitation created");
+
+    Ok((StatusCode::CREATED, invitation_token.base64encoded()))
+}
+
+/// Link to the signup page with the invitation `token` and its `group` encrypted, expiring along
+/// with the invitation.
+#[cfg(feature = "smtp")]
+pub fn invitation_link(
+    host: &str,
+    (kid, key): &(Option<String>, signature::EncryptionKey),
+    token: &InvitationToken,
+    group: &str,
+    ttl: Duration,
+) -> Result<String, signature::EncodeError> {
+    let claims = InvitationClaims {
+        invitation: token.base64encoded(),
+        group: group.to_string(),
+    };
+    let mut encrypted = signature::Encrypted::new(claims)
+        .with_ttl(ttl)
+        .with_audience(INVITATION_AUDIENCE);
+    if let Some(kid) = kid {
+        encrypted = encrypted.with_kid(kid);
+    }
+
+    Ok(format!(
+        "{host}{}?invitation={}",
+        crate::api::signup::PATH,
+        encrypted.encrypt(key, signature::PayloadFormat::Json)?
+    ))
+}
+
+#[cfg(feature = "smtp")]
+pub fn invitation_email<'a>(
+    smtp: &crate::smtp::Smtp,
+    locale: Option<&str>,
+    email: &'a Email,
+    invitation_link: &str,
+) -> Result<crate::outbox::OutgoingEmail<'a>, tera::Error> {
+    let notification = crate::notification::Invitation {
+        invitation_link: invitation_link.to_string(),
+    };
+
+    smtp.render_notification(locale, email, &notification)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    InsufficientPermissions(#[from] InsufficientPermissionsError),
+
+    #[error("{0}")]
+    ImpersonationRestricted(#[from] ImpersonationRestrictedError),
+
+    #[error("{0}")]
+    InvalidEmailFormat(&'static str),
+
+    #[error("ttl must be between 1 and {0} seconds")]
+    InvalidTtl(u64),
+
+    #[error("permission group `{0}` not found")]
+    GroupNotFound(String),
+
+    #[error("email `{0}` already linked to another account")]
+    EmailExists(Email),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+
+    #[cfg(feature = "smtp")]
+    #[error("{0}")]
+    Io(#[from] contextual::Error<std::io::Error>),
+
+    #[cfg(feature = "smtp")]
+    #[error("{0}")]
+    TokenEncode(#[from] contextual::Error<signature::EncodeError>),
+
+    #[cfg(feature = "smtp")]
+    #[error("{0}")]
+    EmailTemplate(#[from] contextual::Error<tera::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::InsufficientPermissions(err) => err.kind(),
+            Error::ImpersonationRestricted(err) => err.kind(),
+            Error::InvalidEmailFormat(_) => "email.invalid",
+            Error::InvalidTtl(_) => "invitation.ttl.invalid",
+            Error::GroupNotFound(_) => "permission-group.not-found",
+            Error::EmailExists(_) => "email.exists",
+            Error::Sqlx(_) => "sqlx",
+            #[cfg(feature = "smtp")]
+            Error::Io(_) => "io",
+            #[cfg(feature = "smtp")]
+            Error::TokenEncode(_) => "invitation.token.encode",
+            #[cfg(feature = "smtp")]
+            Error::EmailTemplate(_) => "invitation.email-template",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::InsufficientPermissions(err) => err.into_response(),
+            Error::ImpersonationRestricted(err) => err.into_response(),
+            Error::InvalidEmailFormat(_) | Error::InvalidTtl(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::GroupNotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::EmailExists(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::CONFLICT, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+            #[cfg(feature = "smtp")]
+            Error::Io(_) | Error::TokenEncode(_) | Error::EmailTemplate(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,8 @@
+pub mod impersonate;
+pub mod invitations;
+#[cfg(feature = "smtp")]
+pub mod outbox;
+pub mod secrets;
+pub mod users;
+#[cfg(feature = "webhooks")]
+pub mod webhooks;
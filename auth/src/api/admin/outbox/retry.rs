@@ -0,0 +1,117 @@
+use axum::{
+    Form, Json,
+    extract::State,
+    response::IntoResponse,
+    routing::{MethodRouter, post},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::StatusCode;
+use serde::Deserialize;
+
+use crate::{
+    AppState,
+    core::{ImpersonationRestrictedError, InsufficientPermissionsError, Principal},
+    outbox,
+};
+
+pub const PATH: &str = "/admin/outbox/retry";
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = admin::outbox::retry::RequestBody))]
+#[derive(Deserialize)]
+pub struct RequestBody {
+    #[cfg_attr(feature = "openapi", schema(examples(1)))]
+    pub id: i64,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    post(handler)
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = PATH,
+    operation_id = PATH,
+    request_body(
+        content = RequestBody,
+        content_type = "application/x-www-form-urlencoded",
+    ),
+    responses(
+        (status = 200, description = "Message queued for delivery again"),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Insufficient permissions", body = ErrorResponse),
+        (status = 404, description = "No failed message with that id", body = ErrorResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin"
+))]
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%principal, %id), skip_all, ret))]
+pub async fn handler(
+    State(AppState { pool, .. }): State<AppState>,
+    principal: Principal,
+    Form(RequestBody { id }): Form<RequestBody>,
+) -> Result<StatusCode, Error> {
+    principal
+        .require_permission::<Error>(&pool, "post:/admin/outbox/retry")
+        .await?;
+    principal.forbid_impersonation()?;
+
+    if !outbox::retry(&pool, id)
+        .await
+        .context("retry outbox message")?
+    {
+        return Err(Error::MessageNotFound(id));
+    }
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    InsufficientPermissions(#[from] InsufficientPermissionsError),
+
+    #[error("{0}")]
+    ImpersonationRestricted(#[from] ImpersonationRestrictedError),
+
+    #[error("no failed message with id `{0}`")]
+    MessageNotFound(i64),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::InsufficientPermissions(err) => err.kind(),
+            Error::ImpersonationRestricted(err) => err.kind(),
+            Error::MessageNotFound(_) => "outbox.message.not-found",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::InsufficientPermissions(err) => err.into_response(),
+            Error::ImpersonationRestricted(err) => err.into_response(),
+            Error::MessageNotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    response::IntoResponse,
+    routing::{MethodRouter, get},
+};
+use axum_macros::debug_handler;
+use extra::ErrorResponse;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    core::{ImpersonationRestrictedError, InsufficientPermissionsError, Principal},
+};
+
+pub const PATH: &str = "/admin/secrets";
+
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+#[derive(Deserialize, Debug)]
+pub struct QueryParams {
+    #[cfg_attr(feature = "openapi", param(example = "hmac"))]
+    pub key: String,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = admin::secrets::RotationStatus))]
+#[derive(Debug, Serialize)]
+pub struct RotationStatus {
+    #[cfg_attr(feature = "openapi", schema(examples("hmac")))]
+    pub key: String,
+
+    /// `None` until the secret has been rotated for the first time
+    #[cfg_attr(feature = "openapi", schema(examples("v2")))]
+    pub current_kid: Option<String>,
+
+    #[cfg_attr(feature = "openapi", schema(examples(86400)))]
+    pub grace_period_sec: u64,
+
+    /// oldest first
+    pub versions: Vec<Version>,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = admin::secrets::Version))]
+#[derive(Debug, Serialize)]
+pub struct Version {
+    #[cfg_attr(feature = "openapi", schema(examples("v1")))]
+    pub kid: String,
+
+    /// one of `current`, `retired` or `expired`
+    #[cfg_attr(feature = "openapi", schema(examples("retired")))]
+    pub status: &'static str,
+
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<String>, format = DateTime))]
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub created_at: Option<OffsetDateTime>,
+
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<String>, format = DateTime))]
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub retired_at: Option<OffsetDateTime>,
+
+    /// end of the grace period, after which the version no longer verifies anything
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<String>, format = DateTime))]
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    get(handler)
+}
+
+/// Shows the versions of a secret and how long rotated-out versions remain valid.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = PATH,
+    operation_id = PATH,
+    params(QueryParams),
+    responses(
+        (status = 200, description = "Rotation status of the secret", body = RotationStatus),
+        (status = 400, description = "Invalid secret name", body = ErrorResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Insufficient permissions", body = ErrorResponse),
+        (status = 404, description = "Secret not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "secrets"
+))]
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%principal, ?params), skip_all))]
+pub async fn handler(
+    State(AppState { pool, secrets, .. }): State<AppState>,
+    principal: Principal,
+    Query(params): Query<QueryParams>,
+) -> Result<Json<RotationStatus>, Error> {
+    principal
+        .require_permission::<Error>(&pool, "get:/admin/secrets")
+        .await?;
+    principal.forbid_impersonation()?;
+
+    let now = OffsetDateTime::now_utc();
+    let versions = secrets
+        .versions(&params.key)
+        .map_err(|err| Error::from_io(params.key.clone(), err))?
+        .into_iter()
+        .map(|version| Version {
+            status: match version.expires_at {
+                None => "current",
+                Some(expires_at) if expires_at > now => "retired",
+                Some(_) => "expired",
+            },
+            kid: version.kid,
+            created_at: version.created_at,
+            retired_at: version.retired_at,
+            expires_at: version.expires_at,
+        })
+        .collect::<Vec<_>>();
+
+    let current_kid = versions
+        .iter()
+        .rfind(|version| version.status == "current")
+        .map(|version| version.kid.clone());
+
+    Ok(Json(RotationStatus {
+        key: params.key,
+        current_kid,
+        grace_period_sec: secrets.grace_period().as_secs(),
+        versions,
+    }))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    InsufficientPermissions(#[from] InsufficientPermissionsError),
+
+    #[error("{0}")]
+    ImpersonationRestricted(#[from] ImpersonationRestrictedError),
+
+    #[error("invalid secret name `{0}`")]
+    InvalidName(String),
+
+    #[error("secret `{0}` not found")]
+    SecretNotFound(String),
+
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl Error {
+    fn from_io(key: String, err: std::io::Error) -> Self {
+        match err.kind() {
+            std::io::ErrorKind::InvalidInput => Error::InvalidName(key),
+            std::io::ErrorKind::NotFound => Error::SecretNotFound(key),
+            _ => Error::Io(err),
+        }
+    }
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::InsufficientPermissions(err) => err.kind(),
+            Error::ImpersonationRestricted(err) => err.kind(),
+            Error::InvalidName(_) => "secret.name.invalid",
+            Error::SecretNotFound(_) => "secret.not-found",
+            Error::Io(_) => "io",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::InsufficientPermissions(err) => err.into_response(),
+            Error::ImpersonationRestricted(err) => err.into_response(),
+            Error::InvalidName(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::SecretNotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Io(_) | Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
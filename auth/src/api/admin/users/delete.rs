@@ -0,0 +1,145 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    response::IntoResponse,
+    routing::{MethodRouter, delete},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::StatusCode;
+use serde::Deserialize;
+
+use crate::{
+    AppState,
+    core::{ImpersonationRestrictedError, InsufficientPermissionsError, Principal, delete_user},
+};
+
+pub const PATH: &str = "/admin/users";
+
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+#[derive(Deserialize)]
+pub struct QueryParams {
+    #[cfg_attr(feature = "openapi", param(example = "joe"))]
+    pub username: String,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    delete(handler)
+}
+
+/// Permanently deletes the account along with its sessions, access tokens and permissions.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    delete,
+    path = PATH,
+    operation_id = "delete:/admin/users",
+    params(QueryParams),
+    responses(
+        (status = 200, description = "Account deleted"),
+        (status = 400, description = "Cannot delete own account", body = ErrorResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Insufficient permissions", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin"
+))]
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%principal, %username), skip_all, ret))]
+pub async fn handler(
+    State(AppState { pool, .. }): State<AppState>,
+    principal: Principal,
+    Query(QueryParams { username }): Query<QueryParams>,
+) -> Result<StatusCode, Error> {
+    principal
+        .require_permission::<Error>(&pool, "delete:/admin/users")
+        .await?;
+    principal.forbid_impersonation()?;
+
+    let mut tx = pool
+        .begin_with(crate::core::BEGIN_WRITE)
+        .await
+        .context("begin transaction :: delete user")?;
+
+    let user_id = sqlx::query_scalar!(
+        r#"SELECT id as "id!" FROM users WHERE username = ?"#,
+        username
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .context("username -> user_id")?
+    .ok_or(Error::UserNotFound(username))?;
+
+    if user_id == principal.user_id() {
+        return Err(Error::SelfDelete);
+    }
+
+    delete_user(&mut tx, user_id).await.context("delete user")?;
+
+    tx.commit()
+        .await
+        .context("commit transaction :: delete user")?;
+
+    #[cfg(feature = "tracing")]
+    tracing::info!(user_id, "account deleted");
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    InsufficientPermissions(#[from] InsufficientPermissionsError),
+
+    #[error("{0}")]
+    ImpersonationRestricted(#[from] ImpersonationRestrictedError),
+
+    #[error("user `{0}` not found")]
+    UserNotFound(String),
+
+    #[error("cannot delete your own account")]
+    SelfDelete,
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::InsufficientPermissions(err) => err.kind(),
+            Error::ImpersonationRestricted(err) => err.kind(),
+            Error::UserNotFound(_) => "user.not-found",
+            Error::SelfDelete => "user.self-delete",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::InsufficientPermissions(err) => err.into_response(),
+            Error::ImpersonationRestricted(err) => err.into_response(),
+            Error::UserNotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::SelfDelete => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
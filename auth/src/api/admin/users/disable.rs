@@ -0,0 +1,225 @@
+use axum::{
+    Form, Json,
+    extract::State,
+    response::IntoResponse,
+    routing::{MethodRouter, post},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::StatusCode;
+use serde::Deserialize;
+
+use crate::{
+    AppState,
+    core::{
+        ImpersonationRestrictedError, InsufficientPermissionsError, Principal,
+        log_impersonations_stopped,
+    },
+};
+
+pub const PATH: &str = "/admin/users/disable";
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = admin::users::disable::RequestBody))]
+#[derive(Deserialize)]
+pub struct RequestBody {
+    #[cfg_attr(feature = "openapi", schema(examples("joe")))]
+    pub username: String,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    post(handler)
+}
+
+/// Disables the account and invalidates all of its sessions.
+/// Access tokens are kept, but are rejected for as long as the account stays disabled.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = PATH,
+    operation_id = PATH,
+    request_body(
+        content = RequestBody,
+        content_type = "application/x-www-form-urlencoded",
+    ),
+    responses(
+        (status = 200, description = "Account disabled"),
+        (status = 400, description = "Cannot disable own account", body = ErrorResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Insufficient permissions", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin"
+))]
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%principal, %username), skip_all, ret))]
+pub async fn handler(
+    State(AppState {
+        pool,
+
+        #[cfg(feature = "smtp")]
+        smtp,
+        ..
+    }): State<AppState>,
+    principal: Principal,
+    Form(RequestBody { username }): Form<RequestBody>,
+) -> Result<StatusCode, Error> {
+    principal
+        .require_permission::<Error>(&pool, "post:/admin/users/disable")
+        .await?;
+    principal.forbid_impersonation()?;
+
+    let mut tx = pool
+        .begin_with(crate::core::BEGIN_WRITE)
+        .await
+        .context("begin transaction :: disable user")?;
+
+    let user_id = sqlx::query_scalar!(
+        r#"UPDATE users SET disabled = 1 WHERE username = ? RETURNING id as "id!""#,
+        username
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .context("disable user")?
+    .ok_or_else(|| Error::UserNotFound(username.clone()))?;
+
+    if user_id == principal.user_id() {
+        return Err(Error::SelfDisable);
+    }
+
+    let sessions = sqlx::query!(
+        "DELETE FROM sessions WHERE user_id = ? RETURNING user_id, impersonator_id",
+        user_id
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .context("invalidate sessions")?;
+    let _sessions_invalidated = sessions.len();
+
+    log_impersonations_stopped(
+        &mut tx,
+        sessions
+            .into_iter()
+            .map(|session| (session.user_id, session.impersonator_id)),
+    )
+    .await
+    .context("write impersonation audit log")?;
+
+    #[cfg(feature = "webhooks")]
+    if _sessions_invalidated > 0 {
+        crate::webhook::enqueue(
+            &mut *tx,
+            &crate::webhook::Event::SessionRevoked {
+                user_id,
+                reason: "account.disabled",
+            },
+        )
+        .await?;
+    }
+
+    #[cfg(feature = "smtp")]
+    crate::notification::notify(
+        &mut tx,
+        &smtp,
+        user_id,
+        &crate::notification::AccountLocked { username },
+    )
+    .await?;
+
+    tx.commit()
+        .await
+        .context("commit transaction :: disable user")?;
+
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        user_id,
+        sessions_invalidated = _sessions_invalidated,
+        "account disabled"
+    );
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    InsufficientPermissions(#[from] InsufficientPermissionsError),
+
+    #[error("{0}")]
+    ImpersonationRestricted(#[from] ImpersonationRestrictedError),
+
+    #[error("user `{0}` not found")]
+    UserNotFound(String),
+
+    #[error("cannot disable your own account")]
+    SelfDisable,
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+
+    #[cfg(feature = "webhooks")]
+    #[error("{0}")]
+    Webhook(#[from] crate::webhook::EnqueueError),
+
+    #[cfg(feature = "smtp")]
+    #[error("{0}")]
+    Notify(#[from] crate::notification::NotifyError),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::InsufficientPermissions(err) => err.kind(),
+            Error::ImpersonationRestricted(err) => err.kind(),
+            Error::UserNotFound(_) => "user.not-found",
+            Error::SelfDisable => "user.self-disable",
+            Error::Sqlx(_) => "sqlx",
+            #[cfg(feature = "webhooks")]
+            Error::Webhook(_) => "webhook",
+            #[cfg(feature = "smtp")]
+            Error::Notify(_) => "notification",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::InsufficientPermissions(err) => err.into_response(),
+            Error::ImpersonationRestricted(err) => err.into_response(),
+            Error::UserNotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::SelfDisable => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+            #[cfg(feature = "webhooks")]
+            Error::Webhook(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+            #[cfg(feature = "smtp")]
+            Error::Notify(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
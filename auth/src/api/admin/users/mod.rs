@@ -0,0 +1,154 @@
+pub mod delete;
+pub mod disable;
+pub mod enable;
+pub mod force_email_verification;
+
+use axum::{
+    Json,
+    extract::{Query, State},
+    response::IntoResponse,
+    routing::{MethodRouter, get},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    core::{ImpersonationRestrictedError, InsufficientPermissionsError, Principal},
+};
+
+pub const PATH: &str = "/admin/users";
+
+const DEFAULT_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 100;
+
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+#[derive(Deserialize, Debug)]
+pub struct QueryParams {
+    /// matches anywhere in the username or email
+    #[cfg_attr(feature = "openapi", param(example = "joe"))]
+    pub search: Option<String>,
+
+    #[cfg_attr(feature = "openapi", param(example = true))]
+    pub email_verified: Option<bool>,
+
+    #[cfg_attr(feature = "openapi", param(example = 50))]
+    pub limit: Option<i64>,
+
+    #[cfg_attr(feature = "openapi", param(example = 0))]
+    pub offset: Option<i64>,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = admin::users::User))]
+#[derive(Debug, Serialize)]
+pub struct User {
+    #[cfg_attr(feature = "openapi", schema(examples(1)))]
+    pub id: i64,
+
+    #[cfg_attr(feature = "openapi", schema(examples("joe")))]
+    pub username: String,
+
+    #[cfg_attr(feature = "openapi", schema(examples("joe@smith.com")))]
+    pub email: String,
+
+    pub email_verified: bool,
+
+    pub disabled: bool,
+
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<String>, format = DateTime))]
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub created_at: Option<OffsetDateTime>,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    get(handler)
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = PATH,
+    operation_id = PATH,
+    params(QueryParams),
+    responses(
+        (status = 200, description = "Users", body = Vec<User>),
+        (status = 401, description = "Not authenticated", body = extra::ErrorResponse),
+        (status = 403, description = "Insufficient permissions", body = extra::ErrorResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin"
+))]
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%principal, ?params), skip_all))]
+pub async fn handler(
+    State(AppState { pool, .. }): State<AppState>,
+    principal: Principal,
+    Query(params): Query<QueryParams>,
+) -> Result<Json<Vec<User>>, Error> {
+    principal
+        .require_permission::<Error>(&pool, "get:/admin/users")
+        .await?;
+    principal.forbid_impersonation()?;
+
+    let pattern = params.search.map(|search| format!("%{search}%"));
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(0, MAX_LIMIT);
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    let users = sqlx::query_as!(
+        User,
+        r#"
+        SELECT
+            id as "id!",
+            username,
+            email,
+            email_verified,
+            disabled,
+            created_at as "created_at: OffsetDateTime"
+        FROM users
+        WHERE (?1 IS NULL OR username LIKE ?1 OR email LIKE ?1)
+        AND (?2 IS NULL OR email_verified = ?2)
+        ORDER BY id
+        LIMIT ?3 OFFSET ?4
+        "#,
+        pattern,
+        params.email_verified,
+        limit,
+        offset
+    )
+    .fetch_all(&pool)
+    .await
+    .context("list users")?;
+
+    Ok(Json(users))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    InsufficientPermissions(#[from] InsufficientPermissionsError),
+
+    #[error("{0}")]
+    ImpersonationRestricted(#[from] ImpersonationRestrictedError),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::InsufficientPermissions(err) => err.into_response(),
+            Error::ImpersonationRestricted(err) => err.into_response(),
+            Error::Sqlx(_err) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", _err);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use axum::{
+    Json,
+    extract::State,
+    response::IntoResponse,
+    routing::{MethodRouter, post},
+};
+use axum_macros::debug_handler;
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use contextual::Context;
+use extra::ErrorResponse;
+use http::StatusCode;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    core::{ImpersonationRestrictedError, InsufficientPermissionsError, Principal},
+    webhook::Event,
+};
+
+pub const PATH: &str = "/admin/webhooks";
+
+const SECRET_N_BYTES: usize = 32;
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = admin::webhooks::create::RequestBody))]
+#[derive(Deserialize)]
+pub struct RequestBody {
+    #[cfg_attr(
+        feature = "openapi",
+        schema(examples("https://example.com/hooks/auth"))
+    )]
+    pub url: String,
+
+    /// any of `user.created`, `email.verified`, `session.revoked`,
+    /// `permission.assigned` and `token.created`
+    #[cfg_attr(feature = "openapi", schema(examples(json!(["user.created"]))))]
+    pub events: Vec<String>,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = admin::webhooks::create::ResponseBody))]
+#[derive(Serialize)]
+pub struct ResponseBody {
+    #[cfg_attr(feature = "openapi", schema(examples(1)))]
+    pub id: i64,
+
+    /// key of the HMAC-SHA256 `Webhook-Signature`; it is not shown again
+    pub secret: String,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    post(handler)
+}
+
+/// Subscribes a URL to identity events.
+/// Deliveries are signed with the returned secret, see the `Webhook-Signature` header.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = PATH,
+    operation_id = "post:/admin/webhooks",
+    request_body = RequestBody,
+    responses(
+        (status = 201, description = "Webhook created", body = ResponseBody),
+        (status = 400, description = "Invalid URL or unknown event", body = ErrorResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Insufficient permissions", body = ErrorResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin"
+))]
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%principal, url = %request_body.url), skip_all))]
+pub async fn handler(
+    State(AppState { pool, .. }): State<AppState>,
+    principal: Principal,
+    Json(request_body): Json<RequestBody>,
+) -> Result<(StatusCode, Json<ResponseBody>), Error> {
+    principal
+        .require_permission::<Error>(&pool, "post:/admin/webhooks")
+        .await?;
+    principal.forbid_impersonation()?;
+
+    let RequestBody { url, events } = request_body;
+
+    if !(url.starts_with("https://") || url.starts_with("http://")) {
+        return Err(Error::InvalidUrl(url));
+    }
+
+    if events.is_empty() {
+        return Err(Error::NoEvents);
+    }
+
+    if let Some(event) = events
+        .iter()
+        .find(|event| !Event::NAMES.contains(&event.as_str()))
+    {
+        return Err(Error::UnknownEvent(event.clone()));
+    }
+
+    let secret = {
+        let mut buf = [0u8; SECRET_N_BYTES];
+        rand::rng().fill_bytes(&mut buf);
+        format!("whsec_{}", BASE64_URL_SAFE_NO_PAD.encode(buf))
+    };
+    let now = OffsetDateTime::now_utc();
+
+    let mut tx = pool
+        .begin_with(crate::core::BEGIN_WRITE)
+        .await
+        .context("begin transaction :: create webhook")?;
+
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO webhooks (url, secret, created_at)
+        VALUES (?, ?, ?)
+        RETURNING id as "id!"
+        "#,
+        url,
+        secret,
+        now
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .context("insert webhook")?;
+
+    for event in &events {
+        sqlx::query!(
+            r#"
+            INSERT INTO webhook_events (webhook_id, event)
+            VALUES (?, ?)
+            ON CONFLICT (webhook_id, event) DO NOTHING
+            "#,
+            id,
+            event
+        )
+        .execute(&mut *tx)
+        .await
+        .context("insert webhook event")?;
+    }
+
+    tx.commit()
+        .await
+        .context("commit transaction :: create webhook")?;
+
+    #[cfg(feature = "tracing")]
+    tracing::info!(id, ?events, "webhook created");
+
+    Ok((StatusCode::CREATED, Json(ResponseBody { id, secret })))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    InsufficientPermissions(#[from] InsufficientPermissionsError),
+
+    #[error("{0}")]
+    ImpersonationRestricted(#[from] ImpersonationRestrictedError),
+
+    #[error("`{0}` is not an http(s) URL")]
+    InvalidUrl(String),
+
+    #[error("at least one event is required")]
+    NoEvents,
+
+    #[error("unknown event `{0}`")]
+    UnknownEvent(String),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::InsufficientPermissions(err) => err.kind(),
+            Error::ImpersonationRestricted(err) => err.kind(),
+            Error::InvalidUrl(_) => "webhook.url.invalid",
+            Error::NoEvents => "webhook.events.empty",
+            Error::UnknownEvent(_) => "webhook.event.unknown",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::InsufficientPermissions(err) => err.into_response(),
+            Error::ImpersonationRestricted(err) => err.into_response(),
+            Error::InvalidUrl(_) | Error::NoEvents | Error::UnknownEvent(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
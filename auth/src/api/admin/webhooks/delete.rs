@@ -0,0 +1,116 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    response::IntoResponse,
+    routing::{MethodRouter, delete},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::StatusCode;
+use serde::Deserialize;
+
+use crate::{
+    AppState,
+    core::{ImpersonationRestrictedError, InsufficientPermissionsError, Principal},
+};
+
+pub const PATH: &str = "/admin/webhooks";
+
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+#[derive(Deserialize)]
+pub struct QueryParams {
+    #[cfg_attr(feature = "openapi", param(example = 1))]
+    pub id: i64,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    delete(handler)
+}
+
+/// Deletes the webhook along with its pending deliveries and delivery log.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    delete,
+    path = PATH,
+    operation_id = "delete:/admin/webhooks",
+    params(QueryParams),
+    responses(
+        (status = 200, description = "Webhook deleted"),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Insufficient permissions", body = ErrorResponse),
+        (status = 404, description = "Webhook not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin"
+))]
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%principal, %id), skip_all, ret))]
+pub async fn handler(
+    State(AppState { pool, .. }): State<AppState>,
+    principal: Principal,
+    Query(QueryParams { id }): Query<QueryParams>,
+) -> Result<StatusCode, Error> {
+    principal
+        .require_permission::<Error>(&pool, "delete:/admin/webhooks")
+        .await?;
+    principal.forbid_impersonation()?;
+
+    let result = sqlx::query!("DELETE FROM webhooks WHERE id = ?", id)
+        .execute(&pool)
+        .await
+        .context("delete webhook")?;
+
+    if result.rows_affected() == 0 {
+        return Err(Error::WebhookNotFound(id));
+    }
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    InsufficientPermissions(#[from] InsufficientPermissionsError),
+
+    #[error("{0}")]
+    ImpersonationRestricted(#[from] ImpersonationRestrictedError),
+
+    #[error("webhook `{0}` not found")]
+    WebhookNotFound(i64),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::InsufficientPermissions(err) => err.kind(),
+            Error::ImpersonationRestricted(err) => err.kind(),
+            Error::WebhookNotFound(_) => "webhook.not-found",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::InsufficientPermissions(err) => err.into_response(),
+            Error::ImpersonationRestricted(err) => err.into_response(),
+            Error::WebhookNotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
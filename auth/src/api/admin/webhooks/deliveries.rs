@@ -0,0 +1,169 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    response::IntoResponse,
+    routing::{MethodRouter, get},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    core::{ImpersonationRestrictedError, InsufficientPermissionsError, Principal},
+};
+
+pub const PATH: &str = "/admin/webhooks/deliveries";
+
+const DEFAULT_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 100;
+
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+#[derive(Deserialize, Debug)]
+pub struct QueryParams {
+    #[cfg_attr(feature = "openapi", param(example = 1))]
+    pub webhook_id: Option<i64>,
+
+    /// one of `pending`, `delivered` or `failed`
+    #[cfg_attr(feature = "openapi", param(example = "failed"))]
+    pub status: Option<String>,
+
+    #[cfg_attr(feature = "openapi", param(example = 50))]
+    pub limit: Option<i64>,
+
+    #[cfg_attr(feature = "openapi", param(example = 0))]
+    pub offset: Option<i64>,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = admin::webhooks::deliveries::Delivery))]
+#[derive(Debug, Serialize)]
+pub struct Delivery {
+    #[cfg_attr(feature = "openapi", schema(examples(1)))]
+    pub id: i64,
+
+    #[cfg_attr(feature = "openapi", schema(examples(1)))]
+    pub webhook_id: i64,
+
+    #[cfg_attr(feature = "openapi", schema(examples("user.created")))]
+    pub event: String,
+
+    /// the JSON body that is POSTed to the webhook
+    pub payload: String,
+
+    #[cfg_attr(feature = "openapi", schema(examples("delivered")))]
+    pub status: String,
+
+    pub attempts: i64,
+
+    pub last_error: Option<String>,
+
+    /// status code of the receiver's last response
+    pub response_status: Option<i64>,
+
+    #[cfg_attr(feature = "openapi", schema(value_type = String, format = DateTime))]
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+
+    #[cfg_attr(feature = "openapi", schema(value_type = String, format = DateTime))]
+    #[serde(with = "time::serde::rfc3339")]
+    pub next_attempt_at: OffsetDateTime,
+
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<String>, format = DateTime))]
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub delivered_at: Option<OffsetDateTime>,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    get(handler)
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = PATH,
+    operation_id = PATH,
+    params(QueryParams),
+    responses(
+        (status = 200, description = "Webhook deliveries, newest first", body = Vec<Delivery>),
+        (status = 401, description = "Not authenticated", body = extra::ErrorResponse),
+        (status = 403, description = "Insufficient permissions", body = extra::ErrorResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin"
+))]
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%principal, ?params), skip_all))]
+pub async fn handler(
+    State(AppState { pool, .. }): State<AppState>,
+    principal: Principal,
+    Query(params): Query<QueryParams>,
+) -> Result<Json<Vec<Delivery>>, Error> {
+    principal
+        .require_permission::<Error>(&pool, "get:/admin/webhooks/deliveries")
+        .await?;
+    principal.forbid_impersonation()?;
+
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(0, MAX_LIMIT);
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    let deliveries = sqlx::query_as!(
+        Delivery,
+        r#"
+        SELECT
+            id as "id!",
+            webhook_id,
+            event,
+            payload,
+            status,
+            attempts,
+            last_error,
+            response_status,
+            created_at as "created_at: OffsetDateTime",
+            next_attempt_at as "next_attempt_at: OffsetDateTime",
+            delivered_at as "delivered_at: OffsetDateTime"
+        FROM webhook_deliveries
+        WHERE (?1 IS NULL OR webhook_id = ?1) AND (?2 IS NULL OR status = ?2)
+        ORDER BY id DESC
+        LIMIT ?3 OFFSET ?4
+        "#,
+        params.webhook_id,
+        params.status,
+        limit,
+        offset
+    )
+    .fetch_all(&pool)
+    .await
+    .context("list webhook deliveries")?;
+
+    Ok(Json(deliveries))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    InsufficientPermissions(#[from] InsufficientPermissionsError),
+
+    #[error("{0}")]
+    ImpersonationRestricted(#[from] ImpersonationRestrictedError),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::InsufficientPermissions(err) => err.into_response(),
+            Error::ImpersonationRestricted(err) => err.into_response(),
+            Error::Sqlx(_err) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", _err);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
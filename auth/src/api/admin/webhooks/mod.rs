@@ -0,0 +1,131 @@
+pub mod create;
+pub mod delete;
+pub mod deliveries;
+
+use axum::{
+    Json,
+    extract::State,
+    response::IntoResponse,
+    routing::{MethodRouter, get},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use http::StatusCode;
+use serde::Serialize;
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    core::{ImpersonationRestrictedError, InsufficientPermissionsError, Principal},
+};
+
+pub const PATH: &str = "/admin/webhooks";
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = admin::webhooks::Webhook))]
+#[derive(Debug, Serialize)]
+pub struct Webhook {
+    #[cfg_attr(feature = "openapi", schema(examples(1)))]
+    pub id: i64,
+
+    #[cfg_attr(
+        feature = "openapi",
+        schema(examples("https://example.com/hooks/auth"))
+    )]
+    pub url: String,
+
+    #[cfg_attr(feature = "openapi", schema(examples(json!(["user.created", "token.created"]))))]
+    pub events: Vec<String>,
+
+    #[cfg_attr(feature = "openapi", schema(value_type = String, format = DateTime))]
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    get(handler)
+}
+
+/// Lists the webhook subscriptions. Secrets are only shown once, when the webhook is created.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = PATH,
+    operation_id = PATH,
+    responses(
+        (status = 200, description = "Webhook subscriptions", body = Vec<Webhook>),
+        (status = 401, description = "Not authenticated", body = extra::ErrorResponse),
+        (status = 403, description = "Insufficient permissions", body = extra::ErrorResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "admin"
+))]
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%principal), skip_all))]
+pub async fn handler(
+    State(AppState { pool, .. }): State<AppState>,
+    principal: Principal,
+) -> Result<Json<Vec<Webhook>>, Error> {
+    principal
+        .require_permission::<Error>(&pool, "get:/admin/webhooks")
+        .await?;
+    principal.forbid_impersonation()?;
+
+    let webhooks = sqlx::query!(
+        r#"
+        SELECT
+            w.id as "id!",
+            w.url,
+            w.created_at as "created_at: OffsetDateTime",
+            GROUP_CONCAT(e.event, ' ') as "events: String"
+        FROM webhooks w
+        LEFT JOIN webhook_events e ON e.webhook_id = w.id
+        GROUP BY w.id
+        ORDER BY w.id
+        "#
+    )
+    .fetch_all(&pool)
+    .await
+    .context("list webhooks")?
+    .into_iter()
+    .map(|record| Webhook {
+        id: record.id,
+        url: record.url,
+        events: record
+            .events
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(str::to_string)
+            .collect(),
+        created_at: record.created_at,
+    })
+    .collect();
+
+    Ok(Json(webhooks))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    InsufficientPermissions(#[from] InsufficientPermissionsError),
+
+    #[error("{0}")]
+    ImpersonationRestricted(#[from] ImpersonationRestrictedError),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::InsufficientPermissions(err) => err.into_response(),
+            Error::ImpersonationRestricted(err) => err.into_response(),
+            Error::Sqlx(_err) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", _err);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -3,6 +3,7 @@ use std::str::FromStr;
 use axum::{
     Form, Json,
     extract::State,
+    http::header::RETRY_AFTER,
     response::IntoResponse,
     routing::{MethodRouter, post},
 };
@@ -15,9 +16,9 @@ use http::StatusCode;
 use serde::Deserialize;
 
 use super::{
-    SendVerificationEmailError, send_verification_email, verification_link, verification_token,
+    issue_verification_token, last_verification_issued_at, verification_email, verification_link,
 };
-use crate::AppState;
+use crate::{AppState, outbox};
 
 pub const PATH: &str = "/initiate-email-verification";
 
@@ -42,8 +43,9 @@ pub fn method_router() -> MethodRouter<AppState> {
         content_type = "application/x-www-form-urlencoded",
     ),
     responses(
-        (status = 200, description = "Verification email sent successfully"),
+        (status = 200, description = "Verification email queued for delivery"),
         (status = 400, description = "Invalid email address or request"),
+        (status = 429, description = "Verification email sent too recently, see `Retry-After`", body = ErrorResponse),
         (status = 500, description = "Internal server error"),
     ),
     tag = "email"
@@ -63,7 +65,7 @@ pub async fn handler(
     let email = Email::from_str(&email).map_err(Error::InvalidEmailFormat)?;
 
     let record = sqlx::query!(
-        r#"SELECT email_verified FROM users WHERE email = ? LIMIT 1"#,
+        r#"SELECT email_verified, locale FROM users WHERE email = ? LIMIT 1"#,
         email
     )
     .fetch_optional(&pool)
@@ -81,26 +83,47 @@ pub async fn handler(
         return Ok(StatusCode::OK);
     }
 
-    let hmac_secret = secrets.get("hmac").context("get HMAC key")?;
-    let verification_token = verification_token(email.clone());
-    let verification_link = verification_link(&hmac_secret, &host, &verification_token)
-        .context("base64 encode email verification link")?;
+    let mut tx = pool
+        .begin_with(crate::core::BEGIN_WRITE)
+        .await
+        .context("begin transaction")?;
 
-    let response = send_verification_email(&smtp, &email, &verification_link).await?;
-    match response.is_positive() {
-        true => {
-            #[cfg(feature = "tracing")]
-            tracing::info!("{response:?}");
+    let last_issued_at = last_verification_issued_at(&mut *tx, &email)
+        .await
+        .context("last verification issued at")?;
 
-            Ok(StatusCode::OK)
-        }
-        false => {
-            #[cfg(feature = "tracing")]
-            tracing::warn!("{response:?}");
+    if let Some(last_issued_at) = last_issued_at {
+        let available_at = last_issued_at + smtp.verification_resend_interval;
+        let now = time::OffsetDateTime::now_utc();
 
-            Ok(StatusCode::SERVICE_UNAVAILABLE)
+        if available_at > now {
+            let retry_after = (available_at - now).whole_seconds().max(1) as u64;
+            return Err(Error::ResendThrottled { retry_after });
         }
     }
+
+    let verification_token = issue_verification_token(&mut tx, email.clone())
+        .await
+        .context("issue verification token")?;
+
+    let signing_key = crate::signing::current(&secrets)?;
+    let verification_link = verification_link(&signing_key, &host, verification_token)
+        .context("base64 encode email verification link")?;
+
+    let message = verification_email(&smtp, record.locale.as_deref(), &email, &verification_link)
+        .context("render verification email")?;
+    let _outbox_id = outbox::enqueue(&mut *tx, &message)
+        .await
+        .context("enqueue verification email")?;
+
+    tx.commit()
+        .await
+        .context("commit transaction :: initiate email verification")?;
+
+    #[cfg(feature = "tracing")]
+    tracing::info!(outbox_id = _outbox_id, "verification email queued");
+
+    Ok(StatusCode::OK)
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -111,11 +134,17 @@ pub enum Error {
     #[error("email `{0}` not associated with any user")]
     UnAssociatedEmail(Email),
 
+    #[error("verification email sent too recently, retry after {retry_after} seconds")]
+    ResendThrottled { retry_after: u64 },
+
     #[error("{0}")]
     TokenEncodeError(#[from] contextual::Error<signature::EncodeError>),
 
     #[error("{0}")]
-    SendVerificationEmail(#[from] SendVerificationEmailError),
+    SigningKey(#[from] crate::signing::SigningKeyError),
+
+    #[error("{0}")]
+    EmailTemplate(#[from] contextual::Error<tera::Error>),
 
     #[error("{0}")]
     Io(#[from] contextual::Error<std::io::Error>),
@@ -129,8 +158,10 @@ impl extra::ErrorKind for Error {
         match self {
             Error::InvalidEmailFormat(_) => "email.invalid",
             Error::UnAssociatedEmail(_) => "email.unassociated",
+            Error::ResendThrottled { .. } => "email.verification.throttled",
             Error::TokenEncodeError(_) => "email.verification.token.encode",
-            Error::SendVerificationEmail(_) => "email.verification.send",
+            Error::SigningKey(_) => "email.verification.signing-key",
+            Error::EmailTemplate(_) => "email.verification.email-template",
             Error::Io(_) => "email.verification.io",
             Error::Sqlx(_) => "email.verification.sqlx",
         }
@@ -152,8 +183,22 @@ impl IntoResponse for Error {
 
                 (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
             }
-            Error::SendVerificationEmail(err) => err.into_response(),
-            Error::TokenEncodeError(_) | Error::Io(_) | Error::Sqlx(_) => {
+            Error::ResendThrottled { retry_after } => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(RETRY_AFTER, retry_after.to_string())],
+                    Json(ErrorResponse::from(self)),
+                )
+                    .into_response()
+            }
+            Error::TokenEncodeError(_)
+            | Error::SigningKey(_)
+            | Error::EmailTemplate(_)
+            | Error::Io(_)
+            | Error::Sqlx(_) => {
                 #[cfg(feature = "tracing")]
                 tracing::error!("{:?}", self);
 
@@ -9,120 +9,130 @@ pub mod initiate_verification;
 use email::Email;
 use sqlx::{Executor, Sqlite};
 
+/// Claims of an email verification token.
+#[cfg(feature = "smtp")]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct VerificationClaims {
+    pub email: Email,
+}
+
+/// Links sent before verification tokens were JWTs carry the bare address as their payload.
+#[cfg(feature = "smtp")]
+impl TryFrom<Vec<u8>> for VerificationClaims {
+    type Error = email::ParseError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
+        Email::try_from(bytes).map(|email| Self { email })
+    }
+}
+
+/// Signs the token as a JWT with the current signing key, see [`crate::signing::current`],
+/// recording its `kid` so the link survives a key rotation.
 #[cfg(feature = "smtp")]
 pub fn verification_link(
-    secret: &[u8],
+    (kid, key): &(String, signature::SigningKey),
     host: &str,
-    token: &signature::Signed<Email>,
+    token: signature::Signed<VerificationClaims>,
 ) -> Result<String, signature::EncodeError> {
     Ok(format!(
         "{host}/{}?token={}",
         verify_email::PATH,
-        token.encode(secret)?
+        token.with_kid(kid.clone()).encode_jws(key)?
     ))
 }
 
 #[cfg(feature = "smtp")]
-pub fn verification_token(email: Email) -> signature::Signed<Email> {
-    signature::Signed::new(email).with_ttl(std::time::Duration::from_secs(60 * 60))
-}
+const VERIFICATION_TOKEN_TTL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
 
+/// Audience of verification tokens, so that no other token signed with the same key passes as one.
 #[cfg(feature = "smtp")]
-pub async fn send_verification_email(
-    smtp: &crate::smtp::Smtp,
-    email: &Email,
-    verification_link: &str,
-) -> Result<lettre::transport::smtp::response::Response, SendVerificationEmailError> {
-    use contextual::Context;
-    use lettre::{
-        AsyncTransport, Message,
-        message::{Mailbox, MultiPart},
-    };
+const VERIFICATION_AUDIENCE: &str = "email-verification";
 
-    let message = {
-        let noreply: Email = smtp
-            .senders
-            .get("noreply")
-            .await
-            .context("SmtpSenders::get `noreply`")?;
-
-        let from = Mailbox::new(Some("noreply".into()), noreply.into());
-        let to = Mailbox::new(None, email.clone().into());
-
-        let subject = "Verify your Email";
-
-        let plain_text_content = format!("verfication link: {verification_link}");
-        let html_content = {
-            let mut context = tera::Context::new();
-            context.insert("verification_link", &verification_link);
-            smtp.tera
-                .render("verify-email.html", &context)
-                .context("render verify-email template")?
-        };
-
-        Message::builder()
-            .from(from)
-            .to(to)
-            .subject(subject)
-            .multipart(MultiPart::alternative_plain_html(
-                plain_text_content,
-                html_content,
-            ))
-            .context("verify-email message builder")?
-    };
+/// Issues a single-use verification token for `email`.
+/// Tokens previously issued for the same email stop working.
+#[cfg(feature = "smtp")]
+pub async fn issue_verification_token(
+    conn: &mut sqlx::SqliteConnection,
+    email: Email,
+) -> Result<signature::Signed<VerificationClaims>, sqlx::Error> {
+    let jti = token::Token::<16>::random().base64encoded();
+    let issued_at = time::OffsetDateTime::now_utc();
+    let expires_at = issued_at + VERIFICATION_TOKEN_TTL;
+
+    sqlx::query!(
+        "DELETE FROM email_verification_tokens WHERE email = ?",
+        email
+    )
+    .execute(&mut *conn)
+    .await?;
 
-    let response = smtp
-        .transport
-        .send(message)
-        .await
-        .context("send verification email")?;
+    sqlx::query!(
+        r#"
+        INSERT INTO email_verification_tokens (jti, email, issued_at, expires_at)
+        VALUES (?, ?, ?, ?)
+        "#,
+        jti,
+        email,
+        issued_at,
+        expires_at
+    )
+    .execute(&mut *conn)
+    .await?;
 
-    Ok(response)
+    Ok(signature::Signed::new(VerificationClaims { email })
+        .with_ttl(VERIFICATION_TOKEN_TTL)
+        .with_audience(VERIFICATION_AUDIENCE)
+        .with_jti(jti))
 }
 
+/// Consumes the verification token identified by `jti`.
+/// Returns `false` if it was never issued for `email`, was already used or has been superseded.
 #[cfg(feature = "smtp")]
-#[derive(thiserror::Error, Debug)]
-pub enum SendVerificationEmailError {
-    #[error("{0}")]
-    SmtpSenders(#[from] contextual::Error<crate::smtp::SmtpSendersError>),
-
-    #[error("{0}")]
-    EmailTemplate(#[from] contextual::Error<tera::Error>),
-
-    #[error("{0}")]
-    EmailContent(#[from] contextual::Error<lettre::error::Error>),
+pub async fn consume_verification_token<'a, E: Executor<'a, Database = Sqlite>>(
+    ex: E,
+    jti: &str,
+    email: &Email,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "DELETE FROM email_verification_tokens WHERE jti = ? AND email = ?",
+        jti,
+        email
+    )
+    .execute(ex)
+    .await?;
 
-    #[error("{0}")]
-    SmtpTransport(#[from] contextual::Error<lettre::transport::smtp::Error>),
+    Ok(result.rows_affected() > 0)
 }
 
+/// When the latest verification token was issued for `email`, if any.
 #[cfg(feature = "smtp")]
-impl extra::ErrorKind for SendVerificationEmailError {
-    fn kind(&self) -> &'static str {
-        match self {
-            SendVerificationEmailError::SmtpSenders(_) => "email.verification.smtp-senders",
-            SendVerificationEmailError::EmailTemplate(_) => "email.verification.email-template",
-            SendVerificationEmailError::EmailContent(_) => "email.verification.email-content",
-            SendVerificationEmailError::SmtpTransport(_) => "email.verification.smtp-transport",
-        }
-    }
+pub async fn last_verification_issued_at<'a, E: Executor<'a, Database = Sqlite>>(
+    ex: E,
+    email: &Email,
+) -> Result<Option<time::OffsetDateTime>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT MAX(issued_at) as "issued_at?: time::OffsetDateTime"
+        FROM email_verification_tokens WHERE email = ?
+        "#,
+        email
+    )
+    .fetch_one(ex)
+    .await
 }
 
 #[cfg(feature = "smtp")]
-impl axum::response::IntoResponse for SendVerificationEmailError {
-    fn into_response(self) -> axum::response::Response {
-        match self {
-            SendVerificationEmailError::SmtpSenders(_)
-            | SendVerificationEmailError::EmailTemplate(_)
-            | SendVerificationEmailError::EmailContent(_)
-            | SendVerificationEmailError::SmtpTransport(_) => {
-                #[cfg(feature = "tracing")]
-                tracing::error!("{:?}", self);
-
-                http::StatusCode::INTERNAL_SERVER_ERROR.into_response()
-            }
-        }
-    }
+pub fn verification_email<'a>(
+    smtp: &crate::smtp::Smtp,
+    locale: Option<&str>,
+    email: &'a Email,
+    verification_link: &str,
+) -> Result<crate::outbox::OutgoingEmail<'a>, tera::Error> {
+    let notification = crate::notification::VerifyEmail {
+        verification_link: verification_link.to_string(),
+    };
+
+    smtp.render_notification(locale, email, &notification)
 }
 
 pub async fn exists<'a, E: Executor<'a, Database = Sqlite>>(
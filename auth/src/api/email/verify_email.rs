@@ -1,17 +1,24 @@
 use axum::{
     Json,
     extract::{Query, State},
-    response::IntoResponse,
+    http::{
+        HeaderMap,
+        header::{ACCEPT, USER_AGENT},
+    },
+    response::{Html, IntoResponse, Response},
     routing::{MethodRouter, get},
 };
+use axum_extra::extract::CookieJar;
 use axum_macros::debug_handler;
 use contextual::Context;
-use email::Email;
 use extra::ErrorResponse;
 use http::StatusCode;
 use serde::Deserialize;
 
-use crate::AppState;
+use super::{VERIFICATION_AUDIENCE, VerificationClaims, consume_verification_token};
+use crate::{
+    AppState, api::login::start_session, core::preferred_locale, notification::VerifyEmailPage,
+};
 
 pub const PATH: &str = "/verify-email";
 
@@ -32,35 +39,168 @@ pub fn method_router() -> MethodRouter<AppState> {
     operation_id = PATH,
     params(QueryParams),
     responses(
-        (status = 200, description = "Email verified successfully"),
+        (status = 200, description = "Email verified successfully, session cookie set when login after verification is enabled. Renders an HTML page unless `Accept: application/json` is sent"),
         (status = 400, description = "Invalid or malformed token", body = ErrorResponse),
         (status = 404, description = "Token not found", body = ErrorResponse),
-        (status = 410, description = "Token expired", body = ErrorResponse),
+        (status = 410, description = "Token already used or superseded by a newer one", body = ErrorResponse),
         (status = 500, description = "Internal server error"),
     ),
     tag = "email"
 ))]
-#[cfg_attr(feature = "tracing", tracing::instrument(fields(email = tracing::field::Empty), skip_all, ret))]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(email = tracing::field::Empty), skip_all))]
 #[debug_handler]
 pub async fn handler(
-    State(AppState { pool, secrets, .. }): State<AppState>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    jar: CookieJar,
     Query(QueryParams {
         token: token_base64_encoded,
     }): Query<QueryParams>,
-) -> Result<StatusCode, Error> {
-    let hmac_secret = secrets.get("hmac").context("get HMAC key")?;
-    let signed_token = signature::Signed::<Email>::decode(&token_base64_encoded, &hmac_secret)?;
-    let email = signed_token.token()?;
+) -> Response {
+    let result = verify(&state, &headers, jar, &token_base64_encoded).await;
+
+    let accepts_json = headers
+        .get(ACCEPT)
+        .and_then(|val| val.to_str().ok())
+        .is_some_and(|val| val.contains("application/json"));
+
+    if accepts_json {
+        return result.into_response();
+    }
+
+    let (template, status) = match &result {
+        Ok(_) => (VerifyEmailPage::SUCCESS, StatusCode::OK),
+        Err(Error::TokenValidity(signature::ValidationError::Temporal(_)))
+        | Err(Error::TokenDecode(signature::DecodeError::UnknownKey(_))) => {
+            (VerifyEmailPage::EXPIRED, StatusCode::GONE)
+        }
+        Err(Error::TokenUsed) => (VerifyEmailPage::INVALID, StatusCode::GONE),
+        Err(Error::TokenValidity(_)) => (VerifyEmailPage::INVALID, StatusCode::BAD_REQUEST),
+        Err(Error::TokenDecode(err))
+            if !matches!(err, signature::DecodeError::InvalidKeyLength) =>
+        {
+            (VerifyEmailPage::INVALID, StatusCode::BAD_REQUEST)
+        }
+        Err(_) => return result.into_response(),
+    };
+
+    let page = VerifyEmailPage {
+        redirect_url: state.smtp.verification_redirect_url.clone(),
+    };
+    let locale = preferred_locale(&headers);
+    let page = match tera::Context::from_serialize(page)
+        .and_then(|context| state.smtp.render(locale.as_deref(), template, &context))
+    {
+        Ok(page) => page,
+        Err(_err) => {
+            #[cfg(feature = "tracing")]
+            tracing::error!("render {template}: {:?}", _err);
+
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    match result {
+        Ok((jar, _)) => (status, jar, Html(page)).into_response(),
+        Err(_err) => {
+            #[cfg(feature = "tracing")]
+            tracing::info!("{:?}", _err);
+
+            (status, Html(page)).into_response()
+        }
+    }
+}
+
+async fn verify(
+    AppState {
+        pool,
+        secrets,
+        signup,
+        ..
+    }: &AppState,
+    headers: &HeaderMap,
+    jar: CookieJar,
+    token_base64_encoded: &str,
+) -> Result<(CookieJar, StatusCode), Error> {
+    let mut key_lookup = Ok(None);
+    // links sent before verification tokens were JWTs are still accepted
+    let decoded = signature::Signed::<VerificationClaims>::decode_any_with_keys(
+        token_base64_encoded,
+        |kid| {
+            key_lookup = crate::signing::verifying_key(secrets, kid);
+            key_lookup.as_ref().ok().cloned().flatten()
+        },
+    );
+    key_lookup?;
+    let signed_token = decoded?;
+
+    // Links sent before the cutover to the signing key were signed with the HMAC secret, which
+    // issues no tokens anymore. They carry no audience and, if older than single-use tokens, no
+    // jti either, the latter stay valid until they expire.
+    let issued_before_cutover = signed_token.alg() == signature::Algorithm::HS256
+        && signed_token.audiences().is_empty();
+    let validation = match issued_before_cutover {
+        true => signature::Validation::default(),
+        false => signature::Validation::audience(VERIFICATION_AUDIENCE).require_jti(),
+    };
+
+    let jti = signed_token.jti().map(str::to_owned);
+    let email = signed_token.token(&validation)?.email;
 
     #[cfg(feature = "tracing")]
     tracing::Span::current().record("email", tracing::field::display(&email));
 
-    sqlx::query!("UPDATE users SET email_verified = 1 WHERE email = ?", email)
-        .execute(&pool)
+    let mut tx = pool
+        .begin_with(crate::core::BEGIN_WRITE)
+        .await
+        .context("begin transaction")?;
+
+    if let Some(jti) = &jti
+        && !consume_verification_token(&mut *tx, jti, &email)
+            .await
+            .context("consume verification token")?
+    {
+        return Err(Error::TokenUsed);
+    }
+
+    let user = sqlx::query!(
+        r#"
+        UPDATE users SET email_verified = 1 WHERE email = ?
+        RETURNING id as "id!", disabled
+        "#,
+        email
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .context("user email_verified")?;
+
+    #[cfg(feature = "webhooks")]
+    if user.is_some() {
+        crate::webhook::enqueue(
+            &mut *tx,
+            &crate::webhook::Event::EmailVerified {
+                email: email.to_string(),
+            },
+        )
+        .await?;
+    }
+
+    let jar = match user {
+        Some(user) if signup.login_after_email_verification && !user.disabled => {
+            let user_agent = headers.get(USER_AGENT).and_then(|val| val.to_str().ok());
+            let session_cookie = start_session(&mut tx, user.id, user_agent)
+                .await
+                .context("start session")?;
+            jar.add(session_cookie)
+        }
+        _ => jar,
+    };
+
+    tx.commit()
         .await
-        .context("user email_verified")?;
+        .context("commit transaction :: verify email")?;
 
-    Ok(StatusCode::OK)
+    Ok((jar, StatusCode::OK))
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -69,22 +209,32 @@ pub enum Error {
     TokenDecode(#[from] signature::DecodeError<email::ParseError>),
 
     #[error("{0}")]
-    TemporalTokenValidity(#[from] signature::TemporalValidityError),
+    TokenValidity(#[from] signature::ValidationError),
+
+    #[error("verification token already used or superseded")]
+    TokenUsed,
 
     #[error("{0}")]
-    Io(#[from] contextual::Error<std::io::Error>),
+    SigningKey(#[from] crate::signing::SigningKeyError),
 
     #[error("{0}")]
     Sqlx(#[from] contextual::Error<sqlx::Error>),
+
+    #[cfg(feature = "webhooks")]
+    #[error("{0}")]
+    Webhook(#[from] crate::webhook::EnqueueError),
 }
 
 impl extra::ErrorKind for Error {
     fn kind(&self) -> &'static str {
         match self {
             Error::TokenDecode(_) => "token.decode",
-            Error::TemporalTokenValidity(_) => "token.validity",
-            Error::Io(_) => "io",
+            Error::TokenValidity(_) => "token.validity",
+            Error::TokenUsed => "token.used",
+            Error::SigningKey(_) => "signing-key",
             Error::Sqlx(_) => "sqlx",
+            #[cfg(feature = "webhooks")]
+            Error::Webhook(_) => "webhook",
         }
     }
 }
@@ -106,7 +256,10 @@ impl IntoResponse for Error {
                     )
                         .into_response()
                 }
-                signature::DecodeError::MacMismatch(_)
+                signature::DecodeError::AlgorithmMismatch { .. }
+                | signature::DecodeError::MacMismatch(_)
+                | signature::DecodeError::SignatureMismatch
+                | signature::DecodeError::UnsupportedHeader(_)
                 | signature::DecodeError::NonUTF8(_)
                 | signature::DecodeError::Serde(_)
                 | signature::DecodeError::Base64(_)
@@ -123,6 +276,19 @@ impl IntoResponse for Error {
                     )
                         .into_response()
                 }
+                signature::DecodeError::UnknownKey(_) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::info!("{:?}", decode_error);
+
+                    (
+                        StatusCode::GONE,
+                        Json(ErrorResponse::new(
+                            "Verification token signed with a retired key",
+                            "token.key.retired",
+                        )),
+                    )
+                        .into_response()
+                }
                 signature::DecodeError::InvalidKeyLength => {
                     #[cfg(feature = "tracing")]
                     tracing::error!("{:?}", decode_error);
@@ -130,7 +296,7 @@ impl IntoResponse for Error {
                     StatusCode::INTERNAL_SERVER_ERROR.into_response()
                 }
             },
-            Error::TemporalTokenValidity(err) => {
+            Error::TokenValidity(signature::ValidationError::Temporal(err)) => {
                 #[cfg(feature = "tracing")]
                 tracing::error!("{:?}", err);
 
@@ -143,7 +309,33 @@ impl IntoResponse for Error {
                 )
                     .into_response()
             }
-            Error::Io(_) | Error::Sqlx(_) => {
+            Error::TokenValidity(_err) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", _err);
+
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse::new(
+                        "Invalid Verification Token",
+                        "token.invalid",
+                    )),
+                )
+                    .into_response()
+            }
+            Error::TokenUsed => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::GONE, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::SigningKey(_) | Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+            #[cfg(feature = "webhooks")]
+            Error::Webhook(_) => {
                 #[cfg(feature = "tracing")]
                 tracing::error!("{:?}", self);
 
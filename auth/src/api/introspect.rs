@@ -1,25 +1,123 @@
-// TODO
+use std::collections::BTreeMap;
+
+use axum::{
+    Json,
+    extract::State,
+    response::IntoResponse,
+    routing::{MethodRouter, post},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::{AppState, core::Principal};
 
 pub const PATH: &str = "/introspect";
 
-/*
- Request
- {
-     "permissions": [
-         "/sysinfo",
-         "/access-token/generate"
-     ]
- }
-
- Response
- {
-     "permissions": {
-         "/sysinfo": false,
-         "/access-token/generate": true
-     }
- }
-*/
+/// Upper bound of permissions checked in a single request.
+const MAX_PERMISSIONS: usize = 64;
 
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = introspect::RequestBody))]
+#[derive(Deserialize, Debug)]
 pub struct RequestBody {
+    #[cfg_attr(
+        feature = "openapi",
+        schema(examples(json!(["get:/sysinfo", "post:/access-token/generate"])))
+    )]
     pub permissions: Vec<String>,
 }
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = introspect::ResponseBody))]
+#[derive(Serialize, Debug)]
+pub struct ResponseBody {
+    /// whether the principal holds the permission, for every requested permission
+    #[cfg_attr(
+        feature = "openapi",
+        schema(examples(json!({"get:/sysinfo": false, "post:/access-token/generate": true})))
+    )]
+    pub permissions: BTreeMap<String, bool>,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    post(handler)
+}
+
+/// Checks which of the given permissions the authenticated principal holds.
+/// Lets other services (e.g. the vault) reuse the `method:/path` permission model
+/// by forwarding the caller's credentials.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = PATH,
+    operation_id = "post:/introspect",
+    request_body = RequestBody,
+    responses(
+        (status = 200, description = "Permissions held by the principal", body = ResponseBody),
+        (status = 400, description = "Too many permissions", body = ErrorResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "permissions"
+))]
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%principal, ?request_body), skip_all, ret))]
+pub async fn handler(
+    State(AppState { pool, .. }): State<AppState>,
+    principal: Principal,
+    Json(request_body): Json<RequestBody>,
+) -> Result<Json<ResponseBody>, Error> {
+    if request_body.permissions.len() > MAX_PERMISSIONS {
+        return Err(Error::TooManyPermissions(request_body.permissions.len()));
+    }
+
+    let mut permissions = BTreeMap::new();
+    for permission in request_body.permissions {
+        let granted = principal
+            .has_permission(&pool, &permission)
+            .await
+            .context("has permission")?;
+        permissions.insert(permission, granted);
+    }
+
+    Ok(Json(ResponseBody { permissions }))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("at most {MAX_PERMISSIONS} permissions can be checked at once, got {0}")]
+    TooManyPermissions(usize),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::TooManyPermissions(_) => "introspect.permissions.too-many",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::TooManyPermissions(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use axum::{
+    Json,
+    extract::State,
+    response::IntoResponse,
+    routing::{MethodRouter, get},
+};
+use axum_macros::debug_handler;
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use http::StatusCode;
+use serde::Serialize;
+
+use crate::{AppState, signing::SigningKeyError};
+
+pub const PATH: &str = "/.well-known/jwks.json";
+
+/// JSON Web Key Set (RFC 7517) of the keys that tokens issued by auth are signed with.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = jwks::KeySet))]
+#[derive(Debug, Serialize)]
+pub struct KeySet {
+    /// current key last
+    pub keys: Vec<Jwk>,
+}
+
+/// An Ed25519 public key (RFC 8037).
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = jwks::Jwk))]
+#[derive(Debug, Serialize)]
+pub struct Jwk {
+    #[cfg_attr(feature = "openapi", schema(examples("OKP")))]
+    pub kty: &'static str,
+
+    #[cfg_attr(feature = "openapi", schema(examples("Ed25519")))]
+    pub crv: &'static str,
+
+    #[cfg_attr(feature = "openapi", schema(examples("EdDSA")))]
+    pub alg: &'static str,
+
+    #[cfg_attr(feature = "openapi", schema(examples("sig")))]
+    #[serde(rename = "use")]
+    pub use_: &'static str,
+
+    #[cfg_attr(feature = "openapi", schema(examples("signing/v1")))]
+    pub kid: String,
+
+    /// url-safe base64 encoded public key, with no padding
+    #[cfg_attr(
+        feature = "openapi",
+        schema(examples("11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"))
+    )]
+    pub x: String,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    get(handler)
+}
+
+/// Serves the public keys that verify tokens issued by auth, e.g. for the gateway.
+/// Retired keys are listed until their grace period is over.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = PATH,
+    operation_id = PATH,
+    responses(
+        (status = 200, description = "Public signing keys", body = KeySet),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "secrets"
+))]
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub async fn handler(
+    State(AppState { secrets, .. }): State<AppState>,
+) -> Result<Json<KeySet>, Error> {
+    let keys = crate::signing::public_keys(&secrets)?
+        .into_iter()
+        .filter_map(|(kid, key)| {
+            Some(Jwk {
+                kty: "OKP",
+                crv: "Ed25519",
+                alg: key.algorithm().as_str(),
+                use_: "sig",
+                kid,
+                x: BASE64_URL_SAFE_NO_PAD.encode(key.public_key()?),
+            })
+        })
+        .collect();
+
+    Ok(Json(KeySet { keys }))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    SigningKey(#[from] SigningKeyError),
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::SigningKey(_err) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", _err);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
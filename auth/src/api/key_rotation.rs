@@ -1,24 +1,29 @@
 use axum::{
-    Form,
+    Form, Json,
     extract::State,
     response::IntoResponse,
     routing::{MethodRouter, post},
 };
+use extra::ErrorResponse;
 use http::StatusCode;
 use serde::Deserialize;
 
 use crate::{
     AppState,
-    core::{InsufficientPermissionsError, Principal},
+    core::{ImpersonationRestrictedError, InsufficientPermissionsError, Principal},
 };
 
 pub const PATH: &str = "/rotate-key";
 
+/// The secret that holds random bytes for HMAC keys.
+const HMAC_KEY: &str = "hmac";
+
 #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[cfg_attr(feature = "openapi", schema(as = key_rotation::RequestBody))]
 #[derive(Deserialize)]
 pub struct RequestBody {
-    #[cfg_attr(feature = "openapi", schema(example = "HMAC"))]
+    /// `hmac`, or with email configured `encryption` or `signing`
+    #[cfg_attr(feature = "openapi", schema(example = "hmac"))]
     pub key: String,
 }
 
@@ -35,7 +40,8 @@ pub fn method_router() -> MethodRouter<AppState> {
         content_type = "application/x-www-form-urlencoded",
     ),
     responses(
-        (status = 200, description = "Successfull Key Rotation"),
+        (status = 200, description = "Successfull Key Rotation; the previous version stays valid for the grace period"),
+        (status = 400, description = "Secret cannot be rotated", body = extra::ErrorResponse),
         (status = 401, description = "Invalid credentials", body = extra::ErrorResponse),
         (status = 403, description = "Insufficient permissions", body = extra::ErrorResponse),
         (status = 500, description = "Internal server error"),
@@ -51,8 +57,23 @@ pub async fn handler(
     principal
         .require_permission::<Error>(&pool, "post:/rotate-key")
         .await?;
+    principal.forbid_impersonation()?;
+
+    // Only keys of auth itself are rotated: rotating creates missing secrets, and new random bytes
+    // would break secrets holding structured material, e.g. the DKIM key.
+    let _kid = match key.as_str() {
+        HMAC_KEY => secrets.rotate(&key)?,
+        #[cfg(feature = "smtp")]
+        crate::encryption::KEY => secrets.rotate(&key)?,
+        // the signing key is an Ed25519 key rather than random bytes
+        #[cfg(feature = "smtp")]
+        crate::signing::KEY => crate::signing::rotate(&secrets)?,
+        _ => return Err(Error::NotRotatable(key)),
+    };
+
+    #[cfg(feature = "tracing")]
+    tracing::info!(%key, kid = %_kid, "secret rotated");
 
-    secrets.reset(&key)?;
     Ok(StatusCode::OK)
 }
 
@@ -61,21 +82,59 @@ pub enum Error {
     #[error("{0}")]
     InsufficientPermissions(#[from] InsufficientPermissionsError),
 
+    #[error("{0}")]
+    ImpersonationRestricted(#[from] ImpersonationRestrictedError),
+
+    #[error("secret `{0}` cannot be rotated")]
+    NotRotatable(String),
+
     #[error("{0}")]
     Io(#[from] std::io::Error),
 
     #[error("{0}")]
     Sqlx(#[from] contextual::Error<sqlx::Error>),
+
+    #[cfg(feature = "smtp")]
+    #[error("{0}")]
+    SigningKey(#[from] crate::signing::SigningKeyError),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::InsufficientPermissions(err) => err.kind(),
+            Error::ImpersonationRestricted(err) => err.kind(),
+            Error::NotRotatable(_) => "secret.not-rotatable",
+            Error::Io(_) => "io",
+            Error::Sqlx(_) => "sqlx",
+            #[cfg(feature = "smtp")]
+            Error::SigningKey(_) => "secret.signing-key",
+        }
+    }
 }
 
 impl IntoResponse for Error {
     fn into_response(self) -> axum::response::Response {
         match self {
             Error::InsufficientPermissions(err) => err.into_response(),
+            Error::ImpersonationRestricted(err) => err.into_response(),
+            Error::NotRotatable(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(self))).into_response()
+            }
             Error::Io(_) | Error::Sqlx(_) => {
                 #[cfg(feature = "tracing")]
                 tracing::error!("{:?}", self);
 
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+            #[cfg(feature = "smtp")]
+            Error::SigningKey(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
                 StatusCode::INTERNAL_SERVER_ERROR.into_response()
             }
         }
@@ -1,5 +1,5 @@
 use axum::{
-    Form,
+    Form, Json,
     extract::State,
     http::{HeaderMap, StatusCode, header::USER_AGENT},
     response::{IntoResponse, Response},
@@ -9,6 +9,8 @@ use axum_extra::extract::CookieJar;
 use axum_macros::debug_handler;
 use bcrypt::verify;
 use contextual::Context;
+use cookie::Cookie;
+use extra::ErrorResponse;
 use serde::Deserialize;
 use time::{Duration, OffsetDateTime};
 
@@ -33,11 +35,21 @@ pub enum Error {
     #[error("invalid credentials")]
     InvalidCredentials,
 
+    #[error("account is disabled")]
+    AccountDisabled,
+
+    #[error("email is not verified")]
+    EmailUnverified,
+
     #[error("{0}")]
     Sqlx(#[from] contextual::Error<sqlx::Error>),
 
     #[error("{0}")]
     Bcrypt(#[from] contextual::Error<bcrypt::BcryptError>),
+
+    #[cfg(feature = "smtp")]
+    #[error("{0}")]
+    Notify(#[from] crate::notification::NotifyError),
 }
 
 pub fn method_router() -> MethodRouter<AppState> {
@@ -55,6 +67,7 @@ pub fn method_router() -> MethodRouter<AppState> {
     responses(
         (status = 200, description = "Login successful, session cookie set"),
         (status = 401, description = "Invalid credentials"),
+        (status = 403, description = "Account disabled or email not verified", body = ErrorResponse),
         (status = 500, description = "Internal server error"),
     ),
     tag = "auth"
@@ -62,7 +75,14 @@ pub fn method_router() -> MethodRouter<AppState> {
 #[debug_handler]
 #[cfg_attr(feature = "tracing", tracing::instrument(fields(%username), skip_all))]
 pub async fn handler(
-    State(AppState { pool, .. }): State<AppState>,
+    State(AppState {
+        pool,
+        signup,
+
+        #[cfg(feature = "smtp")]
+        smtp,
+        ..
+    }): State<AppState>,
     headers: HeaderMap,
     jar: CookieJar,
     Form(Credentials { username, password }): Form<Credentials>,
@@ -71,18 +91,29 @@ pub async fn handler(
     struct User {
         id: i64,
         password_hash: String,
+        disabled: bool,
+        email_verified: bool,
+        deletion_scheduled_at: Option<OffsetDateTime>,
     }
 
     let user = sqlx::query_as!(
         User,
-        r#"SELECT id as "id!", password_hash FROM users WHERE username = ?"#,
+        r#"
+        SELECT
+            id as "id!",
+            password_hash,
+            disabled,
+            email_verified,
+            deletion_scheduled_at as "deletion_scheduled_at: OffsetDateTime"
+        FROM users WHERE username = ?
+        "#,
         username
     )
     .fetch_optional(&pool)
     .await;
 
     let user = user
-        .context("username -> User { id, password_hash }")?
+        .context("username -> User")?
         .ok_or(Error::InvalidCredentials)?;
 
     #[cfg(feature = "tracing")]
@@ -92,11 +123,82 @@ pub async fn handler(
         return Err(Error::InvalidCredentials);
     };
 
+    if user.disabled {
+        return Err(Error::AccountDisabled);
+    }
+
+    if signup.require_verified_email && !user.email_verified {
+        return Err(Error::EmailUnverified);
+    }
+
+    let user_agent = headers.get(USER_AGENT).and_then(|val| val.to_str().ok());
+
+    let mut tx = pool
+        .begin_with(crate::core::BEGIN_WRITE)
+        .await
+        .context("begin transaction :: login")?;
+
+    if user.deletion_scheduled_at.is_some() {
+        sqlx::query!(
+            "UPDATE users SET deletion_scheduled_at = NULL WHERE id = ?",
+            user.id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("cancel scheduled account deletion")?;
+
+        #[cfg(feature = "tracing")]
+        tracing::info!("scheduled account deletion cancelled");
+    }
+
+    #[cfg(feature = "smtp")]
+    {
+        // the very first login is not worth a notification
+        let new_device = sqlx::query_scalar!(
+            r#"
+            SELECT
+                EXISTS(SELECT 1 FROM login_history WHERE user_id = ?1)
+                AND NOT EXISTS(SELECT 1 FROM login_history WHERE user_id = ?1 AND user_agent IS ?2)
+                as "new_device!: bool"
+            "#,
+            user.id,
+            user_agent
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .context("login from new device")?;
+
+        if new_device {
+            let notification = crate::notification::NewDeviceLogin {
+                username,
+                user_agent: user_agent.map(str::to_string),
+                datetime: OffsetDateTime::now_utc().to_string(),
+            };
+            crate::notification::notify(&mut tx, &smtp, user.id, &notification).await?;
+        }
+    }
+
+    let session_cookie = start_session(&mut tx, user.id, user_agent)
+        .await
+        .context("start session")?;
+
+    tx.commit().await.context("commit transaction :: login")?;
+
+    let jar = jar.add(session_cookie);
+
+    Ok((jar, StatusCode::OK))
+}
+
+/// Creates a session for the user, records the login and returns the session cookie.
+pub async fn start_session(
+    conn: &mut sqlx::SqliteConnection,
+    user_id: i64,
+    user_agent: Option<&str>,
+) -> Result<Cookie<'static>, sqlx::Error> {
     let session_id = SessionId::new();
     let session_id_hash = session_id.hash_sha256();
     let created_at = OffsetDateTime::now_utc();
     let expires_at = created_at + COOKIE_DURATION;
-    let user_agent = headers.get(USER_AGENT).and_then(|val| val.to_str().ok());
 
     sqlx::query!(
         r#"
@@ -105,22 +207,45 @@ pub async fn handler(
         VALUES (?, ?, ?, ?, ?)
         "#,
         session_id_hash,
-        user.id,
+        user_id,
         created_at,
         expires_at,
         user_agent
     )
-    .execute(&pool)
-    .await
-    .context("insert session")?;
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO login_history
+        (user_id, datetime, user_agent)
+        VALUES (?, ?, ?)
+        "#,
+        user_id,
+        created_at,
+        user_agent
+    )
+    .execute(&mut *conn)
+    .await?;
 
     #[cfg(feature = "tracing")]
     tracing::info!(?expires_at, ?user_agent, "session created");
 
-    let session_cookie = session_id.into_cookie(COOKIE_DURATION);
-    let jar = jar.add(session_cookie);
+    Ok(session_id.into_cookie(COOKIE_DURATION))
+}
 
-    Ok((jar, StatusCode::OK))
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::InvalidCredentials => "auth.invalid-credentials",
+            Error::AccountDisabled => "auth.account.disabled",
+            Error::EmailUnverified => "auth.email.unverified",
+            Error::Sqlx(_) => "sqlx",
+            Error::Bcrypt(_) => "bcrypt",
+            #[cfg(feature = "smtp")]
+            Error::Notify(_) => "notification",
+        }
+    }
 }
 
 impl IntoResponse for Error {
@@ -132,10 +257,23 @@ impl IntoResponse for Error {
 
                 StatusCode::UNAUTHORIZED.into_response()
             }
+            Error::AccountDisabled | Error::EmailUnverified => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::FORBIDDEN, Json(ErrorResponse::from(self))).into_response()
+            }
             Error::Sqlx(_) | Error::Bcrypt(_) => {
                 #[cfg(feature = "tracing")]
                 tracing::error!("{:?}", self);
 
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+            #[cfg(feature = "smtp")]
+            Error::Notify(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
                 StatusCode::INTERNAL_SERVER_ERROR.into_response()
             }
         }
@@ -7,7 +7,9 @@ use http::{HeaderMap, StatusCode};
 
 use crate::{
     AppState,
-    core::{Credentials, SessionId, expired_session_cookie},
+    core::{
+        Credentials, ImpersonationAction, SessionId, expired_session_cookie, log_impersonation,
+    },
 };
 
 pub const PATH: &str = "/logout";
@@ -16,6 +18,10 @@ pub const PATH: &str = "/logout";
 pub enum Error {
     #[error("{0}")]
     Sqlx(#[from] contextual::Error<sqlx::Error>),
+
+    #[cfg(feature = "webhooks")]
+    #[error("{0}")]
+    Webhook(#[from] crate::webhook::EnqueueError),
 }
 
 pub fn method_router() -> MethodRouter<AppState> {
@@ -39,19 +45,51 @@ pub async fn handler(
     if let Ok(Some(session_id)) = SessionId::try_from_headers(&headers) {
         let session_id_hash = session_id.hash_sha256();
 
-        let _record = sqlx::query!(
+        let mut tx = pool
+            .begin_with(crate::core::BEGIN_WRITE)
+            .await
+            .context("begin transaction :: logout")?;
+
+        let record = sqlx::query!(
             r#"
             DELETE FROM sessions WHERE session_id_hash = ?
-            RETURNING user_id
+            RETURNING user_id, impersonator_id
             "#,
             session_id_hash
         )
-        .fetch_optional(&pool)
+        .fetch_optional(&mut *tx)
         .await
         .context("delete session")?;
 
+        if let Some(record) = &record
+            && let Some(impersonator_id) = record.impersonator_id
+        {
+            log_impersonation(
+                &mut *tx,
+                impersonator_id,
+                record.user_id,
+                ImpersonationAction::Stop,
+            )
+            .await
+            .context("write impersonation audit log")?;
+        }
+
+        #[cfg(feature = "webhooks")]
+        if let Some(record) = &record {
+            crate::webhook::enqueue(
+                &mut *tx,
+                &crate::webhook::Event::SessionRevoked {
+                    user_id: record.user_id,
+                    reason: "logout",
+                },
+            )
+            .await?;
+        }
+
+        tx.commit().await.context("commit transaction :: logout")?;
+
         #[cfg(feature = "tracing")]
-        match _record {
+        match record {
             Some(record) => {
                 tracing::Span::current().record("user_id", tracing::field::display(record.user_id));
                 tracing::info!("session invalidated")
@@ -71,6 +109,13 @@ impl IntoResponse for Error {
                 #[cfg(feature = "tracing")]
                 tracing::error!("{:?}", _err);
 
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+            #[cfg(feature = "webhooks")]
+            Error::Webhook(_err) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", _err);
+
                 StatusCode::INTERNAL_SERVER_ERROR.into_response()
             }
         }
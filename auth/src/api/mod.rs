@@ -1,16 +1,22 @@
 pub mod access_token;
+pub mod account;
+pub mod admin;
 pub mod email;
 pub mod heartbeat;
 pub mod introspect;
 pub mod key_rotation;
 pub mod login;
 pub mod logout;
+pub mod orgs;
 pub mod permissions;
 pub mod private;
 pub mod signup;
 pub mod sysinfo;
 pub mod username;
 
+#[cfg(feature = "smtp")]
+pub mod jwks;
+
 #[cfg(feature = "openapi")]
 pub const OPEN_API_DOCS_PATH: &str = "/api-docs/openapi.json";
 
@@ -20,11 +26,27 @@ pub const OPEN_API_DOCS_PATH: &str = "/api-docs/openapi.json";
     paths(
         access_token::generate::handler,
         access_token::verify::handler,
+        account::delete::handler,
+        account::export::handler,
+        admin::impersonate::handler,
+        admin::impersonate::stop::handler,
+        admin::invitations::handler,
+        admin::secrets::handler,
+        admin::users::handler,
+        admin::users::delete::handler,
+        admin::users::disable::handler,
+        admin::users::enable::handler,
+        admin::users::force_email_verification::handler,
         email::check_availability::handler,
         heartbeat::handler,
+        introspect::handler,
         key_rotation::handler,
         login::handler,
         logout::handler,
+        orgs::handler,
+        orgs::members::handler,
+        orgs::members::add::handler,
+        orgs::members::remove::handler,
         permissions::handler,
         permissions::assign::handler,
         signup::handler,
@@ -33,9 +55,25 @@ pub const OPEN_API_DOCS_PATH: &str = "/api-docs/openapi.json";
     ),
     components(schemas(
         access_token::generate::Config,
+        account::delete::RequestBody,
+        admin::impersonate::RequestBody,
+        admin::invitations::RequestBody,
+        admin::secrets::RotationStatus,
+        admin::secrets::Version,
+        admin::users::User,
+        admin::users::disable::RequestBody,
+        admin::users::enable::RequestBody,
+        admin::users::force_email_verification::RequestBody,
+        crate::core::Organization,
         crate::core::Permission,
+        introspect::RequestBody,
+        introspect::ResponseBody,
         key_rotation::RequestBody,
         login::Credentials,
+        orgs::RequestBody,
+        orgs::members::Member,
+        orgs::members::add::RequestBody,
+        orgs::members::remove::RequestBody,
         permissions::assign::RequestBody,
         signup::RequestBody,
         sysinfo::Info
@@ -45,9 +83,42 @@ struct OpenApiDoc;
 
 #[cfg(all(feature = "openapi", feature = "smtp"))]
 #[derive(utoipa::OpenApi)]
-#[openapi(paths(email::verify_email::handler, email::initiate_verification::handler,))]
+#[openapi(
+    paths(
+        admin::outbox::handler,
+        admin::outbox::retry::handler,
+        email::verify_email::handler,
+        email::initiate_verification::handler,
+        jwks::handler,
+        signup::page
+    ),
+    components(schemas(
+        admin::outbox::Message,
+        admin::outbox::retry::RequestBody,
+        jwks::Jwk,
+        jwks::KeySet
+    ))
+)]
 struct SmtpOpenApiDoc;
 
+#[cfg(all(feature = "openapi", feature = "webhooks"))]
+#[derive(utoipa::OpenApi)]
+#[openapi(
+    paths(
+        admin::webhooks::handler,
+        admin::webhooks::create::handler,
+        admin::webhooks::delete::handler,
+        admin::webhooks::deliveries::handler
+    ),
+    components(schemas(
+        admin::webhooks::Webhook,
+        admin::webhooks::create::RequestBody,
+        admin::webhooks::create::ResponseBody,
+        admin::webhooks::deliveries::Delivery
+    ))
+)]
+struct WebhooksOpenApiDoc;
+
 #[cfg(feature = "openapi")]
 pub fn openapi() -> utoipa::openapi::OpenApi {
     use utoipa::OpenApi;
@@ -58,5 +129,8 @@ pub fn openapi() -> utoipa::openapi::OpenApi {
     #[cfg(feature = "smtp")]
     openapi.merge(SmtpOpenApiDoc::openapi());
 
+    #[cfg(feature = "webhooks")]
+    openapi.merge(WebhooksOpenApiDoc::openapi());
+
     openapi
 }
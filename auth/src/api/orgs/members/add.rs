@@ -0,0 +1,200 @@
+use axum::{
+    Form, Json,
+    extract::State,
+    response::IntoResponse,
+    routing::{MethodRouter, post},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::StatusCode;
+use serde::Deserialize;
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    core::{ImpersonationRestrictedError, InsufficientPermissionsError, Organization, Principal},
+};
+
+pub const PATH: &str = "/orgs/members";
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = orgs::members::add::RequestBody))]
+#[derive(Deserialize)]
+pub struct RequestBody {
+    #[cfg_attr(feature = "openapi", schema(examples("acme")))]
+    pub organization: String,
+
+    #[cfg_attr(feature = "openapi", schema(examples("joe")))]
+    pub username: String,
+
+    /// permission group that applies within the organization
+    #[cfg_attr(feature = "openapi", schema(examples("org-member")))]
+    pub role: String,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    post(handler)
+}
+
+/// Adds the user to the organization, or changes their role if they already are a member.
+/// The caller must hold every permission of the role within the organization.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = PATH,
+    operation_id = "post:/orgs/members",
+    request_body(
+        content = RequestBody,
+        content_type = "application/x-www-form-urlencoded",
+    ),
+    responses(
+        (status = 200, description = "Member added or role changed"),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Insufficient permissions or impersonating", body = ErrorResponse),
+        (status = 404, description = "Organization, user or role not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "orgs"
+))]
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%principal, %organization, %username, %role), skip_all, ret))]
+pub async fn handler(
+    State(AppState { pool, .. }): State<AppState>,
+    principal: Principal,
+    Form(RequestBody {
+        organization,
+        username,
+        role,
+    }): Form<RequestBody>,
+) -> Result<StatusCode, Error> {
+    let organization = Organization::from_name(&organization, &pool)
+        .await
+        .context("name -> Organization")?
+        .ok_or(Error::OrganizationNotFound(organization))?;
+
+    principal
+        .require_org_permission::<Error>(&pool, organization.id, "post:/orgs/members")
+        .await?;
+    principal.forbid_impersonation()?;
+
+    let permission_group_id = sqlx::query_scalar!(
+        r#"SELECT id as "id!" FROM permission_groups WHERE [group] = ?"#,
+        role
+    )
+    .fetch_optional(&pool)
+    .await
+    .context("role -> permission_group_id")?
+    .ok_or(Error::RoleNotFound(role))?;
+
+    // The caller must have every permission of the role within the organization
+    // before they hand it out to others
+    let role_permissions = sqlx::query_scalar!(
+        r#"
+        SELECT p.permission FROM permissions p
+        INNER JOIN permission_group_association pga ON pga.permission_id = p.id
+        WHERE pga.permission_group_id = ?
+        "#,
+        permission_group_id
+    )
+    .fetch_all(&pool)
+    .await
+    .context("role permissions")?;
+
+    for permission in &role_permissions {
+        principal
+            .require_org_permission::<Error>(&pool, organization.id, permission)
+            .await?;
+    }
+
+    let user_id = sqlx::query_scalar!(
+        r#"SELECT id as "id!" FROM users WHERE username = ?"#,
+        username
+    )
+    .fetch_optional(&pool)
+    .await
+    .context("username -> user_id")?
+    .ok_or(Error::UserNotFound(username))?;
+
+    let created_at = OffsetDateTime::now_utc();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO organization_memberships
+        (organization_id, user_id, permission_group_id, created_at)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT (organization_id, user_id) DO UPDATE SET
+            permission_group_id = excluded.permission_group_id
+        "#,
+        organization.id,
+        user_id,
+        permission_group_id,
+        created_at
+    )
+    .execute(&pool)
+    .await
+    .context("upsert organization membership")?;
+
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        organization_id = organization.id,
+        user_id,
+        "membership saved"
+    );
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    InsufficientPermissions(#[from] InsufficientPermissionsError),
+
+    #[error("{0}")]
+    ImpersonationRestricted(#[from] ImpersonationRestrictedError),
+
+    #[error("organization `{0}` not found")]
+    OrganizationNotFound(String),
+
+    #[error("user `{0}` not found")]
+    UserNotFound(String),
+
+    #[error("role `{0}` not found")]
+    RoleNotFound(String),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::InsufficientPermissions(err) => err.kind(),
+            Error::ImpersonationRestricted(err) => err.kind(),
+            Error::OrganizationNotFound(_) => "organization.not-found",
+            Error::UserNotFound(_) => "user.not-found",
+            Error::RoleNotFound(_) => "permission-group.not-found",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::InsufficientPermissions(err) => err.into_response(),
+            Error::ImpersonationRestricted(err) => err.into_response(),
+            Error::OrganizationNotFound(_) | Error::UserNotFound(_) | Error::RoleNotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
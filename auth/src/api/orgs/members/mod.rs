@@ -0,0 +1,144 @@
+pub mod add;
+pub mod remove;
+
+use axum::{
+    Json,
+    extract::{Query, State},
+    response::IntoResponse,
+    routing::{MethodRouter, get},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    core::{InsufficientPermissionsError, Organization, Principal},
+};
+
+pub const PATH: &str = "/orgs/members";
+
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+#[derive(Deserialize, Debug)]
+pub struct QueryParams {
+    #[cfg_attr(feature = "openapi", param(example = "acme"))]
+    pub organization: String,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = orgs::members::Member))]
+#[derive(Debug, Serialize)]
+pub struct Member {
+    #[cfg_attr(feature = "openapi", schema(examples("joe")))]
+    pub username: String,
+
+    /// permission group that applies within the organization
+    #[cfg_attr(feature = "openapi", schema(examples("org-member")))]
+    pub role: String,
+
+    #[cfg_attr(feature = "openapi", schema(value_type = String, format = DateTime))]
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    get(handler)
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = PATH,
+    operation_id = PATH,
+    params(QueryParams),
+    responses(
+        (status = 200, description = "Members of the organization", body = Vec<Member>),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Insufficient permissions", body = ErrorResponse),
+        (status = 404, description = "Organization not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "orgs"
+))]
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%principal, ?params), skip_all))]
+pub async fn handler(
+    State(AppState { pool, .. }): State<AppState>,
+    principal: Principal,
+    Query(params): Query<QueryParams>,
+) -> Result<Json<Vec<Member>>, Error> {
+    let organization = Organization::from_name(&params.organization, &pool)
+        .await
+        .context("name -> Organization")?
+        .ok_or(Error::OrganizationNotFound(params.organization))?;
+
+    principal
+        .require_org_permission::<Error>(&pool, organization.id, "get:/orgs/members")
+        .await?;
+
+    let members = sqlx::query_as!(
+        Member,
+        r#"
+        SELECT
+            u.username,
+            pg.[group] as role,
+            om.created_at as "created_at: OffsetDateTime"
+        FROM organization_memberships om
+        INNER JOIN users u ON u.id = om.user_id
+        INNER JOIN permission_groups pg ON pg.id = om.permission_group_id
+        WHERE om.organization_id = ?
+        ORDER BY u.username
+        "#,
+        organization.id
+    )
+    .fetch_all(&pool)
+    .await
+    .context("list organization members")?;
+
+    Ok(Json(members))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    InsufficientPermissions(#[from] InsufficientPermissionsError),
+
+    #[error("organization `{0}` not found")]
+    OrganizationNotFound(String),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::InsufficientPermissions(err) => err.kind(),
+            Error::OrganizationNotFound(_) => "organization.not-found",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::InsufficientPermissions(err) => err.into_response(),
+            Error::OrganizationNotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use axum::{
+    Form, Json,
+    extract::State,
+    response::IntoResponse,
+    routing::{MethodRouter, delete},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::StatusCode;
+use serde::Deserialize;
+
+use crate::{
+    AppState,
+    core::{ImpersonationRestrictedError, InsufficientPermissionsError, Organization, Principal},
+};
+
+pub const PATH: &str = "/orgs/members";
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = orgs::members::remove::RequestBody))]
+#[derive(Deserialize)]
+pub struct RequestBody {
+    #[cfg_attr(feature = "openapi", schema(examples("acme")))]
+    pub organization: String,
+
+    #[cfg_attr(feature = "openapi", schema(examples("joe")))]
+    pub username: String,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    delete(handler)
+}
+
+/// Removes the user from the organization.
+/// Access tokens of the user that are bound to the organization are deleted as well.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    delete,
+    path = PATH,
+    operation_id = "delete:/orgs/members",
+    request_body(
+        content = RequestBody,
+        content_type = "application/x-www-form-urlencoded",
+    ),
+    responses(
+        (status = 200, description = "Member removed"),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Insufficient permissions or impersonating", body = ErrorResponse),
+        (status = 404, description = "Organization or member not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "orgs"
+))]
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%principal, %organization, %username), skip_all, ret))]
+pub async fn handler(
+    State(AppState { pool, .. }): State<AppState>,
+    principal: Principal,
+    Form(RequestBody {
+        organization,
+        username,
+    }): Form<RequestBody>,
+) -> Result<StatusCode, Error> {
+    let organization = Organization::from_name(&organization, &pool)
+        .await
+        .context("name -> Organization")?
+        .ok_or(Error::OrganizationNotFound(organization))?;
+
+    principal
+        .require_org_permission::<Error>(&pool, organization.id, "delete:/orgs/members")
+        .await?;
+    principal.forbid_impersonation()?;
+
+    let mut tx = pool
+        .begin_with(crate::core::BEGIN_WRITE)
+        .await
+        .context("begin transaction :: remove organization member")?;
+
+    let user_id = sqlx::query_scalar!(
+        r#"
+        DELETE FROM organization_memberships
+        WHERE organization_id = ? AND user_id = (SELECT id FROM users WHERE username = ?)
+        RETURNING user_id
+        "#,
+        organization.id,
+        username
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .context("delete organization membership")?
+    .ok_or(Error::MemberNotFound(username))?;
+
+    sqlx::query!(
+        "DELETE FROM access_tokens WHERE user_id = ? AND organization_id = ?",
+        user_id,
+        organization.id
+    )
+    .execute(&mut *tx)
+    .await
+    .context("delete organization bound access tokens")?;
+
+    tx.commit()
+        .await
+        .context("commit transaction :: remove organization member")?;
+
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        organization_id = organization.id,
+        user_id,
+        "membership removed"
+    );
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    InsufficientPermissions(#[from] InsufficientPermissionsError),
+
+    #[error("{0}")]
+    ImpersonationRestricted(#[from] ImpersonationRestrictedError),
+
+    #[error("organization `{0}` not found")]
+    OrganizationNotFound(String),
+
+    #[error("`{0}` is not a member of the organization")]
+    MemberNotFound(String),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::InsufficientPermissions(err) => err.kind(),
+            Error::ImpersonationRestricted(err) => err.kind(),
+            Error::OrganizationNotFound(_) => "organization.not-found",
+            Error::MemberNotFound(_) => "organization.member.not-found",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::InsufficientPermissions(err) => err.into_response(),
+            Error::ImpersonationRestricted(err) => err.into_response(),
+            Error::OrganizationNotFound(_) | Error::MemberNotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+pub mod members;
+
+use axum::{
+    Form, Json,
+    extract::State,
+    response::IntoResponse,
+    routing::{MethodRouter, post},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::StatusCode;
+use serde::Deserialize;
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    core::{ImpersonationRestrictedError, InsufficientPermissionsError, Organization, Principal},
+};
+
+pub const PATH: &str = "/orgs";
+const OWNER_ROLE: &str = "org-owner";
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = orgs::RequestBody))]
+#[derive(Deserialize)]
+pub struct RequestBody {
+    #[cfg_attr(feature = "openapi", schema(examples("acme")))]
+    pub name: String,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    post(handler)
+}
+
+/// Creates an organization with the caller as its owner.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = PATH,
+    operation_id = PATH,
+    request_body(
+        content = RequestBody,
+        content_type = "application/x-www-form-urlencoded",
+    ),
+    responses(
+        (status = 201, description = "Organization created", body = Organization),
+        (status = 400, description = "Invalid organization name", body = ErrorResponse),
+        (status = 401, description = "Not authenticated", body = ErrorResponse),
+        (status = 403, description = "Insufficient permissions", body = ErrorResponse),
+        (status = 409, description = "Organization name already taken", body = ErrorResponse),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "orgs"
+))]
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%principal, %name), skip_all, ret))]
+pub async fn handler(
+    State(AppState { pool, .. }): State<AppState>,
+    principal: Principal,
+    Form(RequestBody { name }): Form<RequestBody>,
+) -> Result<(StatusCode, Json<Organization>), Error> {
+    principal
+        .require_permission::<Error>(&pool, "post:/orgs")
+        .await?;
+    principal.forbid_impersonation()?;
+
+    let name = name.trim().to_owned();
+    if name.is_empty() {
+        return Err(Error::InvalidName);
+    }
+
+    let mut tx = pool
+        .begin_with(crate::core::BEGIN_WRITE)
+        .await
+        .context("begin transaction :: create organization")?;
+
+    if Organization::from_name(&name, &mut *tx)
+        .await
+        .context("name -> Organization")?
+        .is_some()
+    {
+        return Err(Error::NameExists(name));
+    }
+
+    let user_id = principal.user_id();
+    let created_at = OffsetDateTime::now_utc();
+
+    let organization = sqlx::query_as!(
+        Organization,
+        r#"
+        INSERT INTO organizations (name, created_at)
+        VALUES (?, ?)
+        RETURNING id as "id!", name, created_at as "created_at: OffsetDateTime"
+        "#,
+        name,
+        created_at
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .context("insert organization")?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO organization_memberships
+        (organization_id, user_id, permission_group_id, created_at)
+        SELECT ?, ?, pg.id, ? FROM permission_groups pg WHERE pg.[group] = ?
+        "#,
+        organization.id,
+        user_id,
+        created_at,
+        OWNER_ROLE
+    )
+    .execute(&mut *tx)
+    .await
+    .context("insert owner membership")?;
+
+    tx.commit()
+        .await
+        .context("commit transaction :: create organization")?;
+
+    #[cfg(feature = "tracing")]
+    tracing::info!(organization_id = organization.id, "organization created");
+
+    Ok((StatusCode::CREATED, Json(organization)))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    InsufficientPermissions(#[from] InsufficientPermissionsError),
+
+    #[error("{0}")]
+    ImpersonationRestricted(#[from] ImpersonationRestrictedError),
+
+    #[error("organization name must not be empty")]
+    InvalidName,
+
+    #[error("organization `{0}` already exists")]
+    NameExists(String),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::InsufficientPermissions(err) => err.kind(),
+            Error::ImpersonationRestricted(err) => err.kind(),
+            Error::InvalidName => "organization.name.invalid",
+            Error::NameExists(_) => "organization.name.exists",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::InsufficientPermissions(err) => err.into_response(),
+            Error::ImpersonationRestricted(err) => err.into_response(),
+            Error::InvalidName => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::NameExists(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::CONFLICT, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -11,7 +11,7 @@ use time::OffsetDateTime;
 
 use crate::{
     AppState,
-    core::{InsufficientPermissionsError, Principal},
+    core::{ImpersonationRestrictedError, InsufficientPermissionsError, Principal},
 };
 
 // TODO: mark this as admin endpoint. maybe using tags
@@ -61,13 +61,20 @@ pub fn method_router() -> MethodRouter<AppState> {
 ))]
 #[cfg_attr(feature = "tracing", tracing::instrument(fields(%principal), skip_all, ret))]
 pub async fn handler(
-    State(AppState { pool, .. }): State<AppState>,
+    State(AppState {
+        pool,
+
+        #[cfg(feature = "smtp")]
+        smtp,
+        ..
+    }): State<AppState>,
     principal: Principal,
     Json(request_body): Json<RequestBody>,
 ) -> Result<StatusCode, Error> {
     principal
         .require_permission::<Error>(&pool, "post:/permissions")
         .await?;
+    principal.forbid_impersonation()?;
 
     // The Assigner must have the requested permission themselves first
     // before they assign it to others
@@ -79,10 +86,11 @@ pub async fn handler(
         Principal::Session(info) => ("user", info.user_id),
         Principal::AccessToken(info) => ("access_token", info.id),
         Principal::Basic(info) => ("user", info.user_id),
+        Principal::Impersonation(info) => ("user", info.user_id),
     };
 
     let mut tx = pool
-        .begin()
+        .begin_with(crate::core::BEGIN_WRITE)
         .await
         .context("begin transaction :: assign permission")?;
 
@@ -169,6 +177,31 @@ pub async fn handler(
     .await
     .context("write permission audit log")?;
 
+    #[cfg(feature = "webhooks")]
+    crate::webhook::enqueue(
+        &mut *tx,
+        &crate::webhook::Event::PermissionAssigned {
+            permission: request_body.permission.clone(),
+            assignee_type,
+            assignee_id,
+        },
+    )
+    .await?;
+
+    #[cfg(feature = "smtp")]
+    if assignee_type == "user" {
+        let username = sqlx::query_scalar!("SELECT username FROM users WHERE id = ?", assignee_id)
+            .fetch_one(&mut *tx)
+            .await
+            .context("user_id -> username")?;
+
+        let notification = crate::notification::PermissionGranted {
+            username,
+            permission: request_body.permission,
+        };
+        crate::notification::notify(&mut tx, &smtp, assignee_id, &notification).await?;
+    }
+
     tx.commit()
         .await
         .context("commit transaction :: assign permission")?;
@@ -184,19 +217,35 @@ pub enum Error {
     #[error("{0}")]
     InsufficientPermissions(#[from] InsufficientPermissionsError),
 
+    #[error("{0}")]
+    ImpersonationRestricted(#[from] ImpersonationRestrictedError),
+
     #[error("either the assignee or the permission does not exist")]
     DoesNotExist,
 
     #[error("{0}")]
     Sqlx(#[from] contextual::Error<sqlx::Error>),
+
+    #[cfg(feature = "webhooks")]
+    #[error("{0}")]
+    Webhook(#[from] crate::webhook::EnqueueError),
+
+    #[cfg(feature = "smtp")]
+    #[error("{0}")]
+    Notify(#[from] crate::notification::NotifyError),
 }
 
 impl extra::ErrorKind for Error {
     fn kind(&self) -> &'static str {
         match self {
             Error::InsufficientPermissions(e) => e.kind(),
+            Error::ImpersonationRestricted(e) => e.kind(),
             Error::DoesNotExist => "does_not_exist",
             Error::Sqlx(_) => "sqlx",
+            #[cfg(feature = "webhooks")]
+            Error::Webhook(_) => "webhook",
+            #[cfg(feature = "smtp")]
+            Error::Notify(_) => "notification",
         }
     }
 }
@@ -205,6 +254,7 @@ impl axum::response::IntoResponse for Error {
     fn into_response(self) -> axum::response::Response {
         match self {
             Error::InsufficientPermissions(err) => err.into_response(),
+            Error::ImpersonationRestricted(err) => err.into_response(),
             Error::DoesNotExist => {
                 #[cfg(feature = "tracing")]
                 tracing::info!("{:?}", self);
@@ -215,6 +265,20 @@ impl axum::response::IntoResponse for Error {
                 #[cfg(feature = "tracing")]
                 tracing::error!("{:?}", _err);
 
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+            #[cfg(feature = "webhooks")]
+            Error::Webhook(_err) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", _err);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+            #[cfg(feature = "smtp")]
+            Error::Notify(_err) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", _err);
+
                 StatusCode::INTERNAL_SERVER_ERROR.into_response()
             }
         }
@@ -1,7 +1,7 @@
 use axum::{
     Form, Json,
     extract::State,
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     routing::{MethodRouter, post},
 };
@@ -9,9 +9,14 @@ use contextual::Context;
 use email::Email;
 use extra::ErrorResponse;
 use serde::Deserialize;
+use time::OffsetDateTime;
 use validation::{validate_password, validate_username};
 
-use crate::{AppState, core::assign_permission_group};
+use crate::{
+    AppState,
+    core::{InvitationToken, assign_permission_group, preferred_locale},
+    signup_policy::SignupPolicyError,
+};
 
 pub const PATH: &str = "/signup";
 
@@ -27,6 +32,10 @@ pub struct RequestBody {
 
     #[cfg_attr(feature = "openapi", schema(examples("h?P7o]37")))]
     pub password: String,
+
+    /// invitation token from the invitation link.
+    /// required when the server runs in invite-only mode
+    pub invitation: Option<String>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -46,15 +55,100 @@ pub enum Error {
     #[error("{0}")]
     WeakPassword(&'static str),
 
+    #[error("{0}")]
+    SignupPolicy(#[from] SignupPolicyError),
+
+    #[error("signup requires an invitation")]
+    InvitationRequired,
+
+    #[error("invalid or already used invitation")]
+    InvalidInvitation,
+
+    #[error("invitation expired")]
+    InvitationExpired,
+
+    #[error("invitation was issued for a different email")]
+    InvitationEmailMismatch,
+
     #[error("{0}")]
     Sqlx(#[from] contextual::Error<sqlx::Error>),
 
+    #[cfg(feature = "webhooks")]
+    #[error("{0}")]
+    Webhook(#[from] crate::webhook::EnqueueError),
+
     #[error("{0}")]
     Bcrypt(#[from] contextual::Error<bcrypt::BcryptError>),
+
+    #[cfg(feature = "smtp")]
+    #[error("{0}")]
+    Io(#[from] contextual::Error<std::io::Error>),
+
+    #[cfg(feature = "smtp")]
+    #[error("{0}")]
+    TokenEncode(#[from] contextual::Error<signature::EncodeError>),
+
+    #[cfg(feature = "smtp")]
+    #[error("{0}")]
+    SigningKey(#[from] crate::signing::SigningKeyError),
+
+    #[cfg(feature = "smtp")]
+    #[error("{0}")]
+    EmailTemplate(#[from] contextual::Error<tera::Error>),
+}
+
+/// Query of the invitation link, see
+/// [`invitation_link`](super::admin::invitations::invitation_link).
+#[cfg(feature = "smtp")]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+#[derive(Deserialize)]
+pub struct QueryParams {
+    pub invitation: Option<String>,
 }
 
 pub fn method_router() -> MethodRouter<AppState> {
-    post(handler)
+    let router = post(handler);
+
+    #[cfg(feature = "smtp")]
+    let router = router.get(page);
+
+    router
+}
+
+/// Renders the signup form, with the invitation of the link that leads here filled in.
+/// The form is posted to [`handler`].
+#[cfg(feature = "smtp")]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = PATH,
+    operation_id = "get:/signup",
+    params(QueryParams),
+    responses(
+        (status = 200, description = "Signup form", content_type = "text/html"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "auth"
+))]
+pub async fn page(
+    State(AppState { smtp, .. }): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(QueryParams { invitation }): axum::extract::Query<QueryParams>,
+) -> Response {
+    use crate::notification::SignupPage;
+
+    let locale = preferred_locale(&headers);
+    match tera::Context::from_serialize(SignupPage { invitation })
+        .and_then(|context| smtp.render(locale.as_deref(), SignupPage::TEMPLATE, &context))
+    {
+        Ok(page) => axum::response::Html(page).into_response(),
+        Err(_err) => {
+            #[cfg(feature = "tracing")]
+            tracing::error!("render {}: {:?}", SignupPage::TEMPLATE, _err);
+
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
 }
 
 #[cfg_attr(feature = "openapi", utoipa::path(
@@ -67,7 +161,8 @@ pub fn method_router() -> MethodRouter<AppState> {
     ),
     responses(
         (status = 201, description = "User created"),
-        (status = 400, description = "Invalid input", body = ErrorResponse),
+        (status = 400, description = "Invalid input or invitation", body = ErrorResponse),
+        (status = 403, description = "Invitation required or email domain not allowed", body = ErrorResponse),
         (status = 409, description = "Username or email already exists", body = ErrorResponse),
         (status = 500, description = "Internal server error"),
     ),
@@ -77,6 +172,7 @@ pub fn method_router() -> MethodRouter<AppState> {
 pub async fn handler(
     State(AppState {
         pool,
+        signup,
 
         #[cfg(feature = "smtp")]
         secrets,
@@ -86,17 +182,68 @@ pub async fn handler(
         ..
     }): State<AppState>,
     #[cfg(feature = "smtp")] axum_extra::extract::Host(host): axum_extra::extract::Host,
+    headers: HeaderMap,
     Form(RequestBody {
         username,
         email,
         password,
+        invitation,
     }): Form<RequestBody>,
 ) -> Result<StatusCode, Error> {
     let username = validate_username(username).map_err(Error::InvalidUsername)?;
     let password = validate_password(password).map_err(Error::WeakPassword)?;
     let email = Email::try_from(email).map_err(Error::InvalidEmailFormat)?;
 
-    let mut tx = pool.begin().await.context("begin transaction :: signup")?;
+    // invitations are addressed by an administrator and bypass the domain policy
+    if invitation.is_none() {
+        if signup.invite_only {
+            return Err(Error::InvitationRequired);
+        }
+
+        signup.check_email(&email)?;
+    }
+
+    let mut tx = pool
+        .begin_with(crate::core::BEGIN_WRITE)
+        .await
+        .context("begin transaction :: signup")?;
+
+    let invitation = match invitation {
+        Some(invitation_token) => {
+            #[cfg(feature = "smtp")]
+            let (invitation_token, group) = open_invitation_link(&secrets, invitation_token)?;
+
+            let invitation = InvitationToken::base64decode(&invitation_token)
+                .ok_or(Error::InvalidInvitation)?
+                .info(&mut *tx)
+                .await
+                .context("InvitationToken -> InvitationInfo")?
+                .ok_or(Error::InvalidInvitation)?;
+
+            if invitation.is_accepted() {
+                return Err(Error::InvalidInvitation);
+            }
+
+            if invitation.is_expired() {
+                return Err(Error::InvitationExpired);
+            }
+
+            if invitation.email != email {
+                return Err(Error::InvitationEmailMismatch);
+            }
+
+            #[cfg(feature = "smtp")]
+            if group.is_some_and(|group| group != invitation.permission_group) {
+                return Err(Error::InvalidInvitation);
+            }
+
+            Some(invitation)
+        }
+        None => None,
+    };
+
+    // the invitation link was delivered to this address, so it counts as verified
+    let email_verified = invitation.is_some();
 
     if super::username::exists(&mut *tx, &username)
         .await
@@ -113,101 +260,130 @@ pub async fn handler(
     }
 
     let password_hash = bcrypt::hash(password, bcrypt::DEFAULT_COST).context("hash password")?;
+    let created_at = OffsetDateTime::now_utc();
+    let locale = preferred_locale(&headers);
 
     let user_id = sqlx::query!(
         r#"
         INSERT INTO users
-        (username, email, password_hash)
-        VALUES (?, ?, ?)
+        (username, email, password_hash, created_at, email_verified, locale)
+        VALUES (?, ?, ?, ?, ?, ?)
         RETURNING id as "user_id!"
         "#,
         username,
         email,
         password_hash,
+        created_at,
+        email_verified,
+        locale,
     )
     .fetch_one(&mut *tx)
     .await
     .context("insert user")?
     .user_id;
 
-    assign_permission_group(&mut *tx, user_id, "signup")
-        .await
-        .context("assign `signup` permission group")?;
-
-    tx.commit().await.context("commit transaction :: signup")?;
+    let group = match &invitation {
+        Some(invitation) => {
+            let accepted = sqlx::query!(
+                r#"
+                UPDATE invitations SET accepted_at = ?, user_id = ?
+                WHERE id = ? AND accepted_at IS NULL
+                "#,
+                created_at,
+                user_id,
+                invitation.id
+            )
+            .execute(&mut *tx)
+            .await
+            .context("accept invitation")?;
+
+            if accepted.rows_affected() == 0 {
+                return Err(Error::InvalidInvitation);
+            }
 
-    #[cfg(feature = "smtp")]
-    {
-        use super::email::{
-            SendVerificationEmailError, send_verification_email, verification_link,
-            verification_token,
-        };
-        use crate::{secrets::Secrets, smtp::Smtp};
-        use lettre::transport::smtp::response::Response as SmtpResponse;
-
-        async fn initiate_email_verification(
-            smtp: &Smtp,
-            secrets: &Secrets,
-            host: &str,
-            email: Email,
-        ) -> Result<SmtpResponse, InitiateEmailVerificationError> {
-            let hmac_secret = secrets.get("hmac").context("get HMAC key")?;
-            let verification_token = verification_token(email.clone());
-            let verification_link = verification_link(&hmac_secret, &host, &verification_token)
-                .context("base64 encode email verification link")?;
-            let response = send_verification_email(&smtp, &email, &verification_link).await?;
-            Ok(response)
+            invitation.permission_group.as_str()
         }
+        None => "signup",
+    };
 
-        #[derive(thiserror::Error, Debug)]
-        enum InitiateEmailVerificationError {
-            #[error("{0}")]
-            TokenEncodeError(#[from] contextual::Error<signature::EncodeError>),
+    assign_permission_group(&mut *tx, user_id, group)
+        .await
+        .context(format!("assign `{group}` permission group"))?;
 
-            #[error("{0}")]
-            SendVerificationEmail(#[from] SendVerificationEmailError),
+    #[cfg(feature = "smtp")]
+    if !email_verified {
+        use super::email::{issue_verification_token, verification_email, verification_link};
 
-            #[error("{0}")]
-            Io(#[from] contextual::Error<std::io::Error>),
-        }
+        let verification_token = issue_verification_token(&mut tx, email.clone())
+            .await
+            .context("issue verification token")?;
 
-        let _handle = tokio::spawn({
-            #[cfg(feature = "tracing")]
-            tracing::info!("spawn task to send verification email for {email}");
+        let signing_key = crate::signing::current(&secrets)?;
+        let verification_link = verification_link(&signing_key, &host, verification_token)
+            .context("base64 encode email verification link")?;
 
-            let fut = async move {
-                let _res = initiate_email_verification(&smtp, &secrets, &host, email).await;
+        let message = verification_email(&smtp, locale.as_deref(), &email, &verification_link)
+            .context("render verification email")?;
+        let _outbox_id = crate::outbox::enqueue(&mut *tx, &message)
+            .await
+            .context("enqueue verification email")?;
 
-                #[cfg(feature = "tracing")]
-                match _res {
-                    Ok(response) => match response.is_positive() {
-                        true => tracing::info!("{response:?}"),
-                        false => tracing::warn!("{response:?}"),
-                    },
-                    Err(err) => tracing::error!("{err:?}"),
-                }
-            };
+        #[cfg(feature = "tracing")]
+        tracing::info!(outbox_id = _outbox_id, "verification email queued");
+    }
 
-            #[cfg(feature = "tracing")]
-            {
-                use tracing::Instrument;
-                fut.instrument(tracing::Span::current())
-            }
+    #[cfg(feature = "webhooks")]
+    crate::webhook::enqueue(
+        &mut *tx,
+        &crate::webhook::Event::UserCreated { user_id, username },
+    )
+    .await?;
 
-            #[cfg(not(feature = "tracing"))]
-            fut
-        });
+    tx.commit().await.context("commit transaction :: signup")?;
 
-        #[cfg(feature = "await-tasks")]
-        {
-            if let Err(err) = _handle.await {
-                #[cfg(feature = "tracing")]
-                tracing::error!("failed to await email verification task: {err:?}");
-            }
-        }
+    Ok(StatusCode::CREATED)
+}
+
+/// The invitation token and group from the encrypted token of an invitation link, see
+/// [`invitation_link`](super::admin::invitations::invitation_link). Bare invitation tokens, as
+/// returned to the inviter, are passed through without a group.
+#[cfg(feature = "smtp")]
+fn open_invitation_link(
+    secrets: &secrets::Secrets,
+    invitation: String,
+) -> Result<(String, Option<String>), Error> {
+    use super::admin::invitations::{INVITATION_AUDIENCE, InvitationClaims};
+
+    if InvitationToken::base64decode(&invitation).is_some() {
+        return Ok((invitation, None));
     }
 
-    Ok(StatusCode::CREATED)
+    let mut key_lookup = Ok(());
+    let decrypted =
+        signature::Encrypted::<InvitationClaims>::decrypt_with_keys(&invitation, |kid| {
+            match crate::encryption::key(secrets, kid) {
+                Ok(key) => key,
+                Err(err) => {
+                    key_lookup = Err(err);
+                    None
+                }
+            }
+        });
+    key_lookup?;
+
+    let claims = match decrypted {
+        Ok(encrypted) => encrypted
+            .token(&signature::Validation::audience(INVITATION_AUDIENCE))
+            .map_err(|err| match err {
+                signature::ValidationError::Temporal(_) => Error::InvitationExpired,
+                _ => Error::InvalidInvitation,
+            })?,
+        // the version of the key that encrypted the link is past its grace period
+        Err(signature::DecryptError::UnknownKey(_)) => return Err(Error::InvitationExpired),
+        Err(_) => return Err(Error::InvalidInvitation),
+    };
+
+    Ok((claims.invitation, Some(claims.group)))
 }
 
 impl extra::ErrorKind for Error {
@@ -216,10 +392,25 @@ impl extra::ErrorKind for Error {
             Error::InvalidUsername(_) => "username.invalid",
             Error::InvalidEmailFormat(_) => "email.invalid",
             Error::WeakPassword(_) => "password.weak",
+            Error::SignupPolicy(err) => err.kind(),
+            Error::InvitationRequired => "invitation.required",
+            Error::InvalidInvitation => "invitation.invalid",
+            Error::InvitationExpired => "invitation.expired",
+            Error::InvitationEmailMismatch => "invitation.email-mismatch",
             Error::UsernameExists(_) => "username.exists",
             Error::EmailExists(_) => "email.exists",
             Error::Sqlx(_) => "sqlx",
+            #[cfg(feature = "webhooks")]
+            Error::Webhook(_) => "webhook",
             Error::Bcrypt(_) => "bcrypt",
+            #[cfg(feature = "smtp")]
+            Error::Io(_) => "io",
+            #[cfg(feature = "smtp")]
+            Error::TokenEncode(_) => "email.verification.token.encode",
+            #[cfg(feature = "smtp")]
+            Error::SigningKey(_) => "email.verification.signing-key",
+            #[cfg(feature = "smtp")]
+            Error::EmailTemplate(_) => "email.verification.email-template",
         }
     }
 }
@@ -233,6 +424,21 @@ impl IntoResponse for Error {
 
                 (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(self))).into_response()
             }
+            Error::InvalidInvitation
+            | Error::InvitationExpired
+            | Error::InvitationEmailMismatch => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::SignupPolicy(err) => err.into_response(),
+            Error::InvitationRequired => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::FORBIDDEN, Json(ErrorResponse::from(self))).into_response()
+            }
             Error::UsernameExists(_) | Error::EmailExists(_) => {
                 #[cfg(feature = "tracing")]
                 tracing::info!("{:?}", self);
@@ -243,6 +449,23 @@ impl IntoResponse for Error {
                 #[cfg(feature = "tracing")]
                 tracing::error!("{:?}", self);
 
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+            #[cfg(feature = "webhooks")]
+            Error::Webhook(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+            #[cfg(feature = "smtp")]
+            Error::Io(_)
+            | Error::TokenEncode(_)
+            | Error::SigningKey(_)
+            | Error::EmailTemplate(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
                 StatusCode::INTERNAL_SERVER_ERROR.into_response()
             }
         }
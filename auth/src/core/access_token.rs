@@ -8,7 +8,9 @@ use http::StatusCode;
 use time::OffsetDateTime;
 use token::Token;
 
-use crate::core::{Credentials, Permission, Verified, permission::Authorizable};
+use crate::core::{
+    Credentials, Permission, Verified, has_org_permission, permission::Authorizable,
+};
 
 pub struct AccessToken(Token<32>);
 
@@ -51,6 +53,10 @@ pub struct AccessTokenInfo {
     pub user_id: i64,
     pub created_at: OffsetDateTime,
     pub expires_at: OffsetDateTime,
+
+    /// set when the token is bound to a single organization.
+    /// such tokens hold no global permissions and only act within that organization
+    pub organization_id: Option<i64>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -73,7 +79,7 @@ impl AccessToken {
         sqlx::query_as!(
             AccessTokenInfo,
             r#"
-            SELECT id as "id!", name, user_id, created_at, expires_at
+            SELECT id as "id!", name, user_id, created_at, expires_at, organization_id
             FROM access_tokens
             WHERE access_token_hash = ?
             "#,
@@ -118,6 +124,10 @@ impl Authorizable for Verified<AccessTokenInfo> {
         pool: &sqlx::Pool<sqlx::Sqlite>,
         permission: &str,
     ) -> Result<bool, sqlx::Error> {
+        if self.0.organization_id.is_some() {
+            return Ok(false);
+        }
+
         let access_token_id = self.0.id;
 
         let exists = sqlx::query_scalar!(
@@ -125,7 +135,10 @@ impl Authorizable for Verified<AccessTokenInfo> {
             SELECT EXISTS(
                 SELECT 1 FROM permissions p
                 INNER JOIN access_token_permissions atp ON atp.permission_id = p.id
+                INNER JOIN access_tokens a ON a.id = atp.access_token_id
+                INNER JOIN users u ON u.id = a.user_id
                 WHERE atp.access_token_id = ? AND p.permission = ?
+                AND (u.email_verified OR p.permission NOT IN (SELECT permission FROM verified_email_permissions))
             )
             "#,
             access_token_id,
@@ -141,6 +154,10 @@ impl Authorizable for Verified<AccessTokenInfo> {
         &self,
         pool: &sqlx::Pool<sqlx::Sqlite>,
     ) -> Result<Vec<Permission>, sqlx::Error> {
+        if self.0.organization_id.is_some() {
+            return Ok(vec![]);
+        }
+
         let access_token_id = self.0.id;
 
         sqlx::query_as!(
@@ -148,13 +165,30 @@ impl Authorizable for Verified<AccessTokenInfo> {
             r#"
             SELECT p.id as "id!", p.permission, p.description from permissions p
             INNER JOIN access_token_permissions atp ON atp.permission_id = p.id
+            INNER JOIN access_tokens a ON a.id = atp.access_token_id
+            INNER JOIN users u ON u.id = a.user_id
             WHERE atp.access_token_id = ?
+            AND (u.email_verified OR p.permission NOT IN (SELECT permission FROM verified_email_permissions))
             "#,
             access_token_id
         )
         .fetch_all(pool)
         .await
     }
+
+    async fn has_org_permission(
+        &self,
+        pool: &sqlx::Pool<sqlx::Sqlite>,
+        organization_id: i64,
+        permission: &str,
+    ) -> Result<bool, sqlx::Error> {
+        // the token's own permissions are global ones, organization roles reach only bound tokens
+        if self.0.organization_id != Some(organization_id) {
+            return Ok(false);
+        }
+
+        has_org_permission(pool, self.0.user_id, organization_id, permission).await
+    }
 }
 
 impl Deref for AccessToken {
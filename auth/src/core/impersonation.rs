@@ -0,0 +1,86 @@
+use axum::{
+    Json,
+    response::{IntoResponse, Response},
+};
+use http::StatusCode;
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, Copy)]
+pub enum ImpersonationAction {
+    Start,
+    Stop,
+}
+
+/// Writes an entry to the impersonation audit log.
+/// An impersonation ends with a `stop` entry whenever its session is deleted, see
+/// [`log_impersonations_stopped`], but not when the session merely expires.
+pub async fn log_impersonation<'a, E: sqlx::Executor<'a, Database = sqlx::Sqlite>>(
+    ex: E,
+    impersonator_id: i64,
+    target_id: i64,
+    action: ImpersonationAction,
+) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+    let action = match action {
+        ImpersonationAction::Start => "start",
+        ImpersonationAction::Stop => "stop",
+    };
+    let now = OffsetDateTime::now_utc();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO impersonation_audit_log
+        (impersonator_id, target_id, action, datetime)
+        VALUES (?, ?, ?, ?)
+        "#,
+        impersonator_id,
+        target_id,
+        action,
+        now
+    )
+    .execute(ex)
+    .await
+}
+
+/// Writes a `stop` entry for each of the deleted sessions, given as the `(user_id, impersonator_id)`
+/// a `DELETE FROM sessions .. RETURNING` yields, that was an impersonation.
+pub async fn log_impersonations_stopped(
+    conn: &mut sqlx::SqliteConnection,
+    sessions: impl IntoIterator<Item = (i64, Option<i64>)>,
+) -> Result<(), sqlx::Error> {
+    for (user_id, impersonator_id) in sessions {
+        if let Some(impersonator_id) = impersonator_id {
+            log_impersonation(
+                &mut *conn,
+                impersonator_id,
+                user_id,
+                ImpersonationAction::Stop,
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("action not allowed while impersonating another user")]
+pub struct ImpersonationRestrictedError;
+
+impl extra::ErrorKind for ImpersonationRestrictedError {
+    fn kind(&self) -> &'static str {
+        "auth.impersonation.restricted"
+    }
+}
+
+impl IntoResponse for ImpersonationRestrictedError {
+    fn into_response(self) -> Response {
+        #[cfg(feature = "tracing")]
+        tracing::info!("{:?}", self);
+
+        (
+            StatusCode::FORBIDDEN,
+            Json(extra::ErrorResponse::from(self)),
+        )
+            .into_response()
+    }
+}
@@ -0,0 +1,75 @@
+use std::ops::Deref;
+
+use email::Email;
+use time::OffsetDateTime;
+use token::Token;
+
+pub struct InvitationToken(Token<32>);
+
+#[derive(Debug, Clone)]
+pub struct InvitationInfo {
+    pub id: i64,
+    pub email: Email,
+    pub permission_group: String,
+    pub expires_at: OffsetDateTime,
+    pub accepted_at: Option<OffsetDateTime>,
+}
+
+impl InvitationToken {
+    pub fn new() -> Self {
+        Self(Token::random())
+    }
+
+    pub fn base64decode(s: &str) -> Option<Self> {
+        Token::base64decode(s).ok().map(Self)
+    }
+
+    pub async fn info<'a, E: sqlx::Executor<'a, Database = sqlx::Sqlite>>(
+        &self,
+        ex: E,
+    ) -> Result<Option<InvitationInfo>, sqlx::Error> {
+        let invitation_token_hash = self.hash_sha256();
+
+        sqlx::query_as!(
+            InvitationInfo,
+            r#"
+            SELECT
+                i.id as "id!",
+                i.email as "email: Email",
+                pg.[group] as permission_group,
+                i.expires_at,
+                i.accepted_at as "accepted_at: OffsetDateTime"
+            FROM invitations i
+            INNER JOIN permission_groups pg ON pg.id = i.permission_group_id
+            WHERE i.invitation_token_hash = ?
+            "#,
+            invitation_token_hash
+        )
+        .fetch_optional(ex)
+        .await
+    }
+}
+
+impl Default for InvitationToken {
+    fn default() -> Self {
+        Self(Token::random())
+    }
+}
+
+impl InvitationInfo {
+    pub fn is_expired(&self) -> bool {
+        OffsetDateTime::now_utc() > self.expires_at
+    }
+
+    pub fn is_accepted(&self) -> bool {
+        self.accepted_at.is_some()
+    }
+}
+
+impl Deref for InvitationToken {
+    type Target = Token<32>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
@@ -0,0 +1,47 @@
+use http::{HeaderMap, header::ACCEPT_LANGUAGE};
+
+/// Locale used whenever no better match is available.
+#[cfg(feature = "smtp")]
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// The most preferred language tag of the `Accept-Language` header, lowercased (e.g. `de-at`).
+/// Wildcards and malformed tags are ignored.
+pub fn preferred_locale(headers: &HeaderMap) -> Option<String> {
+    let header = headers.get(ACCEPT_LANGUAGE)?.to_str().ok()?;
+
+    header
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split(';');
+            let tag = parts.next()?.trim();
+            let quality = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+
+            let valid = !tag.is_empty()
+                && tag.len() <= 35
+                && tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+
+            (valid && quality > 0.0).then(|| (tag.to_ascii_lowercase(), quality))
+        })
+        // the first of equally weighted tags wins
+        .fold(None::<(String, f32)>, |best, candidate| match best {
+            Some(best) if best.1 >= candidate.1 => Some(best),
+            _ => Some(candidate),
+        })
+        .map(|(tag, _)| tag)
+}
+
+/// Locales to try in order: the locale itself, its parent tags (`de-at` → `de`) and finally
+/// [`DEFAULT_LOCALE`].
+#[cfg(feature = "smtp")]
+pub fn locale_fallbacks(locale: Option<&str>) -> impl Iterator<Item = &str> {
+    let mut current = locale;
+
+    std::iter::from_fn(move || {
+        let locale = current?;
+        current = locale.rsplit_once('-').map(|(parent, _)| parent);
+        Some(locale)
+    })
+    .chain(std::iter::once(DEFAULT_LOCALE))
+}
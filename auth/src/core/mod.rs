@@ -1,6 +1,10 @@
 mod access_token;
 mod basic;
 mod credentials;
+mod impersonation;
+mod invitation;
+mod locale;
+mod organization;
 mod permission;
 mod principal;
 mod session;
@@ -12,6 +16,15 @@ pub use access_token::{
 };
 pub use basic::{Basic, BasicAuthorizationExtractionError};
 pub use credentials::Credentials;
+pub use impersonation::{
+    ImpersonationAction, ImpersonationRestrictedError, log_impersonation,
+    log_impersonations_stopped,
+};
+pub use invitation::InvitationToken;
+pub use locale::preferred_locale;
+#[cfg(feature = "smtp")]
+pub use locale::{DEFAULT_LOCALE, locale_fallbacks};
+pub use organization::{Organization, has_org_permission};
 pub use permission::{Authorizable, InsufficientPermissionsError, Permission};
 pub use principal::{Principal, PrincipalError};
 pub use session::{
@@ -20,6 +33,11 @@ pub use session::{
 };
 pub use user::UserInfo;
 
+/// Begins a transaction that takes the write lock upfront.
+/// SQLite fails a deferred transaction that reads and then writes while another connection (e.g.
+/// the outbox worker) is writing, instead of waiting for the busy timeout.
+pub const BEGIN_WRITE: &str = "BEGIN IMMEDIATE";
+
 pub struct Verified<T>(T);
 
 impl<T> Verified<T> {
@@ -67,3 +85,113 @@ pub async fn assign_permission_group<'a, E: sqlx::Executor<'a, Database = sqlx::
     .execute(ex)
     .await
 }
+
+/// Replaces the permissions that are withheld from users whose email is not verified.
+pub async fn sync_verified_email_permissions(
+    pool: &sqlx::Pool<sqlx::Sqlite>,
+    permissions: &[String],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin_with(BEGIN_WRITE).await?;
+
+    sqlx::query!("DELETE FROM verified_email_permissions")
+        .execute(&mut *tx)
+        .await?;
+
+    for permission in permissions {
+        sqlx::query!(
+            r#"
+            INSERT INTO verified_email_permissions (permission) VALUES (?)
+            ON CONFLICT (permission) DO NOTHING
+            "#,
+            permission
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await
+}
+
+/// Permanently removes the user along with everything that references it
+/// (sessions, access tokens and their permissions, user permissions, organization memberships,
+/// pending email verification tokens, queued and sent emails).
+/// The permissions audit log is intentionally left untouched,
+/// ended impersonations are added to the impersonation audit log.
+pub async fn delete_user(
+    conn: &mut sqlx::SqliteConnection,
+    user_id: i64,
+) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error> {
+    let sessions = sqlx::query!(
+        r#"
+        DELETE FROM sessions WHERE user_id = ?1 OR impersonator_id = ?1
+        RETURNING user_id, impersonator_id
+        "#,
+        user_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    log_impersonations_stopped(
+        &mut *conn,
+        sessions
+            .into_iter()
+            .map(|session| (session.user_id, session.impersonator_id)),
+    )
+    .await?;
+
+    // access_token_permissions are removed by `ON DELETE CASCADE`
+    sqlx::query!("DELETE FROM access_tokens WHERE user_id = ?", user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query!("DELETE FROM user_permissions WHERE user_id = ?", user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query!(
+        "DELETE FROM organization_memberships WHERE user_id = ?",
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM email_verification_tokens WHERE email = (SELECT email FROM users WHERE id = ?)",
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM email_outbox WHERE recipient = (SELECT email FROM users WHERE id = ?)",
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!("DELETE FROM users WHERE id = ?", user_id)
+        .execute(&mut *conn)
+        .await
+}
+
+/// Deletes every account whose deletion grace period has elapsed.
+/// Returns the number of deleted accounts.
+pub async fn purge_scheduled_account_deletions(
+    pool: &sqlx::Pool<sqlx::Sqlite>,
+) -> Result<usize, sqlx::Error> {
+    let now = time::OffsetDateTime::now_utc();
+
+    let user_ids = sqlx::query_scalar!(
+        r#"SELECT id as "id!" FROM users WHERE deletion_scheduled_at <= ?"#,
+        now
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for user_id in &user_ids {
+        let mut tx = pool.begin_with(BEGIN_WRITE).await?;
+        delete_user(&mut tx, *user_id).await?;
+        tx.commit().await?;
+    }
+
+    Ok(user_ids.len())
+}
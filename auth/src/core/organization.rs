@@ -0,0 +1,63 @@
+use serde::Serialize;
+use time::OffsetDateTime;
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize)]
+pub struct Organization {
+    #[cfg_attr(feature = "openapi", schema(examples(1)))]
+    pub id: i64,
+
+    #[cfg_attr(feature = "openapi", schema(examples("acme")))]
+    pub name: String,
+
+    #[cfg_attr(feature = "openapi", schema(value_type = String, format = DateTime))]
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+impl Organization {
+    pub async fn from_name<'a, E: sqlx::Executor<'a, Database = sqlx::Sqlite>>(
+        name: &str,
+        ex: E,
+    ) -> Result<Option<Organization>, sqlx::Error> {
+        sqlx::query_as!(
+            Organization,
+            r#"
+            SELECT id as "id!", name, created_at as "created_at: OffsetDateTime"
+            FROM organizations WHERE name = ?
+            "#,
+            name
+        )
+        .fetch_optional(ex)
+        .await
+    }
+}
+
+/// Checks whether the role (permission group) of the user
+/// within the organization grants the permission.
+pub async fn has_org_permission(
+    pool: &sqlx::Pool<sqlx::Sqlite>,
+    user_id: i64,
+    organization_id: i64,
+    permission: &str,
+) -> Result<bool, sqlx::Error> {
+    let exists = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM organization_memberships om
+            INNER JOIN permission_group_association pga ON pga.permission_group_id = om.permission_group_id
+            INNER JOIN permissions p ON p.id = pga.permission_id
+            INNER JOIN users u ON u.id = om.user_id
+            WHERE om.user_id = ? AND om.organization_id = ? AND p.permission = ?
+            AND (u.email_verified OR p.permission NOT IN (SELECT permission FROM verified_email_permissions))
+        )
+        "#,
+        user_id,
+        organization_id,
+        permission
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(exists != 0)
+}
@@ -36,6 +36,15 @@ pub trait Authorizable {
         Ok(permissions.iter().any(|p| p.permission == permission))
     }
 
+    /// Checks the permission against the principal's role within the organization
+    /// instead of its global permissions.
+    async fn has_org_permission(
+        &self,
+        pool: &sqlx::Pool<sqlx::Sqlite>,
+        organization_id: i64,
+        permission: &str,
+    ) -> Result<bool, sqlx::Error>;
+
     async fn require_permission<E>(
         &self,
         pool: &sqlx::Pool<sqlx::Sqlite>,
@@ -56,6 +65,29 @@ pub trait Authorizable {
             Err(e) => Err(e.into()),
         }
     }
+
+    async fn require_org_permission<E>(
+        &self,
+        pool: &sqlx::Pool<sqlx::Sqlite>,
+        organization_id: i64,
+        permission: &str,
+    ) -> Result<(), E>
+    where
+        E: std::error::Error
+            + From<InsufficientPermissionsError>
+            + From<contextual::Error<sqlx::Error>>,
+    {
+        match self
+            .has_org_permission(pool, organization_id, permission)
+            .await
+            .context(format!(
+                "require_org_permission `{permission}` (organization_id: {organization_id})"
+            )) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(InsufficientPermissionsError.into()),
+            Err(e) => Err(e.into()),
+        }
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -8,17 +8,26 @@ use axum::{
 use contextual::Context;
 use http::{HeaderMap, StatusCode, request::Parts};
 
-use crate::core::{
-    AccessToken, AccessTokenAuthorizationExtractionError, AccessTokenInfo,
-    AccessTokenValidationError, Basic, BasicAuthorizationExtractionError, Credentials,
-    InsufficientPermissionsError, Permission, SessionCookieExtractionError, SessionId, SessionInfo,
-    SessionValidationError, UserInfo, Verified, permission::Authorizable,
+use crate::{
+    core::{
+        AccessToken, AccessTokenAuthorizationExtractionError, AccessTokenInfo,
+        AccessTokenValidationError, Basic, BasicAuthorizationExtractionError, Credentials,
+        ImpersonationRestrictedError, InsufficientPermissionsError, Permission,
+        SessionCookieExtractionError, SessionId, SessionInfo, SessionValidationError, UserInfo,
+        Verified, permission::Authorizable,
+    },
+    signup_policy::SignupPolicy,
 };
 
 pub enum Principal {
     Session(Verified<SessionInfo>),
     AccessToken(Verified<AccessTokenInfo>),
     Basic(Verified<UserInfo>),
+
+    /// A session of `user_id` that was started by `impersonator_id`.
+    /// Acts with the permissions of the impersonated user,
+    /// but sensitive actions must be guarded with [`Principal::forbid_impersonation`].
+    Impersonation(Verified<SessionInfo>),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -53,6 +62,15 @@ pub enum PrincipalError {
     #[error("no credentials provided")]
     NoCredentialsProvided,
 
+    #[error("account is disabled")]
+    AccountDisabled,
+
+    #[error("account is scheduled for deletion, log in to cancel")]
+    AccountDeletionScheduled,
+
+    #[error("email is not verified")]
+    EmailUnverified,
+
     #[error("{0}")]
     Sqlx(#[from] contextual::Error<sqlx::Error>),
 
@@ -66,6 +84,21 @@ impl Principal {
             Principal::Session(info) => info.user_id,
             Principal::AccessToken(info) => info.user_id,
             Principal::Basic(info) => info.user_id,
+            Principal::Impersonation(info) => info.user_id,
+        }
+    }
+
+    pub fn impersonator_id(&self) -> Option<i64> {
+        match self {
+            Principal::Impersonation(info) => info.impersonator_id,
+            _ => None,
+        }
+    }
+
+    pub fn forbid_impersonation(&self) -> Result<(), ImpersonationRestrictedError> {
+        match self {
+            Principal::Impersonation(_) => Err(ImpersonationRestrictedError),
+            _ => Ok(()),
         }
     }
 
@@ -83,6 +116,7 @@ impl Principal {
             Principal::Session(info) => info.require_permission(pool, permission).await,
             Principal::AccessToken(info) => info.require_permission(pool, permission).await,
             Principal::Basic(info) => info.require_permission(pool, permission).await,
+            Principal::Impersonation(info) => info.require_permission(pool, permission).await,
         }
     }
 
@@ -95,6 +129,38 @@ impl Principal {
             Principal::Session(info) => info.has_permission(pool, permission).await,
             Principal::AccessToken(info) => info.has_permission(pool, permission).await,
             Principal::Basic(info) => info.has_permission(pool, permission).await,
+            Principal::Impersonation(info) => info.has_permission(pool, permission).await,
+        }
+    }
+
+    pub async fn require_org_permission<E>(
+        &self,
+        pool: &sqlx::Pool<sqlx::Sqlite>,
+        organization_id: i64,
+        permission: &str,
+    ) -> Result<(), E>
+    where
+        E: std::error::Error
+            + From<InsufficientPermissionsError>
+            + From<contextual::Error<sqlx::Error>>,
+    {
+        match self {
+            Principal::Session(info) => {
+                info.require_org_permission(pool, organization_id, permission)
+                    .await
+            }
+            Principal::AccessToken(info) => {
+                info.require_org_permission(pool, organization_id, permission)
+                    .await
+            }
+            Principal::Basic(info) => {
+                info.require_org_permission(pool, organization_id, permission)
+                    .await
+            }
+            Principal::Impersonation(info) => {
+                info.require_org_permission(pool, organization_id, permission)
+                    .await
+            }
         }
     }
 
@@ -106,12 +172,50 @@ impl Principal {
             Principal::Session(info) => info.permissions(pool).await,
             Principal::AccessToken(info) => info.permissions(pool).await,
             Principal::Basic(info) => info.permissions(pool).await,
+            Principal::Impersonation(info) => info.permissions(pool).await,
         }
     }
 
     pub async fn from(
         headers: &HeaderMap,
         pool: &sqlx::Pool<sqlx::Sqlite>,
+        signup: &SignupPolicy,
+    ) -> Result<Self, PrincipalError> {
+        let principal = Self::authenticate(headers, pool).await?;
+
+        for user_id in std::iter::once(principal.user_id()).chain(principal.impersonator_id()) {
+            if UserInfo::is_disabled(user_id, pool)
+                .await
+                .context("is user disabled")?
+            {
+                return Err(PrincipalError::AccountDisabled);
+            }
+
+            // only logging in again cancels the deletion, see `login`
+            if UserInfo::is_deletion_scheduled(user_id, pool)
+                .await
+                .context("is account deletion scheduled")?
+            {
+                return Err(PrincipalError::AccountDeletionScheduled);
+            }
+        }
+
+        // impersonators are deliberately let in to look into the account
+        if signup.require_verified_email
+            && principal.impersonator_id().is_none()
+            && !UserInfo::is_email_verified(principal.user_id(), pool)
+                .await
+                .context("is email verified")?
+        {
+            return Err(PrincipalError::EmailUnverified);
+        }
+
+        Ok(principal)
+    }
+
+    async fn authenticate(
+        headers: &HeaderMap,
+        pool: &sqlx::Pool<sqlx::Sqlite>,
     ) -> Result<Self, PrincipalError> {
         if let Some(access_token) = AccessToken::try_from_headers(headers)? {
             let info = access_token
@@ -142,7 +246,10 @@ impl Principal {
                 .context("SessionId -> SessionInfo")?
                 .ok_or(PrincipalError::UnAssociatedSessionId)?;
             let validated_info = info.validate()?;
-            return Ok(Principal::Session(validated_info));
+            return match validated_info.impersonator_id {
+                Some(_) => Ok(Principal::Impersonation(validated_info)),
+                None => Ok(Principal::Session(validated_info)),
+            };
         }
 
         Err(PrincipalError::NoCredentialsProvided)
@@ -153,6 +260,7 @@ impl<S> FromRequestParts<S> for Principal
 where
     S: Send + Sync,
     sqlx::Pool<sqlx::Sqlite>: FromRef<S>,
+    std::sync::Arc<SignupPolicy>: FromRef<S>,
 {
     type Rejection = PrincipalError;
 
@@ -160,7 +268,12 @@ where
         Parts { headers, .. }: &mut Parts,
         state: &S,
     ) -> Result<Self, Self::Rejection> {
-        Principal::from(headers, &sqlx::Pool::<sqlx::Sqlite>::from_ref(state)).await
+        Principal::from(
+            headers,
+            &sqlx::Pool::<sqlx::Sqlite>::from_ref(state),
+            &std::sync::Arc::<SignupPolicy>::from_ref(state),
+        )
+        .await
     }
 }
 
@@ -171,6 +284,9 @@ impl extra::ErrorKind for PrincipalError {
             PrincipalError::UnAssociatedSessionId => "auth.session.id.unassociated",
             PrincipalError::InvalidBasicCredentials => "auth.basic.invalid-credentials",
             PrincipalError::NoCredentialsProvided => "auth.no-credentials",
+            PrincipalError::AccountDisabled => "auth.account.disabled",
+            PrincipalError::AccountDeletionScheduled => "auth.account.deletion-scheduled",
+            PrincipalError::EmailUnverified => "auth.email.unverified",
             PrincipalError::UsernameNotFound(_) => "auth.basic.username.not-found",
             PrincipalError::AccessTokenAuthorizationExtraction(err) => err.kind(),
             PrincipalError::BasicAuthorizationExtraction(err) => err.kind(),
@@ -199,6 +315,17 @@ impl IntoResponse for PrincipalError {
                 )
                     .into_response()
             }
+            PrincipalError::AccountDisabled
+            | PrincipalError::AccountDeletionScheduled
+            | PrincipalError::EmailUnverified => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+                (
+                    StatusCode::FORBIDDEN,
+                    Json(extra::ErrorResponse::from(self)),
+                )
+                    .into_response()
+            }
             PrincipalError::AccessTokenAuthorizationExtraction(err) => err.into_response(),
             PrincipalError::BasicAuthorizationExtraction(err) => err.into_response(),
             PrincipalError::SessionCookieExtraction(err) => err.into_response(),
@@ -227,6 +354,15 @@ impl Display for Principal {
             Principal::Basic(user_info) => {
                 write!(f, "Principal::Basic::(user_id: {})", user_info.user_id)
             }
+            Principal::Impersonation(session_info) => write!(
+                f,
+                "Principal::Impersonation::(user_id: {}, impersonator_id: {})",
+                session_info.user_id,
+                session_info
+                    .impersonator_id
+                    .map(|id| id.to_string())
+                    .unwrap_or_default()
+            ),
         }
     }
 }
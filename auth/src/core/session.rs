@@ -9,7 +9,9 @@ use http::{StatusCode, header::COOKIE};
 use time::OffsetDateTime;
 use token::Token;
 
-use crate::core::{Credentials, Permission, Verified, permission::Authorizable};
+use crate::core::{
+    Credentials, Permission, Verified, has_org_permission, permission::Authorizable,
+};
 
 const SESSION_ID: &str = "session_id";
 
@@ -51,6 +53,9 @@ pub struct SessionInfo {
     pub created_at: OffsetDateTime,
     pub expires_at: OffsetDateTime,
     pub user_agent: Option<String>,
+
+    /// set when the session was created by an admin impersonating `user_id`
+    pub impersonator_id: Option<i64>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -83,7 +88,7 @@ impl SessionId {
         sqlx::query_as!(
             SessionInfo,
             r#"
-            SELECT user_id, created_at, expires_at, user_agent
+            SELECT user_id, created_at, expires_at, user_agent, impersonator_id
             FROM sessions WHERE session_id_hash = ?
             "#,
             session_id_hash
@@ -144,7 +149,9 @@ impl Authorizable for Verified<SessionInfo> {
             SELECT EXISTS(
                 SELECT 1 FROM permissions p
                 INNER JOIN user_permissions up ON up.permission_id = p.id
+                INNER JOIN users u ON u.id = up.user_id
                 WHERE up.user_id = ? AND p.permission = ?
+                AND (u.email_verified OR p.permission NOT IN (SELECT permission FROM verified_email_permissions))
             )
             "#,
             user_id,
@@ -167,13 +174,24 @@ impl Authorizable for Verified<SessionInfo> {
             r#"
             SELECT p.id as "id!", p.permission, p.description from permissions p
             INNER JOIN user_permissions up ON up.permission_id = p.id
+            INNER JOIN users u ON u.id = up.user_id
             WHERE up.user_id = ?
+            AND (u.email_verified OR p.permission NOT IN (SELECT permission FROM verified_email_permissions))
             "#,
             user_id
         )
         .fetch_all(pool)
         .await
     }
+
+    async fn has_org_permission(
+        &self,
+        pool: &sqlx::Pool<sqlx::Sqlite>,
+        organization_id: i64,
+        permission: &str,
+    ) -> Result<bool, sqlx::Error> {
+        has_org_permission(pool, self.0.user_id, organization_id, permission).await
+    }
 }
 
 impl Deref for SessionId {
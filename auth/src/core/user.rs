@@ -1,13 +1,27 @@
 use bcrypt::verify;
+use serde::Serialize;
+use time::OffsetDateTime;
 
 use email::Email;
 
-use crate::core::{Permission, Verified, permission::Authorizable};
+use crate::core::{Permission, Verified, has_org_permission, permission::Authorizable};
 
+#[derive(Serialize)]
 pub struct UserInfo {
     pub user_id: i64,
     pub username: String,
     pub email: Email,
+    pub email_verified: bool,
+    pub disabled: bool,
+    pub locale: Option<String>,
+
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub created_at: Option<OffsetDateTime>,
+
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub deletion_scheduled_at: Option<OffsetDateTime>,
+
+    #[serde(skip)]
     password_hash: String,
 }
 
@@ -21,13 +35,27 @@ impl UserInfo {
             user_id: i64,
             username: String,
             email: String,
+            email_verified: bool,
+            disabled: bool,
+            locale: Option<String>,
+            created_at: Option<OffsetDateTime>,
+            deletion_scheduled_at: Option<OffsetDateTime>,
             password_hash: String,
         }
 
         let record = sqlx::query_as!(
             Row,
             r#"
-            SELECT id as "user_id!", username, email, password_hash
+            SELECT
+                id as "user_id!",
+                username,
+                email,
+                email_verified,
+                disabled,
+                locale,
+                created_at as "created_at: OffsetDateTime",
+                deletion_scheduled_at as "deletion_scheduled_at: OffsetDateTime",
+                password_hash
             FROM users WHERE id = ?
             "#,
             user_id
@@ -40,6 +68,11 @@ impl UserInfo {
                 user_id: record.user_id,
                 username: record.username,
                 email: Email::try_from_sqlx(record.email)?,
+                email_verified: record.email_verified,
+                disabled: record.disabled,
+                locale: record.locale,
+                created_at: record.created_at,
+                deletion_scheduled_at: record.deletion_scheduled_at,
                 password_hash: record.password_hash,
             })),
             None => Ok(None),
@@ -55,13 +88,27 @@ impl UserInfo {
             user_id: i64,
             username: String,
             email: String,
+            email_verified: bool,
+            disabled: bool,
+            locale: Option<String>,
+            created_at: Option<OffsetDateTime>,
+            deletion_scheduled_at: Option<OffsetDateTime>,
             password_hash: String,
         }
 
         let record = sqlx::query_as!(
             Row,
             r#"
-            SELECT id as "user_id!", username, email, password_hash
+            SELECT
+                id as "user_id!",
+                username,
+                email,
+                email_verified,
+                disabled,
+                locale,
+                created_at as "created_at: OffsetDateTime",
+                deletion_scheduled_at as "deletion_scheduled_at: OffsetDateTime",
+                password_hash
             FROM users WHERE username = ?
             "#,
             username
@@ -74,12 +121,55 @@ impl UserInfo {
                 user_id: record.user_id,
                 username: record.username,
                 email: Email::try_from_sqlx(record.email)?,
+                email_verified: record.email_verified,
+                disabled: record.disabled,
+                locale: record.locale,
+                created_at: record.created_at,
+                deletion_scheduled_at: record.deletion_scheduled_at,
                 password_hash: record.password_hash,
             })),
             None => Ok(None),
         }
     }
 
+    pub async fn is_disabled(
+        user_id: i64,
+        pool: &sqlx::Pool<sqlx::Sqlite>,
+    ) -> Result<bool, sqlx::Error> {
+        let disabled = sqlx::query_scalar!(r#"SELECT disabled FROM users WHERE id = ?"#, user_id)
+            .fetch_optional(pool)
+            .await?;
+
+        Ok(disabled.unwrap_or(false))
+    }
+
+    pub async fn is_email_verified(
+        user_id: i64,
+        pool: &sqlx::Pool<sqlx::Sqlite>,
+    ) -> Result<bool, sqlx::Error> {
+        let email_verified =
+            sqlx::query_scalar!(r#"SELECT email_verified FROM users WHERE id = ?"#, user_id)
+                .fetch_optional(pool)
+                .await?;
+
+        Ok(email_verified.unwrap_or(false))
+    }
+
+    /// Whether the user asked to delete their account and has not logged in since.
+    pub async fn is_deletion_scheduled(
+        user_id: i64,
+        pool: &sqlx::Pool<sqlx::Sqlite>,
+    ) -> Result<bool, sqlx::Error> {
+        let deletion_scheduled_at = sqlx::query_scalar!(
+            r#"SELECT deletion_scheduled_at FROM users WHERE id = ?"#,
+            user_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(deletion_scheduled_at.flatten().is_some())
+    }
+
     pub fn verify_password(
         self,
         password: &str,
@@ -105,7 +195,9 @@ impl Authorizable for Verified<UserInfo> {
             SELECT EXISTS(
                 SELECT 1 FROM permissions p
                 INNER JOIN user_permissions up ON up.permission_id = p.id
+                INNER JOIN users u ON u.id = up.user_id
                 WHERE up.user_id = ? AND p.permission = ?
+                AND (u.email_verified OR p.permission NOT IN (SELECT permission FROM verified_email_permissions))
             )
             "#,
             user_id,
@@ -128,11 +220,22 @@ impl Authorizable for Verified<UserInfo> {
             r#"
             SELECT p.id as "id!", p.permission, p.description FROM permissions p
             INNER JOIN user_permissions up ON up.permission_id = p.id
+            INNER JOIN users u ON u.id = up.user_id
             WHERE up.user_id = ?
+            AND (u.email_verified OR p.permission NOT IN (SELECT permission FROM verified_email_permissions))
             "#,
             user_id
         )
         .fetch_all(pool)
         .await
     }
+
+    async fn has_org_permission(
+        &self,
+        pool: &sqlx::Pool<sqlx::Sqlite>,
+        organization_id: i64,
+        permission: &str,
+    ) -> Result<bool, sqlx::Error> {
+        has_org_permission(pool, self.0.user_id, organization_id, permission).await
+    }
 }
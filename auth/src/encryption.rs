@@ -0,0 +1,45 @@
+//! The key auth encrypts tokens with, see [`signature::Encrypted`], e.g. for links whose content
+//! must not show in the email they are sent with.
+//!
+//! The key is derived from the `encryption` secret in [`Secrets`] and rotates with it, tokens record
+//! the version that encrypted them as their kid.
+
+use std::io;
+
+use contextual::Context;
+use secrets::Secrets;
+use signature::EncryptionKey;
+
+/// Name of the secret the key is derived from.
+pub const KEY: &str = "encryption";
+
+/// Creates the secret unless there is one already.
+pub fn init(secrets: &Secrets) -> Result<(), contextual::Error<io::Error>> {
+    match secrets.versions(KEY) {
+        Ok(_) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => secrets
+            .rotate(KEY)
+            .map(|_kid| ())
+            .context("create encryption key"),
+        Err(err) => Err(err).context("encryption key versions"),
+    }
+}
+
+/// The current version of the key along with its kid.
+pub fn current(
+    secrets: &Secrets,
+) -> Result<(Option<String>, EncryptionKey), contextual::Error<io::Error>> {
+    let version = secrets.current(KEY).context("get encryption key")?;
+    Ok((version.kid, EncryptionKey::derive(&version.material)))
+}
+
+/// The key that decrypts a token with `kid`, `None` once the version is gone.
+pub fn key(
+    secrets: &Secrets,
+    kid: Option<&str>,
+) -> Result<Option<EncryptionKey>, contextual::Error<io::Error>> {
+    Ok(secrets
+        .get_version(KEY, kid)
+        .context("get encryption key")?
+        .map(|material| EncryptionKey::derive(&material)))
+}
@@ -1,28 +1,58 @@
 mod api;
 mod core;
-mod secrets;
+mod signup_policy;
 
 #[cfg(feature = "tracing")]
 mod span;
 
+#[cfg(feature = "smtp")]
+mod notification;
+
+#[cfg(feature = "smtp")]
+mod outbox;
+
+#[cfg(any(feature = "smtp", feature = "webhooks"))]
+mod retry;
+
+#[cfg(feature = "smtp")]
+mod encryption;
+
+#[cfg(feature = "smtp")]
+mod signing;
+
 #[cfg(feature = "smtp")]
 mod smtp;
 
+#[cfg(feature = "webhooks")]
+mod webhook;
+
+#[cfg(feature = "smtp")]
+pub use smtp::{InMemoryTransport, SentEmail};
+
+pub use secrets::sealing::{
+    DirReport, MasterKey, MasterKeyError, MasterKeySource, rekey_dir, seal_dir, unseal_dir,
+};
+
 use std::net::SocketAddr;
 
 use axum::{Router, extract::FromRef, middleware::from_fn};
 use contextual::Context;
 use http::HeaderName;
+use secrets::Secrets;
 use tokio::net::TcpListener;
 use tower::ServiceBuilder;
 use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
 
-use crate::secrets::Secrets;
-
 #[derive(Debug)]
 pub struct ServerOpts {
     pub database: DatabaseConfig,
     pub secrets_dir: std::path::PathBuf,
+    /// How long a rotated-out secret version keeps verifying what it signed.
+    pub secret_rotation_grace_period: std::time::Duration,
+    /// When set, key material in `secrets_dir` is encrypted at rest under this key.
+    pub secrets_master_key: Option<MasterKeySource>,
+    pub account: AccountConfig,
+    pub signup: SignupConfig,
 
     #[cfg(feature = "rate-limit")]
     pub rate_limiter: RateLimiterConfig,
@@ -32,6 +62,9 @@ pub struct ServerOpts {
 
     #[cfg(feature = "smtp")]
     pub smtp: SmtpConfig,
+
+    #[cfg(feature = "webhooks")]
+    pub webhooks: WebhookConfig,
 }
 
 #[derive(Debug)]
@@ -39,6 +72,42 @@ pub struct DatabaseConfig {
     pub url: String,
 }
 
+#[derive(Debug, Clone)]
+pub struct AccountConfig {
+    /// How long a self-deleted account is kept around (and can be restored by logging in)
+    /// before it is permanently purged. A zero duration deletes the account immediately.
+    pub deletion_grace_period: std::time::Duration,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SignupConfig {
+    /// When set, `/signup` only accepts requests carrying a valid invitation token.
+    pub invite_only: bool,
+
+    /// Only emails of these domains (or their subdomains) may sign up.
+    /// An empty list allows every domain.
+    pub allowed_domains: Vec<String>,
+
+    /// Emails of these domains (or their subdomains) may not sign up.
+    pub blocked_domains: Vec<String>,
+
+    /// Rejects signups from disposable email providers.
+    pub block_disposable_domains: bool,
+
+    /// File with one domain per line (`#` starts a comment) that replaces
+    /// the bundled list of disposable email providers.
+    pub disposable_domains_file: Option<std::path::PathBuf>,
+
+    /// Users have to verify their email before they can log in.
+    pub require_verified_email: bool,
+
+    /// Permissions that are withheld from users whose email is not verified.
+    pub verified_email_permissions: Vec<String>,
+
+    /// Following the email verification link also logs the user in.
+    pub login_after_email_verification: bool,
+}
+
 #[cfg(feature = "rate-limit")]
 #[derive(Debug, Clone)]
 pub struct RateLimiterConfig {
@@ -49,18 +118,69 @@ pub struct RateLimiterConfig {
 #[cfg(feature = "smtp")]
 #[derive(Debug)]
 pub struct SmtpConfig {
-    pub relay: String,
-    pub port: Option<u16>,
-    pub username: Option<String>,
-    pub password: Option<String>,
+    pub transport: MailTransportConfig,
     pub senders_dir: std::path::PathBuf,
     pub templates_dir: std::path::PathBuf,
+    pub verification_redirect_url: Option<String>,
+    /// Minimum time between two verification emails sent to the same address.
+    pub verification_resend_interval: std::time::Duration,
+    /// How often the email outbox is checked for messages that are due.
+    pub outbox_poll_interval: std::time::Duration,
+    /// Signs every outgoing email when set.
+    pub dkim: Option<DkimConfig>,
+}
+
+#[cfg(feature = "smtp")]
+#[derive(Debug, Clone)]
+pub struct DkimConfig {
+    pub selector: String,
+    pub domain: String,
+    /// Name of the secret holding the private key:
+    /// a PKCS#1 PEM for RSA, the base64 encoded 32 byte seed for Ed25519.
+    pub key_secret: String,
+    pub algorithm: DkimAlgorithm,
+    /// Headers covered by the signature, e.g. `From`, `To`, `Subject`.
+    pub signed_headers: Vec<String>,
+}
+
+#[cfg(feature = "smtp")]
+#[derive(Debug, Clone, Copy)]
+pub enum DkimAlgorithm {
+    Rsa,
+    Ed25519,
+}
+
+#[cfg(feature = "webhooks")]
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    /// How often the webhook delivery queue is checked for deliveries that are due.
+    pub poll_interval: std::time::Duration,
+    /// How long a receiver has to respond before the delivery counts as failed.
+    pub timeout: std::time::Duration,
+}
+
+/// Where outgoing emails are delivered to.
+#[cfg(feature = "smtp")]
+#[derive(Debug)]
+pub enum MailTransportConfig {
+    Smtp {
+        relay: String,
+        port: Option<u16>,
+        username: Option<String>,
+        password: Option<String>,
+    },
+    /// Writes every email as an `.eml` file into `dir`.
+    File { dir: std::path::PathBuf },
+    /// Keeps every email in memory, see [`InMemoryTransport::sent`].
+    Memory(InMemoryTransport),
 }
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: sqlx::Pool<sqlx::Sqlite>,
     pub secrets: Secrets,
+    pub account: AccountConfig,
+    pub signup: std::sync::Arc<crate::signup_policy::SignupPolicy>,
 
     #[cfg(feature = "smtp")]
     pub smtp: crate::smtp::Smtp,
@@ -68,11 +188,16 @@ pub struct AppState {
 
 pub async fn router(opts: ServerOpts) -> Result<Router, ServerError> {
     use crate::api::{
-        access_token, email, heartbeat, key_rotation, login, logout, permissions, private, signup,
-        sysinfo, username,
+        access_token, account, admin, email, heartbeat, introspect, key_rotation, login, logout,
+        orgs, permissions, private, signup, sysinfo, username,
     };
 
+    #[cfg(feature = "smtp")]
+    use crate::api::jwks;
+
     let router = Router::new()
+        .route(account::delete::PATH, account::delete::method_router())
+        .route(account::export::PATH, account::export::method_router())
         .route(
             access_token::generate::PATH,
             access_token::generate::method_router(),
@@ -81,14 +206,55 @@ pub async fn router(opts: ServerOpts) -> Result<Router, ServerError> {
             access_token::verify::PATH,
             access_token::verify::method_router(),
         )
+        .route(
+            admin::impersonate::PATH,
+            admin::impersonate::method_router(),
+        )
+        .route(
+            admin::impersonate::stop::PATH,
+            admin::impersonate::stop::method_router(),
+        )
+        .route(
+            admin::invitations::PATH,
+            admin::invitations::method_router(),
+        )
+        .route(admin::secrets::PATH, admin::secrets::method_router())
+        .route(admin::users::PATH, admin::users::method_router())
+        .route(
+            admin::users::delete::PATH,
+            admin::users::delete::method_router(),
+        )
+        .route(
+            admin::users::disable::PATH,
+            admin::users::disable::method_router(),
+        )
+        .route(
+            admin::users::enable::PATH,
+            admin::users::enable::method_router(),
+        )
+        .route(
+            admin::users::force_email_verification::PATH,
+            admin::users::force_email_verification::method_router(),
+        )
         .route(
             email::check_availability::PATH,
             email::check_availability::method_router(),
         )
         .route(heartbeat::PATH, heartbeat::method_router())
+        .route(introspect::PATH, introspect::method_router())
         .route(key_rotation::PATH, key_rotation::method_router())
         .route(login::PATH, login::method_router())
         .route(logout::PATH, logout::method_router())
+        .route(orgs::PATH, orgs::method_router())
+        .route(orgs::members::PATH, orgs::members::method_router())
+        .route(
+            orgs::members::add::PATH,
+            orgs::members::add::method_router(),
+        )
+        .route(
+            orgs::members::remove::PATH,
+            orgs::members::remove::method_router(),
+        )
         .route(permissions::PATH, permissions::method_router())
         .route(
             permissions::assign::PATH,
@@ -102,12 +268,34 @@ pub async fn router(opts: ServerOpts) -> Result<Router, ServerError> {
             username::check_availability::method_router(),
         );
 
+    #[cfg(feature = "webhooks")]
+    let router = router
+        .route(admin::webhooks::PATH, admin::webhooks::method_router())
+        .route(
+            admin::webhooks::create::PATH,
+            admin::webhooks::create::method_router(),
+        )
+        .route(
+            admin::webhooks::delete::PATH,
+            admin::webhooks::delete::method_router(),
+        )
+        .route(
+            admin::webhooks::deliveries::PATH,
+            admin::webhooks::deliveries::method_router(),
+        );
+
     #[cfg(feature = "smtp")]
     let router = router
+        .route(admin::outbox::PATH, admin::outbox::method_router())
+        .route(
+            admin::outbox::retry::PATH,
+            admin::outbox::retry::method_router(),
+        )
         .route(
             email::initiate_verification::PATH,
             email::initiate_verification::method_router(),
         )
+        .route(jwks::PATH, jwks::method_router())
         .route(
             email::verify_email::PATH,
             email::verify_email::method_router(),
@@ -142,20 +330,138 @@ pub async fn router(opts: ServerOpts) -> Result<Router, ServerError> {
 
     let router = router.layer(middleware);
 
+    let pool = opts
+        .database
+        .pool()
+        .await
+        .context(format!("connect database :: {}", opts.database.url))?;
+
+    crate::core::sync_verified_email_permissions(&pool, &opts.signup.verified_email_permissions)
+        .await
+        .context("sync verified email permissions")?;
+
+    tokio::spawn(purge_scheduled_account_deletions(pool.clone()));
+
+    #[cfg(feature = "smtp")]
+    let outbox_poll_interval = opts.smtp.outbox_poll_interval;
+
+    let secrets = match &opts.secrets_master_key {
+        Some(source) => Secrets::new(opts.secrets_dir.clone(), opts.secret_rotation_grace_period)
+            .with_master_key(MasterKey::load(source, &opts.secrets_dir)?),
+        None => Secrets::new(opts.secrets_dir, opts.secret_rotation_grace_period),
+    };
+
+    #[cfg(feature = "smtp")]
+    crate::signing::init(&secrets)?;
+
+    #[cfg(feature = "smtp")]
+    crate::encryption::init(&secrets)?;
+
+    #[cfg(feature = "smtp")]
+    let smtp = crate::smtp::Smtp::new(opts.smtp, &secrets)?;
+
+    #[cfg(feature = "smtp")]
+    tokio::spawn(deliver_outbox(
+        pool.clone(),
+        smtp.clone(),
+        outbox_poll_interval,
+    ));
+
+    #[cfg(feature = "webhooks")]
+    {
+        let client = reqwest::Client::builder()
+            .timeout(opts.webhooks.timeout)
+            .build()
+            .context("build webhook http client")?;
+
+        tokio::spawn(deliver_webhooks(
+            pool.clone(),
+            client,
+            opts.webhooks.poll_interval,
+        ));
+    }
+
     let router = router.with_state(AppState {
-        pool: opts
-            .database
-            .pool()
-            .await
-            .context(format!("connect database :: {}", opts.database.url))?,
-        secrets: Secrets::new(opts.secrets_dir),
+        pool,
+        secrets,
+        account: opts.account,
+        signup: std::sync::Arc::new(crate::signup_policy::SignupPolicy::try_from(opts.signup)?),
         #[cfg(feature = "smtp")]
-        smtp: crate::smtp::Smtp::try_from(opts.smtp)?,
+        smtp,
     });
 
     Ok(router)
 }
 
+async fn purge_scheduled_account_deletions(pool: sqlx::Pool<sqlx::Sqlite>) {
+    const INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+    let mut interval = tokio::time::interval(INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let _res = crate::core::purge_scheduled_account_deletions(&pool).await;
+
+        #[cfg(feature = "tracing")]
+        match _res {
+            Ok(0) => {}
+            Ok(n) => tracing::info!("purged {n} accounts scheduled for deletion"),
+            Err(err) => tracing::error!("purge scheduled account deletions :: {err:?}"),
+        }
+    }
+}
+
+#[cfg(feature = "smtp")]
+async fn deliver_outbox(
+    pool: sqlx::Pool<sqlx::Sqlite>,
+    smtp: crate::smtp::Smtp,
+    poll_interval: std::time::Duration,
+) {
+    let mut interval = tokio::time::interval(poll_interval);
+    loop {
+        interval.tick().await;
+
+        let _res = crate::outbox::deliver_due(&pool, &smtp).await;
+
+        #[cfg(feature = "tracing")]
+        match _res {
+            Ok(crate::outbox::DeliveryReport {
+                sent: 0,
+                retried: 0,
+                failed: 0,
+                purged: 0,
+            }) => {}
+            Ok(report) => tracing::info!("email outbox :: {report:?}"),
+            Err(err) => tracing::error!("deliver email outbox :: {err:?}"),
+        }
+    }
+}
+
+#[cfg(feature = "webhooks")]
+async fn deliver_webhooks(
+    pool: sqlx::Pool<sqlx::Sqlite>,
+    client: reqwest::Client,
+    poll_interval: std::time::Duration,
+) {
+    let mut interval = tokio::time::interval(poll_interval);
+    loop {
+        interval.tick().await;
+
+        let _res = crate::webhook::deliver_due(&pool, &client).await;
+
+        #[cfg(feature = "tracing")]
+        match _res {
+            Ok(crate::webhook::DeliveryReport {
+                delivered: 0,
+                retried: 0,
+                failed: 0,
+            }) => {}
+            Ok(report) => tracing::info!("webhooks :: {report:?}"),
+            Err(err) => tracing::error!("deliver webhooks :: {err:?}"),
+        }
+    }
+}
+
 /// Returns the local address that the listener is bound to.
 /// This can be useful, for example, when binding to port 0 to figure out which port was actually bound.
 pub async fn serve(server: Router, port: u16) -> Result<SocketAddr, ServerError> {
@@ -189,6 +495,17 @@ pub enum ServerError {
 
     #[error("{0}")]
     Io(#[from] contextual::Error<std::io::Error>),
+
+    #[error("{0}")]
+    MasterKey(#[from] MasterKeyError),
+
+    #[cfg(feature = "smtp")]
+    #[error("{0}")]
+    SigningKey(#[from] crate::signing::SigningKeyError),
+
+    #[cfg(feature = "webhooks")]
+    #[error("{0}")]
+    WebhookClient(#[from] contextual::Error<reqwest::Error>),
 }
 
 #[cfg(feature = "smtp")]
@@ -201,6 +518,18 @@ pub enum SmtpInitializationError {
     #[cfg(feature = "smtp")]
     #[error("{0}")]
     Tera(#[from] contextual::Error<tera::Error>),
+
+    #[cfg(feature = "smtp")]
+    #[error("{0}")]
+    Io(#[from] contextual::Error<std::io::Error>),
+
+    #[cfg(feature = "smtp")]
+    #[error("{0}")]
+    NotificationCatalogue(#[from] crate::notification::CatalogueError),
+
+    #[cfg(feature = "smtp")]
+    #[error("{0}")]
+    Dkim(#[from] crate::smtp::DkimError),
 }
 
 impl FromRef<AppState> for sqlx::Pool<sqlx::Sqlite> {
@@ -209,6 +538,12 @@ impl FromRef<AppState> for sqlx::Pool<sqlx::Sqlite> {
     }
 }
 
+impl FromRef<AppState> for std::sync::Arc<crate::signup_policy::SignupPolicy> {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.signup.clone()
+    }
+}
+
 impl DatabaseConfig {
     pub async fn pool(&self) -> Result<sqlx::Pool<sqlx::Sqlite>, sqlx::Error> {
         sqlx::Pool::<sqlx::Sqlite>::connect(&self.url).await
@@ -223,43 +558,71 @@ impl From<RateLimiterConfig> for middleware::RateLimiter {
 }
 
 #[cfg(feature = "smtp")]
-impl TryFrom<SmtpConfig> for crate::smtp::Smtp {
-    type Error = SmtpInitializationError;
+impl crate::smtp::Smtp {
+    fn new(config: SmtpConfig, secrets: &Secrets) -> Result<Self, SmtpInitializationError> {
+        let senders = crate::smtp::SmtpSenders::new(config.senders_dir);
+        let tera = {
+            // one directory per locale, e.g. `en/verify-email.html`
+            let glob = config.templates_dir.join("*/*.{html,txt}");
+            let glob_str = glob.to_string_lossy().to_string();
+            tera::Tera::new(&glob_str).context("initialize Tera")?
+        };
+
+        crate::notification::validate(&tera, &senders)?;
+
+        // a key that does not parse must fail startup rather than every delivery
+        let dkim = config
+            .dkim
+            .map(|dkim| crate::smtp::dkim_config(dkim, secrets))
+            .transpose()?;
 
-    fn try_from(config: SmtpConfig) -> Result<Self, Self::Error> {
         Ok(Self {
-            transport: {
-                #[cfg(not(feature = "smtp--no-tls"))]
-                let mut transport =
-                    lettre::AsyncSmtpTransport::<lettre::Tokio1Executor>::starttls_relay(
-                        &config.relay,
-                    )
-                    .context("smtp relay")?;
-
-                #[cfg(feature = "smtp--no-tls")]
-                let mut transport =
-                    lettre::AsyncSmtpTransport::<lettre::Tokio1Executor>::builder_dangerous(
-                        &config.relay,
-                    );
-
-                if let (Some(username), Some(password)) = (config.username, config.password) {
-                    use lettre::transport::smtp::authentication::Credentials;
-                    transport = transport.credentials(Credentials::new(username, password));
+            transport: match config.transport {
+                MailTransportConfig::Smtp {
+                    relay,
+                    port,
+                    username,
+                    password,
+                } => {
+                    #[cfg(not(feature = "smtp--no-tls"))]
+                    let mut transport =
+                        lettre::AsyncSmtpTransport::<lettre::Tokio1Executor>::starttls_relay(
+                            &relay,
+                        )
+                        .context("smtp relay")?;
+
+                    #[cfg(feature = "smtp--no-tls")]
+                    let mut transport =
+                        lettre::AsyncSmtpTransport::<lettre::Tokio1Executor>::builder_dangerous(
+                            &relay,
+                        );
+
+                    if let (Some(username), Some(password)) = (username, password) {
+                        use lettre::transport::smtp::authentication::Credentials;
+                        transport = transport.credentials(Credentials::new(username, password));
+                    }
+
+                    if let Some(port) = port {
+                        transport = transport.port(port);
+                    }
+
+                    std::sync::Arc::new(transport.build())
                 }
+                MailTransportConfig::File { dir } => {
+                    std::fs::create_dir_all(&dir)
+                        .context(format!("create mail drop dir :: {}", dir.display()))?;
 
-                if let Some(port) = config.port {
-                    transport = transport.port(port);
+                    std::sync::Arc::new(lettre::AsyncFileTransport::<lettre::Tokio1Executor>::new(
+                        dir,
+                    ))
                 }
-
-                transport.build()
-            },
-            senders: std::sync::Arc::new(crate::smtp::SmtpSenders::new(config.senders_dir)),
-            tera: {
-                let glob = config.templates_dir.join("*.html");
-                let glob_str = glob.to_string_lossy().to_string();
-                let tera = tera::Tera::new(&glob_str).context("initialize Tera")?;
-                std::sync::Arc::new(tera)
+                MailTransportConfig::Memory(transport) => std::sync::Arc::new(transport),
             },
+            senders: std::sync::Arc::new(senders),
+            tera: std::sync::Arc::new(tera),
+            verification_redirect_url: config.verification_redirect_url,
+            verification_resend_interval: config.verification_resend_interval,
+            dkim: dkim.map(std::sync::Arc::new),
         })
     }
 }
@@ -23,6 +23,8 @@ const FEATURES: &[&str] = &[
     "smtp--no-tls",
     #[cfg(feature = "tracing")]
     "tracing",
+    #[cfg(feature = "webhooks")]
+    "webhooks",
 ];
 
 #[cfg(feature = "tracing")]
@@ -62,6 +64,62 @@ struct Serve {
     #[arg(long, env("SECRETS_DIR"))]
     secrets_dir: std::path::PathBuf,
 
+    /// How long (in seconds) a rotated-out secret version keeps verifying tokens it signed,
+    /// e.g. outstanding email verification links.
+    /// Example: `86400` (1 day)
+    #[arg(long, env("SECRET_ROTATION_GRACE_PERIOD_SEC"), default_value_t = 24 * 60 * 60)]
+    secret_rotation_grace_period_sec: u64,
+
+    #[command(flatten)]
+    secrets_master_key: MasterKeyArgs,
+
+    /// How long (in seconds) a self-deleted account is kept before it is permanently purged.
+    /// Logging in during this period cancels the deletion. `0` deletes the account immediately.
+    /// Example: `2592000` (30 days)
+    #[arg(long, env("ACCOUNT_DELETION_GRACE_PERIOD_SEC"), default_value_t = 30 * 24 * 60 * 60)]
+    account_deletion_grace_period_sec: u64,
+
+    /// Only allow signing up with an invitation created by an administrator.
+    /// Example: `true`
+    #[arg(long, env("SIGNUP_INVITE_ONLY"), default_value_t = false)]
+    signup_invite_only: bool,
+
+    /// Comma separated email domains that may sign up (subdomains included).
+    /// All domains are allowed when empty.
+    /// Example: `example.com,example.org`
+    #[arg(long, env("SIGNUP_ALLOWED_DOMAINS"), value_delimiter = ',')]
+    signup_allowed_domains: Vec<String>,
+
+    /// Comma separated email domains that may not sign up (subdomains included).
+    /// Example: `competitor.com`
+    #[arg(long, env("SIGNUP_BLOCKED_DOMAINS"), value_delimiter = ',')]
+    signup_blocked_domains: Vec<String>,
+
+    /// Reject signups from disposable email providers.
+    /// Example: `true`
+    #[arg(long, env("SIGNUP_BLOCK_DISPOSABLE_DOMAINS"), default_value_t = false)]
+    signup_block_disposable_domains: bool,
+
+    /// File with one disposable email domain per line, replacing the bundled list.
+    /// Example: `./disposable-domains.txt`
+    #[arg(long, env("SIGNUP_DISPOSABLE_DOMAINS_FILE"))]
+    signup_disposable_domains_file: Option<std::path::PathBuf>,
+
+    /// Users have to verify their email before they can log in.
+    /// Example: `true`
+    #[arg(long, env("REQUIRE_VERIFIED_EMAIL"), default_value_t = false)]
+    require_verified_email: bool,
+
+    /// Comma separated permissions that are withheld from users whose email is not verified.
+    /// Example: `post:/access-token/generate,post:/permissions/assign`
+    #[arg(long, env("VERIFIED_EMAIL_PERMISSIONS"), value_delimiter = ',')]
+    verified_email_permissions: Vec<String>,
+
+    /// Log the user in when they follow the email verification link.
+    /// Example: `true`
+    #[arg(long, env("LOGIN_AFTER_EMAIL_VERIFICATION"), default_value_t = false)]
+    login_after_email_verification: bool,
+
     #[cfg(feature = "serve-dir")]
     /// The directory where the server's UI files are located.
     /// This should point to a valid local path containing frontend assets.
@@ -76,12 +134,24 @@ struct Serve {
     #[cfg_attr(debug_assertions, arg(default_value = "100/s"))]
     rate_limit: auth::RateLimiterConfig,
 
+    #[cfg(feature = "smtp")]
+    /// How outgoing emails are delivered: through an SMTP relay or
+    /// written as `.eml` files into `MAIL_DROP_DIR`.
+    #[arg(long, env("MAIL_TRANSPORT"), value_enum, default_value_t = MailTransport::Smtp)]
+    mail_transport: MailTransport,
+
+    #[cfg(feature = "smtp")]
+    /// Directory receiving the `.eml` files when `MAIL_TRANSPORT` is `file`.
+    /// Example: `./mail`
+    #[arg(long, env("MAIL_DROP_DIR"), required_if_eq("mail_transport", "file"))]
+    mail_drop_dir: Option<std::path::PathBuf>,
+
     #[cfg(feature = "smtp")]
     /// The SMTP relay server used for sending emails.
     /// This should be a valid SMTP server address.
     /// Example: `"smtp.gmail.com"`
-    #[arg(long, env("SMTP_RELAY"))]
-    smtp_relay: String,
+    #[arg(long, env("SMTP_RELAY"), required_if_eq("mail_transport", "smtp"))]
+    smtp_relay: Option<String>,
 
     #[cfg(feature = "smtp")]
     /// The port on which the SMTP relay server listens.
@@ -122,6 +192,235 @@ struct Serve {
     #[arg(long, env("SMTP_TEMPLATES_DIR"))]
     #[cfg_attr(debug_assertions, arg(default_value_os_t = std::path::PathBuf::from("./templates/")))]
     smtp_templates_dir: std::path::PathBuf,
+
+    #[cfg(feature = "smtp")]
+    /// URL the email verification pages link to once rendered (e.g. the UI login page).
+    #[arg(long, env("EMAIL_VERIFICATION_REDIRECT_URL"))]
+    email_verification_redirect_url: Option<String>,
+
+    #[cfg(feature = "smtp")]
+    /// Minimum time (in seconds) between two verification emails sent to the same address.
+    /// Example: `60`
+    #[arg(
+        long,
+        env("EMAIL_VERIFICATION_RESEND_INTERVAL_SEC"),
+        default_value_t = 60
+    )]
+    email_verification_resend_interval_sec: u64,
+
+    #[cfg(feature = "smtp")]
+    /// How often (in milliseconds) the email outbox is checked for messages that are due.
+    /// Example: `5000`
+    #[arg(long, env("EMAIL_OUTBOX_POLL_INTERVAL_MS"), default_value_t = 5000)]
+    email_outbox_poll_interval_ms: u64,
+
+    #[cfg(feature = "smtp")]
+    /// DKIM selector; outgoing emails are signed when it is set.
+    /// Example: `mail` for the `mail._domainkey.example.com` record
+    #[arg(long, env("DKIM_SELECTOR"), requires("dkim_domain"))]
+    dkim_selector: Option<String>,
+
+    #[cfg(feature = "smtp")]
+    /// Signing domain (`d=`) of the DKIM signature.
+    /// Example: `example.com`
+    #[arg(long, env("DKIM_DOMAIN"), requires("dkim_selector"))]
+    dkim_domain: Option<String>,
+
+    #[cfg(feature = "smtp")]
+    /// Name of the secret in `SECRETS_DIR` holding the DKIM private key.
+    #[arg(long, env("DKIM_KEY_SECRET"), default_value = "dkim")]
+    dkim_key_secret: String,
+
+    #[cfg(feature = "smtp")]
+    /// Algorithm of the DKIM private key.
+    #[arg(long, env("DKIM_ALGORITHM"), value_enum, default_value_t = DkimAlgorithm::Rsa)]
+    dkim_algorithm: DkimAlgorithm,
+
+    #[cfg(feature = "smtp")]
+    /// Headers covered by the DKIM signature.
+    #[arg(
+        long,
+        env("DKIM_SIGNED_HEADERS"),
+        value_delimiter = ',',
+        default_value = "From,To,Subject,Date,Message-ID,MIME-Version,Content-Type"
+    )]
+    dkim_signed_headers: Vec<String>,
+
+    #[cfg(feature = "webhooks")]
+    /// How often (in milliseconds) the webhook delivery queue is checked for deliveries that are due.
+    /// Example: `5000`
+    #[arg(long, env("WEBHOOK_POLL_INTERVAL_MS"), default_value_t = 5000)]
+    webhook_poll_interval_ms: u64,
+
+    #[cfg(feature = "webhooks")]
+    /// How long (in seconds) a webhook receiver has to respond.
+    /// Example: `10`
+    #[arg(long, env("WEBHOOK_TIMEOUT_SEC"), default_value_t = 10)]
+    webhook_timeout_sec: u64,
+}
+
+/// Master key sealing the files in `SECRETS_DIR`, at most one of the options.
+#[derive(Debug, clap::Args)]
+#[group(multiple = false)]
+struct MasterKeyArgs {
+    /// Base64 encoded 32 byte key encrypting the secrets at rest.
+    /// Generate one with `openssl rand -base64 32`.
+    #[arg(long, env("SECRETS_MASTER_KEY"), hide_env_values = true)]
+    secrets_master_key: Option<String>,
+
+    /// File descriptor to read the base64 encoded master key from.
+    /// Example: `3` (with `3< master.key`)
+    #[arg(long, env("SECRETS_MASTER_KEY_FD"))]
+    secrets_master_key_fd: Option<i32>,
+
+    /// Passphrase the master key is derived from (Argon2id).
+    #[arg(long, env("SECRETS_MASTER_PASSPHRASE"), hide_env_values = true)]
+    secrets_master_passphrase: Option<String>,
+}
+
+/// The new master key of `secrets rekey`, at most one of the options.
+#[derive(Debug, clap::Args)]
+#[group(multiple = false)]
+struct NewMasterKeyArgs {
+    /// Base64 encoded 32 byte key the secrets are re-encrypted with.
+    #[arg(long, env("NEW_SECRETS_MASTER_KEY"), hide_env_values = true)]
+    new_secrets_master_key: Option<String>,
+
+    /// File descriptor to read the new base64 encoded master key from.
+    #[arg(long, env("NEW_SECRETS_MASTER_KEY_FD"))]
+    new_secrets_master_key_fd: Option<i32>,
+
+    /// Passphrase the new master key is derived from (Argon2id).
+    #[arg(long, env("NEW_SECRETS_MASTER_PASSPHRASE"), hide_env_values = true)]
+    new_secrets_master_passphrase: Option<String>,
+}
+
+impl From<MasterKeyArgs> for Option<auth::MasterKeySource> {
+    fn from(args: MasterKeyArgs) -> Self {
+        master_key_source(
+            args.secrets_master_key,
+            args.secrets_master_key_fd,
+            args.secrets_master_passphrase,
+        )
+    }
+}
+
+impl From<NewMasterKeyArgs> for Option<auth::MasterKeySource> {
+    fn from(args: NewMasterKeyArgs) -> Self {
+        master_key_source(
+            args.new_secrets_master_key,
+            args.new_secrets_master_key_fd,
+            args.new_secrets_master_passphrase,
+        )
+    }
+}
+
+fn master_key_source(
+    key: Option<String>,
+    fd: Option<i32>,
+    passphrase: Option<String>,
+) -> Option<auth::MasterKeySource> {
+    use zeroize::Zeroizing;
+
+    match (key, fd, passphrase) {
+        (Some(key), _, _) => Some(auth::MasterKeySource::Base64(Zeroizing::new(key))),
+        (_, Some(fd), _) => Some(auth::MasterKeySource::Fd(fd)),
+        (_, _, Some(passphrase)) => Some(auth::MasterKeySource::Passphrase(Zeroizing::new(
+            passphrase,
+        ))),
+        _ => None,
+    }
+}
+
+/// `auth secrets <command>`: maintenance of the secrets directory.
+#[derive(Debug, clap::Parser)]
+#[command(name = "secrets")]
+struct SecretsCli {
+    /// The directory where the server's secrets are located.
+    #[arg(long, env("SECRETS_DIR"), global = true)]
+    secrets_dir: Option<std::path::PathBuf>,
+
+    #[command(subcommand)]
+    command: SecretsCommand,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum SecretsCommand {
+    /// Encrypts every plain secret file under the master key.
+    Seal {
+        #[command(flatten)]
+        master_key: MasterKeyArgs,
+    },
+    /// Decrypts every sealed secret file back to plain text.
+    Unseal {
+        #[command(flatten)]
+        master_key: MasterKeyArgs,
+    },
+    /// Re-encrypts every sealed secret file under a new master key.
+    Rekey {
+        #[command(flatten)]
+        master_key: MasterKeyArgs,
+
+        #[command(flatten)]
+        new_master_key: NewMasterKeyArgs,
+    },
+}
+
+fn secrets(cli: SecretsCli) {
+    use clap::{CommandFactory, error::ErrorKind};
+
+    let missing = |what: &str| -> ! {
+        SecretsCli::command()
+            .error(ErrorKind::MissingRequiredArgument, what)
+            .exit()
+    };
+    let load = |source: Option<auth::MasterKeySource>, dir: &std::path::Path| {
+        let source = source.unwrap_or_else(|| missing("a master key is required"));
+        auth::MasterKey::load(&source, dir).unwrap_or_else(|e| exit(e))
+    };
+
+    let dir = cli
+        .secrets_dir
+        .unwrap_or_else(|| missing("--secrets-dir is required"));
+
+    let (action, report) = match cli.command {
+        SecretsCommand::Seal { master_key } => {
+            let master_key = load(master_key.into(), &dir);
+            ("sealed", auth::seal_dir(&dir, &master_key))
+        }
+        SecretsCommand::Unseal { master_key } => {
+            let master_key = load(master_key.into(), &dir);
+            ("unsealed", auth::unseal_dir(&dir, &master_key))
+        }
+        SecretsCommand::Rekey {
+            master_key,
+            new_master_key,
+        } => {
+            let old = load(master_key.into(), &dir);
+            let new = load(new_master_key.into(), &dir);
+            ("re-keyed", auth::rekey_dir(&dir, &old, &new))
+        }
+    };
+
+    let report = report.unwrap_or_else(|e| exit(e));
+    println!(
+        "{action} {} file(s), skipped {}",
+        report.changed, report.skipped
+    );
+}
+
+#[cfg(feature = "smtp")]
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum MailTransport {
+    Smtp,
+    File,
+}
+
+#[cfg(feature = "smtp")]
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum DkimAlgorithm {
+    Rsa,
+    Ed25519,
 }
 
 #[tokio::main]
@@ -150,6 +449,13 @@ async fn main() {
     #[cfg(feature = "profiles")]
     load_profile();
 
+    if let Some(arg) = args_os.peek()
+        && arg == "secrets"
+    {
+        secrets(SecretsCli::parse_from(args_os));
+        return;
+    }
+
     let args = Serve::parse();
 
     let port = args.port;
@@ -216,6 +522,27 @@ impl From<Serve> for auth::ServerOpts {
             },
 
             secrets_dir: serve.secrets_dir,
+            secret_rotation_grace_period: std::time::Duration::from_secs(
+                serve.secret_rotation_grace_period_sec,
+            ),
+            secrets_master_key: serve.secrets_master_key.into(),
+
+            account: auth::AccountConfig {
+                deletion_grace_period: std::time::Duration::from_secs(
+                    serve.account_deletion_grace_period_sec,
+                ),
+            },
+
+            signup: auth::SignupConfig {
+                invite_only: serve.signup_invite_only,
+                allowed_domains: serve.signup_allowed_domains,
+                blocked_domains: serve.signup_blocked_domains,
+                block_disposable_domains: serve.signup_block_disposable_domains,
+                disposable_domains_file: serve.signup_disposable_domains_file,
+                require_verified_email: serve.require_verified_email,
+                verified_email_permissions: serve.verified_email_permissions,
+                login_after_email_verification: serve.login_after_email_verification,
+            },
 
             #[cfg(feature = "rate-limit")]
             rate_limiter: serve.rate_limit,
@@ -225,12 +552,45 @@ impl From<Serve> for auth::ServerOpts {
 
             #[cfg(feature = "smtp")]
             smtp: auth::SmtpConfig {
-                relay: serve.smtp_relay,
-                port: serve.smtp_port,
-                username: serve.smtp_username,
-                password: serve.smtp_password,
+                transport: match serve.mail_transport {
+                    MailTransport::Smtp => auth::MailTransportConfig::Smtp {
+                        relay: serve.smtp_relay.unwrap_or_default(),
+                        port: serve.smtp_port,
+                        username: serve.smtp_username,
+                        password: serve.smtp_password,
+                    },
+                    MailTransport::File => auth::MailTransportConfig::File {
+                        dir: serve.mail_drop_dir.unwrap_or_default(),
+                    },
+                },
                 senders_dir: serve.smtp_senders_dir,
                 templates_dir: serve.smtp_templates_dir,
+                verification_redirect_url: serve.email_verification_redirect_url,
+                verification_resend_interval: std::time::Duration::from_secs(
+                    serve.email_verification_resend_interval_sec,
+                ),
+                outbox_poll_interval: std::time::Duration::from_millis(
+                    serve.email_outbox_poll_interval_ms,
+                ),
+                dkim: serve
+                    .dkim_selector
+                    .zip(serve.dkim_domain)
+                    .map(|(selector, domain)| auth::DkimConfig {
+                        selector,
+                        domain,
+                        key_secret: serve.dkim_key_secret,
+                        algorithm: match serve.dkim_algorithm {
+                            DkimAlgorithm::Rsa => auth::DkimAlgorithm::Rsa,
+                            DkimAlgorithm::Ed25519 => auth::DkimAlgorithm::Ed25519,
+                        },
+                        signed_headers: serve.dkim_signed_headers,
+                    }),
+            },
+
+            #[cfg(feature = "webhooks")]
+            webhooks: auth::WebhookConfig {
+                poll_interval: std::time::Duration::from_millis(serve.webhook_poll_interval_ms),
+                timeout: std::time::Duration::from_secs(serve.webhook_timeout_sec),
             },
         }
     }
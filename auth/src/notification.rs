@@ -0,0 +1,236 @@
+//! Catalogue of the emails sent to users.
+//!
+//! Every notification is a context struct bound to its template set
+//! (`<locale>/<TEMPLATE>.subject.txt`, `.txt` and `.html`) and sender id.
+//! [`validate`] renders each of them with a default context at startup,
+//! so missing templates, senders or context variables fail `router()` instead of the first send.
+//! The pages a verification or invitation link leads to are validated the same way.
+
+use contextual::Context;
+use email::Email;
+use serde::Serialize;
+use tera::Tera;
+
+use crate::{
+    core::DEFAULT_LOCALE,
+    outbox,
+    smtp::{Smtp, SmtpSenders},
+};
+
+pub trait Notification: Serialize + Default {
+    /// basename of the template set
+    const TEMPLATE: &'static str;
+
+    /// logical sender, resolved through [`SmtpSenders`]
+    const SENDER: &'static str = "noreply";
+
+    /// subject, plain-text and HTML template names
+    fn templates() -> [String; 3] {
+        [
+            format!("{}.subject.txt", Self::TEMPLATE),
+            format!("{}.txt", Self::TEMPLATE),
+            format!("{}.html", Self::TEMPLATE),
+        ]
+    }
+}
+
+#[derive(Serialize, Default)]
+pub struct VerifyEmail {
+    pub verification_link: String,
+}
+
+#[derive(Serialize, Default)]
+pub struct Invitation {
+    pub invitation_link: String,
+}
+
+#[derive(Serialize, Default)]
+pub struct NewDeviceLogin {
+    pub username: String,
+    pub user_agent: Option<String>,
+    pub datetime: String,
+}
+
+#[derive(Serialize, Default)]
+pub struct AccessTokenCreated {
+    pub username: String,
+    pub token_name: String,
+    pub expires_at: Option<String>,
+}
+
+#[derive(Serialize, Default)]
+pub struct PermissionGranted {
+    pub username: String,
+    pub permission: String,
+}
+
+#[derive(Serialize, Default)]
+pub struct AccountLocked {
+    pub username: String,
+}
+
+/// Context of the pages rendered for a verification link.
+#[derive(Serialize, Default)]
+pub struct VerifyEmailPage {
+    pub redirect_url: Option<String>,
+}
+
+impl VerifyEmailPage {
+    pub const SUCCESS: &'static str = "verify-email-success.html";
+    pub const EXPIRED: &'static str = "verify-email-expired.html";
+    pub const INVALID: &'static str = "verify-email-invalid.html";
+    pub const ALL: [&'static str; 3] = [Self::SUCCESS, Self::EXPIRED, Self::INVALID];
+}
+
+/// Context of the signup page an invitation link leads to.
+#[derive(Serialize, Default)]
+pub struct SignupPage {
+    pub invitation: Option<String>,
+}
+
+impl SignupPage {
+    pub const TEMPLATE: &'static str = "signup.html";
+}
+
+impl Notification for VerifyEmail {
+    const TEMPLATE: &'static str = "verify-email";
+}
+
+impl Notification for Invitation {
+    const TEMPLATE: &'static str = "invitation";
+}
+
+impl Notification for NewDeviceLogin {
+    const TEMPLATE: &'static str = "new-device-login";
+}
+
+impl Notification for AccessTokenCreated {
+    const TEMPLATE: &'static str = "access-token-created";
+}
+
+impl Notification for PermissionGranted {
+    const TEMPLATE: &'static str = "permission-granted";
+}
+
+impl Notification for AccountLocked {
+    const TEMPLATE: &'static str = "account-locked";
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CatalogueError {
+    #[error("sender `{sender}` of notification `{notification}` not found")]
+    MissingSender {
+        notification: &'static str,
+        sender: &'static str,
+    },
+
+    #[error("template `{0}` not found")]
+    MissingTemplate(String),
+
+    #[error("{0}")]
+    Render(#[from] contextual::Error<tera::Error>),
+}
+
+/// Checks that every notification has a sender and a template set in the default locale,
+/// and that all of its translations render with the notification's context.
+/// Checks the verification and signup pages likewise.
+pub fn validate(tera: &Tera, senders: &SmtpSenders) -> Result<(), CatalogueError> {
+    validate_notification::<VerifyEmail>(tera, senders)?;
+    validate_notification::<Invitation>(tera, senders)?;
+    validate_notification::<NewDeviceLogin>(tera, senders)?;
+    validate_notification::<AccessTokenCreated>(tera, senders)?;
+    validate_notification::<PermissionGranted>(tera, senders)?;
+    validate_notification::<AccountLocked>(tera, senders)?;
+
+    let context = tera::Context::from_serialize(VerifyEmailPage::default())
+        .context("verify email page context")?;
+    for page in VerifyEmailPage::ALL {
+        validate_template(tera, page, &context)?;
+    }
+
+    let context =
+        tera::Context::from_serialize(SignupPage::default()).context("signup page context")?;
+    validate_template(tera, SignupPage::TEMPLATE, &context)?;
+
+    Ok(())
+}
+
+fn validate_notification<N: Notification>(
+    tera: &Tera,
+    senders: &SmtpSenders,
+) -> Result<(), CatalogueError> {
+    if !senders.contains(N::SENDER) {
+        return Err(CatalogueError::MissingSender {
+            notification: N::TEMPLATE,
+            sender: N::SENDER,
+        });
+    }
+
+    let context =
+        tera::Context::from_serialize(N::default()).context(format!("{} context", N::TEMPLATE))?;
+
+    for template in N::templates() {
+        validate_template(tera, &template, &context)?;
+    }
+
+    Ok(())
+}
+
+/// Checks that `template` exists in the default locale and that all of its translations render.
+fn validate_template(
+    tera: &Tera,
+    template: &str,
+    context: &tera::Context,
+) -> Result<(), CatalogueError> {
+    let names = tera.get_template_names().collect::<Vec<_>>();
+
+    let default = format!("{DEFAULT_LOCALE}/{template}");
+    if !names.contains(&default.as_str()) {
+        return Err(CatalogueError::MissingTemplate(default));
+    }
+
+    for name in names.iter().filter(|name| {
+        name.split_once('/')
+            .is_some_and(|(_, rest)| rest == template)
+    }) {
+        tera.render(name, context)
+            .context(format!("render `{name}`"))?;
+    }
+
+    Ok(())
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum NotifyError {
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+
+    #[error("{0}")]
+    Render(#[from] contextual::Error<tera::Error>),
+}
+
+/// Queues `notification` for the user in their preferred locale and returns its outbox id.
+pub async fn notify<N: Notification>(
+    conn: &mut sqlx::SqliteConnection,
+    smtp: &Smtp,
+    user_id: i64,
+    notification: &N,
+) -> Result<i64, NotifyError> {
+    let user = sqlx::query!(
+        r#"SELECT email as "email: Email", locale FROM users WHERE id = ?"#,
+        user_id
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .context("user_id -> email, locale")?;
+
+    let message = smtp
+        .render_notification(user.locale.as_deref(), &user.email, notification)
+        .context(format!("render `{}` notification", N::TEMPLATE))?;
+
+    let outbox_id = outbox::enqueue(&mut *conn, &message)
+        .await
+        .context(format!("enqueue `{}` notification", N::TEMPLATE))?;
+
+    Ok(outbox_id)
+}
@@ -0,0 +1,284 @@
+//! Durable queue of outgoing emails.
+//!
+//! Emails are written to the `email_outbox` table (ideally in the same transaction as the change
+//! that triggers them) and delivered by a background worker. Failed deliveries are retried (see
+//! [`crate::retry`]) until the message is dead-lettered (`failed`), after which it can be
+//! inspected or retried by an administrator.
+//!
+//! Bodies carry live links (e.g. verification and invitation tokens), so they are cleared once a
+//! message is sent, and sent messages are purged after [`SENT_RETENTION`].
+
+use std::time::Duration;
+
+use contextual::Context;
+use email::Email;
+use sqlx::{Executor, Sqlite};
+use time::OffsetDateTime;
+
+use crate::{
+    retry::{Entry, Failure, Queue},
+    smtp::Smtp,
+};
+
+/// How long sent messages remain inspectable before they are purged.
+pub const SENT_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+pub struct OutgoingEmail<'a> {
+    /// Logical sender identifier, resolved through [`crate::smtp::SmtpSenders`].
+    pub sender: &'a str,
+    pub recipient: &'a Email,
+    pub subject: String,
+    pub plain_text: String,
+    pub html: String,
+}
+
+#[cfg_attr(not(feature = "tracing"), allow(dead_code))]
+#[derive(Debug, Default)]
+pub struct DeliveryReport {
+    pub sent: usize,
+    pub retried: usize,
+    pub failed: usize,
+    pub purged: u64,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum DeliveryError {
+    #[error("{0}")]
+    SmtpSenders(#[from] contextual::Error<crate::smtp::SmtpSendersError>),
+
+    #[error("{0}")]
+    InvalidRecipient(&'static str),
+
+    #[error("{0}")]
+    EmailContent(#[from] contextual::Error<lettre::error::Error>),
+
+    #[error("{0}")]
+    Transport(#[from] contextual::Error<crate::smtp::TransportError>),
+}
+
+/// Queues the email for delivery and returns its outbox id.
+pub async fn enqueue<'a, E: Executor<'a, Database = Sqlite>>(
+    ex: E,
+    email: &OutgoingEmail<'_>,
+) -> Result<i64, sqlx::Error> {
+    let now = OffsetDateTime::now_utc();
+
+    sqlx::query_scalar!(
+        r#"
+        INSERT INTO email_outbox
+        (sender, recipient, subject, plain_text, html, created_at, next_attempt_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        RETURNING id as "id!"
+        "#,
+        email.sender,
+        email.recipient,
+        email.subject,
+        email.plain_text,
+        email.html,
+        now,
+        now
+    )
+    .fetch_one(ex)
+    .await
+}
+
+/// Moves a dead-lettered message back into the queue for immediate delivery.
+/// Returns `false` if there is no failed message with that id.
+pub async fn retry<'a, E: Executor<'a, Database = Sqlite>>(
+    ex: E,
+    id: i64,
+) -> Result<bool, sqlx::Error> {
+    let now = OffsetDateTime::now_utc();
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE email_outbox
+        SET status = 'pending', attempts = 0, next_attempt_at = ?
+        WHERE id = ? AND status = 'failed'
+        "#,
+        now,
+        id
+    )
+    .execute(ex)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Attempts delivery of every pending message that is due.
+pub async fn deliver_due(
+    pool: &sqlx::Pool<Sqlite>,
+    smtp: &Smtp,
+) -> Result<DeliveryReport, sqlx::Error> {
+    let report = crate::retry::deliver_due(&Outbox { pool, smtp }).await?;
+
+    Ok(DeliveryReport {
+        sent: report.delivered,
+        retried: report.retried,
+        failed: report.failed,
+        purged: purge_sent(pool).await?,
+    })
+}
+
+struct Outbox<'a> {
+    pool: &'a sqlx::Pool<Sqlite>,
+    smtp: &'a Smtp,
+}
+
+struct Message {
+    sender: String,
+    recipient: String,
+    subject: String,
+    plain_text: String,
+    html: String,
+}
+
+impl Queue for Outbox<'_> {
+    type Item = Message;
+    type Receipt = ();
+    type Error = DeliveryError;
+
+    const NAME: &'static str = "email";
+
+    async fn due(
+        &self,
+        now: OffsetDateTime,
+        limit: i64,
+    ) -> Result<Vec<Entry<Message>>, sqlx::Error> {
+        let messages = sqlx::query!(
+            r#"
+            SELECT id as "id!", sender, recipient, subject, plain_text, html, attempts
+            FROM email_outbox
+            WHERE status = 'pending' AND next_attempt_at <= ?
+            ORDER BY next_attempt_at
+            LIMIT ?
+            "#,
+            now,
+            limit
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(messages
+            .into_iter()
+            .map(|record| Entry {
+                id: record.id,
+                attempts: record.attempts,
+                item: Message {
+                    sender: record.sender,
+                    recipient: record.recipient,
+                    subject: record.subject,
+                    plain_text: record.plain_text,
+                    html: record.html,
+                },
+            })
+            .collect())
+    }
+
+    async fn send(&self, message: Message) -> Result<(), DeliveryError> {
+        send(self.smtp, message).await
+    }
+
+    async fn delivered(&self, id: i64, _: (), now: OffsetDateTime) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE email_outbox
+            SET status = 'sent', attempts = attempts + 1, last_error = NULL, sent_at = ?,
+                plain_text = '', html = ''
+            WHERE id = ?
+            "#,
+            now,
+            id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn failed(
+        &self,
+        id: i64,
+        failure: Failure<'_, DeliveryError>,
+        now: OffsetDateTime,
+    ) -> Result<(), sqlx::Error> {
+        let last_error = failure.error.to_string();
+        let (status, next_attempt_at) = match failure.next_attempt_at {
+            Some(next_attempt_at) => ("pending", next_attempt_at),
+            None => ("failed", now),
+        };
+
+        sqlx::query!(
+            r#"
+            UPDATE email_outbox
+            SET status = ?, attempts = ?, last_error = ?, next_attempt_at = ?
+            WHERE id = ?
+            "#,
+            status,
+            failure.attempts,
+            last_error,
+            next_attempt_at,
+            id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Deletes the messages that were sent longer than [`SENT_RETENTION`] ago.
+/// Returns the number of purged messages.
+async fn purge_sent(pool: &sqlx::Pool<Sqlite>) -> Result<u64, sqlx::Error> {
+    let sent_before = OffsetDateTime::now_utc() - SENT_RETENTION;
+
+    let result = sqlx::query!(
+        "DELETE FROM email_outbox WHERE status = 'sent' AND sent_at <= ?",
+        sent_before
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+async fn send(
+    smtp: &Smtp,
+    Message {
+        sender,
+        recipient,
+        subject,
+        plain_text,
+        html,
+    }: Message,
+) -> Result<(), DeliveryError> {
+    use lettre::message::{Mailbox, MultiPart};
+    use std::str::FromStr;
+
+    let mut message = {
+        let from: Email = smtp
+            .senders
+            .get(&sender)
+            .await
+            .context(format!("SmtpSenders::get `{sender}`"))?;
+        let to = Email::from_str(&recipient).map_err(DeliveryError::InvalidRecipient)?;
+
+        lettre::Message::builder()
+            .from(Mailbox::new(Some(sender), from.into()))
+            .to(Mailbox::new(None, to.into()))
+            .subject(subject)
+            .multipart(MultiPart::alternative_plain_html(plain_text, html))
+            .context("outbox message builder")?
+    };
+
+    if let Some(dkim) = &smtp.dkim {
+        message.sign(dkim);
+    }
+
+    smtp.transport
+        .send(message)
+        .await
+        .context("send outbox email")?;
+
+    Ok(())
+}
@@ -0,0 +1,129 @@
+//! Delivery with retries, shared by the background workers of the email outbox and the webhooks.
+//!
+//! Each worker keeps its entries in a table with `status`, `attempts` and `next_attempt_at`
+//! columns and implements [`Queue`] over it. [`deliver_due`] attempts the entries that are due in
+//! batches of [`BATCH_SIZE`]. Failed attempts are retried with exponential backoff until
+//! [`MAX_ATTEMPTS`] is reached, after which the entry is given up (`failed`).
+
+use std::time::Duration;
+
+use time::OffsetDateTime;
+
+/// Number of delivery attempts before an entry is given up.
+pub const MAX_ATTEMPTS: i64 = 8;
+
+const BASE_BACKOFF: Duration = Duration::from_secs(30);
+const MAX_BACKOFF: Duration = Duration::from_secs(6 * 60 * 60);
+const BATCH_SIZE: i64 = 32;
+
+/// A pending entry of a [`Queue`].
+pub struct Entry<T> {
+    pub id: i64,
+    /// failed attempts so far
+    pub attempts: i64,
+    pub item: T,
+}
+
+/// A failed attempt, to be recorded on the entry.
+pub struct Failure<'a, E> {
+    /// failed attempts including this one
+    pub attempts: i64,
+    pub error: &'a E,
+    /// `None` when the entry is given up
+    pub next_attempt_at: Option<OffsetDateTime>,
+}
+
+pub trait Queue {
+    /// what is delivered, e.g. an email
+    type Item;
+
+    /// what a successful delivery returns, e.g. the receiver's status code
+    type Receipt;
+
+    type Error: std::fmt::Debug;
+
+    /// name used in logs
+    #[cfg_attr(not(feature = "tracing"), allow(dead_code))]
+    const NAME: &'static str;
+
+    /// Up to `limit` pending entries whose next attempt is due at `now`, the most overdue first.
+    async fn due(
+        &self,
+        now: OffsetDateTime,
+        limit: i64,
+    ) -> Result<Vec<Entry<Self::Item>>, sqlx::Error>;
+
+    async fn send(&self, item: Self::Item) -> Result<Self::Receipt, Self::Error>;
+
+    /// Marks the entry as delivered.
+    async fn delivered(
+        &self,
+        id: i64,
+        receipt: Self::Receipt,
+        now: OffsetDateTime,
+    ) -> Result<(), sqlx::Error>;
+
+    /// Records the failed attempt and either reschedules the entry or gives it up.
+    async fn failed(
+        &self,
+        id: i64,
+        failure: Failure<'_, Self::Error>,
+        now: OffsetDateTime,
+    ) -> Result<(), sqlx::Error>;
+}
+
+#[derive(Debug, Default)]
+pub struct DeliveryReport {
+    pub delivered: usize,
+    pub retried: usize,
+    pub failed: usize,
+}
+
+/// Attempts every pending entry of the queue that is due.
+pub async fn deliver_due<Q: Queue>(queue: &Q) -> Result<DeliveryReport, sqlx::Error> {
+    let entries = queue.due(OffsetDateTime::now_utc(), BATCH_SIZE).await?;
+
+    let mut report = DeliveryReport::default();
+
+    for Entry { id, attempts, item } in entries {
+        let result = queue.send(item).await;
+        let now = OffsetDateTime::now_utc();
+
+        match result {
+            Ok(receipt) => {
+                queue.delivered(id, receipt, now).await?;
+                report.delivered += 1;
+            }
+            Err(error) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(id, "{} delivery failed :: {error:?}", Q::NAME);
+
+                let attempts = attempts + 1;
+                let next_attempt_at = (attempts < MAX_ATTEMPTS).then(|| now + backoff(attempts));
+                let give_up = next_attempt_at.is_none();
+
+                let failure = Failure {
+                    attempts,
+                    error: &error,
+                    next_attempt_at,
+                };
+                queue.failed(id, failure, now).await?;
+
+                match give_up {
+                    true => report.failed += 1,
+                    false => report.retried += 1,
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Delay before the next attempt, doubling with every failed attempt.
+fn backoff(attempts: i64) -> Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    BASE_BACKOFF
+        .saturating_mul(2u32.pow(exponent))
+        .min(MAX_BACKOFF)
+}
@@ -0,0 +1,96 @@
+//! The Ed25519 key auth signs its tokens with, kept in [`Secrets`] like every other key.
+//!
+//! Unlike the HMAC secret, other services (e.g. the gateway) verify these tokens with the public
+//! keys served at [`jwks::PATH`](crate::api::jwks::PATH) and never hold the signing key.
+//! Tokens signed with the HMAC secret before, which carry a plain `vN` kid or none at all, keep
+//! verifying until they expire.
+
+use std::io;
+
+use contextual::Context;
+use secrets::Secrets;
+use signature::{SigningKey, VerifyingKey};
+
+/// Name of the secret holding the PKCS#8 encoded key.
+pub const KEY: &str = "signing";
+
+/// Tokens signed with the key carry `signing/<version>` as their kid, which tells them apart from
+/// tokens signed with versions of the HMAC secret.
+const KID_PREFIX: &str = "signing/";
+
+#[derive(thiserror::Error, Debug)]
+pub enum SigningKeyError {
+    #[error("{0}")]
+    Secret(#[from] contextual::Error<io::Error>),
+
+    #[error("signing key `{0}`: {1}")]
+    Key(String, #[source] signature::KeyError),
+}
+
+/// Creates the first version of the key unless there is one already.
+pub fn init(secrets: &Secrets) -> Result<(), SigningKeyError> {
+    match secrets.versions(KEY) {
+        Ok(_) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => rotate(secrets).map(|_kid| ()),
+        Err(err) => Err(err).context("signing key versions")?,
+    }
+}
+
+/// Creates a new current version of the key, see [`Secrets::rotate`].
+pub fn rotate(secrets: &Secrets) -> Result<String, SigningKeyError> {
+    let pkcs8 =
+        SigningKey::generate_ed25519().map_err(|err| SigningKeyError::Key(KEY.to_string(), err))?;
+    Ok(secrets
+        .rotate_with(KEY, &pkcs8)
+        .context("rotate signing key")?)
+}
+
+/// The current version of the key along with the kid its tokens carry.
+pub fn current(secrets: &Secrets) -> Result<(String, SigningKey), SigningKeyError> {
+    let version = secrets.current(KEY).context("get signing key")?;
+    let kid = version.kid.unwrap_or_default();
+    let key = SigningKey::ed25519(&version.material)
+        .map_err(|err| SigningKeyError::Key(kid.clone(), err))?;
+
+    Ok((format!("{KID_PREFIX}{kid}"), key))
+}
+
+/// The key that verifies a token with `kid`, the HMAC secret's version for tokens that were not
+/// signed with the signing key. `None` once the version is gone.
+pub fn verifying_key(
+    secrets: &Secrets,
+    kid: Option<&str>,
+) -> Result<Option<VerifyingKey>, SigningKeyError> {
+    let Some(version) = kid.and_then(|kid| kid.strip_prefix(KID_PREFIX)) else {
+        return Ok(secrets
+            .get_version("hmac", kid)
+            .context("get HMAC key")?
+            .map(|material| VerifyingKey::hmac(material.as_slice())));
+    };
+
+    secrets
+        .get_version(KEY, Some(version))
+        .context("get signing key")?
+        .map(|material| {
+            SigningKey::ed25519(&material)
+                .map(|key| key.verifying_key())
+                .map_err(|err| SigningKeyError::Key(version.to_string(), err))
+        })
+        .transpose()
+}
+
+/// The public keys of the versions that still verify tokens, current one last.
+pub fn public_keys(secrets: &Secrets) -> Result<Vec<(String, VerifyingKey)>, SigningKeyError> {
+    secrets
+        .versions(KEY)
+        .context("signing key versions")?
+        .into_iter()
+        // versions past their grace period have no key anymore
+        .filter_map(|version| {
+            let kid = format!("{KID_PREFIX}{}", version.kid);
+            verifying_key(secrets, Some(&kid))
+                .transpose()
+                .map(|key| key.map(|key| (kid, key)))
+        })
+        .collect()
+}
@@ -0,0 +1,148 @@
+use std::collections::HashSet;
+
+use axum::{
+    Json,
+    response::{IntoResponse, Response},
+};
+use contextual::Context;
+use email::Email;
+use http::StatusCode;
+
+use crate::SignupConfig;
+
+const BUNDLED_DISPOSABLE_DOMAINS: &str = include_str!("../disposable-domains.txt");
+
+#[derive(Debug, Clone)]
+pub struct SignupPolicy {
+    pub invite_only: bool,
+    pub require_verified_email: bool,
+    pub login_after_email_verification: bool,
+    allowed_domains: Vec<String>,
+    blocked_domains: Vec<String>,
+    disposable_domains: HashSet<String>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SignupPolicyError {
+    #[error("signups from `{0}` are not allowed")]
+    DomainNotAllowed(String),
+
+    #[error("signups from `{0}` are blocked")]
+    DomainBlocked(String),
+
+    #[error("disposable email addresses (`{0}`) are not allowed")]
+    DisposableDomain(String),
+}
+
+impl SignupPolicy {
+    /// Checks the domain of the email against the allow, block and disposable domain lists.
+    /// A domain also matches all of its subdomains.
+    pub fn check_email(&self, email: &Email) -> Result<(), SignupPolicyError> {
+        let domain = email.domain().to_lowercase();
+
+        if !self.allowed_domains.is_empty()
+            && !self
+                .allowed_domains
+                .iter()
+                .any(|allowed| matches_domain(&domain, allowed))
+        {
+            return Err(SignupPolicyError::DomainNotAllowed(domain));
+        }
+
+        if self
+            .blocked_domains
+            .iter()
+            .any(|blocked| matches_domain(&domain, blocked))
+        {
+            return Err(SignupPolicyError::DomainBlocked(domain));
+        }
+
+        if parent_domains(&domain).any(|parent| self.disposable_domains.contains(parent)) {
+            return Err(SignupPolicyError::DisposableDomain(domain));
+        }
+
+        Ok(())
+    }
+}
+
+impl TryFrom<SignupConfig> for SignupPolicy {
+    type Error = contextual::Error<std::io::Error>;
+
+    fn try_from(config: SignupConfig) -> Result<Self, Self::Error> {
+        let disposable_domains = match (
+            config.block_disposable_domains,
+            config.disposable_domains_file,
+        ) {
+            (false, _) => HashSet::new(),
+            (true, None) => parse_domains(BUNDLED_DISPOSABLE_DOMAINS),
+            (true, Some(path)) => {
+                let content = std::fs::read_to_string(&path)
+                    .context(format!("disposable domains file :: {}", path.display()))?;
+                parse_domains(&content)
+            }
+        };
+
+        Ok(Self {
+            invite_only: config.invite_only,
+            require_verified_email: config.require_verified_email,
+            login_after_email_verification: config.login_after_email_verification,
+            allowed_domains: normalize_domains(config.allowed_domains),
+            blocked_domains: normalize_domains(config.blocked_domains),
+            disposable_domains,
+        })
+    }
+}
+
+fn matches_domain(domain: &str, pattern: &str) -> bool {
+    domain == pattern
+        || domain
+            .strip_suffix(pattern)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// `a.b.example.com` -> `a.b.example.com`, `b.example.com`, `example.com`, `com`
+fn parent_domains(domain: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(domain), |domain| {
+        domain.split_once('.').map(|(_, parent)| parent)
+    })
+}
+
+fn parse_domains(content: &str) -> HashSet<String> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn normalize_domains(domains: Vec<String>) -> Vec<String> {
+    domains
+        .into_iter()
+        .map(|domain| domain.trim().trim_start_matches('@').to_lowercase())
+        .filter(|domain| !domain.is_empty())
+        .collect()
+}
+
+impl extra::ErrorKind for SignupPolicyError {
+    fn kind(&self) -> &'static str {
+        match self {
+            SignupPolicyError::DomainNotAllowed(_) => "signup.email.domain.not-allowed",
+            SignupPolicyError::DomainBlocked(_) => "signup.email.domain.blocked",
+            SignupPolicyError::DisposableDomain(_) => "signup.email.disposable",
+        }
+    }
+}
+
+impl IntoResponse for SignupPolicyError {
+    fn into_response(self) -> Response {
+        #[cfg(feature = "tracing")]
+        tracing::info!("{:?}", self);
+
+        (
+            StatusCode::FORBIDDEN,
+            Json(extra::ErrorResponse::from(self)),
+        )
+            .into_response()
+    }
+}
@@ -1,15 +1,122 @@
-use std::{path::PathBuf, str::FromStr, sync::Arc};
+use std::{
+    future::Future,
+    path::PathBuf,
+    pin::Pin,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 
 use contextual::Context;
 use email::Email;
-use lettre::{AsyncSmtpTransport, Tokio1Executor};
+use lettre::{
+    AsyncFileTransport, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    message::{
+        dkim::{
+            DkimCanonicalization, DkimCanonicalizationType, DkimConfig, DkimSigningAlgorithm,
+            DkimSigningKey, DkimSigningKeyError,
+        },
+        header::HeaderName,
+    },
+};
+use secrets::Secrets;
 use tera::Tera;
 
+use crate::{notification::Notification, outbox::OutgoingEmail};
+
 #[derive(Clone)]
 pub struct Smtp {
-    pub transport: AsyncSmtpTransport<Tokio1Executor>,
+    pub transport: Arc<dyn MailTransport>,
     pub senders: Arc<SmtpSenders>,
     pub tera: Arc<Tera>,
+    pub verification_redirect_url: Option<String>,
+    pub verification_resend_interval: std::time::Duration,
+    pub dkim: Option<Arc<DkimConfig>>,
+}
+
+impl Smtp {
+    /// Renders `<locale>/<name>` for the first locale of the fallback chain that provides it.
+    pub fn render(
+        &self,
+        locale: Option<&str>,
+        name: &str,
+        context: &tera::Context,
+    ) -> Result<String, tera::Error> {
+        let template = crate::core::locale_fallbacks(locale)
+            .map(|locale| format!("{locale}/{name}"))
+            .find(|template| self.tera.get_template_names().any(|name| name == template))
+            .ok_or_else(|| tera::Error::template_not_found(name))?;
+
+        self.tera.render(&template, context)
+    }
+
+    /// Renders the notification's subject, plain-text and HTML templates into an email.
+    pub fn render_notification<'a, N: Notification>(
+        &self,
+        locale: Option<&str>,
+        recipient: &'a Email,
+        notification: &N,
+    ) -> Result<OutgoingEmail<'a>, tera::Error> {
+        let context = tera::Context::from_serialize(notification)?;
+        let [subject, plain_text, html] = N::templates();
+
+        Ok(OutgoingEmail {
+            sender: N::SENDER,
+            recipient,
+            subject: self.render(locale, &subject, &context)?.trim().to_string(),
+            plain_text: self.render(locale, &plain_text, &context)?,
+            html: self.render(locale, &html, &context)?,
+        })
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum DkimError {
+    #[error("{0}")]
+    Secret(#[from] contextual::Error<std::io::Error>),
+
+    #[error("DKIM key secret `{0}` is not valid UTF-8")]
+    KeyEncoding(String),
+
+    #[error("DKIM key: {0}")]
+    Key(#[from] DkimSigningKeyError),
+
+    #[error("invalid DKIM signed header `{0}`")]
+    SignedHeader(String),
+}
+
+/// Loads the signing key from [`Secrets`] and builds the signer's configuration.
+pub fn dkim_config(config: crate::DkimConfig, secrets: &Secrets) -> Result<DkimConfig, DkimError> {
+    let secret = secrets
+        .get(&config.key_secret)
+        .context(format!("DKIM key secret `{}`", config.key_secret))?;
+    let key = std::str::from_utf8(&secret)
+        .map_err(|_| DkimError::KeyEncoding(config.key_secret.clone()))?;
+
+    let algorithm = match config.algorithm {
+        crate::DkimAlgorithm::Rsa => DkimSigningAlgorithm::Rsa,
+        crate::DkimAlgorithm::Ed25519 => DkimSigningAlgorithm::Ed25519,
+    };
+    let key = DkimSigningKey::new(key.trim(), algorithm)?;
+
+    let headers = config
+        .signed_headers
+        .into_iter()
+        .map(|header| {
+            HeaderName::new_from_ascii(header.trim().to_string())
+                .map_err(|_| DkimError::SignedHeader(header))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(DkimConfig::new(
+        config.selector,
+        config.domain,
+        key,
+        headers,
+        DkimCanonicalization {
+            header: DkimCanonicalizationType::Relaxed,
+            body: DkimCanonicalizationType::Relaxed,
+        },
+    ))
 }
 
 pub struct SmtpSenders {
@@ -30,6 +137,10 @@ impl SmtpSenders {
         Self { dir }
     }
 
+    pub fn contains(&self, sender: &str) -> bool {
+        self.dir.join(sender).is_file() || self.dir.join(format!("{sender}.txt")).is_file()
+    }
+
     pub async fn get(&self, sender: &str) -> Result<Email, SmtpSendersError> {
         let content = std::fs::read_to_string(self.dir.join(sender))
             .or_else(|_| std::fs::read_to_string(self.dir.join(format!("{sender}.txt"))))
@@ -38,3 +149,84 @@ impl SmtpSenders {
         Email::from_str(content.trim()).map_err(SmtpSendersError::EmailFormat)
     }
 }
+
+/// Delivers fully built messages. Implemented for SMTP relays, `.eml` file drops and
+/// an in-memory mailbox that tests can inspect.
+pub trait MailTransport: Send + Sync {
+    fn send(&self, message: Message) -> TransportFuture<'_>;
+}
+
+pub type TransportFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + 'a>>;
+
+#[derive(thiserror::Error, Debug)]
+pub enum TransportError {
+    #[error("{0}")]
+    Smtp(#[from] lettre::transport::smtp::Error),
+
+    #[error("{0}")]
+    File(#[from] lettre::transport::file::Error),
+
+    #[error("rejected by relay: {0}")]
+    Rejected(String),
+}
+
+impl MailTransport for AsyncSmtpTransport<Tokio1Executor> {
+    fn send(&self, message: Message) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let response = AsyncTransport::send(self, message).await?;
+
+            match response.is_positive() {
+                true => Ok(()),
+                false => Err(TransportError::Rejected(format!("{response:?}"))),
+            }
+        })
+    }
+}
+
+/// Writes every message as `<id>.eml` into a directory.
+impl MailTransport for AsyncFileTransport<Tokio1Executor> {
+    fn send(&self, message: Message) -> TransportFuture<'_> {
+        Box::pin(async move {
+            AsyncTransport::send(self, message).await?;
+            Ok(())
+        })
+    }
+}
+
+/// Keeps every message in memory. Clones share the same mailbox.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryTransport {
+    sent: Arc<Mutex<Vec<SentEmail>>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SentEmail {
+    pub to: Vec<String>,
+    /// the message in RFC 5322 format
+    pub raw: String,
+}
+
+impl InMemoryTransport {
+    pub fn sent(&self) -> Vec<SentEmail> {
+        self.sent.lock().expect("poisoned mailbox").clone()
+    }
+}
+
+impl MailTransport for InMemoryTransport {
+    fn send(&self, message: Message) -> TransportFuture<'_> {
+        let email = SentEmail {
+            to: message
+                .envelope()
+                .to()
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            raw: String::from_utf8_lossy(&message.formatted()).into_owned(),
+        };
+
+        self.sent.lock().expect("poisoned mailbox").push(email);
+
+        Box::pin(async { Ok(()) })
+    }
+}
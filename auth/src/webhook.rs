@@ -0,0 +1,307 @@
+//! Outgoing webhooks for identity events.
+//!
+//! Events are written to `webhook_deliveries`, once per webhook subscribed to them, ideally in the
+//! same transaction as the change that triggers them, and POSTed by a background worker.
+//! Every request carries a `Webhook-Signature: t=<unix timestamp>,v1=<signature>` header, where the
+//! signature is the HMAC-SHA256 of `<timestamp>.<body>` keyed with the webhook's secret
+//! (see [`signature::hmac_sha256`]). Failed deliveries are retried (see [`crate::retry`]) until
+//! the delivery is marked as `failed`.
+
+use contextual::Context;
+use serde::Serialize;
+use sqlx::{Executor, Sqlite};
+use time::OffsetDateTime;
+
+use crate::retry::{Entry, Failure, Queue};
+
+pub use crate::retry::DeliveryReport;
+
+pub const SIGNATURE_HEADER: &str = "webhook-signature";
+pub const EVENT_HEADER: &str = "webhook-event";
+pub const DELIVERY_ID_HEADER: &str = "webhook-id";
+
+/// An identity event, serialized as `{"event": "<name>", "data": {..}}`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", content = "data")]
+pub enum Event {
+    #[serde(rename = "user.created")]
+    UserCreated { user_id: i64, username: String },
+
+    #[serde(rename = "email.verified")]
+    #[cfg_attr(not(feature = "smtp"), allow(dead_code))]
+    EmailVerified { email: String },
+
+    #[serde(rename = "session.revoked")]
+    SessionRevoked {
+        user_id: i64,
+        /// `logout` or `account.disabled`
+        reason: &'static str,
+    },
+
+    #[serde(rename = "permission.assigned")]
+    PermissionAssigned {
+        permission: String,
+        /// `user` or `access_token`
+        assignee_type: &'static str,
+        assignee_id: i64,
+    },
+
+    #[serde(rename = "token.created")]
+    TokenCreated {
+        user_id: i64,
+        token_name: String,
+        #[serde(with = "time::serde::rfc3339::option")]
+        expires_at: Option<OffsetDateTime>,
+    },
+}
+
+impl Event {
+    /// Names a webhook can subscribe to.
+    pub const NAMES: [&'static str; 5] = [
+        "user.created",
+        "email.verified",
+        "session.revoked",
+        "permission.assigned",
+        "token.created",
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::UserCreated { .. } => "user.created",
+            Event::EmailVerified { .. } => "email.verified",
+            Event::SessionRevoked { .. } => "session.revoked",
+            Event::PermissionAssigned { .. } => "permission.assigned",
+            Event::TokenCreated { .. } => "token.created",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    #[serde(flatten)]
+    event: &'a Event,
+
+    #[serde(with = "time::serde::rfc3339")]
+    created_at: OffsetDateTime,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum DeliveryError {
+    #[error("{0}")]
+    Signature(#[from] signature::EncodeError),
+
+    #[error("{0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("receiver responded with status {0}")]
+    Status(u16),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum EnqueueError {
+    #[error("{0}")]
+    Serde(#[from] contextual::Error<serde_json::Error>),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+/// Queues the event for every webhook subscribed to it.
+/// Returns the number of queued deliveries.
+pub async fn enqueue<'a, E: Executor<'a, Database = Sqlite>>(
+    ex: E,
+    event: &Event,
+) -> Result<u64, EnqueueError> {
+    let now = OffsetDateTime::now_utc();
+    let name = event.name();
+    let payload = serde_json::to_string(&Payload {
+        event,
+        created_at: now,
+    })
+    .context(format!("serialize `{name}` webhook payload"))?;
+
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO webhook_deliveries (webhook_id, event, payload, created_at, next_attempt_at)
+        SELECT webhook_id, ?1, ?2, ?3, ?3
+        FROM webhook_events
+        WHERE event = ?1
+        "#,
+        name,
+        payload,
+        now
+    )
+    .execute(ex)
+    .await
+    .context(format!("enqueue `{name}` webhook"))?;
+
+    Ok(result.rows_affected())
+}
+
+/// Attempts every pending delivery that is due.
+pub async fn deliver_due(
+    pool: &sqlx::Pool<Sqlite>,
+    client: &reqwest::Client,
+) -> Result<DeliveryReport, sqlx::Error> {
+    crate::retry::deliver_due(&Deliveries { pool, client }).await
+}
+
+struct Deliveries<'a> {
+    pool: &'a sqlx::Pool<Sqlite>,
+    client: &'a reqwest::Client,
+}
+
+struct Delivery {
+    id: i64,
+    event: String,
+    payload: String,
+    url: String,
+    secret: String,
+}
+
+impl Queue for Deliveries<'_> {
+    type Item = Delivery;
+    type Receipt = u16;
+    type Error = DeliveryError;
+
+    const NAME: &'static str = "webhook";
+
+    async fn due(
+        &self,
+        now: OffsetDateTime,
+        limit: i64,
+    ) -> Result<Vec<Entry<Delivery>>, sqlx::Error> {
+        let deliveries = sqlx::query!(
+            r#"
+            SELECT d.id as "id!", d.event, d.payload, d.attempts, w.url, w.secret
+            FROM webhook_deliveries d
+            INNER JOIN webhooks w ON w.id = d.webhook_id
+            WHERE d.status = 'pending' AND d.next_attempt_at <= ?
+            ORDER BY d.next_attempt_at
+            LIMIT ?
+            "#,
+            now,
+            limit
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(deliveries
+            .into_iter()
+            .map(|record| Entry {
+                id: record.id,
+                attempts: record.attempts,
+                item: Delivery {
+                    id: record.id,
+                    event: record.event,
+                    payload: record.payload,
+                    url: record.url,
+                    secret: record.secret,
+                },
+            })
+            .collect())
+    }
+
+    async fn send(&self, delivery: Delivery) -> Result<u16, DeliveryError> {
+        send(
+            self.client,
+            &delivery.url,
+            &delivery.secret,
+            delivery.id,
+            &delivery.event,
+            delivery.payload,
+        )
+        .await
+    }
+
+    async fn delivered(
+        &self,
+        id: i64,
+        status: u16,
+        now: OffsetDateTime,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = 'delivered', attempts = attempts + 1, last_error = NULL,
+                response_status = ?, delivered_at = ?
+            WHERE id = ?
+            "#,
+            status,
+            now,
+            id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn failed(
+        &self,
+        id: i64,
+        failure: Failure<'_, DeliveryError>,
+        now: OffsetDateTime,
+    ) -> Result<(), sqlx::Error> {
+        let last_error = failure.error.to_string();
+        let response_status = match failure.error {
+            DeliveryError::Status(status) => Some(*status),
+            _ => None,
+        };
+        let (status, next_attempt_at) = match failure.next_attempt_at {
+            Some(next_attempt_at) => ("pending", next_attempt_at),
+            None => ("failed", now),
+        };
+
+        sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = ?, attempts = ?, last_error = ?, response_status = ?,
+                next_attempt_at = ?
+            WHERE id = ?
+            "#,
+            status,
+            failure.attempts,
+            last_error,
+            response_status,
+            next_attempt_at,
+            id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// POSTs the signed payload and returns the receiver's (successful) status code.
+async fn send(
+    client: &reqwest::Client,
+    url: &str,
+    secret: &str,
+    delivery_id: i64,
+    event: &str,
+    payload: String,
+) -> Result<u16, DeliveryError> {
+    let timestamp = OffsetDateTime::now_utc().unix_timestamp();
+    let signature = signature::hmac_sha256(
+        secret.as_bytes(),
+        format!("{timestamp}.{payload}").as_bytes(),
+    )?;
+
+    let response = client
+        .post(url)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(SIGNATURE_HEADER, format!("t={timestamp},v1={signature}"))
+        .header(EVENT_HEADER, event)
+        .header(DELIVERY_ID_HEADER, delivery_id)
+        .body(payload)
+        .send()
+        .await?;
+
+    let status = response.status();
+    match status.is_success() {
+        true => Ok(status.as_u16()),
+        false => Err(DeliveryError::Status(status.as_u16())),
+    }
+}
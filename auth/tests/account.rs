@@ -0,0 +1,188 @@
+mod shared;
+
+use shared::{TestClient, basic};
+use test_proc_macros::{email, password, username};
+
+#[tokio::test]
+async fn export_and_delete() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let username = username!("user1");
+    let email = email!("user1@test.com");
+    let password = password!("Aa!1aaaa");
+    let wrong_password = password!("Bb!2bbbb");
+
+    let mut client = TestClient::default().await;
+
+    client
+        .send(request!(
+            POST "/signup";
+            "host" => "localhost"
+            "accept-language" => "de-AT"
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}&email={}&password={}", username, email, password)
+        ))
+        .await
+        .status(201);
+
+    client.assign_permission_group(username, "signup").await;
+
+    client
+        .send(request!(
+            POST "/login";
+            "content-type" => "application/x-www-form-urlencoded"
+            "user-agent" => "test-agent";
+            format!("username={}&password={}", username, password)
+        ))
+        .await
+        .status(200);
+
+    client
+        .send(request!(
+            GET "/account/export";
+            "authorization" => basic(username, password);
+        ))
+        .await
+        .status(200)
+        .json_body::<serde_json::Value>(|body| {
+            assert_eq!(body["profile"]["username"], username);
+            assert_eq!(body["profile"]["email"], email);
+            assert_eq!(body["profile"]["disabled"], false);
+            assert_eq!(body["profile"]["locale"], "de-at");
+            assert_eq!(body["sessions"].as_array().map(Vec::len), Some(1));
+            assert_eq!(body["login_history"][0]["user_agent"], "test-agent");
+            assert!(body["profile"].get("password_hash").is_none());
+            for table in [
+                "impersonation_audit_log",
+                "invitations",
+                "organization_memberships",
+                "email_verification_tokens",
+            ] {
+                assert!(body[table].is_array(), "`{table}` not exported");
+            }
+        })
+        .await;
+
+    client
+        .send(request!(
+            DELETE "/account";
+            "authorization" => basic(username, password)
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("password={}", wrong_password)
+        ))
+        .await
+        .status(401);
+
+    client
+        .send(request!(
+            DELETE "/account";
+            "authorization" => basic(username, password)
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("password={}", password)
+        ))
+        .await
+        .status(200);
+
+    client
+        .send(request!(
+            POST "/login";
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}&password={}", username, password)
+        ))
+        .await
+        .status(401);
+}
+
+#[tokio::test]
+async fn scheduled_deletion_rejects_access_tokens() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let username = username!("user1");
+    let password = password!("Aa!1aaaa");
+
+    let mut client = TestClient::with_account_config(auth::AccountConfig {
+        deletion_grace_period: std::time::Duration::from_secs(60 * 60),
+    })
+    .await;
+
+    client
+        .create_user(username, email!("user1@test.com"), password)
+        .await;
+    client.assign_permission_group(username, "signup").await;
+
+    let token = String::from_utf8(
+        axum::body::to_bytes(
+            client
+                .send(request!(
+                    POST "/access-token/generate";
+                    "authorization" => basic(username, password)
+                    "content-type" => "application/x-www-form-urlencoded";
+                    "name=ci-token&ttl_sec=3600"
+                ))
+                .await
+                .status(201)
+                .into_response()
+                .into_body(),
+            usize::MAX,
+        )
+        .await
+        .expect("unable to read response body")
+        .to_vec(),
+    )
+    .expect("access token must be utf-8");
+
+    client
+        .send(request!(
+            GET "/private";
+            "authorization" => format!("Token {}", token);
+        ))
+        .await
+        .status(200);
+
+    client
+        .send(request!(
+            DELETE "/account";
+            "authorization" => basic(username, password)
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("password={}", password)
+        ))
+        .await
+        .status(202);
+
+    for authorization in [format!("Token {}", token), basic(username, password)] {
+        client
+            .send(request!(
+                GET "/private";
+                "authorization" => authorization;
+            ))
+            .await
+            .status(403)
+            .json_body::<serde_json::Value>(|body| {
+                assert_eq!(
+                    body.get("kind"),
+                    Some(&serde_json::Value::from("auth.account.deletion-scheduled"))
+                );
+            })
+            .await;
+    }
+
+    // logging in cancels the deletion
+    client
+        .send(request!(
+            POST "/login";
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}&password={}", username, password)
+        ))
+        .await
+        .status(200);
+
+    client
+        .send(request!(
+            GET "/private";
+            "authorization" => format!("Token {}", token);
+        ))
+        .await
+        .status(200);
+}
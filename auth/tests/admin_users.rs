@@ -0,0 +1,188 @@
+mod shared;
+
+use shared::{TestClient, basic};
+use test_proc_macros::{email, password, username};
+
+#[tokio::test]
+async fn disabled_account_is_rejected() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let admin = username!("admin");
+    let admin_password = password!("Aa!1aaaa");
+    let user = username!("user1");
+    let user_password = password!("Bb!2bbbb");
+
+    let mut client = TestClient::default().await;
+
+    for (username, email, password) in [
+        (admin, email!("admin@test.com"), admin_password),
+        (user, email!("user1@test.com"), user_password),
+    ] {
+        client
+            .send(request!(
+                POST "/signup";
+                "host" => "localhost"
+                "content-type" => "application/x-www-form-urlencoded";
+                format!("username={}&email={}&password={}", username, email, password)
+            ))
+            .await
+            .status(201);
+    }
+
+    client.assign_permission_group(admin, "admin").await;
+
+    client
+        .send(request!(
+            POST "/admin/users/disable";
+            "authorization" => basic(admin, admin_password)
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}", user)
+        ))
+        .await
+        .status(200);
+
+    client
+        .send(request!(
+            GET "/private";
+            "authorization" => basic(user, user_password);
+        ))
+        .await
+        .status(403)
+        .json_body::<serde_json::Value>(|body| {
+            assert_eq!(
+                body.get("kind"),
+                Some(&serde_json::Value::from("auth.account.disabled"))
+            );
+        })
+        .await;
+
+    client
+        .send(request!(
+            POST "/login";
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}&password={}", user, user_password)
+        ))
+        .await
+        .status(403);
+
+    client
+        .send(request!(
+            POST "/admin/users/enable";
+            "authorization" => basic(admin, admin_password)
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}", user)
+        ))
+        .await
+        .status(200);
+
+    client
+        .send(request!(
+            POST "/login";
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}&password={}", user, user_password)
+        ))
+        .await
+        .status(200);
+}
+
+#[tokio::test]
+async fn delete_account() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let admin = username!("admin");
+    let admin_password = password!("Aa!1aaaa");
+    let user = username!("user1");
+    let user_password = password!("Bb!2bbbb");
+
+    let mut client = TestClient::default().await;
+
+    for (username, email, password) in [
+        (admin, email!("admin@test.com"), admin_password),
+        (user, email!("user1@test.com"), user_password),
+    ] {
+        client
+            .send(request!(
+                POST "/signup";
+                "host" => "localhost"
+                "content-type" => "application/x-www-form-urlencoded";
+                format!("username={}&email={}&password={}", username, email, password)
+            ))
+            .await
+            .status(201);
+    }
+
+    client.assign_permission_group(admin, "admin").await;
+
+    client
+        .send(request!(
+            GET format!("/admin/users?search={}", user);
+            "authorization" => basic(admin, admin_password);
+        ))
+        .await
+        .status(200)
+        .json_body::<serde_json::Value>(|body| {
+            assert_eq!(body.as_array().map(Vec::len), Some(1));
+        })
+        .await;
+
+    client
+        .send(request!(
+            DELETE format!("/admin/users?username={}", user);
+            "authorization" => basic(admin, admin_password);
+        ))
+        .await
+        .status(200);
+
+    client
+        .send(request!(
+            GET format!("/admin/users?search={}", user);
+            "authorization" => basic(admin, admin_password);
+        ))
+        .await
+        .status(200)
+        .json_body::<serde_json::Value>(|body| {
+            assert_eq!(body.as_array().map(Vec::len), Some(0));
+        })
+        .await;
+
+    client
+        .send(request!(
+            POST "/login";
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}&password={}", user, user_password)
+        ))
+        .await
+        .status(401);
+}
+
+#[tokio::test]
+async fn requires_permission() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let username = username!("user1");
+    let email = email!("user1@test.com");
+    let password = password!("Aa!1aaaa");
+
+    let mut client = TestClient::default().await;
+
+    client
+        .send(request!(
+            POST "/signup";
+            "host" => "localhost"
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}&email={}&password={}", username, email, password)
+        ))
+        .await
+        .status(201);
+
+    client
+        .send(request!(
+            GET "/admin/users";
+            "authorization" => basic(username, password);
+        ))
+        .await
+        .status(403);
+}
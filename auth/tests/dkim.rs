@@ -0,0 +1,36 @@
+#![cfg(feature = "smtp")]
+
+mod shared;
+
+use shared::TestClient;
+use test_proc_macros::{password, username};
+
+#[tokio::test]
+async fn outgoing_emails_are_signed() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let mut client = TestClient::default().await;
+
+    client
+        .send(request!(
+            POST "/signup";
+            "host" => "localhost"
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}&email={}&password={}", username!("user1"), "user1@test.com", password!("Aa!1aaaa"))
+        ))
+        .await
+        .status(201);
+
+    let email = client.wait_for_email("user1@test.com").await;
+    // unfold long header lines
+    let raw = email.raw.replace("\r\n ", " ").replace("\r\n\t", " ");
+    let signature = raw
+        .lines()
+        .find(|line| line.starts_with("DKIM-Signature:"))
+        .expect("email is not DKIM signed");
+
+    assert!(signature.contains("a=ed25519-sha256"));
+    assert!(signature.contains("d=example.com"));
+    assert!(signature.contains("s=test"));
+}
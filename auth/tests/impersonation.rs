@@ -0,0 +1,365 @@
+mod shared;
+
+use shared::{TestClient, basic};
+use test_proc_macros::{email, password, username};
+
+#[tokio::test]
+async fn impersonation_is_restricted_and_stoppable() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let admin = username!("admin");
+    let admin_password = password!("Aa!1aaaa");
+    let user = username!("user1");
+    let user_password = password!("Bb!2bbbb");
+
+    let mut client = TestClient::default().await;
+
+    for (username, email, password) in [
+        (admin, email!("admin@test.com"), admin_password),
+        (user, email!("user1@test.com"), user_password),
+    ] {
+        client
+            .send(request!(
+                POST "/signup";
+                "host" => "localhost"
+                "content-type" => "application/x-www-form-urlencoded";
+                format!("username={}&email={}&password={}", username, email, password)
+            ))
+            .await
+            .status(201);
+    }
+
+    client.assign_permission_group(admin, "admin").await;
+    client.assign_permission_group(user, "signup").await;
+
+    client
+        .send(request!(
+            POST "/admin/impersonate";
+            "authorization" => basic(user, user_password)
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}", admin)
+        ))
+        .await
+        .status(403);
+
+    let response = client
+        .send(request!(
+            POST "/admin/impersonate";
+            "authorization" => basic(admin, admin_password)
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}", user)
+        ))
+        .await
+        .status(200)
+        .into_response();
+
+    let session_cookie = response
+        .headers()
+        .get("set-cookie")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .expect("session cookie not set")
+        .to_owned();
+
+    client
+        .send(request!(
+            POST "/access-token/generate";
+            "cookie" => &session_cookie
+            "content-type" => "application/x-www-form-urlencoded";
+            "name=support-token"
+        ))
+        .await
+        .status(403)
+        .json_body::<serde_json::Value>(|body| {
+            assert_eq!(
+                body.get("kind"),
+                Some(&serde_json::Value::from("auth.impersonation.restricted"))
+            );
+        })
+        .await;
+
+    client
+        .send(request!(
+            POST "/admin/impersonate/stop";
+            "cookie" => &session_cookie;
+        ))
+        .await
+        .status(200);
+
+    client
+        .send(request!(
+            GET "/private";
+            "cookie" => &session_cookie;
+        ))
+        .await
+        .status(401);
+
+    client
+        .send(request!(
+            POST "/admin/impersonate/stop";
+            "authorization" => basic(admin, admin_password);
+        ))
+        .await
+        .status(400);
+
+    client
+        .send(request!(
+            GET "/account/export";
+            "authorization" => basic(user, user_password);
+        ))
+        .await
+        .status(200)
+        .json_body::<serde_json::Value>(|body| {
+            let actions = body["impersonation_audit_log"]
+                .as_array()
+                .expect("impersonation audit log not exported")
+                .iter()
+                .map(|entry| entry["action"].clone())
+                .collect::<Vec<_>>();
+            assert_eq!(actions, ["start", "stop"]);
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn impersonation_cannot_escalate_privileges() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let admin = username!("admin");
+    let admin_password = password!("Aa!1aaaa");
+    let root = username!("root");
+    let root_password = password!("Cc!3cccc");
+
+    let mut client = TestClient::default().await;
+
+    for (username, email, password) in [
+        (admin, email!("admin@test.com"), admin_password),
+        (root, email!("root@test.com"), root_password),
+    ] {
+        client
+            .send(request!(
+                POST "/signup";
+                "host" => "localhost"
+                "content-type" => "application/x-www-form-urlencoded";
+                format!("username={}&email={}&password={}", username, email, password)
+            ))
+            .await
+            .status(201);
+    }
+
+    client.assign_permission_group(admin, "admin").await;
+    client.assign_permission_group(root, "root").await;
+
+    client
+        .send(request!(
+            POST "/admin/impersonate";
+            "authorization" => basic(admin, admin_password)
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}", root)
+        ))
+        .await
+        .status(403)
+        .json_body::<serde_json::Value>(|body| {
+            assert_eq!(
+                body.get("kind"),
+                Some(&serde_json::Value::from("user.impersonation.privileged"))
+            );
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn admin_endpoints_are_forbidden_while_impersonating() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let admin = username!("admin");
+    let admin_password = password!("Aa!1aaaa");
+    let other_admin = username!("admin2");
+    let other_admin_password = password!("Dd!4dddd");
+
+    let mut client = TestClient::default().await;
+
+    for (username, email, password) in [
+        (admin, email!("admin@test.com"), admin_password),
+        (other_admin, email!("admin2@test.com"), other_admin_password),
+    ] {
+        client
+            .send(request!(
+                POST "/signup";
+                "host" => "localhost"
+                "content-type" => "application/x-www-form-urlencoded";
+                format!("username={}&email={}&password={}", username, email, password)
+            ))
+            .await
+            .status(201);
+    }
+
+    client.assign_permission_group(admin, "admin").await;
+    client.assign_permission_group(other_admin, "admin").await;
+
+    let response = client
+        .send(request!(
+            POST "/admin/impersonate";
+            "authorization" => basic(admin, admin_password)
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}", other_admin)
+        ))
+        .await
+        .status(200)
+        .into_response();
+
+    let session_cookie = response
+        .headers()
+        .get("set-cookie")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .expect("session cookie not set")
+        .to_owned();
+
+    for request in [
+        request!(
+            POST "/admin/users/disable";
+            "cookie" => &session_cookie
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}", admin)
+        ),
+        request!(
+            POST "/rotate-key";
+            "cookie" => &session_cookie
+            "content-type" => "application/x-www-form-urlencoded";
+            "key=hmac"
+        ),
+        request!(
+            GET "/admin/secrets?key=hmac";
+            "cookie" => &session_cookie;
+        ),
+        request!(
+            POST "/admin/outbox/retry";
+            "cookie" => &session_cookie
+            "content-type" => "application/x-www-form-urlencoded";
+            "id=1"
+        ),
+    ] {
+        client
+            .send(request)
+            .await
+            .status(403)
+            .json_body::<serde_json::Value>(|body| {
+                assert_eq!(
+                    body.get("kind"),
+                    Some(&serde_json::Value::from("auth.impersonation.restricted"))
+                );
+            })
+            .await;
+    }
+}
+
+#[tokio::test]
+async fn deleting_impersonation_sessions_is_audited() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let admin = username!("admin");
+    let admin_password = password!("Aa!1aaaa");
+    let user = username!("user1");
+    let user_password = password!("Bb!2bbbb");
+
+    let mut client = TestClient::default().await;
+
+    client
+        .create_user(admin, email!("admin@test.com"), admin_password)
+        .await;
+    client.assign_permission_group(admin, "admin").await;
+    client.assign_permission_group(admin, "signup").await;
+    client
+        .create_user(user, email!("user1@test.com"), user_password)
+        .await;
+    client.assign_permission_group(user, "signup").await;
+
+    let impersonate = || {
+        request!(
+            POST "/admin/impersonate";
+            "authorization" => basic(admin, admin_password)
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}", user)
+        )
+    };
+    let session_cookie = |response: axum::response::Response| {
+        response
+            .headers()
+            .get("set-cookie")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .expect("session cookie not set")
+            .to_owned()
+    };
+
+    // logging out ends the impersonation
+    let cookie = session_cookie(client.send(impersonate()).await.status(200).into_response());
+    client
+        .send(request!(
+            POST "/logout";
+            "cookie" => &cookie;
+        ))
+        .await
+        .status(200);
+
+    // so does disabling the impersonated account
+    let cookie = session_cookie(client.send(impersonate()).await.status(200).into_response());
+    client
+        .send(request!(
+            POST "/admin/users/disable";
+            "authorization" => basic(admin, admin_password)
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}", user)
+        ))
+        .await
+        .status(200);
+    client
+        .send(request!(
+            GET "/private";
+            "cookie" => &cookie;
+        ))
+        .await
+        .status(401);
+    client
+        .send(request!(
+            POST "/admin/users/enable";
+            "authorization" => basic(admin, admin_password)
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}", user)
+        ))
+        .await
+        .status(200);
+
+    // and deleting it
+    client.send(impersonate()).await.status(200);
+    client
+        .send(request!(
+            DELETE format!("/admin/users?username={}", user);
+            "authorization" => basic(admin, admin_password);
+        ))
+        .await
+        .status(200);
+
+    client
+        .send(request!(
+            GET "/account/export";
+            "authorization" => basic(admin, admin_password);
+        ))
+        .await
+        .status(200)
+        .json_body::<serde_json::Value>(|body| {
+            let actions = body["impersonation_audit_log"]
+                .as_array()
+                .expect("impersonation audit log not exported")
+                .iter()
+                .map(|entry| entry["action"].clone())
+                .collect::<Vec<_>>();
+            assert_eq!(actions, ["start", "stop", "start", "stop", "start", "stop"]);
+        })
+        .await;
+}
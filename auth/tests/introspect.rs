@@ -0,0 +1,53 @@
+mod shared;
+
+use shared::{TestClient, basic};
+use test_proc_macros::{password, username};
+
+#[tokio::test]
+async fn introspection_reports_the_principals_permissions() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let user = username!("user1");
+    let password = password!("Aa!1aaaa");
+
+    let mut client = TestClient::default().await;
+
+    client.create_user(user, "user1@test.com", password).await;
+    client.assign_permission_group(user, "signup").await;
+
+    let body = serde_json::json!({
+        "permissions": ["post:/access-token/generate", "get:/sysinfo", "get:/kv/*"]
+    })
+    .to_string();
+
+    client
+        .send(request!(
+            POST "/introspect";
+            "content-type" => "application/json";
+            body.clone()
+        ))
+        .await
+        .status(401);
+
+    client
+        .send(request!(
+            POST "/introspect";
+            "authorization" => basic(user, password)
+            "content-type" => "application/json";
+            body
+        ))
+        .await
+        .status(200)
+        .json_body::<serde_json::Value>(|body| {
+            assert_eq!(
+                body["permissions"],
+                serde_json::json!({
+                    "post:/access-token/generate": true,
+                    "get:/sysinfo": false,
+                    "get:/kv/*": false,
+                })
+            );
+        })
+        .await;
+}
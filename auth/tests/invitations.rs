@@ -0,0 +1,321 @@
+mod shared;
+
+use shared::{TestClient, basic};
+use test_proc_macros::{email, password, username};
+
+#[tokio::test]
+async fn invite_only_signup() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let admin = username!("admin");
+    let admin_email = email!("admin@test.com");
+    let admin_password = password!("Aa!1aaaa");
+    let invitee = username!("user1");
+    let invitee_email = email!("user1@test.com");
+    let invitee_password = password!("Bb!2bbbb");
+
+    let mut client = TestClient::with_signup_config(auth::SignupConfig {
+        invite_only: true,
+        ..Default::default()
+    })
+    .await;
+
+    client
+        .send(request!(
+            POST "/signup";
+            "host" => "localhost"
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}&email={}&password={}", invitee, invitee_email, invitee_password)
+        ))
+        .await
+        .status(403);
+
+    client.create_user(admin, admin_email, admin_password).await;
+    client.assign_permission_group(admin, "admin").await;
+
+    client
+        .send(request!(
+            POST "/admin/invitations";
+            "authorization" => basic(admin, admin_password)
+            "host" => "localhost"
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("email={}&group=root", invitee_email)
+        ))
+        .await
+        .status(403);
+
+    let response = client
+        .send(request!(
+            POST "/admin/invitations";
+            "authorization" => basic(admin, admin_password)
+            "host" => "localhost"
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("email={}&group=admin", invitee_email)
+        ))
+        .await
+        .status(201)
+        .into_response();
+
+    let invitation = String::from_utf8(
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("unable to read response body")
+            .to_vec(),
+    )
+    .expect("invitation token must be utf-8");
+
+    client
+        .send(request!(
+            POST "/signup";
+            "host" => "localhost"
+            "content-type" => "application/x-www-form-urlencoded";
+            format!(
+                "username={}&email={}&password={}&invitation={}",
+                invitee, "other@test.com", invitee_password, invitation
+            )
+        ))
+        .await
+        .status(400);
+
+    client
+        .send(request!(
+            POST "/signup";
+            "host" => "localhost"
+            "content-type" => "application/x-www-form-urlencoded";
+            format!(
+                "username={}&email={}&password={}&invitation={}",
+                invitee, invitee_email, invitee_password, invitation
+            )
+        ))
+        .await
+        .status(201);
+
+    client
+        .send(request!(
+            GET "/admin/users";
+            "authorization" => basic(invitee, invitee_password);
+        ))
+        .await
+        .status(200)
+        .json_body::<serde_json::Value>(|body| {
+            let invitee = body
+                .as_array()
+                .and_then(|users| users.iter().find(|user| user["username"] == invitee))
+                .expect("invitee must be listed");
+            assert_eq!(invitee["email_verified"], true);
+        })
+        .await;
+
+    client
+        .send(request!(
+            POST "/signup";
+            "host" => "localhost"
+            "content-type" => "application/x-www-form-urlencoded";
+            format!(
+                "username={}&email={}&password={}&invitation={}",
+                username!("user2"), email!("user2@test.com"), invitee_password, invitation
+            )
+        ))
+        .await
+        .status(400);
+
+    client
+        .send(request!(
+            GET "/account/export";
+            "authorization" => basic(invitee, invitee_password);
+        ))
+        .await
+        .status(200)
+        .json_body::<serde_json::Value>(|body| {
+            let invitations = body["invitations"]
+                .as_array()
+                .expect("invitations not exported");
+            assert_eq!(invitations.len(), 1);
+            assert_eq!(invitations[0]["group"], "admin");
+            assert!(invitations[0]["accepted_at"].is_string());
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn invitation_ttl_is_bounded() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let admin = username!("admin");
+    let admin_password = password!("Aa!1aaaa");
+
+    let mut client = TestClient::default().await;
+    client
+        .create_user(admin, email!("admin@test.com"), admin_password)
+        .await;
+    client.assign_permission_group(admin, "admin").await;
+
+    for ttl_sec in [0, 365 * 24 * 60 * 60 + 1, u64::MAX] {
+        client
+            .send(request!(
+                POST "/admin/invitations";
+                "authorization" => basic(admin, admin_password)
+                "host" => "localhost"
+                "content-type" => "application/x-www-form-urlencoded";
+                format!("email={}&group=admin&ttl_sec={}", email!("user1@test.com"), ttl_sec)
+            ))
+            .await
+            .status(400)
+            .json_body::<serde_json::Value>(|body| {
+                assert_eq!(
+                    body.get("kind"),
+                    Some(&serde_json::Value::from("invitation.ttl.invalid"))
+                );
+            })
+            .await;
+    }
+
+    client
+        .send(request!(
+            POST "/admin/invitations";
+            "authorization" => basic(admin, admin_password)
+            "host" => "localhost"
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("email={}&group=admin&ttl_sec={}", email!("user1@test.com"), 365 * 24 * 60 * 60)
+        ))
+        .await
+        .status(201);
+}
+
+#[cfg(feature = "smtp")]
+#[tokio::test]
+async fn signup_with_emailed_invitation_link() {
+    use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let admin = username!("admin");
+    let admin_password = password!("Aa!1aaaa");
+    let invitee = username!("user1");
+    let invitee_email = email!("user1@test.com");
+    let invitee_password = password!("Bb!2bbbb");
+
+    let mut client = TestClient::with_signup_config(auth::SignupConfig {
+        invite_only: true,
+        ..Default::default()
+    })
+    .await;
+
+    client
+        .create_user(admin, email!("admin@test.com"), admin_password)
+        .await;
+    client.assign_permission_group(admin, "admin").await;
+
+    let response = client
+        .send(request!(
+            POST "/admin/invitations";
+            "authorization" => basic(admin, admin_password)
+            "host" => "localhost"
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("email={}&group=admin", invitee_email)
+        ))
+        .await
+        .status(201)
+        .into_response();
+
+    let invitation_token = String::from_utf8(
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("unable to read response body")
+            .to_vec(),
+    )
+    .expect("invitation token must be utf-8");
+
+    let link = invitation_link(&client.wait_for_email(invitee_email).await.raw);
+
+    // following the link opens the signup form with the invitation filled in
+    let response = client
+        .send(request!(GET &link;;))
+        .await
+        .status(200)
+        .into_response();
+    let page = String::from_utf8(
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("unable to read response body")
+            .to_vec(),
+    )
+    .expect("signup page must be utf-8");
+    assert!(page.contains(r#"<form method="post" action="/signup">"#));
+    let (_, invitation) = page
+        .split_once(r#"name="invitation" value=""#)
+        .expect("no invitation in signup form");
+    let invitation = invitation
+        .split_once('"')
+        .expect("unterminated invitation")
+        .0
+        .to_string();
+    assert_eq!(
+        Some(invitation.as_str()),
+        link.split_once("?invitation=").map(|(_, token)| token)
+    );
+
+    // neither the group nor the invitation token show in the link
+    let readable = invitation
+        .split('.')
+        .filter_map(|part| BASE64_URL_SAFE_NO_PAD.decode(part).ok())
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+        .collect::<String>();
+    assert!(!readable.contains("admin"), "group readable in {readable}");
+    assert!(!invitation.contains(&invitation_token));
+
+    let mut tampered = invitation.clone();
+    let last = tampered.pop().expect("empty invitation link");
+    tampered.push(if last == 'A' { 'B' } else { 'A' });
+
+    for (invitation, status) in [(tampered, 400), (invitation, 201)] {
+        client
+            .send(request!(
+                POST "/signup";
+                "host" => "localhost"
+                "content-type" => "application/x-www-form-urlencoded";
+                format!(
+                    "username={}&email={}&password={}&invitation={}",
+                    invitee, invitee_email, invitee_password, invitation
+                )
+            ))
+            .await
+            .status(status);
+    }
+
+    client
+        .send(request!(
+            GET "/account/export";
+            "authorization" => basic(invitee, invitee_password);
+        ))
+        .await
+        .status(200)
+        .json_body::<serde_json::Value>(|body| {
+            let invitations = body["invitations"]
+                .as_array()
+                .expect("invitations not exported");
+            assert_eq!(invitations.len(), 1);
+            assert_eq!(invitations[0]["group"], "admin");
+        })
+        .await;
+}
+
+/// Path and query of the link in the HTML part of the invitation email.
+#[cfg(feature = "smtp")]
+fn invitation_link(raw: &str) -> String {
+    let body = raw
+        .replace("=\r\n", "")
+        .replace("=3D", "=")
+        .replace("&#x2F;", "/");
+    let (_, link) = body
+        .split_once(r#"<a href=""#)
+        .expect("no invitation link in email");
+    let (link, _) = link.split_once('"').expect("unterminated invitation link");
+
+    link.strip_prefix("localhost")
+        .expect("invitation link to another host")
+        .to_string()
+}
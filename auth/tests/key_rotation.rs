@@ -0,0 +1,226 @@
+#![cfg(feature = "smtp")]
+
+mod shared;
+
+use shared::{TestClient, basic};
+use test_proc_macros::{password, username};
+
+#[tokio::test]
+async fn verification_links_survive_key_rotation() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let admin = username!("admin");
+    let password = password!("Aa!1aaaa");
+
+    let mut client = TestClient::default().await;
+
+    client.create_user(admin, "admin@test.com", password).await;
+    client.assign_permission_group(admin, "admin").await;
+
+    let signup = |username: &str, email: &str| {
+        request!(
+            POST "/signup";
+            "host" => "localhost"
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={username}&email={email}&password={password}")
+        )
+    };
+
+    // signed with the first version of the key, created at startup
+    client
+        .send(signup(username!("user1"), "user1@test.com"))
+        .await
+        .status(201);
+    let before_rotation = verification_token(&client.wait_for_email("user1@test.com").await.raw);
+
+    client
+        .send(request!(
+            POST "/rotate-key";
+            "authorization" => basic(admin, password)
+            "content-type" => "application/x-www-form-urlencoded";
+            "key=signing"
+        ))
+        .await
+        .status(200);
+
+    client
+        .send(signup(username!("user2"), "user2@test.com"))
+        .await
+        .status(201);
+    let after_rotation = verification_token(&client.wait_for_email("user2@test.com").await.raw);
+
+    for token in [before_rotation, after_rotation] {
+        client
+            .send(request!(
+                GET format!("/verify-email?token={token}");
+                "accept" => "application/json";
+            ))
+            .await
+            .status(200);
+    }
+
+    client
+        .send(request!(
+            GET "/admin/secrets?key=signing";
+            "authorization" => basic(admin, password);
+        ))
+        .await
+        .status(200)
+        .json_body::<serde_json::Value>(|body| {
+            assert_eq!(body["current_kid"], "v2");
+            assert_eq!(body["versions"][0]["kid"], "v1");
+            assert_eq!(body["versions"][0]["status"], "retired");
+            assert_eq!(body["versions"][1]["kid"], "v2");
+            assert_eq!(body["versions"][1]["status"], "current");
+        })
+        .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn concurrent_rotations_create_distinct_versions() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let admin = username!("admin");
+    let password = password!("Aa!1aaaa");
+
+    let mut client = TestClient::default().await;
+
+    client.create_user(admin, "admin@test.com", password).await;
+    client.assign_permission_group(admin, "admin").await;
+
+    let rotations = (0..8)
+        .map(|_| {
+            request!(
+                POST "/rotate-key";
+                "authorization" => basic(admin, password)
+                "content-type" => "application/x-www-form-urlencoded";
+                "key=hmac"
+            )
+        })
+        .collect();
+    for response in client.send_concurrently(rotations).await {
+        response.status(200);
+    }
+
+    client
+        .send(request!(
+            GET "/admin/secrets?key=hmac";
+            "authorization" => basic(admin, password);
+        ))
+        .await
+        .status(200)
+        .json_body::<serde_json::Value>(|body| {
+            let kids: Vec<_> = body["versions"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|version| version["kid"].as_str().unwrap().to_owned())
+                .collect();
+            let expected: Vec<_> = (0..=8).map(|n| format!("v{n}")).collect();
+            assert_eq!(kids, expected);
+            assert_eq!(body["current_kid"], "v8");
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn only_keys_of_auth_are_rotated() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let admin = username!("admin");
+    let password = password!("Aa!1aaaa");
+
+    let mut client = TestClient::default().await;
+
+    client.create_user(admin, "admin@test.com", password).await;
+    client.assign_permission_group(admin, "admin").await;
+
+    for key in ["dkim", "unknown", "..%2Fhmac"] {
+        client
+            .send(request!(
+                POST "/rotate-key";
+                "authorization" => basic(admin, password)
+                "content-type" => "application/x-www-form-urlencoded";
+                format!("key={key}")
+            ))
+            .await
+            .status(400)
+            .json_body::<serde_json::Value>(|body| {
+                assert_eq!(
+                    body.get("kind"),
+                    Some(&serde_json::Value::from("secret.not-rotatable"))
+                );
+            })
+            .await;
+    }
+
+    client
+        .send(request!(
+            GET "/admin/secrets?key=unknown";
+            "authorization" => basic(admin, password);
+        ))
+        .await
+        .status(404);
+
+    #[cfg(feature = "smtp")]
+    client
+        .send(request!(
+            POST "/rotate-key";
+            "authorization" => basic(admin, password)
+            "content-type" => "application/x-www-form-urlencoded";
+            "key=encryption"
+        ))
+        .await
+        .status(200);
+}
+
+#[tokio::test]
+async fn interrupted_migration_is_completed() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let admin = username!("admin");
+    let password = password!("Aa!1aaaa");
+
+    let mut client = TestClient::default().await;
+
+    client.create_user(admin, "admin@test.com", password).await;
+    client.assign_permission_group(admin, "admin").await;
+
+    // crashed after removing the plain file, before moving the staged directory into place
+    let secrets_dir = client.secrets_dir();
+    let staging = secrets_dir.join(".hmac.rotating");
+    std::fs::create_dir_all(&staging).unwrap();
+    std::fs::rename(secrets_dir.join("hmac"), staging.join("v0")).unwrap();
+
+    client
+        .send(request!(
+            GET "/admin/secrets?key=hmac";
+            "authorization" => basic(admin, password);
+        ))
+        .await
+        .status(200)
+        .json_body::<serde_json::Value>(|body| {
+            assert_eq!(body["current_kid"], "v0");
+        })
+        .await;
+
+    assert!(secrets_dir.join("hmac").join("v0").is_file());
+    assert!(!staging.exists());
+}
+
+/// Extracts the token of the first verification link,
+/// undoing the soft line breaks of a quoted-printable body.
+fn verification_token(raw: &str) -> String {
+    let body = raw.replace("=\r\n", "").replace("=3D", "=");
+    let (_, link) = body
+        .split_once("?token=")
+        .expect("no verification link in email");
+
+    link.chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        .collect()
+}
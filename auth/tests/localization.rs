@@ -0,0 +1,45 @@
+#![cfg(feature = "smtp")]
+
+mod shared;
+
+use shared::TestClient;
+use test_proc_macros::{password, username};
+
+#[tokio::test]
+async fn verification_email_follows_accept_language() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let password = password!("Aa!1aaaa");
+
+    let mut client = TestClient::default().await;
+
+    for (username, email, accept_language) in [
+        (
+            username!("user1"),
+            "user1@test.com",
+            "de-AT,de;q=0.9,en;q=0.5",
+        ),
+        (username!("user2"), "user2@test.com", "fr-CH, fr;q=0.9"),
+    ] {
+        client
+            .send(request!(
+                POST "/signup";
+                "host" => "localhost"
+                "accept-language" => accept_language
+                "content-type" => "application/x-www-form-urlencoded";
+                format!("username={}&email={}&password={}", username, email, password)
+            ))
+            .await
+            .status(201);
+    }
+
+    // `de-at` falls back to `de`
+    let email = client.wait_for_email("user1@test.com").await;
+    assert!(email.raw.contains("Hallo,"));
+    assert!(!email.raw.contains("Subject: Verify your Email"));
+
+    // there is no `fr` translation, so the default locale is used
+    let email = client.wait_for_email("user2@test.com").await;
+    assert!(email.raw.contains("Subject: Verify your Email"));
+}
@@ -0,0 +1,66 @@
+#![cfg(feature = "smtp")]
+
+mod shared;
+
+use shared::{TestClient, basic};
+use test_proc_macros::{password, username};
+
+#[tokio::test]
+async fn security_events_notify_the_user() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let admin = username!("admin");
+    let user = username!("user1");
+    let password = password!("Aa!1aaaa");
+
+    let mut client = TestClient::default().await;
+
+    client.create_user(admin, "admin@test.com", password).await;
+    client.assign_permission_group(admin, "admin").await;
+    client.create_user(user, "user1@test.com", password).await;
+    client.assign_permission_group(user, "signup").await;
+
+    client
+        .send(request!(
+            POST "/access-token/generate";
+            "authorization" => basic(user, password)
+            "content-type" => "application/x-www-form-urlencoded";
+            "name=ci-token&ttl_sec=3600"
+        ))
+        .await
+        .status(201);
+
+    let email = client.wait_for_email("user1@test.com").await;
+    assert!(
+        email
+            .raw
+            .contains("Subject: A new access token was created")
+    );
+    assert!(email.raw.contains("ci-token"));
+
+    client
+        .send(request!(
+            POST "/admin/users/disable";
+            "authorization" => basic(admin, password)
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={user}")
+        ))
+        .await
+        .status(200);
+
+    for _ in 0..200 {
+        if client
+            .wait_for_email("user1@test.com")
+            .await
+            .raw
+            .contains("Subject: Your account has been locked")
+        {
+            return;
+        }
+
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+
+    panic!("account locked notification not delivered");
+}
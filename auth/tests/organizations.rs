@@ -0,0 +1,204 @@
+mod shared;
+
+use shared::{TestClient, basic};
+use test_proc_macros::{email, password, username};
+
+#[tokio::test]
+async fn org_scoped_permissions() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let owner = username!("owner");
+    let owner_password = password!("Aa!1aaaa");
+    let member = username!("member");
+    let member_password = password!("Bb!2bbbb");
+
+    let mut client = TestClient::default().await;
+
+    for (username, email, password) in [
+        (owner, email!("owner@test.com"), owner_password),
+        (member, email!("member@test.com"), member_password),
+    ] {
+        client
+            .send(request!(
+                POST "/signup";
+                "host" => "localhost"
+                "content-type" => "application/x-www-form-urlencoded";
+                format!("username={}&email={}&password={}", username, email, password)
+            ))
+            .await
+            .status(201);
+
+        client.assign_permission_group(username, "signup").await;
+    }
+
+    client
+        .send(request!(
+            POST "/orgs";
+            "authorization" => basic(owner, owner_password)
+            "content-type" => "application/x-www-form-urlencoded";
+            "name=acme"
+        ))
+        .await
+        .status(201);
+
+    client
+        .send(request!(
+            GET "/orgs/members?organization=acme";
+            "authorization" => basic(member, member_password);
+        ))
+        .await
+        .status(403);
+
+    client
+        .send(request!(
+            POST "/orgs/members";
+            "authorization" => basic(owner, owner_password)
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("organization=acme&username={}&role=org-member", member)
+        ))
+        .await
+        .status(200);
+
+    client
+        .send(request!(
+            GET "/orgs/members?organization=acme";
+            "authorization" => basic(member, member_password);
+        ))
+        .await
+        .status(200)
+        .json_body::<serde_json::Value>(|body| {
+            assert_eq!(body.as_array().map(Vec::len), Some(2));
+        })
+        .await;
+
+    client
+        .send(request!(
+            GET "/account/export";
+            "authorization" => basic(member, member_password);
+        ))
+        .await
+        .status(200)
+        .json_body::<serde_json::Value>(|body| {
+            assert_eq!(body["organization_memberships"][0]["organization"], "acme");
+            assert_eq!(body["organization_memberships"][0]["role"], "org-member");
+        })
+        .await;
+
+    client
+        .send(request!(
+            POST "/orgs/members";
+            "authorization" => basic(member, member_password)
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("organization=acme&username={}&role=org-owner", member)
+        ))
+        .await
+        .status(403);
+
+    let personal_token = String::from_utf8(
+        axum::body::to_bytes(
+            client
+                .send(request!(
+                    POST "/access-token/generate";
+                    "authorization" => basic(owner, owner_password)
+                    "content-type" => "application/x-www-form-urlencoded";
+                    "name=personal-token&ttl_sec=3600"
+                ))
+                .await
+                .status(201)
+                .into_response()
+                .into_body(),
+            usize::MAX,
+        )
+        .await
+        .expect("unable to read response body")
+        .to_vec(),
+    )
+    .expect("access token must be utf-8");
+
+    // organization roles reach only tokens bound to the organization
+    client
+        .send(request!(
+            GET "/orgs/members?organization=acme";
+            "authorization" => format!("Token {}", personal_token);
+        ))
+        .await
+        .status(403);
+
+    let token = String::from_utf8(
+        axum::body::to_bytes(
+            client
+                .send(request!(
+                    POST "/access-token/generate";
+                    "authorization" => basic(owner, owner_password)
+                    "content-type" => "application/x-www-form-urlencoded";
+                    "name=acme-token&organization=acme&ttl_sec=3600"
+                ))
+                .await
+                .status(201)
+                .into_response()
+                .into_body(),
+            usize::MAX,
+        )
+        .await
+        .expect("unable to read response body")
+        .to_vec(),
+    )
+    .expect("access token must be utf-8");
+
+    client
+        .send(request!(
+            GET "/orgs/members?organization=acme";
+            "authorization" => format!("Token {}", token);
+        ))
+        .await
+        .status(200);
+
+    client
+        .send(request!(
+            POST "/orgs";
+            "authorization" => format!("Token {}", token)
+            "content-type" => "application/x-www-form-urlencoded";
+            "name=other"
+        ))
+        .await
+        .status(403);
+}
+
+#[tokio::test]
+async fn org_permissions_require_verified_email() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let owner = username!("owner");
+    let owner_password = password!("Aa!1aaaa");
+
+    let mut client = TestClient::with_signup_config(auth::SignupConfig {
+        verified_email_permissions: vec!["get:/orgs/members".to_string()],
+        ..Default::default()
+    })
+    .await;
+
+    client
+        .create_user(owner, "owner@test.com", owner_password)
+        .await;
+    client.assign_permission_group(owner, "signup").await;
+
+    client
+        .send(request!(
+            POST "/orgs";
+            "authorization" => basic(owner, owner_password)
+            "content-type" => "application/x-www-form-urlencoded";
+            "name=acme"
+        ))
+        .await
+        .status(201);
+
+    client
+        .send(request!(
+            GET "/orgs/members?organization=acme";
+            "authorization" => basic(owner, owner_password);
+        ))
+        .await
+        .status(403);
+}
@@ -0,0 +1,123 @@
+#![cfg(feature = "smtp")]
+
+mod shared;
+
+use shared::{TestClient, basic};
+use test_proc_macros::{password, username};
+
+#[tokio::test]
+async fn signup_queues_verification_email() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let admin = username!("admin");
+    let password = password!("Aa!1aaaa");
+
+    let mut client = TestClient::default().await;
+
+    client.create_user(admin, "admin@test.com", password).await;
+    client.assign_permission_group(admin, "admin").await;
+
+    client
+        .send(request!(
+            POST "/signup";
+            "host" => "localhost"
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}&email={}&password={}", username!("user1"), "user1@test.com", password)
+        ))
+        .await
+        .status(201);
+
+    let messages = client
+        .send(request!(
+            GET "/admin/outbox";
+            "authorization" => basic(admin, password);
+        ))
+        .await
+        .status(200)
+        .into_deserialized_json_body::<Vec<serde_json::Value>>()
+        .await;
+
+    let message = messages
+        .iter()
+        .find(|message| message["recipient"] == "user1@test.com")
+        .expect("verification email not queued");
+    assert_eq!(message["subject"], "Verify your Email");
+
+    // only dead-lettered messages can be retried
+    client
+        .send(request!(
+            POST "/admin/outbox/retry";
+            "authorization" => basic(admin, password)
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("id={}", message["id"])
+        ))
+        .await
+        .status(404);
+
+    client
+        .send(request!(
+            GET "/admin/outbox";
+            "authorization" => basic(username!("user1"), password);
+        ))
+        .await
+        .status(403);
+}
+
+#[tokio::test]
+async fn sent_emails_are_redacted_and_deleted_with_the_account() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let admin = username!("admin");
+    let user = username!("user1");
+    let password = password!("Aa!1aaaa");
+
+    let mut client = TestClient::default().await;
+
+    client.create_user(admin, "admin@test.com", password).await;
+    client.assign_permission_group(admin, "admin").await;
+
+    client
+        .send(request!(
+            POST "/signup";
+            "host" => "localhost"
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}&email={}&password={}", user, "user1@test.com", password)
+        ))
+        .await
+        .status(201);
+
+    client.assign_permission_group(user, "signup").await;
+
+    // the verification link must not outlive the delivery
+    let (plain_text, html) = client.wait_for_sent_outbox_bodies("user1@test.com").await;
+    assert!(plain_text.is_empty());
+    assert!(html.is_empty());
+
+    client
+        .send(request!(
+            DELETE "/account";
+            "authorization" => basic(user, password)
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("password={}", password)
+        ))
+        .await
+        .status(200);
+
+    client
+        .send(request!(
+            GET "/admin/outbox";
+            "authorization" => basic(admin, password);
+        ))
+        .await
+        .status(200)
+        .json_body::<Vec<serde_json::Value>>(|messages| {
+            assert!(
+                messages
+                    .iter()
+                    .all(|message| message["recipient"] != "user1@test.com")
+            );
+        })
+        .await;
+}
@@ -10,15 +10,50 @@ use tower::Service;
 
 pub mod macros;
 
+#[allow(dead_code)]
+pub fn basic(username: &str, password: &str) -> String {
+    use base64::{Engine, prelude::BASE64_STANDARD};
+    format!(
+        "Basic {}",
+        BASE64_STANDARD.encode(format!("{username}:{password}"))
+    )
+}
+
 pub struct TestClient {
     router: Router,
 
+    #[allow(dead_code)]
+    pool: Pool<Sqlite>,
+
+    #[cfg(feature = "smtp")]
+    #[allow(dead_code)]
+    mailbox: auth::InMemoryTransport,
+
     // hold TempDir because the temporary directory will be deleted on Drop
     _temp_dir: TempDir,
 }
 
 impl TestClient {
     pub async fn default() -> Self {
+        Self::with_signup_config(auth::SignupConfig::default()).await
+    }
+
+    pub async fn with_signup_config(signup: auth::SignupConfig) -> Self {
+        Self::with_config(
+            signup,
+            auth::AccountConfig {
+                deletion_grace_period: std::time::Duration::ZERO,
+            },
+        )
+        .await
+    }
+
+    #[allow(dead_code)]
+    pub async fn with_account_config(account: auth::AccountConfig) -> Self {
+        Self::with_config(auth::SignupConfig::default(), account).await
+    }
+
+    async fn with_config(signup: auth::SignupConfig, account: auth::AccountConfig) -> Self {
         let temp_dir = tempdir().expect("unable to create temp dir");
 
         let database_config = auth::DatabaseConfig {
@@ -27,10 +62,13 @@ impl TestClient {
                 path.to_string_lossy().to_string()
             },
         };
-        Self::prepare_database(&database_config).await;
+        let pool = Self::prepare_database(&database_config).await;
 
         // let secrets = Secret
 
+        #[cfg(feature = "smtp")]
+        let mailbox = auth::InMemoryTransport::default();
+
         let router = auth::router(ServerOpts {
             database: database_config,
 
@@ -39,6 +77,11 @@ impl TestClient {
                 Self::prepare_secrets(&dir);
                 dir
             },
+            secret_rotation_grace_period: std::time::Duration::from_secs(60 * 60),
+            secrets_master_key: None,
+
+            account,
+            signup,
 
             #[cfg(feature = "rate-limit")]
             rate_limiter: auth::RateLimiterConfig {
@@ -55,20 +98,38 @@ impl TestClient {
                 Self::prepare_senders(&senders_dir);
 
                 auth::SmtpConfig {
-                    relay: "127.0.0.1".into(),
-                    port: Some(1025),
-                    username: None,
-                    password: None,
+                    transport: auth::MailTransportConfig::Memory(mailbox.clone()),
                     senders_dir,
                     templates_dir: "../templates".into(),
+                    verification_redirect_url: Some("http://localhost/#/login".into()),
+                    verification_resend_interval: std::time::Duration::from_secs(60),
+                    outbox_poll_interval: std::time::Duration::from_millis(10),
+                    dkim: Some(auth::DkimConfig {
+                        selector: "test".into(),
+                        domain: "example.com".into(),
+                        key_secret: "dkim".into(),
+                        algorithm: auth::DkimAlgorithm::Ed25519,
+                        signed_headers: vec!["From".into(), "To".into(), "Subject".into()],
+                    }),
                 }
             },
+
+            #[cfg(feature = "webhooks")]
+            webhooks: auth::WebhookConfig {
+                poll_interval: std::time::Duration::from_millis(10),
+                timeout: std::time::Duration::from_secs(5),
+            },
         })
         .await
         .expect("unable to create router");
 
         Self {
             router,
+            pool,
+
+            #[cfg(feature = "smtp")]
+            mailbox,
+
             _temp_dir: temp_dir,
         }
     }
@@ -81,7 +142,131 @@ impl TestClient {
         Asserter::from(response)
     }
 
-    async fn prepare_database(config: &auth::DatabaseConfig) {
+    /// Sends the requests at once, each on its own task.
+    #[allow(dead_code)]
+    pub async fn send_concurrently(&self, requests: Vec<Request<Body>>) -> Vec<Asserter> {
+        let tasks: Vec<_> = requests
+            .into_iter()
+            .map(|request| tokio::spawn(tower::ServiceExt::oneshot(self.router.clone(), request)))
+            .collect();
+
+        let mut asserters = Vec::new();
+        for task in tasks {
+            let response = task
+                .await
+                .expect("request task panicked")
+                .unwrap(/* Infallible */);
+            asserters.push(Asserter::from(response));
+        }
+        asserters
+    }
+
+    #[allow(dead_code)]
+    pub fn secrets_dir(&self) -> std::path::PathBuf {
+        self._temp_dir.path().join("secrets")
+    }
+
+    /// Waits for the outbox worker to deliver an email to `recipient` and returns the latest one.
+    #[cfg(feature = "smtp")]
+    #[allow(dead_code)]
+    pub async fn wait_for_email(&self, recipient: &str) -> auth::SentEmail {
+        for _ in 0..200 {
+            let sent = self.mailbox.sent();
+            if let Some(email) = sent
+                .into_iter()
+                .rev()
+                .find(|email| email.to.iter().any(|to| to == recipient))
+            {
+                return email;
+            }
+
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        panic!("no email delivered to `{recipient}`");
+    }
+
+    /// Waits for the outbox worker to mark the email to `recipient` as sent
+    /// and returns the plain text and html bodies left in the outbox.
+    #[cfg(feature = "smtp")]
+    #[allow(dead_code)]
+    pub async fn wait_for_sent_outbox_bodies(&self, recipient: &str) -> (String, String) {
+        for _ in 0..200 {
+            let bodies = sqlx::query_as::<_, (String, String)>(
+                "SELECT plain_text, html FROM email_outbox WHERE recipient = ? AND status = 'sent'",
+            )
+            .bind(recipient)
+            .fetch_optional(&self.pool)
+            .await
+            .expect("unable to query outbox");
+
+            if let Some(bodies) = bodies {
+                return bodies;
+            }
+
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        panic!("no email to `{recipient}` marked as sent");
+    }
+
+    /// The jti of the outstanding verification token issued for `email`.
+    #[cfg(feature = "smtp")]
+    #[allow(dead_code)]
+    pub async fn verification_jti(&self, email: &str) -> String {
+        sqlx::query_scalar("SELECT jti FROM email_verification_tokens WHERE email = ?")
+            .bind(email)
+            .fetch_one(&self.pool)
+            .await
+            .expect("no outstanding verification token")
+    }
+
+    /// Inserts the user straight into the database, bypassing `/signup` and its policies.
+    #[allow(dead_code)]
+    pub async fn create_user(&self, username: &str, email: &str, password: &str) {
+        let password_hash = bcrypt::hash(password, 4).expect("unable to hash password");
+
+        sqlx::query(
+            r#"
+            INSERT INTO users (username, email, password_hash, created_at)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(username)
+        .bind(email)
+        .bind(password_hash)
+        .bind(time::OffsetDateTime::now_utc())
+        .execute(&self.pool)
+        .await
+        .expect("unable to create user");
+    }
+
+    /// Seeds the permissions and permission groups and assigns `group` to the user.
+    #[allow(dead_code)]
+    pub async fn assign_permission_group(&self, username: &str, group: &str) {
+        sqlx::raw_sql(include_str!("../../migrations/seed/permissions.sql"))
+            .execute(&self.pool)
+            .await
+            .expect("unable to seed permissions");
+
+        sqlx::query(
+            r#"
+            INSERT INTO user_permissions (user_id, permission_id)
+            SELECT u.id, pga.permission_id FROM users u
+            CROSS JOIN permission_groups pg
+            INNER JOIN permission_group_association pga ON pga.permission_group_id = pg.id
+            WHERE u.username = ? AND pg.[group] = ?
+            ON CONFLICT (user_id, permission_id) DO NOTHING
+            "#,
+        )
+        .bind(username)
+        .bind(group)
+        .execute(&self.pool)
+        .await
+        .expect("unable to assign permission group");
+    }
+
+    async fn prepare_database(config: &auth::DatabaseConfig) -> Pool<Sqlite> {
         let pool = Pool::<Sqlite>::connect_with(
             SqliteConnectOptions::new()
                 .filename(&config.url)
@@ -93,11 +278,20 @@ impl TestClient {
             .run(&pool)
             .await
             .expect("unable to run migrations");
+        pool
     }
 
     fn prepare_secrets(dir: &std::path::Path) {
         std::fs::create_dir_all(dir).expect("unable to create secrets dir");
         std::fs::write(dir.join("hmac"), vec![0; 1]).expect("unable to create hmac secret");
+
+        // base64 encoded Ed25519 seed
+        #[cfg(feature = "smtp")]
+        std::fs::write(
+            dir.join("dkim"),
+            "nWGxne/9WmC6hEr0kuwsxERJxWl7MmkZcDusAxyuf2A=",
+        )
+        .expect("unable to create dkim secret");
     }
 
     #[cfg(feature = "smtp")]
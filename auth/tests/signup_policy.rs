@@ -0,0 +1,139 @@
+mod shared;
+
+use shared::{TestClient, basic};
+use test_proc_macros::{password, username};
+
+#[tokio::test]
+async fn email_domain_policy() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let password = password!("Aa!1aaaa");
+
+    let mut client = TestClient::with_signup_config(auth::SignupConfig {
+        allowed_domains: vec!["example.com".into(), "mailinator.com".into()],
+        blocked_domains: vec!["blocked.example.com".into()],
+        block_disposable_domains: true,
+        ..Default::default()
+    })
+    .await;
+
+    for (username, email, kind) in [
+        (
+            username!("user1"),
+            "user1@other.com",
+            "signup.email.domain.not-allowed",
+        ),
+        (
+            username!("user2"),
+            "user2@blocked.example.com",
+            "signup.email.domain.blocked",
+        ),
+        (
+            username!("user3"),
+            "user3@mailinator.com",
+            "signup.email.disposable",
+        ),
+    ] {
+        client
+            .send(request!(
+                POST "/signup";
+                "host" => "localhost"
+                "content-type" => "application/x-www-form-urlencoded";
+                format!("username={}&email={}&password={}", username, email, password)
+            ))
+            .await
+            .status(403)
+            .json_body::<serde_json::Value>(|body| {
+                assert_eq!(body.get("kind"), Some(&serde_json::Value::from(kind)));
+            })
+            .await;
+    }
+
+    client
+        .send(request!(
+            POST "/signup";
+            "host" => "localhost"
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}&email={}&password={}", username!("user4"), "user4@eu.example.com", password)
+        ))
+        .await
+        .status(201);
+}
+
+#[tokio::test]
+async fn login_requires_verified_email() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let username = username!("user1");
+    let password = password!("Aa!1aaaa");
+
+    let mut client = TestClient::with_signup_config(auth::SignupConfig {
+        require_verified_email: true,
+        ..Default::default()
+    })
+    .await;
+
+    client
+        .send(request!(
+            POST "/signup";
+            "host" => "localhost"
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}&email={}&password={}", username, "user1@test.com", password)
+        ))
+        .await
+        .status(201);
+
+    client
+        .send(request!(
+            POST "/login";
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}&password={}", username, password)
+        ))
+        .await
+        .status(403)
+        .json_body::<serde_json::Value>(|body| {
+            assert_eq!(
+                body.get("kind"),
+                Some(&serde_json::Value::from("auth.email.unverified"))
+            );
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn verified_email_permissions() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let username = username!("admin");
+    let password = password!("Aa!1aaaa");
+
+    let mut client = TestClient::with_signup_config(auth::SignupConfig {
+        verified_email_permissions: vec!["get:/sysinfo".to_string()],
+        ..Default::default()
+    })
+    .await;
+
+    client
+        .create_user(username, "admin@test.com", password)
+        .await;
+    client.assign_permission_group(username, "admin").await;
+
+    client
+        .send(request!(
+            GET "/sysinfo";
+            "authorization" => basic(username, password);
+        ))
+        .await
+        .status(403);
+
+    client
+        .send(request!(
+            GET "/permissions";
+            "authorization" => basic(username, password);
+        ))
+        .await
+        .status(200);
+}
@@ -0,0 +1,272 @@
+#![cfg(feature = "smtp")]
+
+mod shared;
+
+use shared::{TestClient, basic};
+use test_proc_macros::{password, username};
+
+#[tokio::test]
+async fn invalid_token_page() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let mut client = TestClient::default().await;
+
+    let response = client
+        .send(request!(
+            GET "/verify-email?token=a.b.c";
+            "accept" => "text/html";
+        ))
+        .await
+        .status(400)
+        .into_response();
+
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .and_then(|val| val.to_str().ok())
+        .unwrap_or_default();
+    assert!(content_type.starts_with("text/html"));
+
+    client
+        .send(request!(
+            GET "/verify-email?token=a.b.c";
+            "accept" => "application/json";
+        ))
+        .await
+        .status(400)
+        .json_body::<serde_json::Value>(|body| {
+            assert_eq!(
+                body.get("kind"),
+                Some(&serde_json::Value::from("token.invalid"))
+            );
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn resend_throttling() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let mut client = TestClient::default().await;
+
+    client
+        .create_user(username!("user1"), "user1@test.com", password!("Aa!1aaaa"))
+        .await;
+
+    // the first request issues a token, regardless of whether the email could be delivered
+    client
+        .send(request!(
+            POST "/initiate-email-verification";
+            "host" => "localhost"
+            "content-type" => "application/x-www-form-urlencoded";
+            "email=user1@test.com"
+        ))
+        .await;
+
+    let response = client
+        .send(request!(
+            POST "/initiate-email-verification";
+            "host" => "localhost"
+            "content-type" => "application/x-www-form-urlencoded";
+            "email=user1@test.com"
+        ))
+        .await
+        .status(429)
+        .into_response();
+
+    let retry_after = response
+        .headers()
+        .get("retry-after")
+        .and_then(|val| val.to_str().ok())
+        .and_then(|val| val.parse::<u64>().ok())
+        .expect("missing Retry-After header");
+    assert!(retry_after > 0 && retry_after <= 60);
+}
+
+#[tokio::test]
+async fn verification_link_is_single_use() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let username = username!("user1");
+    let password = password!("Aa!1aaaa");
+
+    let mut client = TestClient::default().await;
+
+    client
+        .send(request!(
+            POST "/signup";
+            "host" => "localhost"
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}&email={}&password={}", username, "user1@test.com", password)
+        ))
+        .await
+        .status(201);
+
+    client.assign_permission_group(username, "signup").await;
+
+    let email = client.wait_for_email("user1@test.com").await;
+    assert!(email.raw.contains("Subject: Verify your Email"));
+
+    let token = verification_token(&email.raw);
+
+    for (expected_status, outstanding_tokens) in [(200, 1), (410, 0)] {
+        client
+            .send(request!(
+                GET "/account/export";
+                "authorization" => basic(username, password);
+            ))
+            .await
+            .status(200)
+            .json_body::<serde_json::Value>(|body| {
+                assert_eq!(
+                    body["email_verification_tokens"].as_array().map(Vec::len),
+                    Some(outstanding_tokens)
+                );
+            })
+            .await;
+
+        client
+            .send(request!(
+                GET format!("/verify-email?token={token}");
+                "accept" => "application/json";
+            ))
+            .await
+            .status(expected_status);
+    }
+}
+
+#[tokio::test]
+async fn verification_token_verifies_with_published_key() {
+    use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let mut client = TestClient::default().await;
+
+    client
+        .send(request!(
+            POST "/signup";
+            "host" => "localhost"
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}&email={}&password={}", username!("user1"), "user1@test.com", password!("Aa!1aaaa"))
+        ))
+        .await
+        .status(201);
+
+    let token = verification_token(&client.wait_for_email("user1@test.com").await.raw);
+
+    let mut keys = Vec::new();
+    client
+        .send(request!(GET "/.well-known/jwks.json";;))
+        .await
+        .status(200)
+        .json_body::<serde_json::Value>(|body| {
+            keys = body["keys"].as_array().cloned().unwrap_or_default();
+        })
+        .await;
+
+    // a service holding nothing but the published key, e.g. the gateway
+    let [key] = keys.as_slice() else {
+        panic!("expected a single key, got {keys:?}");
+    };
+    assert_eq!(key["kty"], "OKP");
+    assert_eq!(key["alg"], "EdDSA");
+    let public_key = BASE64_URL_SAFE_NO_PAD
+        .decode(key["x"].as_str().unwrap())
+        .unwrap();
+
+    let signed = signature::Signed::<serde_json::Value>::decode_jws(
+        &token,
+        &signature::VerifyingKey::ed25519(public_key),
+    )
+    .expect("verification token does not verify with the published key");
+    assert_eq!(signed.kid(), key["kid"].as_str());
+    assert_eq!(
+        signed
+            .token(&signature::Validation::audience("email-verification"))
+            .unwrap()["email"],
+        "user1@test.com"
+    );
+}
+
+#[tokio::test]
+async fn links_sent_before_the_cutover_still_verify() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let mut client = TestClient::default().await;
+
+    for n in 1..=3 {
+        client
+            .send(request!(
+                POST "/signup";
+                "host" => "localhost"
+                "content-type" => "application/x-www-form-urlencoded";
+                format!("username=user{n}&email=user{n}@test.com&password={}", password!("Aa!1aaaa"))
+            ))
+            .await
+            .status(201);
+    }
+
+    // signed with the HMAC secret and without an audience, as links were until then
+    let hmac = signature::SigningKey::hmac(vec![0; 1]);
+    let ttl = std::time::Duration::from_secs(60 * 60);
+
+    let jti = client.verification_jti("user1@test.com").await;
+    let jws = signature::Signed::new(serde_json::json!({ "email": "user1@test.com" }))
+        .with_ttl(ttl)
+        .with_jti(jti)
+        .encode_jws(&hmac)
+        .unwrap();
+
+    let jti = client.verification_jti("user2@test.com").await;
+    let original = signature::Signed::new(b"user2@test.com".to_vec())
+        .with_ttl(ttl)
+        .with_jti(jti)
+        .encode_with(&hmac)
+        .unwrap();
+
+    // issued before verification tokens were single use
+    let without_jti = signature::Signed::new(b"user3@test.com".to_vec())
+        .with_ttl(ttl)
+        .encode_with(&hmac)
+        .unwrap();
+
+    for token in [&jws, &original, &without_jti] {
+        client
+            .send(request!(
+                GET format!("/verify-email?token={token}");
+                "accept" => "application/json";
+            ))
+            .await
+            .status(200);
+    }
+
+    // still single use, as long as they carry a jti
+    for token in [&jws, &original] {
+        client
+            .send(request!(
+                GET format!("/verify-email?token={token}");
+                "accept" => "application/json";
+            ))
+            .await
+            .status(410);
+    }
+}
+
+/// Extracts the token of the first verification link,
+/// undoing the soft line breaks of a quoted-printable body.
+fn verification_token(raw: &str) -> String {
+    let body = raw.replace("=\r\n", "").replace("=3D", "=");
+    let (_, link) = body
+        .split_once("?token=")
+        .expect("no verification link in email");
+
+    link.chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        .collect()
+}
@@ -0,0 +1,142 @@
+#![cfg(feature = "webhooks")]
+
+mod shared;
+
+use std::sync::{Arc, Mutex};
+
+use axum::{extract::State, http::HeaderMap, routing::post};
+use shared::{TestClient, basic};
+use test_proc_macros::{password, username};
+
+type Received = Arc<Mutex<Vec<(HeaderMap, String)>>>;
+
+/// Spawns a local receiver recording every request and returns its URL.
+async fn spawn_receiver(received: Received) -> String {
+    async fn receive(State(received): State<Received>, headers: HeaderMap, body: String) {
+        received.lock().unwrap().push((headers, body));
+    }
+
+    let router = axum::Router::new()
+        .route("/hook", post(receive))
+        .with_state(received);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await });
+
+    format!("http://{addr}/hook")
+}
+
+#[tokio::test]
+async fn subscribed_events_are_delivered_signed() {
+    #[cfg(feature = "tracing")]
+    shared::tracing_init();
+
+    let admin = username!("admin");
+    let user = username!("user1");
+    let password = password!("Aa!1aaaa");
+
+    let mut client = TestClient::default().await;
+
+    client.create_user(admin, "admin@test.com", password).await;
+    client.assign_permission_group(admin, "admin").await;
+
+    let received = Received::default();
+    let url = spawn_receiver(received.clone()).await;
+
+    let webhook = client
+        .send(request!(
+            POST "/admin/webhooks";
+            "authorization" => basic(admin, password)
+            "content-type" => "application/json";
+            serde_json::json!({ "url": url, "events": ["user.created"] }).to_string()
+        ))
+        .await
+        .status(201)
+        .into_deserialized_json_body::<serde_json::Value>()
+        .await;
+    let secret = webhook["secret"].as_str().unwrap().to_string();
+
+    client
+        .send(request!(
+            POST "/admin/webhooks";
+            "authorization" => basic(admin, password)
+            "content-type" => "application/json";
+            serde_json::json!({ "url": url, "events": ["user.deleted"] }).to_string()
+        ))
+        .await
+        .status(400);
+
+    // not subscribed to `token.created`
+    client
+        .send(request!(
+            POST "/access-token/generate";
+            "authorization" => basic(admin, password)
+            "content-type" => "application/x-www-form-urlencoded";
+            "name=ci-token&ttl_sec=3600"
+        ))
+        .await
+        .status(201);
+
+    client
+        .send(request!(
+            POST "/signup";
+            "host" => "localhost"
+            "content-type" => "application/x-www-form-urlencoded";
+            format!("username={}&email={}&password={}", user, "user1@test.com", password)
+        ))
+        .await
+        .status(201);
+
+    let (headers, body) = {
+        let mut delivery = None;
+        for _ in 0..200 {
+            if let Some(received) = received.lock().unwrap().first() {
+                delivery = Some(received.clone());
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        delivery.expect("webhook not delivered")
+    };
+
+    let payload = serde_json::from_str::<serde_json::Value>(&body).unwrap();
+    assert_eq!(payload["event"], "user.created");
+    assert_eq!(payload["data"]["username"], user);
+    assert_eq!(headers["webhook-event"], "user.created");
+
+    let signature_header = headers["webhook-signature"].to_str().unwrap();
+    let (timestamp, signature) = signature_header
+        .strip_prefix("t=")
+        .and_then(|rest| rest.split_once(",v1="))
+        .expect("malformed signature header");
+    let expected =
+        signature::hmac_sha256(secret.as_bytes(), format!("{timestamp}.{body}").as_bytes())
+            .unwrap();
+    assert_eq!(signature, expected);
+
+    // the delivery is marked as delivered once the receiver's response is in
+    for _ in 0..200 {
+        let deliveries = client
+            .send(request!(
+                GET "/admin/webhooks/deliveries";
+                "authorization" => basic(admin, password);
+            ))
+            .await
+            .status(200)
+            .into_deserialized_json_body::<Vec<serde_json::Value>>()
+            .await;
+
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0]["event"], "user.created");
+
+        if deliveries[0]["status"] == "delivered" {
+            assert_eq!(deliveries[0]["response_status"], 200);
+            return;
+        }
+
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+
+    panic!("delivery not marked as delivered");
+}
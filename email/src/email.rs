@@ -21,6 +21,12 @@ impl TryFrom<String> for Email {
     }
 }
 
+impl Email {
+    pub fn domain(&self) -> &str {
+        self.0.domain()
+    }
+}
+
 impl From<Email> for lettre::Address {
     fn from(email: Email) -> Self {
         email.0
@@ -71,6 +77,16 @@ impl<'de> serde::Deserialize<'de> for Email {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Email {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(&self.0)
+    }
+}
+
 #[cfg(feature = "sqlx")]
 impl Email {
     pub fn try_from_sqlx(value: String) -> Result<Self, sqlx::Error> {
@@ -0,0 +1,390 @@
+//! File based secrets.
+//!
+//! A secret is either a plain file `<dir>/<key>` or, once it has been rotated, a directory
+//! `<dir>/<key>/` holding one file per version (`v1`, `v2`, ...). The version's name is its key id
+//! (`kid`). Rotating retires the current version by writing a `<kid>.retired` marker (unix
+//! timestamp) next to it; retired versions keep verifying for the grace period and are purged
+//! afterwards. A plain file is migrated to version `v0` on its first rotation, which is also the
+//! version used for anything signed without a `kid`. The migration stages the directory as
+//! `.<key>.rotating` and only removes the plain file once `v0` is on disk; a staged directory
+//! left behind by a crash is moved into place on the next access.
+//!
+//! With a master key, key material is sealed at rest, see [`sealing`].
+
+pub mod sealing;
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+use rand::RngCore;
+use time::OffsetDateTime;
+use zeroize::Zeroizing;
+
+use self::sealing::MasterKey;
+
+#[derive(Debug, Clone)]
+pub struct Secrets {
+    dir: PathBuf,
+    grace_period: Duration,
+    master_key: Option<Arc<MasterKey>>,
+    /// serializes rotations, which read and then extend the version list
+    rotation: Arc<Mutex<()>>,
+}
+
+/// Key material along with the id of its version (`None` for never rotated secrets).
+pub struct SecretVersion {
+    pub kid: Option<String>,
+    pub material: Zeroizing<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct VersionStatus {
+    pub kid: String,
+    pub created_at: Option<OffsetDateTime>,
+    /// `None` for the current version
+    pub retired_at: Option<OffsetDateTime>,
+    /// end of the grace period of a retired version
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+impl Secrets {
+    const DEFAULT_N_BYTES: usize = 32;
+
+    /// kid of a migrated plain file secret
+    const LEGACY_KID: &str = "v0";
+
+    pub fn new(dir: PathBuf, grace_period: Duration) -> Self {
+        Self {
+            dir,
+            grace_period,
+            master_key: None,
+            rotation: Arc::default(),
+        }
+    }
+
+    /// Seals newly written key material and opens sealed files.
+    /// Plain files stay readable until the directory is sealed, see [`sealing::seal_dir`].
+    pub fn with_master_key(mut self, master_key: impl Into<Arc<MasterKey>>) -> Self {
+        self.master_key = Some(master_key.into());
+        self
+    }
+
+    pub fn grace_period(&self) -> Duration {
+        self.grace_period
+    }
+
+    /// Returns the material of the current version.
+    pub fn get(&self, key: &str) -> Result<Zeroizing<Vec<u8>>, io::Error> {
+        self.current(key).map(|version| version.material)
+    }
+
+    /// Returns the current version, which is the one to sign with.
+    pub fn current(&self, key: &str) -> Result<SecretVersion, io::Error> {
+        let path = self.path(key)?;
+        if !path.is_dir() {
+            return Ok(SecretVersion {
+                kid: None,
+                material: self.read(&path)?,
+            });
+        }
+
+        let kid = self
+            .versions(key)?
+            .into_iter()
+            .filter(|version| version.retired_at.is_none())
+            .map(|version| version.kid)
+            .next_back()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no current `{key}` version"),
+                )
+            })?;
+
+        Ok(SecretVersion {
+            material: self.read(&path.join(&kid))?,
+            kid: Some(kid),
+        })
+    }
+
+    /// Returns the material of version `kid`, as long as it is current or within its grace period.
+    pub fn get_version(
+        &self,
+        key: &str,
+        kid: Option<&str>,
+    ) -> Result<Option<Zeroizing<Vec<u8>>>, io::Error> {
+        let path = self.path(key)?;
+        if !path.is_dir() {
+            return match kid {
+                None => self.read(&path).map(Some),
+                Some(_) => Ok(None),
+            };
+        }
+
+        let kid = kid.unwrap_or(Self::LEGACY_KID);
+        let now = OffsetDateTime::now_utc();
+
+        let Some(version) = self
+            .versions(key)?
+            .into_iter()
+            .find(|version| version.kid == kid)
+        else {
+            return Ok(None);
+        };
+
+        if version
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= now)
+        {
+            return Ok(None);
+        }
+
+        self.read(&path.join(kid)).map(Some)
+    }
+
+    /// Creates a new current version, retires the previous one and purges versions
+    /// whose grace period is over. Returns the new kid.
+    pub fn rotate(&self, key: &str) -> Result<String, io::Error> {
+        let buf = {
+            let mut rng = rand::rng();
+            let mut buf = vec![0u8; Secrets::DEFAULT_N_BYTES];
+            rng.fill_bytes(&mut buf);
+            Zeroizing::new(buf)
+        };
+        self.rotate_with(key, &buf)
+    }
+
+    /// Rotates like [`Secrets::rotate`], with `material` as the new version instead of random
+    /// bytes, e.g. for keys that must be in a specific format. Creates the secret if it is missing.
+    pub fn rotate_with(&self, key: &str, material: &[u8]) -> Result<String, io::Error> {
+        let _rotation = self.rotation.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let path = self.path(key)?;
+        let now = OffsetDateTime::now_utc();
+
+        if path.is_file() {
+            self.migrate(key)?;
+        }
+        fs::create_dir_all(&path)?;
+
+        let versions = self.versions(key)?;
+        let kid = format!(
+            "v{}",
+            versions
+                .iter()
+                .filter_map(|version| version_number(&version.kid))
+                .max()
+                .map_or(1, |n| n + 1)
+        );
+
+        self.write(&path.join(&kid), material)?;
+
+        for version in versions {
+            match version.expires_at {
+                Some(expires_at) if expires_at <= now => {
+                    fs::remove_file(path.join(&version.kid))?;
+                    fs::remove_file(path.join(format!("{}.retired", version.kid)))?;
+                }
+                Some(_) => {}
+                None => fs::write(
+                    path.join(format!("{}.retired", version.kid)),
+                    now.unix_timestamp().to_string(),
+                )?,
+            }
+        }
+
+        Ok(kid)
+    }
+
+    /// Writes version `kid` without retiring any other, for keys whose versions are managed by
+    /// the caller. An existing version of the same kid is replaced.
+    pub fn put_version(&self, key: &str, kid: &str, material: &[u8]) -> Result<(), io::Error> {
+        if version_number(kid).is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid kid `{kid}`"),
+            ));
+        }
+
+        let _rotation = self.rotation.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let path = self.path(key)?;
+        if path.is_file() {
+            self.migrate(key)?;
+        }
+        fs::create_dir_all(&path)?;
+
+        self.write(&path.join(kid), material)
+    }
+
+    /// Lists the versions of the secret, oldest first.
+    /// A never rotated secret has no versions.
+    pub fn versions(&self, key: &str) -> Result<Vec<VersionStatus>, io::Error> {
+        let path = self.path(key)?;
+        if !path.is_dir() {
+            return match path.is_file() {
+                true => Ok(Vec::new()),
+                false => Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("secret `{key}`"),
+                )),
+            };
+        }
+
+        let mut versions = Vec::new();
+        for entry in fs::read_dir(&path)? {
+            let entry = entry?;
+            let kid = entry.file_name().to_string_lossy().into_owned();
+            if version_number(&kid).is_none() {
+                continue;
+            }
+
+            let created_at = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .map(OffsetDateTime::from);
+
+            let retired_at = match fs::read_to_string(path.join(format!("{kid}.retired"))) {
+                Ok(timestamp) => timestamp
+                    .trim()
+                    .parse::<i64>()
+                    .ok()
+                    .and_then(|timestamp| OffsetDateTime::from_unix_timestamp(timestamp).ok()),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+                Err(err) => return Err(err),
+            };
+
+            versions.push(VersionStatus {
+                kid,
+                created_at,
+                retired_at,
+                expires_at: retired_at.map(|retired_at| retired_at + self.grace_period),
+            });
+        }
+
+        versions.sort_by_key(|version| version_number(&version.kid));
+        Ok(versions)
+    }
+}
+
+impl Secrets {
+    /// Path of the secret, finishing a migration interrupted by a crash.
+    fn path(&self, key: &str) -> Result<PathBuf, io::Error> {
+        validate_key(key)?;
+
+        let path = self.dir.join(key);
+        let staging = self.staging(key);
+        if !path.exists() && staging.is_dir() {
+            match fs::rename(&staging, &path) {
+                Ok(()) => sync_dir(&self.dir)?,
+                // finished concurrently
+                Err(_) if path.is_dir() => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(path)
+    }
+
+    fn staging(&self, key: &str) -> PathBuf {
+        self.dir.join(format!(".{key}.rotating"))
+    }
+
+    /// Moves a plain file secret to version `v0` without ever leaving it without key material.
+    fn migrate(&self, key: &str) -> Result<(), io::Error> {
+        let path = self.dir.join(key);
+        let staging = self.staging(key);
+        let material = self.read(&path)?;
+
+        fs::create_dir_all(&staging)?;
+        write_synced(
+            &staging.join(Self::LEGACY_KID),
+            &self.encode(&path.join(Self::LEGACY_KID), &material),
+        )?;
+        sync_dir(&staging)?;
+
+        fs::remove_file(&path)?;
+        fs::rename(&staging, &path)?;
+        sync_dir(&self.dir)
+    }
+
+    fn read(&self, path: &Path) -> Result<Zeroizing<Vec<u8>>, io::Error> {
+        let bytes = Zeroizing::new(fs::read(path)?);
+
+        match (&self.master_key, sealing::is_sealed(&bytes)) {
+            (Some(master_key), true) => {
+                let aad = sealing::aad(&self.dir, path);
+                Ok(sealing::open(master_key, &aad, &bytes)?)
+            }
+            (None, true) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is sealed, but no master key is set", path.display()),
+            )),
+            (_, false) => Ok(bytes),
+        }
+    }
+
+    fn write(&self, path: &Path, material: &[u8]) -> Result<(), io::Error> {
+        replace(path, &self.encode(path, material))
+    }
+
+    /// Seals `material` for `path` if there is a master key.
+    fn encode(&self, path: &Path, material: &[u8]) -> Zeroizing<Vec<u8>> {
+        match &self.master_key {
+            Some(master_key) => {
+                let aad = sealing::aad(&self.dir, path);
+                Zeroizing::new(sealing::seal(master_key, &aad, material))
+            }
+            None => Zeroizing::new(material.to_vec()),
+        }
+    }
+}
+
+/// Writes next to the file first, so a crash never leaves a half written secret behind.
+/// The temporary file is hidden, like everything that is not a secret.
+fn replace(path: &Path, bytes: &[u8]) -> Result<(), io::Error> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = path.with_file_name(format!(".{name}.tmp"));
+
+    write_synced(&tmp, bytes)?;
+    fs::rename(tmp, path)?;
+    sync_dir(path.parent().unwrap_or(Path::new(".")))
+}
+
+/// Writes the file and flushes it to disk.
+fn write_synced(path: &Path, bytes: &[u8]) -> Result<(), io::Error> {
+    let mut file = fs::File::create(path)?;
+    file.write_all(bytes)?;
+    file.sync_all()
+}
+
+/// Flushes renames and removals in the directory to disk.
+fn sync_dir(dir: &Path) -> Result<(), io::Error> {
+    fs::File::open(dir)?.sync_all()
+}
+
+fn version_number(kid: &str) -> Option<u64> {
+    kid.strip_prefix('v')?.parse().ok()
+}
+
+/// Secret names end up in file paths.
+fn validate_key(key: &str) -> Result<(), io::Error> {
+    let valid = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    match valid {
+        true => Ok(()),
+        false => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid secret name `{key}`"),
+        )),
+    }
+}
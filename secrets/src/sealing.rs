@@ -0,0 +1,348 @@
+//! Envelope encryption of secret files.
+//!
+//! Every sealed file gets its own random data key, which encrypts the secret with
+//! XChaCha20-Poly1305 and is itself encrypted (wrapped) under the master key:
+//!
+//! `MAGIC | wrap nonce (24) | wrapped data key (48) | data nonce (24) | ciphertext`
+//!
+//! The file's path relative to the secrets directory is authenticated along with the secret,
+//! so sealed files cannot be swapped for one another. Re-keying only re-wraps the data keys.
+//! [`seal`] and [`open`] take any such context, e.g. the vault seals its database rows with them.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use chacha20poly1305::{
+    Key, KeyInit, XChaCha20Poly1305, XNonce,
+    aead::{Aead, Payload},
+};
+use rand::RngCore;
+use zeroize::Zeroizing;
+
+use super::replace;
+
+const MAGIC: &[u8] = b"auth-sealed-v1\n";
+const NONCE_LEN: usize = 24;
+/// Length of the master key and of every key used with [`encrypt`].
+pub const KEY_LEN: usize = 32;
+const TAG_LEN: usize = 16;
+const HEADER_LEN: usize = MAGIC.len() + NONCE_LEN + KEY_LEN + TAG_LEN + NONCE_LEN;
+
+/// Argon2id salt of passphrase derived master keys; not a secret itself.
+pub const SALT_FILE: &str = ".master-key-salt";
+const SALT_LEN: usize = 16;
+
+pub struct MasterKey(Zeroizing<[u8; KEY_LEN]>);
+
+impl std::fmt::Debug for MasterKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MasterKey(..)")
+    }
+}
+
+/// Where the master key comes from at startup.
+#[derive(Clone)]
+pub enum MasterKeySource {
+    /// base64 encoded 32 byte key
+    Base64(Zeroizing<String>),
+    /// file descriptor to read the base64 encoded key from, e.g. `3` for `3< master.key`
+    Fd(i32),
+    /// passphrase the key is derived from with Argon2id
+    Passphrase(Zeroizing<String>),
+}
+
+impl std::fmt::Debug for MasterKeySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MasterKeySource::Base64(_) => f.write_str("Base64(..)"),
+            MasterKeySource::Fd(fd) => f.debug_tuple("Fd").field(fd).finish(),
+            MasterKeySource::Passphrase(_) => f.write_str("Passphrase(..)"),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum MasterKeyError {
+    #[error("{0}")]
+    Io(#[from] contextual::Error<io::Error>),
+
+    #[error("master key is not valid base64")]
+    Base64(#[from] base64::DecodeError),
+
+    #[error("master key must be {KEY_LEN} bytes, got {0}")]
+    Length(usize),
+
+    #[error("master key derivation: {0}")]
+    Kdf(argon2::Error),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SealError {
+    #[error("not a sealed secret")]
+    NotSealed,
+
+    #[error("sealed secret is truncated")]
+    Truncated,
+
+    /// wrong master key, or the file was tampered with or moved
+    #[error("unable to decrypt sealed secret")]
+    Decrypt,
+}
+
+impl From<SealError> for io::Error {
+    fn from(err: SealError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+impl MasterKey {
+    pub fn load(source: &MasterKeySource, secrets_dir: &Path) -> Result<Self, MasterKeyError> {
+        use contextual::Context;
+
+        match source {
+            MasterKeySource::Base64(encoded) => Self::from_base64(encoded),
+            MasterKeySource::Fd(fd) => {
+                let path = format!("/dev/fd/{fd}");
+                let encoded = Zeroizing::new(
+                    fs::read_to_string(&path).context(format!("read master key from {path}"))?,
+                );
+                Self::from_base64(&encoded)
+            }
+            MasterKeySource::Passphrase(passphrase) => {
+                let salt = salt(secrets_dir).context("master key salt")?;
+                let mut key = Zeroizing::new([0u8; KEY_LEN]);
+                argon2::Argon2::default()
+                    .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut_slice())
+                    .map_err(MasterKeyError::Kdf)?;
+                Ok(Self(key))
+            }
+        }
+    }
+
+    /// Decodes a base64 encoded 32 byte key, e.g. from `openssl rand -base64 32`.
+    pub fn from_base64(encoded: &str) -> Result<Self, MasterKeyError> {
+        let bytes = Zeroizing::new(BASE64_STANDARD.decode(encoded.trim())?);
+        let key: [u8; KEY_LEN] = bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| MasterKeyError::Length(bytes.len()))?;
+        Ok(Self(Zeroizing::new(key)))
+    }
+}
+
+/// Reads the salt of the secrets directory, creating it on first use.
+fn salt(secrets_dir: &Path) -> Result<Vec<u8>, io::Error> {
+    let path = secrets_dir.join(SALT_FILE);
+    match fs::read(&path) {
+        Ok(salt) => Ok(salt),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            let mut salt = vec![0u8; SALT_LEN];
+            rand::rng().fill_bytes(&mut salt);
+            fs::create_dir_all(secrets_dir)?;
+            fs::write(&path, &salt)?;
+            Ok(salt)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+pub fn is_sealed(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Encrypts `plaintext` under a fresh data key wrapped by `master_key`.
+/// `aad` binds the result to its location, see [`open`].
+pub fn seal(master_key: &MasterKey, aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    let data_key = {
+        let mut key = Zeroizing::new([0u8; KEY_LEN]);
+        rand::rng().fill_bytes(key.as_mut_slice());
+        key
+    };
+
+    let wrapped_key = encrypt(master_key.0.as_slice(), &[], data_key.as_slice());
+    let ciphertext = encrypt(data_key.as_slice(), aad, plaintext);
+
+    [MAGIC, &wrapped_key, &ciphertext].concat()
+}
+
+/// Decrypts a file produced by [`seal`] with the same `aad`.
+pub fn open(
+    master_key: &MasterKey,
+    aad: &[u8],
+    sealed: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, SealError> {
+    let (wrapped_key, ciphertext) = split(sealed)?;
+    let data_key = decrypt(master_key.0.as_slice(), &[], wrapped_key)?;
+
+    decrypt(&data_key, aad, ciphertext)
+}
+
+/// Re-wraps the data key under `new_master_key`, leaving the ciphertext untouched.
+pub fn rewrap(
+    old_master_key: &MasterKey,
+    new_master_key: &MasterKey,
+    sealed: &[u8],
+) -> Result<Vec<u8>, SealError> {
+    let (wrapped_key, ciphertext) = split(sealed)?;
+    let data_key = decrypt(old_master_key.0.as_slice(), &[], wrapped_key)?;
+
+    let wrapped_key = encrypt(new_master_key.0.as_slice(), &[], &data_key);
+
+    Ok([MAGIC, &wrapped_key, ciphertext].concat())
+}
+
+/// Encrypts under a 32 byte key with a random nonce: `nonce (24) | ciphertext`.
+/// The building block of [`seal`], usable with keys of its own, e.g. the vault's transit keys.
+pub fn encrypt(key: &[u8], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::rng().fill_bytes(&mut nonce);
+
+    let ciphertext = XChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .expect("encrypting with a 32 byte key cannot fail");
+
+    [nonce.as_slice(), &ciphertext].concat()
+}
+
+/// Decrypts the output of [`encrypt`] with the same key and `aad`.
+pub fn decrypt(key: &[u8], aad: &[u8], encrypted: &[u8]) -> Result<Zeroizing<Vec<u8>>, SealError> {
+    if encrypted.len() < NONCE_LEN {
+        return Err(SealError::Truncated);
+    }
+    let (nonce, ciphertext) = encrypted.split_at(NONCE_LEN);
+
+    XChaCha20Poly1305::new_from_slice(key)
+        .map_err(|_| SealError::Decrypt)?
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| SealError::Decrypt)
+}
+
+/// Splits a sealed file into the wrapped data key and the ciphertext, both with their nonce.
+fn split(sealed: &[u8]) -> Result<(&[u8], &[u8]), SealError> {
+    let rest = sealed.strip_prefix(MAGIC).ok_or(SealError::NotSealed)?;
+    if sealed.len() < HEADER_LEN {
+        return Err(SealError::Truncated);
+    }
+
+    Ok(rest.split_at(NONCE_LEN + KEY_LEN + TAG_LEN))
+}
+
+/// The additional authenticated data of a secret file: its path relative to the secrets directory.
+pub(super) fn aad(secrets_dir: &Path, path: &Path) -> Vec<u8> {
+    path.strip_prefix(secrets_dir)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+        .into_bytes()
+}
+
+/// Counts of the files touched by [`seal_dir`], [`unseal_dir`] and [`rekey_dir`].
+#[derive(Debug, Default)]
+pub struct DirReport {
+    pub changed: usize,
+    pub skipped: usize,
+}
+
+/// Encrypts every plain secret file in the directory. Already sealed files are skipped.
+pub fn seal_dir(secrets_dir: &Path, master_key: &MasterKey) -> Result<DirReport, io::Error> {
+    let mut report = DirReport::default();
+
+    for path in secret_files(secrets_dir)? {
+        let bytes = Zeroizing::new(fs::read(&path)?);
+        if is_sealed(&bytes) {
+            report.skipped += 1;
+            continue;
+        }
+
+        let sealed = seal(master_key, &aad(secrets_dir, &path), &bytes);
+        replace(&path, &sealed)?;
+        report.changed += 1;
+    }
+
+    Ok(report)
+}
+
+/// Decrypts every sealed secret file in the directory back into a plain file.
+pub fn unseal_dir(secrets_dir: &Path, master_key: &MasterKey) -> Result<DirReport, io::Error> {
+    let mut report = DirReport::default();
+
+    for path in secret_files(secrets_dir)? {
+        let bytes = fs::read(&path)?;
+        if !is_sealed(&bytes) {
+            report.skipped += 1;
+            continue;
+        }
+
+        let plaintext = open(master_key, &aad(secrets_dir, &path), &bytes)?;
+        replace(&path, &plaintext)?;
+        report.changed += 1;
+    }
+
+    Ok(report)
+}
+
+/// Re-wraps the data keys of every sealed secret file under `new_master_key`.
+/// Nothing is written unless every file opens with `old_master_key`.
+pub fn rekey_dir(
+    secrets_dir: &Path,
+    old_master_key: &MasterKey,
+    new_master_key: &MasterKey,
+) -> Result<DirReport, io::Error> {
+    let mut report = DirReport::default();
+    let mut rewrapped = Vec::new();
+
+    for path in secret_files(secrets_dir)? {
+        let bytes = fs::read(&path)?;
+        if !is_sealed(&bytes) {
+            report.skipped += 1;
+            continue;
+        }
+
+        rewrapped.push((path, rewrap(old_master_key, new_master_key, &bytes)?));
+    }
+
+    for (path, sealed) in rewrapped {
+        replace(&path, &sealed)?;
+        report.changed += 1;
+    }
+
+    Ok(report)
+}
+
+/// Every file holding key material, i.e. without rotation markers and hidden entries
+/// (the salt, temporary files and staged migrations).
+fn secret_files(dir: &Path) -> Result<Vec<PathBuf>, io::Error> {
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+        if name.starts_with('.') {
+            continue;
+        } else if path.is_dir() {
+            files.extend(secret_files(&path)?);
+        } else if !name.ends_with(".retired") {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
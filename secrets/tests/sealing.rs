@@ -0,0 +1,91 @@
+use secrets::sealing::{MasterKey, MasterKeySource, rekey_dir, seal_dir, unseal_dir};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use zeroize::Zeroizing;
+
+fn master_key(byte: u8, dir: &std::path::Path) -> MasterKey {
+    let source = MasterKeySource::Base64(Zeroizing::new(BASE64_STANDARD.encode([byte; 32])));
+    MasterKey::load(&source, dir).unwrap()
+}
+
+#[test]
+fn secrets_dir_can_be_sealed_rekeyed_and_unsealed() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let dir = temp_dir.path();
+
+    std::fs::write(dir.join("hmac"), b"plain hmac key").unwrap();
+    std::fs::create_dir_all(dir.join("dkim")).unwrap();
+    std::fs::write(dir.join("dkim").join("v1"), b"plain dkim key").unwrap();
+    std::fs::write(dir.join("dkim").join("v1.retired"), b"0").unwrap();
+
+    let old = master_key(1, dir);
+    let new = master_key(2, dir);
+
+    let report = seal_dir(dir, &old).unwrap();
+    assert_eq!((report.changed, report.skipped), (2, 0));
+
+    let sealed = std::fs::read(dir.join("hmac")).unwrap();
+    assert!(sealed.starts_with(b"auth-sealed-v1\n"));
+    assert!(!sealed.windows(5).any(|window| window == b"plain"));
+    // retirement markers are not key material
+    assert_eq!(
+        std::fs::read(dir.join("dkim").join("v1.retired")).unwrap(),
+        b"0"
+    );
+
+    // sealing twice is a no-op
+    let report = seal_dir(dir, &old).unwrap();
+    assert_eq!((report.changed, report.skipped), (0, 2));
+
+    rekey_dir(dir, &old, &new).unwrap();
+    assert!(unseal_dir(dir, &old).is_err());
+
+    let report = unseal_dir(dir, &new).unwrap();
+    assert_eq!(report.changed, 2);
+    assert_eq!(std::fs::read(dir.join("hmac")).unwrap(), b"plain hmac key");
+    assert_eq!(
+        std::fs::read(dir.join("dkim").join("v1")).unwrap(),
+        b"plain dkim key"
+    );
+}
+
+#[test]
+fn passphrase_derives_the_same_key_for_the_same_dir() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let dir = temp_dir.path();
+    let passphrase = || MasterKeySource::Passphrase(Zeroizing::new("correct horse".into()));
+
+    std::fs::write(dir.join("hmac"), b"plain hmac key").unwrap();
+
+    seal_dir(dir, &MasterKey::load(&passphrase(), dir).unwrap()).unwrap();
+    unseal_dir(dir, &MasterKey::load(&passphrase(), dir).unwrap()).unwrap();
+
+    assert_eq!(std::fs::read(dir.join("hmac")).unwrap(), b"plain hmac key");
+}
+
+#[test]
+fn temporary_files_are_not_secrets() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let dir = temp_dir.path();
+
+    std::fs::write(dir.join("hmac"), b"plain hmac key").unwrap();
+    // left behind by a crash mid-write
+    std::fs::write(dir.join(".dkim.tmp"), b"half written").unwrap();
+
+    let key = master_key(1, dir);
+
+    let report = seal_dir(dir, &key).unwrap();
+    assert_eq!((report.changed, report.skipped), (1, 0));
+    assert_eq!(
+        std::fs::read(dir.join(".dkim.tmp")).unwrap(),
+        b"half written"
+    );
+
+    let names: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert_eq!(names.len(), 2, "{names:?}");
+
+    let report = unseal_dir(dir, &key).unwrap();
+    assert_eq!((report.changed, report.skipped), (1, 0));
+}
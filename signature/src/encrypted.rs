@@ -0,0 +1,461 @@
+//! Encrypted tokens, the counterpart of [`Signed`](crate::Signed) for tokens whose content must
+//! stay hidden from whoever holds them, e.g. an email address sent in a link.
+//!
+//! A token is encoded as `header.nonce.ciphertext`, each part url-safe base64 with no padding. Like
+//! a JWE protected header, the header is readable and authenticated along with the ciphertext. The
+//! token is serialized as with [`Signed::encode_serialized`](crate::Signed::encode_serialized) and
+//! encrypted with XChaCha20-Poly1305 (`enc: "XC20P"`) under a random nonce.
+
+use std::{borrow::Borrow, fmt, time::Duration};
+
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use chacha20poly1305::{
+    AeadCore, Key, KeyInit, XChaCha20Poly1305, XNonce,
+    aead::{Aead, OsRng, Payload},
+};
+use contextual::Context;
+use ring::hkdf::{HKDF_SHA256, Salt};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use time::OffsetDateTime;
+
+use crate::{
+    EncodeError, PayloadError, PayloadFormat, Validation, ValidationError,
+    payload::{deserialize, serialize},
+};
+
+const ENC: &str = "XC20P";
+
+/// Separates the derived key from other uses of the same secret, e.g. as an HMAC key.
+const KEY_INFO: &[u8] = b"signature/encrypted";
+
+/// A generic container for a token that is encrypted and has an expiration date.
+/// Built and validated like a [`Signed`](crate::Signed) token.
+#[derive(Debug, Clone)]
+pub struct Encrypted<T> {
+    claims: Claims,
+    token: T,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Header {
+    enc: String,
+    /// how the token is serialized
+    cty: PayloadFormat,
+    #[serde(flatten)]
+    claims: Claims,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Claims {
+    /// issued at time
+    iat: OffsetDateTime,
+    /// expiry time
+    exp: OffsetDateTime,
+    /// not before time, `iat` if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nbf: Option<OffsetDateTime>,
+    /// audience, i.e. what the token may be used for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    aud: Option<String>,
+    /// token identifier
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    jti: Option<String>,
+    /// identifier of the key that encrypted the token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kid: Option<String>,
+}
+
+/// A 256 bit key derived from a secret (e.g. from `Secrets`) with HKDF-SHA256, so the secret may
+/// have any length and serve other purposes as well.
+pub struct EncryptionKey(Key);
+
+impl EncryptionKey {
+    pub fn derive(secret: &[u8]) -> Self {
+        let mut key = Key::default();
+        Salt::new(HKDF_SHA256, &[])
+            .extract(secret)
+            .expand(&[KEY_INFO], HKDF_SHA256)
+            .and_then(|okm| okm.fill(&mut key))
+            .expect("32 bytes are within the output length of HKDF-SHA256");
+        Self(key)
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+impl<T> Encrypted<T> {
+    const DEFAULT_TTL: Duration = Duration::from_secs(3600);
+
+    /// Creates a new `Encrypted` token with the default settings.
+    /// Settings can be modified using the various builder-style methods.
+    pub fn new(token: T) -> Self {
+        let iat = OffsetDateTime::now_utc();
+        let claims = Claims {
+            iat,
+            exp: iat + Self::DEFAULT_TTL,
+            nbf: None,
+            aud: None,
+            jti: None,
+            kid: None,
+        };
+        Encrypted { claims, token }
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.claims.exp = self.claims.iat + ttl;
+        self
+    }
+
+    /// See [`Signed::with_audience`](crate::Signed::with_audience).
+    pub fn with_audience(mut self, aud: impl Into<String>) -> Self {
+        self.claims.aud = Some(aud.into());
+        self
+    }
+
+    pub fn audience(&self) -> Option<&str> {
+        self.claims.aud.as_deref()
+    }
+
+    /// Delays the start of the token's validity, which begins at `iat` otherwise.
+    pub fn with_not_before(mut self, nbf: OffsetDateTime) -> Self {
+        self.claims.nbf = Some(nbf);
+        self
+    }
+
+    pub fn not_before(&self) -> OffsetDateTime {
+        self.claims.nbf.unwrap_or(self.claims.iat)
+    }
+
+    /// Sets a unique identifier, allowing the issuer to track (e.g. revoke) individual tokens.
+    pub fn with_jti(mut self, jti: impl Into<String>) -> Self {
+        self.claims.jti = Some(jti.into());
+        self
+    }
+
+    pub fn jti(&self) -> Option<&str> {
+        self.claims.jti.as_deref()
+    }
+
+    /// Records which key encrypts the token, so that [`Encrypted::decrypt_with_keys`] can pick it
+    /// among several (e.g. rotated) keys.
+    pub fn with_kid(mut self, kid: impl Into<String>) -> Self {
+        self.claims.kid = Some(kid.into());
+        self
+    }
+
+    pub fn kid(&self) -> Option<&str> {
+        self.claims.kid.as_deref()
+    }
+
+    /// Takes the token out like [`Signed::token`](crate::Signed::token).
+    pub fn token(self, expected: &Validation) -> Result<T, ValidationError> {
+        expected.check(
+            self.claims.exp,
+            self.not_before(),
+            self.claims.aud.as_slice(),
+            self.claims.jti.as_deref(),
+        )?;
+        Ok(self.token)
+    }
+
+    /// Encrypts the token, serialized in `format`, into a url-safe base64 encoded string with no
+    /// padding.
+    pub fn encrypt(&self, key: &EncryptionKey, format: PayloadFormat) -> Result<String, EncodeError>
+    where
+        T: Serialize,
+    {
+        let header = Header {
+            enc: ENC.to_string(),
+            cty: format,
+            claims: self.claims.clone(),
+        };
+        let header_json = serde_json::to_string(&header).context("header")?;
+        let header_base64encoded = BASE64_URL_SAFE_NO_PAD.encode(header_json);
+
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = XChaCha20Poly1305::new(&key.0)
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &serialize(&self.token, format)?,
+                    aad: header_base64encoded.as_bytes(),
+                },
+            )
+            .expect("encrypting a token cannot fail");
+
+        Ok(format!(
+            "{header_base64encoded}.{}.{}",
+            BASE64_URL_SAFE_NO_PAD.encode(nonce),
+            BASE64_URL_SAFE_NO_PAD.encode(ciphertext)
+        ))
+    }
+
+    /// Decrypts a token encoded by [`Encrypted::encrypt`].
+    pub fn decrypt(s: &str, key: &EncryptionKey) -> Result<Self, DecryptError>
+    where
+        T: DeserializeOwned,
+    {
+        Self::decrypt_with_keys(s, |_kid| Some(key))
+    }
+
+    /// Decrypts a token with the key that `keys` returns for the token's `kid` (`None` for
+    /// tokens encrypted without one).
+    pub fn decrypt_with_keys<K, F>(s: &str, keys: F) -> Result<Self, DecryptError>
+    where
+        T: DeserializeOwned,
+        K: Borrow<EncryptionKey>,
+        F: FnOnce(Option<&str>) -> Option<K>,
+    {
+        let [header_part, nonce_part, ciphertext_part] = s
+            .split('.')
+            .collect::<Vec<&str>>()
+            .try_into()
+            .map_err(|_| DecryptError::InvalidFormat)?;
+
+        // the header selects the key, but is trusted only once the token decrypts
+        let header = {
+            let bytes = BASE64_URL_SAFE_NO_PAD
+                .decode(header_part)
+                .context("header")?;
+            serde_json::from_slice::<Header>(&bytes).context("header")?
+        };
+
+        if header.enc != ENC {
+            return Err(DecryptError::InvalidFormat);
+        }
+
+        let key = keys(header.claims.kid.as_deref())
+            .ok_or_else(|| DecryptError::UnknownKey(header.claims.kid.clone()))?;
+
+        let nonce = BASE64_URL_SAFE_NO_PAD.decode(nonce_part).context("nonce")?;
+        if nonce.len() != size_of::<XNonce>() {
+            return Err(DecryptError::InvalidFormat);
+        }
+        let ciphertext = BASE64_URL_SAFE_NO_PAD
+            .decode(ciphertext_part)
+            .context("ciphertext")?;
+
+        let plaintext = XChaCha20Poly1305::new(&key.borrow().0)
+            .decrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: header_part.as_bytes(),
+                },
+            )
+            .map_err(|_| DecryptError::Decrypt)?;
+
+        let token = deserialize(&plaintext, header.cty).map_err(DecryptError::Payload)?;
+
+        Ok(Self {
+            claims: header.claims,
+            token,
+        })
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum DecryptError {
+    #[error("Invalid token format")]
+    InvalidFormat,
+
+    #[error("unknown or retired key `{}`", .0.as_deref().unwrap_or("<none>"))]
+    UnknownKey(Option<String>),
+
+    #[error("token does not decrypt with the key")]
+    Decrypt,
+
+    #[error("{0}")]
+    Serde(#[from] contextual::Error<serde_json::Error>),
+
+    #[error("{0}")]
+    Base64(#[from] contextual::Error<base64::DecodeError>),
+
+    #[error("failed to deserialize token")]
+    Payload(#[source] PayloadError),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+    use serde::{Deserialize, Serialize};
+
+    use super::{DecryptError, Encrypted, EncryptionKey};
+    use crate::{PayloadError, PayloadFormat, TemporalValidityError, Validation, ValidationError};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct EmailChange {
+        new_email: String,
+    }
+
+    fn email_change() -> Encrypted<EmailChange> {
+        Encrypted::new(EmailChange {
+            new_email: "joe@smith.com".to_string(),
+        })
+        .with_audience("email-change")
+        .with_jti("42")
+        .with_kid("v1")
+    }
+
+    fn key() -> EncryptionKey {
+        EncryptionKey::derive(b"secret")
+    }
+
+    fn encrypt(token: &Encrypted<EmailChange>) -> String {
+        token.encrypt(&key(), PayloadFormat::Json).unwrap()
+    }
+
+    fn decrypt(token: &str) -> Result<Encrypted<EmailChange>, DecryptError> {
+        Encrypted::decrypt(token, &key())
+    }
+
+    #[test]
+    fn round_trip() {
+        let encrypted = decrypt(&encrypt(&email_change())).unwrap();
+
+        assert_eq!(encrypted.kid(), Some("v1"));
+        assert_eq!(encrypted.jti(), Some("42"));
+        assert_eq!(encrypted.audience(), Some("email-change"));
+        assert_eq!(
+            encrypted
+                .token(&Validation::audience("email-change"))
+                .unwrap(),
+            EmailChange {
+                new_email: "joe@smith.com".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn hides_the_token() {
+        let token = encrypt(&email_change());
+        let decoded = token
+            .split('.')
+            .flat_map(|part| BASE64_URL_SAFE_NO_PAD.decode(part).unwrap())
+            .collect::<Vec<_>>();
+
+        assert!(!String::from_utf8_lossy(&decoded).contains("joe@smith.com"));
+        assert_ne!(token, encrypt(&email_change()), "nonce is not random");
+    }
+
+    #[test]
+    fn key_is_derived_from_the_secret() {
+        let token = encrypt(&email_change());
+
+        assert!(
+            Encrypted::<EmailChange>::decrypt(&token, &EncryptionKey::derive(b"secret")).is_ok()
+        );
+        assert!(matches!(
+            Encrypted::<EmailChange>::decrypt(&token, &EncryptionKey::derive(b"other")),
+            Err(DecryptError::Decrypt)
+        ));
+    }
+
+    #[test]
+    fn picks_the_key_by_kid() {
+        let token = encrypt(&email_change());
+
+        let decrypted = Encrypted::<EmailChange>::decrypt_with_keys(&token, |kid| {
+            (kid == Some("v1")).then(key)
+        });
+        assert!(decrypted.is_ok());
+
+        assert!(matches!(
+            Encrypted::<EmailChange>::decrypt_with_keys(&token, |_kid| None::<EncryptionKey>),
+            Err(DecryptError::UnknownKey(Some(kid))) if kid == "v1"
+        ));
+    }
+
+    #[test]
+    fn rejects_tampered_tokens() {
+        let token = encrypt(&email_change());
+        let [header, nonce, ciphertext] = token.split('.').collect::<Vec<_>>().try_into().unwrap();
+
+        // the header is authenticated along with the ciphertext
+        let other_header = encrypt(&email_change().with_audience("invitation"));
+        let (other_header, _) = other_header.split_once('.').unwrap();
+        assert!(matches!(
+            decrypt(&format!("{other_header}.{nonce}.{ciphertext}")),
+            Err(DecryptError::Decrypt)
+        ));
+
+        let mut bytes = BASE64_URL_SAFE_NO_PAD.decode(ciphertext).unwrap();
+        bytes[0] ^= 1;
+        let ciphertext = BASE64_URL_SAFE_NO_PAD.encode(bytes);
+        assert!(matches!(
+            decrypt(&format!("{header}.{nonce}.{ciphertext}")),
+            Err(DecryptError::Decrypt)
+        ));
+
+        assert!(matches!(
+            decrypt(&format!("{header}.{ciphertext}")),
+            Err(DecryptError::InvalidFormat)
+        ));
+        assert!(matches!(
+            decrypt(&format!(
+                "{header}.{}.{ciphertext}",
+                BASE64_URL_SAFE_NO_PAD.encode([0; 12])
+            )),
+            Err(DecryptError::InvalidFormat)
+        ));
+    }
+
+    #[test]
+    fn rejects_signed_tokens() {
+        let signed = crate::Signed::new(b"joe@smith.com".to_vec())
+            .encode(b"secret")
+            .unwrap();
+
+        assert!(decrypt(&signed).is_err());
+    }
+
+    #[test]
+    fn rejects_another_payload() {
+        #[derive(Debug, Deserialize)]
+        struct Invitation {
+            #[allow(dead_code)]
+            group: String,
+        }
+
+        assert!(matches!(
+            Encrypted::<Invitation>::decrypt(&encrypt(&email_change()), &key()),
+            Err(DecryptError::Payload(PayloadError::Json(_)))
+        ));
+    }
+
+    #[test]
+    fn validates_like_signed_tokens() {
+        let expired = decrypt(&encrypt(&email_change().with_ttl(Duration::ZERO))).unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(matches!(
+            expired.token(&Validation::audience("email-change").with_leeway(Duration::ZERO)),
+            Err(ValidationError::Temporal(
+                TemporalValidityError::Expired { .. }
+            ))
+        ));
+
+        let not_yet = email_change()
+            .with_not_before(time::OffsetDateTime::now_utc() + Duration::from_secs(600));
+        assert!(matches!(
+            decrypt(&encrypt(&not_yet))
+                .unwrap()
+                .token(&Validation::audience("email-change")),
+            Err(ValidationError::Temporal(
+                TemporalValidityError::NotYetValid { .. }
+            ))
+        ));
+
+        assert!(matches!(
+            decrypt(&encrypt(&email_change()))
+                .unwrap()
+                .token(&Validation::audience("invitation")),
+            Err(ValidationError::AudienceMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,430 @@
+//! JWS compact serialization (RFC 7515) of [`Signed`] tokens as JWTs (RFC 7519), readable by
+//! standard JWT libraries.
+//!
+//! The protected header is `{"alg", "typ": "JWT", "kid"}`, the payload holds the registered claims
+//! `iat`, `exp`, `nbf`, `aud` and `jti` next to the token's own claims, which must serialize to a
+//! JSON object. Tokens whose header asks for more, a nested JWT (`cty`) or extensions (`crit`), are
+//! rejected.
+
+use std::{borrow::Borrow, convert::Infallible};
+
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use contextual::Context;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use time::OffsetDateTime;
+
+use crate::{
+    Algorithm, Audience, DecodeError, EncodeError, Header, Signed, SigningKey, VerifyingKey,
+};
+
+const TYP: &str = "JWT";
+
+#[derive(Serialize, Deserialize)]
+struct JoseHeader {
+    alg: Algorithm,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    typ: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kid: Option<String>,
+    #[serde(default, skip_serializing)]
+    cty: Option<String>,
+    #[serde(default, skip_serializing)]
+    crit: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Claims<T> {
+    #[serde(with = "time::serde::timestamp")]
+    iat: OffsetDateTime,
+    #[serde(with = "time::serde::timestamp")]
+    exp: OffsetDateTime,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "time::serde::timestamp::option"
+    )]
+    nbf: Option<OffsetDateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    aud: Option<Audience>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    jti: Option<String>,
+    #[serde(flatten)]
+    token: T,
+}
+
+impl<T> Signed<T> {
+    /// Encodes the `Signed` token as a JWT in JWS compact serialization, signed with the key's
+    /// algorithm. `iat` and `exp` are truncated to whole seconds.
+    pub fn encode_jws(&self, key: &SigningKey) -> Result<String, EncodeError>
+    where
+        T: Serialize,
+    {
+        let header = JoseHeader {
+            alg: key.algorithm(),
+            typ: Some(TYP.to_string()),
+            kid: self.header.kid.clone(),
+            cty: None,
+            crit: None,
+        };
+        let claims = Claims {
+            iat: self.header.iat,
+            exp: self.header.exp,
+            nbf: self.header.nbf,
+            aud: self.header.aud.clone(),
+            jti: self.header.jti.clone(),
+            token: &self.token,
+        };
+
+        let header_base64encoded =
+            BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header).context("header")?);
+        let claims_base64encoded =
+            BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims).context("claims")?);
+        let signature_base64encoded = {
+            let signing_input = format!("{header_base64encoded}.{claims_base64encoded}");
+            BASE64_URL_SAFE_NO_PAD.encode(key.sign(signing_input.as_bytes())?)
+        };
+        Ok(format!(
+            "{header_base64encoded}.{claims_base64encoded}.{signature_base64encoded}"
+        ))
+    }
+
+    /// Decodes a JWT encoded by [`Signed::encode_jws`], or by another JWT library as long as it
+    /// carries `iat` and `exp`.
+    pub fn decode_jws(s: &str, key: &VerifyingKey) -> Result<Self, DecodeError<Infallible>>
+    where
+        T: DeserializeOwned,
+    {
+        Self::decode_jws_with_keys(s, |_kid| Some(key))
+    }
+
+    /// Decodes a JWT like [`Signed::decode_with_verifying_keys`], picking the key by `kid`.
+    pub fn decode_jws_with_keys<K, F>(s: &str, keys: F) -> Result<Self, DecodeError<Infallible>>
+    where
+        T: DeserializeOwned,
+        K: Borrow<VerifyingKey>,
+        F: FnOnce(Option<&str>) -> Option<K>,
+    {
+        decode_jws(s, keys)
+    }
+
+    /// Decodes a JWT as well as a token in the original `header.token.signature` format, which
+    /// outstanding tokens (e.g. links sent by email) were issued in. `T` is built from the claims
+    /// of a JWT and from the raw payload of the original format.
+    pub fn decode_any_with_keys<K, F>(
+        s: &str,
+        keys: F,
+    ) -> Result<Self, DecodeError<<T as TryFrom<Vec<u8>>>::Error>>
+    where
+        T: DeserializeOwned + TryFrom<Vec<u8>>,
+        <T as TryFrom<Vec<u8>>>::Error: std::error::Error,
+        K: Borrow<VerifyingKey>,
+        F: FnOnce(Option<&str>) -> Option<K>,
+    {
+        // the original header carries `iat` and `exp`, which a JOSE header never does
+        let original = s
+            .split_once('.')
+            .and_then(|(header_part, _)| BASE64_URL_SAFE_NO_PAD.decode(header_part).ok())
+            .is_some_and(|header| serde_json::from_slice::<Header>(&header).is_ok());
+
+        match original {
+            true => Self::decode_with_verifying_keys(s, keys),
+            false => decode_jws(s, keys),
+        }
+    }
+}
+
+fn decode_jws<T, E, K, F>(s: &str, keys: F) -> Result<Signed<T>, DecodeError<E>>
+where
+    T: DeserializeOwned,
+    E: std::error::Error + 'static,
+    K: Borrow<VerifyingKey>,
+    F: FnOnce(Option<&str>) -> Option<K>,
+{
+    let [header_part, claims_part, signature_part] = s
+        .split('.')
+        .collect::<Vec<&str>>()
+        .try_into()
+        .map_err(|_| DecodeError::InvalidFormat)?;
+
+    // the header selects the key, but is trusted only once the signature checks out
+    let header = {
+        let bytes = BASE64_URL_SAFE_NO_PAD
+            .decode(header_part)
+            .context("header")?;
+        serde_json::from_slice::<JoseHeader>(&bytes).context("header")?
+    };
+
+    if header
+        .typ
+        .as_deref()
+        .is_some_and(|typ| !typ.eq_ignore_ascii_case(TYP))
+    {
+        return Err(DecodeError::InvalidFormat);
+    }
+
+    if header.cty.is_some() {
+        return Err(DecodeError::UnsupportedHeader("cty"));
+    }
+
+    if header.crit.is_some() {
+        return Err(DecodeError::UnsupportedHeader("crit"));
+    }
+
+    let key =
+        keys(header.kid.as_deref()).ok_or_else(|| DecodeError::UnknownKey(header.kid.clone()))?;
+    let key = key.borrow();
+
+    if header.alg != key.algorithm() {
+        return Err(DecodeError::AlgorithmMismatch {
+            expected: key.algorithm(),
+            found: header.alg,
+        });
+    }
+
+    let signature = BASE64_URL_SAFE_NO_PAD
+        .decode(signature_part)
+        .context("signature")?;
+    key.verify(
+        format!("{header_part}.{claims_part}").as_bytes(),
+        &signature,
+    )?;
+
+    let claims = {
+        let bytes = BASE64_URL_SAFE_NO_PAD
+            .decode(claims_part)
+            .context("claims")?;
+        serde_json::from_slice::<Claims<T>>(&bytes).context("claims")?
+    };
+
+    Ok(Signed {
+        header: Header {
+            alg: header.alg,
+            iat: claims.iat,
+            exp: claims.exp,
+            nbf: claims.nbf,
+            aud: claims.aud,
+            jti: claims.jti,
+            kid: header.kid,
+            cty: None,
+        },
+        token: claims.token,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+    use serde::{Deserialize, Serialize};
+    use serde_json::{Value, json};
+
+    use crate::{
+        Algorithm, DecodeError, PayloadFormat, Signed, SigningKey, Validation, ValidationError,
+        VerifyingKey,
+    };
+
+    const SECRET: &[u8] = b"secret";
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Invitation {
+        group: String,
+    }
+
+    impl TryFrom<Vec<u8>> for Invitation {
+        type Error = std::string::FromUtf8Error;
+
+        fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
+            String::from_utf8(bytes).map(|group| Self { group })
+        }
+    }
+
+    impl AsRef<[u8]> for Invitation {
+        fn as_ref(&self) -> &[u8] {
+            self.group.as_bytes()
+        }
+    }
+
+    fn invitation() -> Signed<Invitation> {
+        Signed::new(Invitation {
+            group: "admins".to_string(),
+        })
+        .with_audience("invitation")
+        .with_jti("42")
+        .with_kid("v1")
+    }
+
+    /// A JWT as another library would issue it, signed with HMAC-SHA256.
+    fn jwt(header: Value, claims: Value) -> String {
+        let header = BASE64_URL_SAFE_NO_PAD.encode(header.to_string());
+        let claims = BASE64_URL_SAFE_NO_PAD.encode(claims.to_string());
+        let signature =
+            crate::hmac_sha256(SECRET, format!("{header}.{claims}").as_bytes()).unwrap();
+        format!("{header}.{claims}.{signature}")
+    }
+
+    fn claims(aud: Value) -> Value {
+        let iat = time::OffsetDateTime::now_utc().unix_timestamp();
+        json!({ "iat": iat, "exp": iat + 60, "aud": aud, "group": "admins" })
+    }
+
+    fn decode(token: &str) -> Result<Signed<Invitation>, DecodeError<Infallible>> {
+        Signed::decode_jws(token, &VerifyingKey::hmac(SECRET))
+    }
+
+    fn part(token: &str, n: usize) -> Value {
+        let part = token.split('.').nth(n).unwrap();
+        serde_json::from_slice(&BASE64_URL_SAFE_NO_PAD.decode(part).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let ed25519 = SigningKey::ed25519(&SigningKey::generate_ed25519().unwrap()).unwrap();
+
+        for key in [SigningKey::hmac(SECRET), ed25519] {
+            let token = invitation().encode_jws(&key).unwrap();
+            let signed = Signed::<Invitation>::decode_jws(&token, &key.verifying_key()).unwrap();
+
+            assert_eq!(signed.alg(), key.algorithm());
+            assert_eq!(signed.kid(), Some("v1"));
+            assert_eq!(signed.jti(), Some("42"));
+            assert_eq!(signed.audiences(), ["invitation"]);
+            assert_eq!(
+                signed.token(&Validation::audience("invitation")).unwrap(),
+                Invitation {
+                    group: "admins".to_string()
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn encodes_standard_jwt() {
+        let token = invitation().encode_jws(&SigningKey::hmac(SECRET)).unwrap();
+
+        assert_eq!(
+            part(&token, 0),
+            json!({ "alg": "HS256", "typ": "JWT", "kid": "v1" })
+        );
+
+        let claims = part(&token, 1);
+        assert_eq!(claims["aud"], "invitation");
+        assert_eq!(claims["jti"], "42");
+        assert_eq!(claims["group"], "admins");
+        assert!(claims["iat"].is_i64() && claims["exp"].is_i64());
+    }
+
+    #[test]
+    fn decodes_audience_array() {
+        let token = jwt(
+            json!({ "alg": "HS256", "typ": "JWT" }),
+            claims(json!(["gateway", "invitation"])),
+        );
+        let signed = decode(&token).unwrap();
+
+        assert_eq!(signed.audiences(), ["gateway", "invitation"]);
+        assert!(matches!(
+            signed.clone().token(&Validation::audience("other")),
+            Err(ValidationError::AudienceMismatch { .. })
+        ));
+        assert!(signed.token(&Validation::audience("invitation")).is_ok());
+    }
+
+    #[test]
+    fn decodes_without_typ() {
+        let token = jwt(json!({ "alg": "HS256" }), claims(json!("invitation")));
+
+        assert_eq!(decode(&token).unwrap().audiences(), ["invitation"]);
+    }
+
+    #[test]
+    fn rejects_unsupported_headers() {
+        for (header, name) in [
+            (json!({ "alg": "HS256", "typ": "JWT", "cty": "JWT" }), "cty"),
+            (
+                json!({ "alg": "HS256", "typ": "JWT", "crit": ["exp"] }),
+                "crit",
+            ),
+        ] {
+            assert!(matches!(
+                decode(&jwt(header, claims(json!("invitation")))),
+                Err(DecodeError::UnsupportedHeader(found)) if found == name
+            ));
+        }
+
+        assert!(matches!(
+            decode(&jwt(
+                json!({ "alg": "HS256", "typ": "JWE" }),
+                claims(json!("invitation"))
+            )),
+            Err(DecodeError::InvalidFormat)
+        ));
+    }
+
+    #[test]
+    fn rejects_tampered_tokens() {
+        let token = invitation().encode_jws(&SigningKey::hmac(SECRET)).unwrap();
+        let [header, _, signature] = token.split('.').collect::<Vec<_>>().try_into().unwrap();
+
+        let mut claims = part(&token, 1);
+        claims["group"] = json!("owners");
+        let claims = BASE64_URL_SAFE_NO_PAD.encode(claims.to_string());
+        assert!(matches!(
+            decode(&format!("{header}.{claims}.{signature}")),
+            Err(DecodeError::MacMismatch(_))
+        ));
+
+        let (_, rest) = token.split_once('.').unwrap();
+        let eddsa = BASE64_URL_SAFE_NO_PAD.encode(r#"{"alg":"EdDSA","typ":"JWT","kid":"v1"}"#);
+        assert!(matches!(
+            decode(&format!("{eddsa}.{rest}")),
+            Err(DecodeError::AlgorithmMismatch {
+                expected: Algorithm::HS256,
+                found: Algorithm::EdDSA
+            })
+        ));
+
+        assert!(matches!(
+            decode(token.rsplit_once('.').unwrap().0),
+            Err(DecodeError::InvalidFormat)
+        ));
+        assert!(matches!(
+            Signed::<Invitation>::decode_jws(&token, &VerifyingKey::hmac(b"other".to_vec())),
+            Err(DecodeError::MacMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn decodes_any_format() {
+        let key = SigningKey::hmac(SECRET);
+        let original = invitation().encode_with(&key).unwrap();
+        let jws = invitation().encode_jws(&key).unwrap();
+
+        for token in [original, jws] {
+            let signed = Signed::<Invitation>::decode_any_with_keys(&token, |kid| {
+                (kid == Some("v1")).then(|| key.verifying_key())
+            })
+            .unwrap();
+
+            assert_eq!(
+                signed
+                    .token(&Validation::audience("invitation"))
+                    .unwrap()
+                    .group,
+                "admins"
+            );
+        }
+    }
+
+    #[test]
+    fn raw_decode_rejects_serialized_tokens() {
+        let token = invitation()
+            .encode_serialized(&SigningKey::hmac(SECRET), PayloadFormat::Json)
+            .unwrap();
+
+        assert!(matches!(
+            Signed::<Invitation>::decode(&token, SECRET),
+            Err(DecodeError::UnsupportedHeader("cty"))
+        ));
+    }
+}
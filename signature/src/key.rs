@@ -0,0 +1,341 @@
+//! Keys that sign and verify [`Signed`](crate::Signed) tokens.
+//!
+//! HMAC keys are shared secrets, whoever verifies a token could also have signed it. With Ed25519
+//! and ES256 (ECDSA on P-256 with SHA-256) only the issuer holds the private [`SigningKey`], and
+//! every other service verifies with the public [`VerifyingKey`].
+
+use std::fmt;
+
+use hmac::{Hmac, Mac};
+use ring::{
+    rand::SystemRandom,
+    signature::{
+        ECDSA_P256_SHA256_FIXED, ECDSA_P256_SHA256_FIXED_SIGNING, ED25519, EcdsaKeyPair,
+        Ed25519KeyPair, KeyPair, UnparsedPublicKey,
+    },
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Sha256, digest::InvalidLength};
+
+use crate::{DecodeError, EncodeError};
+
+/// The `alg` of a token, named as in JWS (RFC 7518, RFC 8037).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Algorithm {
+    /// HMAC-SHA256, also assumed for tokens issued before `alg` was recorded
+    #[default]
+    HS256,
+
+    /// Ed25519
+    EdDSA,
+
+    /// ECDSA on P-256 with SHA-256
+    ES256,
+}
+
+pub struct SigningKey(SigningKeyInner);
+
+enum SigningKeyInner {
+    Hmac(Vec<u8>),
+    Ed25519(Ed25519KeyPair),
+    Es256(EcdsaKeyPair),
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct VerifyingKey(VerifyingKeyInner);
+
+#[derive(Clone, PartialEq, Eq)]
+enum VerifyingKeyInner {
+    Hmac(Vec<u8>),
+    /// 32 bytes
+    Ed25519(Vec<u8>),
+    /// uncompressed SEC1 point, 65 bytes
+    Es256(Vec<u8>),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum KeyError {
+    #[error("invalid {0} key")]
+    Rejected(Algorithm),
+
+    #[error("unable to generate a {0} key")]
+    Generate(Algorithm),
+}
+
+impl Algorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Algorithm::HS256 => "HS256",
+            Algorithm::EdDSA => "EdDSA",
+            Algorithm::ES256 => "ES256",
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl SigningKey {
+    pub fn hmac(secret: impl Into<Vec<u8>>) -> Self {
+        Self(SigningKeyInner::Hmac(secret.into()))
+    }
+
+    /// Loads a PKCS#8 encoded Ed25519 key, e.g. from [`SigningKey::generate_ed25519`].
+    pub fn ed25519(pkcs8: &[u8]) -> Result<Self, KeyError> {
+        Ed25519KeyPair::from_pkcs8_maybe_unchecked(pkcs8)
+            .map(|key| Self(SigningKeyInner::Ed25519(key)))
+            .map_err(|_| KeyError::Rejected(Algorithm::EdDSA))
+    }
+
+    /// Loads a PKCS#8 encoded P-256 key, e.g. from [`SigningKey::generate_es256`].
+    pub fn es256(pkcs8: &[u8]) -> Result<Self, KeyError> {
+        EcdsaKeyPair::from_pkcs8(
+            &ECDSA_P256_SHA256_FIXED_SIGNING,
+            pkcs8,
+            &SystemRandom::new(),
+        )
+        .map(|key| Self(SigningKeyInner::Es256(key)))
+        .map_err(|_| KeyError::Rejected(Algorithm::ES256))
+    }
+
+    /// A new PKCS#8 encoded Ed25519 key, to be kept as a secret.
+    pub fn generate_ed25519() -> Result<Vec<u8>, KeyError> {
+        Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .map(|pkcs8| pkcs8.as_ref().to_vec())
+            .map_err(|_| KeyError::Generate(Algorithm::EdDSA))
+    }
+
+    /// A new PKCS#8 encoded P-256 key, to be kept as a secret.
+    pub fn generate_es256() -> Result<Vec<u8>, KeyError> {
+        EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &SystemRandom::new())
+            .map(|pkcs8| pkcs8.as_ref().to_vec())
+            .map_err(|_| KeyError::Generate(Algorithm::ES256))
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        match &self.0 {
+            SigningKeyInner::Hmac(_) => Algorithm::HS256,
+            SigningKeyInner::Ed25519(_) => Algorithm::EdDSA,
+            SigningKeyInner::Es256(_) => Algorithm::ES256,
+        }
+    }
+
+    /// The key that verifies this key's signatures; the secret itself for HMAC.
+    pub fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey(match &self.0 {
+            SigningKeyInner::Hmac(secret) => VerifyingKeyInner::Hmac(secret.clone()),
+            SigningKeyInner::Ed25519(key) => {
+                VerifyingKeyInner::Ed25519(key.public_key().as_ref().to_vec())
+            }
+            SigningKeyInner::Es256(key) => {
+                VerifyingKeyInner::Es256(key.public_key().as_ref().to_vec())
+            }
+        })
+    }
+
+    pub(crate) fn sign(&self, message: &[u8]) -> Result<Vec<u8>, EncodeError> {
+        match &self.0 {
+            SigningKeyInner::Hmac(secret) => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(secret)
+                    .map_err(|_: InvalidLength| EncodeError::InvalidKeyLength)?;
+                mac.update(message);
+                Ok(mac.finalize().into_bytes().to_vec())
+            }
+            SigningKeyInner::Ed25519(key) => Ok(key.sign(message).as_ref().to_vec()),
+            SigningKeyInner::Es256(key) => key
+                .sign(&SystemRandom::new(), message)
+                .map(|signature| signature.as_ref().to_vec())
+                .map_err(|_| EncodeError::Sign),
+        }
+    }
+}
+
+impl VerifyingKey {
+    pub fn hmac(secret: impl Into<Vec<u8>>) -> Self {
+        Self(VerifyingKeyInner::Hmac(secret.into()))
+    }
+
+    /// The raw 32 byte public key.
+    pub fn ed25519(public_key: impl Into<Vec<u8>>) -> Self {
+        Self(VerifyingKeyInner::Ed25519(public_key.into()))
+    }
+
+    /// The public key as uncompressed SEC1 point (`04 | x | y`, 65 bytes).
+    pub fn es256(public_key: impl Into<Vec<u8>>) -> Self {
+        Self(VerifyingKeyInner::Es256(public_key.into()))
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        match &self.0 {
+            VerifyingKeyInner::Hmac(_) => Algorithm::HS256,
+            VerifyingKeyInner::Ed25519(_) => Algorithm::EdDSA,
+            VerifyingKeyInner::Es256(_) => Algorithm::ES256,
+        }
+    }
+
+    /// The bytes to hand to other services, in the format of [`VerifyingKey::ed25519`] and
+    /// [`VerifyingKey::es256`]. `None` for HMAC, whose key must stay secret.
+    pub fn public_key(&self) -> Option<&[u8]> {
+        match &self.0 {
+            VerifyingKeyInner::Hmac(_) => None,
+            VerifyingKeyInner::Ed25519(public_key) | VerifyingKeyInner::Es256(public_key) => {
+                Some(public_key)
+            }
+        }
+    }
+
+    pub(crate) fn verify<E>(&self, message: &[u8], signature: &[u8]) -> Result<(), DecodeError<E>>
+    where
+        E: std::error::Error + 'static,
+    {
+        match &self.0 {
+            VerifyingKeyInner::Hmac(secret) => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(secret)
+                    .map_err(|InvalidLength| DecodeError::InvalidKeyLength)?;
+                mac.update(message);
+                Ok(mac.verify_slice(signature)?)
+            }
+            VerifyingKeyInner::Ed25519(public_key) => UnparsedPublicKey::new(&ED25519, public_key)
+                .verify(message, signature)
+                .map_err(|_| DecodeError::SignatureMismatch),
+            VerifyingKeyInner::Es256(public_key) => {
+                UnparsedPublicKey::new(&ECDSA_P256_SHA256_FIXED, public_key)
+                    .verify(message, signature)
+                    .map_err(|_| DecodeError::SignatureMismatch)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SigningKey({}, ..)", self.algorithm())
+    }
+}
+
+impl fmt::Debug for VerifyingKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.public_key() {
+            Some(public_key) => write!(f, "VerifyingKey({}, {public_key:02x?})", self.algorithm()),
+            None => write!(f, "VerifyingKey({}, ..)", self.algorithm()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Algorithm, DecodeError, Signed, SigningKey, VerifyingKey};
+
+    type Decoded = Result<Signed<Vec<u8>>, DecodeError<std::convert::Infallible>>;
+
+    fn sign(key: &SigningKey) -> String {
+        Signed::new(b"token".to_vec()).encode_with(key).unwrap()
+    }
+
+    fn verify(token: &str, key: &VerifyingKey) -> Decoded {
+        Signed::<Vec<u8>>::decode_verified(token, key)
+    }
+
+    fn ed25519() -> SigningKey {
+        SigningKey::ed25519(&SigningKey::generate_ed25519().unwrap()).unwrap()
+    }
+
+    fn es256() -> SigningKey {
+        SigningKey::es256(&SigningKey::generate_es256().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn asymmetric_round_trip() {
+        for key in [ed25519(), es256()] {
+            let signed = verify(&sign(&key), &key.verifying_key()).unwrap();
+
+            assert_eq!(signed.alg(), key.algorithm());
+            assert_eq!(signed.token(&Default::default()).unwrap(), b"token");
+        }
+    }
+
+    #[test]
+    fn verifies_with_public_key_only() {
+        for key in [ed25519(), es256()] {
+            let verifying_key = key.verifying_key();
+            let public_key = verifying_key.public_key().unwrap().to_vec();
+            let from_public_key = match key.algorithm() {
+                Algorithm::EdDSA => VerifyingKey::ed25519(public_key),
+                _ => VerifyingKey::es256(public_key),
+            };
+
+            assert_eq!(from_public_key, verifying_key);
+            assert!(verify(&sign(&key), &from_public_key).is_ok());
+        }
+    }
+
+    #[test]
+    fn hmac_has_no_public_key() {
+        assert!(
+            SigningKey::hmac(b"secret".to_vec())
+                .verifying_key()
+                .public_key()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn wrong_key_is_rejected() {
+        for (key, other) in [(ed25519(), ed25519()), (es256(), es256())] {
+            assert!(matches!(
+                verify(&sign(&key), &other.verifying_key()),
+                Err(DecodeError::SignatureMismatch)
+            ));
+        }
+
+        assert!(matches!(
+            verify(
+                &sign(&SigningKey::hmac(b"a".to_vec())),
+                &VerifyingKey::hmac(b"b".to_vec())
+            ),
+            Err(DecodeError::MacMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn algorithm_mismatch_is_rejected() {
+        let hmac = SigningKey::hmac(b"secret".to_vec());
+
+        for (key, other) in [
+            (es256(), ed25519()),
+            (ed25519(), es256()),
+            (hmac, ed25519()),
+        ] {
+            assert!(matches!(
+                verify(&sign(&key), &other.verifying_key()),
+                Err(DecodeError::AlgorithmMismatch { expected, found })
+                    if expected == other.algorithm() && found == key.algorithm()
+            ));
+        }
+    }
+
+    #[test]
+    fn public_key_cannot_verify_as_hmac() {
+        // an attacker holding the public key must not be able to sign with it as HMAC secret
+        let key = ed25519();
+        let public_key = key.verifying_key().public_key().unwrap().to_vec();
+        let forged = sign(&SigningKey::hmac(public_key));
+
+        assert!(matches!(
+            verify(&forged, &key.verifying_key()),
+            Err(DecodeError::AlgorithmMismatch {
+                expected: Algorithm::EdDSA,
+                found: Algorithm::HS256
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_keys() {
+        assert!(SigningKey::ed25519(b"not a key").is_err());
+        assert!(SigningKey::es256(&SigningKey::generate_ed25519().unwrap()).is_err());
+    }
+}
@@ -1,17 +1,27 @@
-use std::{convert::TryFrom, time::Duration};
+mod encrypted;
+mod jws;
+mod key;
+mod payload;
+
+pub use encrypted::{DecryptError, Encrypted, EncryptionKey};
+pub use key::{Algorithm, KeyError, SigningKey, VerifyingKey};
+pub use payload::{PayloadError, PayloadFormat};
+
+use std::{borrow::Borrow, convert::TryFrom, time::Duration};
 
 use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
 use contextual::Context;
-use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
-use sha2::{
-    Sha256,
-    digest::{InvalidLength, MacError},
-};
+use sha2::digest::MacError;
 use time::OffsetDateTime;
 
 /// A generic container for a token that is signed and has an expiration date.
-/// The token is signed using HMAC-SHA256.
+/// The token is signed using HMAC-SHA256, or Ed25519 / ES256 to be verifiable with a public key
+/// only, see [`SigningKey`]. Besides its original `header.token.signature` format, it can be
+/// encoded as a standard JWT, see [`Signed::encode_jws`]. Tokens that are not bytes can be
+/// serialized, see [`Signed::encode_serialized`].
+/// A token is bound to its purpose by its audience, checked with a [`Validation`] when the
+/// token is taken out with [`Signed::token`].
 #[derive(Debug, Clone)]
 pub struct Signed<T> {
     header: Header,
@@ -20,10 +30,45 @@ pub struct Signed<T> {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct Header {
+    /// signing algorithm, decided by the key
+    #[serde(default)]
+    alg: Algorithm,
     /// issued at time
     iat: OffsetDateTime,
     /// expiry time
     exp: OffsetDateTime,
+    /// not before time, `iat` if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nbf: Option<OffsetDateTime>,
+    /// audience, i.e. what the token may be used for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    aud: Option<Audience>,
+    /// token identifier
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    jti: Option<String>,
+    /// identifier of the key that signed the token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kid: Option<String>,
+    /// how the token is serialized, absent for raw bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cty: Option<PayloadFormat>,
+}
+
+/// The `aud` claim, a single audience or, as JWTs may carry, several (RFC 7519, section 4.1.3).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Audience {
+    fn as_slice(&self) -> &[String] {
+        match self {
+            Audience::One(aud) => std::slice::from_ref(aud),
+            Audience::Many(aud) => aud,
+        }
+    }
 }
 
 impl<T> Signed<T> {
@@ -34,7 +79,16 @@ impl<T> Signed<T> {
     pub fn new(token: T) -> Self {
         let iat = OffsetDateTime::now_utc();
         let exp = iat + Self::DEFAULT_TTL;
-        let header = Header { iat, exp };
+        let header = Header {
+            alg: Algorithm::default(),
+            iat,
+            exp,
+            nbf: None,
+            aud: None,
+            jti: None,
+            kid: None,
+            cty: None,
+        };
         Signed { header, token }
     }
 
@@ -43,45 +97,86 @@ impl<T> Signed<T> {
         self
     }
 
-    pub fn token(self) -> Result<T, TemporalValidityError> {
-        let now = OffsetDateTime::now_utc();
+    /// Sets the token's audience (its purpose, e.g. `email-verification`), so that a token
+    /// issued for one purpose is rejected when presented for another.
+    pub fn with_audience(mut self, aud: impl Into<String>) -> Self {
+        self.header.aud = Some(Audience::One(aud.into()));
+        self
+    }
 
-        if self.header.exp < now {
-            return Err(TemporalValidityError::Expired {
-                exp: self.header.exp,
-                now,
-            });
-        }
+    /// The audiences the token was issued for, usually one, none if it has no `aud`.
+    pub fn audiences(&self) -> &[String] {
+        self.header.aud.as_ref().map_or(&[], Audience::as_slice)
+    }
 
-        if self.header.iat > now {
-            return Err(TemporalValidityError::NotYetValid {
-                iat: self.header.iat,
-                now,
-            });
-        }
+    /// Delays the start of the token's validity, which begins at `iat` otherwise.
+    pub fn with_not_before(mut self, nbf: OffsetDateTime) -> Self {
+        self.header.nbf = Some(nbf);
+        self
+    }
+
+    pub fn not_before(&self) -> OffsetDateTime {
+        self.header.nbf.unwrap_or(self.header.iat)
+    }
 
+    /// Sets a unique identifier, allowing the issuer to track (e.g. revoke) individual tokens.
+    pub fn with_jti(mut self, jti: impl Into<String>) -> Self {
+        self.header.jti = Some(jti.into());
+        self
+    }
+
+    pub fn jti(&self) -> Option<&str> {
+        self.header.jti.as_deref()
+    }
+
+    /// Records which key signs the token, so that [`Signed::decode_with_keys`] can pick it
+    /// among several (e.g. rotated) keys.
+    pub fn with_kid(mut self, kid: impl Into<String>) -> Self {
+        self.header.kid = Some(kid.into());
+        self
+    }
+
+    pub fn kid(&self) -> Option<&str> {
+        self.header.kid.as_deref()
+    }
+
+    /// The algorithm the token was signed with, once encoded or decoded.
+    pub fn alg(&self) -> Algorithm {
+        self.header.alg
+    }
+
+    /// Takes the token out once it is valid now, give or take the leeway, and was issued for
+    /// the expected audience.
+    pub fn token(self, expected: &Validation) -> Result<T, ValidationError> {
+        expected.check(
+            self.header.exp,
+            self.not_before(),
+            self.audiences(),
+            self.header.jti.as_deref(),
+        )?;
         Ok(self.token)
     }
 
-    /// Encodes the `Signed` token into a url-safe base64 encoded string with no padding.
+    /// Encodes the `Signed` token into a url-safe base64 encoded string with no padding,
+    /// signed with HMAC-SHA256.
     pub fn encode(&self, secret: &[u8]) -> Result<String, EncodeError>
     where
         T: AsRef<[u8]>,
     {
-        let header_json = serde_json::to_string(&self.header).context("header")?;
-        let header_base64encoded = BASE64_URL_SAFE_NO_PAD.encode(header_json);
-        let token_base64encoded = BASE64_URL_SAFE_NO_PAD.encode(self.token.as_ref());
-        let signature_base64encoded = {
-            let signing_input = format!("{header_base64encoded}.{token_base64encoded}");
-            let mut mac = Hmac::<Sha256>::new_from_slice(secret)
-                .map_err(|_: InvalidLength| EncodeError::InvalidKeyLength)?;
-            mac.update(signing_input.as_bytes());
-            let signature_bytes = mac.finalize().into_bytes();
-            BASE64_URL_SAFE_NO_PAD.encode(signature_bytes)
+        self.encode_with(&SigningKey::hmac(secret))
+    }
+
+    /// Encodes the `Signed` token like [`Signed::encode`], signed with the key's algorithm.
+    pub fn encode_with(&self, key: &SigningKey) -> Result<String, EncodeError>
+    where
+        T: AsRef<[u8]>,
+    {
+        let header = Header {
+            alg: key.algorithm(),
+            cty: None,
+            ..self.header.clone()
         };
-        Ok(format!(
-            "{header_base64encoded}.{token_base64encoded}.{signature_base64encoded}"
-        ))
+        encode_parts(&header, self.token.as_ref(), key)
     }
 
     /// Decodes a `Signed` token from a url-safe base64 encoded string with no padding.
@@ -93,41 +188,210 @@ impl<T> Signed<T> {
         T: TryFrom<Vec<u8>>,
         <T as TryFrom<Vec<u8>>>::Error: std::error::Error,
     {
-        let parts = s.split('.').collect::<Vec<&str>>();
-
-        match parts.as_slice() {
-            [header_part, token_part, signature_part] => {
-                let signature = BASE64_URL_SAFE_NO_PAD
-                    .decode(signature_part)
-                    .context("signature")?;
-                let mut mac = Hmac::<Sha256>::new_from_slice(secret)
-                    .map_err(|InvalidLength| DecodeError::InvalidKeyLength)?;
-                mac.update(format!("{header_part}.{token_part}").as_bytes());
-                mac.verify_slice(&signature)?;
-
-                let header = {
-                    let bytes = BASE64_URL_SAFE_NO_PAD
-                        .decode(header_part)
-                        .context("header")?;
-
-                    let json_string =
-                        String::from_utf8(bytes).map_err(|_| DecodeError::NonUTF8("header"))?;
-
-                    serde_json::from_str::<Header>(&json_string).context("header")?
-                };
-
-                let token = {
-                    let bytes = BASE64_URL_SAFE_NO_PAD.decode(token_part).context("token")?;
-                    T::try_from(bytes).map_err(DecodeError::TokenFromBytes)?
-                };
-
-                Ok(Self { header, token })
+        Self::decode_with_keys(s, |_kid| Some(secret))
+    }
+
+    /// Decodes a `Signed` token, verifying it with the key that `keys` returns for the
+    /// token's `kid` (`None` for tokens signed without one).
+    pub fn decode_with_keys<K, F>(
+        s: &str,
+        keys: F,
+    ) -> Result<Self, DecodeError<<T as TryFrom<Vec<u8>>>::Error>>
+    where
+        T: TryFrom<Vec<u8>>,
+        <T as TryFrom<Vec<u8>>>::Error: std::error::Error,
+        K: AsRef<[u8]>,
+        F: FnOnce(Option<&str>) -> Option<K>,
+    {
+        Self::decode_with_verifying_keys(s, |kid| {
+            keys(kid).map(|secret| VerifyingKey::hmac(secret.as_ref()))
+        })
+    }
+
+    /// Decodes a `Signed` token signed with any algorithm, e.g. with only the issuer's public key.
+    pub fn decode_verified(
+        s: &str,
+        key: &VerifyingKey,
+    ) -> Result<Self, DecodeError<<T as TryFrom<Vec<u8>>>::Error>>
+    where
+        T: TryFrom<Vec<u8>>,
+        <T as TryFrom<Vec<u8>>>::Error: std::error::Error,
+    {
+        Self::decode_with_verifying_keys(s, |_kid| Some(key))
+    }
+
+    /// Decodes a `Signed` token like [`Signed::decode_with_keys`], with keys of any algorithm.
+    /// The token's `alg` must match the key's, the key decides how the token is verified.
+    pub fn decode_with_verifying_keys<K, F>(
+        s: &str,
+        keys: F,
+    ) -> Result<Self, DecodeError<<T as TryFrom<Vec<u8>>>::Error>>
+    where
+        T: TryFrom<Vec<u8>>,
+        <T as TryFrom<Vec<u8>>>::Error: std::error::Error,
+        K: Borrow<VerifyingKey>,
+        F: FnOnce(Option<&str>) -> Option<K>,
+    {
+        let (header, bytes) = decode_parts(s, keys)?;
+
+        // a serialized token decodes with `Signed::decode_serialized`, not as its raw bytes
+        if header.cty.is_some() {
+            return Err(DecodeError::UnsupportedHeader("cty"));
+        }
+
+        let token = T::try_from(bytes).map_err(DecodeError::TokenFromBytes)?;
+        Ok(Self { header, token })
+    }
+}
+
+/// Signs `header.payload`, both url-safe base64 encoded with no padding.
+fn encode_parts(header: &Header, payload: &[u8], key: &SigningKey) -> Result<String, EncodeError> {
+    let header_json = serde_json::to_string(header).context("header")?;
+    let header_base64encoded = BASE64_URL_SAFE_NO_PAD.encode(header_json);
+    let token_base64encoded = BASE64_URL_SAFE_NO_PAD.encode(payload);
+    let signature_base64encoded = {
+        let signing_input = format!("{header_base64encoded}.{token_base64encoded}");
+        BASE64_URL_SAFE_NO_PAD.encode(key.sign(signing_input.as_bytes())?)
+    };
+    Ok(format!(
+        "{header_base64encoded}.{token_base64encoded}.{signature_base64encoded}"
+    ))
+}
+
+/// Verifies a token in the `header.payload.signature` format, returning its header and payload.
+fn decode_parts<E, K, F>(s: &str, keys: F) -> Result<(Header, Vec<u8>), DecodeError<E>>
+where
+    E: std::error::Error + 'static,
+    K: Borrow<VerifyingKey>,
+    F: FnOnce(Option<&str>) -> Option<K>,
+{
+    let parts = s.split('.').collect::<Vec<&str>>();
+
+    match parts.as_slice() {
+        [header_part, token_part, signature_part] => {
+            // the header selects the key, but is trusted only once the signature checks out
+            let header = {
+                let bytes = BASE64_URL_SAFE_NO_PAD
+                    .decode(header_part)
+                    .context("header")?;
+
+                let json_string =
+                    String::from_utf8(bytes).map_err(|_| DecodeError::NonUTF8("header"))?;
+
+                serde_json::from_str::<Header>(&json_string).context("header")?
+            };
+
+            let key = keys(header.kid.as_deref())
+                .ok_or_else(|| DecodeError::UnknownKey(header.kid.clone()))?;
+            let key = key.borrow();
+
+            if header.alg != key.algorithm() {
+                return Err(DecodeError::AlgorithmMismatch {
+                    expected: key.algorithm(),
+                    found: header.alg,
+                });
             }
-            _ => Err(DecodeError::InvalidFormat),
+
+            let signature = BASE64_URL_SAFE_NO_PAD
+                .decode(signature_part)
+                .context("signature")?;
+            key.verify(format!("{header_part}.{token_part}").as_bytes(), &signature)?;
+
+            let bytes = BASE64_URL_SAFE_NO_PAD.decode(token_part).context("token")?;
+            Ok((header, bytes))
         }
+        _ => Err(DecodeError::InvalidFormat),
     }
 }
 
+/// What [`Signed::token`] expects of a token.
+#[derive(Debug, Clone)]
+pub struct Validation {
+    audience: Option<String>,
+    leeway: Duration,
+    require_jti: bool,
+}
+
+impl Validation {
+    /// Tolerated clock skew between the issuer and the verifier.
+    pub const DEFAULT_LEEWAY: Duration = Duration::from_secs(60);
+
+    /// Expects a token issued for `audience`, among others if it has several.
+    /// Tokens without an audience are rejected.
+    pub fn audience(audience: impl Into<String>) -> Self {
+        Self {
+            audience: Some(audience.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    /// Rejects tokens without a `jti`, e.g. when single use is enforced by the issuer.
+    pub fn require_jti(mut self) -> Self {
+        self.require_jti = true;
+        self
+    }
+
+    /// Checks the claims [`Signed`] and [`Encrypted`] tokens have in common.
+    pub(crate) fn check(
+        &self,
+        exp: OffsetDateTime,
+        nbf: OffsetDateTime,
+        aud: &[String],
+        jti: Option<&str>,
+    ) -> Result<(), ValidationError> {
+        let now = OffsetDateTime::now_utc();
+
+        if exp + self.leeway < now {
+            return Err(TemporalValidityError::Expired { exp, now }.into());
+        }
+
+        if nbf - self.leeway > now {
+            return Err(TemporalValidityError::NotYetValid { nbf, now }.into());
+        }
+
+        let audience_matches = match &self.audience {
+            Some(expected) => aud.contains(expected),
+            None => aud.is_empty(),
+        };
+        if !audience_matches {
+            return Err(ValidationError::AudienceMismatch {
+                expected: self.audience.clone(),
+                found: aud.to_vec(),
+            });
+        }
+
+        if self.require_jti && jti.is_none() {
+            return Err(ValidationError::MissingJti);
+        }
+
+        Ok(())
+    }
+}
+
+/// Expects a token without an audience.
+impl Default for Validation {
+    fn default() -> Self {
+        Self {
+            audience: None,
+            leeway: Self::DEFAULT_LEEWAY,
+            require_jti: false,
+        }
+    }
+}
+
+/// Computes the HMAC-SHA256 of `message` the same way [`Signed`] tokens are signed,
+/// encoded as url-safe base64 with no padding.
+/// Useful for detached signatures, e.g. of webhook payloads.
+pub fn hmac_sha256(secret: &[u8], message: &[u8]) -> Result<String, EncodeError> {
+    let signature_bytes = SigningKey::hmac(secret).sign(message)?;
+    Ok(BASE64_URL_SAFE_NO_PAD.encode(signature_bytes))
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum TemporalValidityError {
     #[error("token expired at {exp} (now: {now})")]
@@ -136,20 +400,45 @@ pub enum TemporalValidityError {
         now: OffsetDateTime,
     },
 
-    #[error("token not valid yet; issued at {iat} (now: {now})")]
+    #[error("token not valid yet; valid from {nbf} (now: {now})")]
     NotYetValid {
-        iat: OffsetDateTime,
+        nbf: OffsetDateTime,
         now: OffsetDateTime,
     },
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum ValidationError {
+    #[error("{0}")]
+    Temporal(#[from] TemporalValidityError),
+
+    #[error(
+        "token issued for audience {found:?}, expected `{}`",
+        .expected.as_deref().unwrap_or("<none>")
+    )]
+    AudienceMismatch {
+        expected: Option<String>,
+        found: Vec<String>,
+    },
+
+    #[error("token has no identifier")]
+    MissingJti,
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum EncodeError {
     #[error("Invalid Key Length")]
     InvalidKeyLength,
 
+    #[error("unable to sign")]
+    Sign,
+
     #[error("{0}")]
     Serde(#[from] contextual::Error<serde_json::Error>),
+
+    #[cfg(feature = "cbor")]
+    #[error("{0}")]
+    Cbor(#[from] contextual::Error<ciborium::ser::Error<std::io::Error>>),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -163,9 +452,24 @@ where
     #[error("Invalid Key Length")]
     InvalidKeyLength,
 
+    #[error("unknown or retired key `{}`", .0.as_deref().unwrap_or("<none>"))]
+    UnknownKey(Option<String>),
+
+    #[error("token signed with {found}, but the key is for {expected}")]
+    AlgorithmMismatch {
+        expected: Algorithm,
+        found: Algorithm,
+    },
+
     #[error("{0}")]
     MacMismatch(#[from] MacError),
 
+    #[error("signature does not match")]
+    SignatureMismatch,
+
+    #[error("unsupported `{0}` header")]
+    UnsupportedHeader(&'static str),
+
     #[error("Non-UTF8 sequence for {0}")]
     NonUTF8(&'static str),
 
@@ -178,3 +482,164 @@ where
     #[error("failed to build token from bytes")]
     TokenFromBytes(#[source] E),
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use time::OffsetDateTime;
+
+    use super::{Signed, TemporalValidityError, Validation, ValidationError};
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn check(
+        validation: &Validation,
+        exp: OffsetDateTime,
+        nbf: OffsetDateTime,
+    ) -> Result<(), ValidationError> {
+        validation.check(exp, nbf, &[], None)
+    }
+
+    #[test]
+    fn expiry_within_leeway() {
+        let now = OffsetDateTime::now_utc();
+        let validation = Validation::default();
+        let leeway = Validation::DEFAULT_LEEWAY;
+
+        assert!(check(&validation, now - leeway + SECOND, now).is_ok());
+        assert!(matches!(
+            check(&validation, now - leeway - SECOND, now),
+            Err(ValidationError::Temporal(
+                TemporalValidityError::Expired { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn not_before_within_leeway() {
+        let now = OffsetDateTime::now_utc();
+        let exp = now + Duration::from_secs(3600);
+        let validation = Validation::default();
+        let leeway = Validation::DEFAULT_LEEWAY;
+
+        assert!(check(&validation, exp, now + leeway - SECOND).is_ok());
+        assert!(matches!(
+            check(&validation, exp, now + leeway + SECOND),
+            Err(ValidationError::Temporal(
+                TemporalValidityError::NotYetValid { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn leeway_is_configurable() {
+        let now = OffsetDateTime::now_utc();
+        let strict = Validation::default().with_leeway(Duration::ZERO);
+        let lenient = Validation::default().with_leeway(Duration::from_secs(600));
+
+        assert!(check(&strict, now - SECOND, now - Duration::from_secs(60)).is_err());
+        assert!(check(&strict, now + 10 * SECOND, now + 10 * SECOND).is_err());
+        assert!(check(&lenient, now - Duration::from_secs(300), now).is_ok());
+        assert!(
+            check(
+                &lenient,
+                now + Duration::from_secs(3600),
+                now + Duration::from_secs(300)
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn audience() {
+        let now = OffsetDateTime::now_utc();
+        let exp = now + Duration::from_secs(60);
+        let validation = Validation::audience("email-verification");
+        let aud = |aud: &[&str]| aud.iter().map(|aud| aud.to_string()).collect::<Vec<_>>();
+
+        assert!(
+            validation
+                .check(exp, now, &aud(&["email-verification"]), None)
+                .is_ok()
+        );
+        assert!(
+            validation
+                .check(exp, now, &aud(&["gateway", "email-verification"]), None)
+                .is_ok()
+        );
+
+        for found in [aud(&[]), aud(&["password-reset"])] {
+            assert!(matches!(
+                validation.check(exp, now, &found, None),
+                Err(ValidationError::AudienceMismatch { expected: Some(_), found: f }) if f == found
+            ));
+        }
+
+        // a token bound to a purpose is no token for no purpose
+        assert!(matches!(
+            Validation::default().check(exp, now, &aud(&["password-reset"]), None),
+            Err(ValidationError::AudienceMismatch { expected: None, .. })
+        ));
+    }
+
+    #[test]
+    fn jti() {
+        let now = OffsetDateTime::now_utc();
+        let exp = now + Duration::from_secs(60);
+
+        assert!(Validation::default().check(exp, now, &[], None).is_ok());
+        assert!(matches!(
+            Validation::default()
+                .require_jti()
+                .check(exp, now, &[], None),
+            Err(ValidationError::MissingJti)
+        ));
+        assert!(
+            Validation::default()
+                .require_jti()
+                .check(exp, now, &[], Some("42"))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn token_is_validated_against_its_claims() {
+        let now = OffsetDateTime::now_utc();
+        let signed = || {
+            Signed::new(b"token".to_vec())
+                .with_audience("email-verification")
+                .with_jti("42")
+        };
+
+        assert!(
+            signed()
+                .token(&Validation::audience("email-verification").require_jti())
+                .is_ok()
+        );
+        assert!(
+            signed()
+                .token(&Validation::audience("password-reset"))
+                .is_err()
+        );
+
+        let not_yet = signed().with_not_before(now + Duration::from_secs(120));
+        assert_eq!(not_yet.not_before(), now + Duration::from_secs(120));
+        assert!(matches!(
+            not_yet
+                .clone()
+                .token(&Validation::audience("email-verification")),
+            Err(ValidationError::Temporal(
+                TemporalValidityError::NotYetValid { .. }
+            ))
+        ));
+        assert!(
+            not_yet
+                .token(
+                    &Validation::audience("email-verification")
+                        .with_leeway(Duration::from_secs(180))
+                )
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,275 @@
+//! Structured tokens in the original `header.token.signature` format, for claims (e.g. of an
+//! invitation or a password reset) that have no byte representation of their own.
+//!
+//! The token is serialized as JSON, or as CBOR with the `cbor` feature for shorter tokens. The
+//! header's `cty` records which, so that decoding needs no hint.
+
+use std::borrow::Borrow;
+
+use contextual::Context;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{DecodeError, EncodeError, Header, Signed, SigningKey, VerifyingKey};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadFormat {
+    Json,
+
+    #[cfg(feature = "cbor")]
+    Cbor,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PayloadError {
+    #[error("token is raw bytes, not a serialized payload")]
+    NotSerialized,
+
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+
+    #[cfg(feature = "cbor")]
+    #[error("{0}")]
+    Cbor(#[from] ciborium::de::Error<std::io::Error>),
+}
+
+impl<T> Signed<T> {
+    /// Encodes the `Signed` token like [`Signed::encode_with`], serializing the token in `format`.
+    pub fn encode_serialized(
+        &self,
+        key: &SigningKey,
+        format: PayloadFormat,
+    ) -> Result<String, EncodeError>
+    where
+        T: Serialize,
+    {
+        let payload = serialize(&self.token, format)?;
+        let header = Header {
+            alg: key.algorithm(),
+            cty: Some(format),
+            ..self.header.clone()
+        };
+        crate::encode_parts(&header, &payload, key)
+    }
+
+    /// Decodes a token encoded by [`Signed::encode_serialized`].
+    pub fn decode_serialized(s: &str, key: &VerifyingKey) -> Result<Self, DecodeError<PayloadError>>
+    where
+        T: DeserializeOwned,
+    {
+        Self::decode_serialized_with_keys(s, |_kid| Some(key))
+    }
+
+    /// Decodes a token encoded by [`Signed::encode_serialized`] like
+    /// [`Signed::decode_with_verifying_keys`], picking the key by `kid`.
+    pub fn decode_serialized_with_keys<K, F>(
+        s: &str,
+        keys: F,
+    ) -> Result<Self, DecodeError<PayloadError>>
+    where
+        T: DeserializeOwned,
+        K: Borrow<VerifyingKey>,
+        F: FnOnce(Option<&str>) -> Option<K>,
+    {
+        let (header, bytes) = crate::decode_parts(s, keys)?;
+
+        let token = match header.cty {
+            Some(format) => deserialize(&bytes, format),
+            None => Err(PayloadError::NotSerialized),
+        }
+        .map_err(DecodeError::TokenFromBytes)?;
+
+        Ok(Self { header, token })
+    }
+}
+
+pub(crate) fn serialize<T: Serialize>(
+    token: &T,
+    format: PayloadFormat,
+) -> Result<Vec<u8>, EncodeError> {
+    match format {
+        PayloadFormat::Json => Ok(serde_json::to_vec(token).context("token")?),
+        #[cfg(feature = "cbor")]
+        PayloadFormat::Cbor => {
+            let mut bytes = Vec::new();
+            ciborium::into_writer(token, &mut bytes).context("token")?;
+            Ok(bytes)
+        }
+    }
+}
+
+pub(crate) fn deserialize<T: DeserializeOwned>(
+    bytes: &[u8],
+    format: PayloadFormat,
+) -> Result<T, PayloadError> {
+    match format {
+        PayloadFormat::Json => Ok(serde_json::from_slice(bytes)?),
+        #[cfg(feature = "cbor")]
+        PayloadFormat::Cbor => Ok(ciborium::from_reader(bytes)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+    use serde::{Deserialize, Serialize};
+
+    use crate::{DecodeError, PayloadError, PayloadFormat, Signed, SigningKey, VerifyingKey};
+
+    const SECRET: &[u8] = b"secret";
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct PasswordReset {
+        user_id: i64,
+        scopes: Vec<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct MfaChallenge {
+        #[allow(dead_code)]
+        challenge: String,
+    }
+
+    fn reset() -> PasswordReset {
+        PasswordReset {
+            user_id: 42,
+            scopes: vec!["password".to_string()],
+        }
+    }
+
+    fn round_trip(format: PayloadFormat) {
+        let key = SigningKey::hmac(SECRET);
+        let token = Signed::new(reset())
+            .with_audience("password-reset")
+            .encode_serialized(&key, format)
+            .unwrap();
+        let signed =
+            Signed::<PasswordReset>::decode_serialized(&token, &key.verifying_key()).unwrap();
+
+        assert_eq!(signed.audiences(), ["password-reset"]);
+        assert_eq!(
+            signed
+                .token(&crate::Validation::audience("password-reset"))
+                .unwrap(),
+            reset()
+        );
+    }
+
+    #[test]
+    fn json_round_trip() {
+        round_trip(PayloadFormat::Json);
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_round_trip() {
+        round_trip(PayloadFormat::Cbor);
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_is_shorter() {
+        let key = SigningKey::hmac(SECRET);
+        let encode = |format| {
+            Signed::new(reset())
+                .encode_serialized(&key, format)
+                .unwrap()
+                .len()
+        };
+
+        assert!(encode(PayloadFormat::Cbor) < encode(PayloadFormat::Json));
+    }
+
+    #[test]
+    fn header_records_the_format() {
+        let token = Signed::new(reset())
+            .encode_serialized(&SigningKey::hmac(SECRET), PayloadFormat::Json)
+            .unwrap();
+        let (header, _) = token.split_once('.').unwrap();
+        let header: serde_json::Value =
+            serde_json::from_slice(&BASE64_URL_SAFE_NO_PAD.decode(header).unwrap()).unwrap();
+
+        assert_eq!(header["cty"], "json");
+    }
+
+    #[test]
+    fn rejects_raw_tokens() {
+        let token = Signed::new(b"raw".to_vec()).encode(SECRET).unwrap();
+
+        assert!(matches!(
+            Signed::<PasswordReset>::decode_serialized(&token, &VerifyingKey::hmac(SECRET)),
+            Err(DecodeError::TokenFromBytes(PayloadError::NotSerialized))
+        ));
+    }
+
+    #[test]
+    fn rejects_another_payload() {
+        let key = SigningKey::hmac(SECRET);
+        let token = Signed::new(reset())
+            .encode_serialized(&key, PayloadFormat::Json)
+            .unwrap();
+
+        assert!(matches!(
+            Signed::<MfaChallenge>::decode_serialized(&token, &key.verifying_key()),
+            Err(DecodeError::TokenFromBytes(PayloadError::Json(_)))
+        ));
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn rejects_another_cbor_payload() {
+        let key = SigningKey::hmac(SECRET);
+        let token = Signed::new(reset())
+            .encode_serialized(&key, PayloadFormat::Cbor)
+            .unwrap();
+
+        assert!(matches!(
+            Signed::<MfaChallenge>::decode_serialized(&token, &key.verifying_key()),
+            Err(DecodeError::TokenFromBytes(PayloadError::Cbor(_)))
+        ));
+    }
+
+    /// Without the `cbor` feature, a CBOR token fails to decode rather than being misread.
+    #[cfg(not(feature = "cbor"))]
+    #[test]
+    fn rejects_unknown_format() {
+        let header = BASE64_URL_SAFE_NO_PAD.encode(
+            serde_json::json!({
+                "alg": "HS256",
+                "iat": time::OffsetDateTime::now_utc(),
+                "exp": time::OffsetDateTime::now_utc(),
+                "cty": "cbor",
+            })
+            .to_string(),
+        );
+        let payload = BASE64_URL_SAFE_NO_PAD.encode([0xa0]);
+        let signature =
+            crate::hmac_sha256(SECRET, format!("{header}.{payload}").as_bytes()).unwrap();
+
+        assert!(matches!(
+            Signed::<PasswordReset>::decode_serialized(
+                &format!("{header}.{payload}.{signature}"),
+                &VerifyingKey::hmac(SECRET)
+            ),
+            Err(DecodeError::Serde(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_tampered_payload() {
+        let key = SigningKey::hmac(SECRET);
+        let token = Signed::new(reset())
+            .encode_serialized(&key, PayloadFormat::Json)
+            .unwrap();
+        let [header, _, signature] = token.split('.').collect::<Vec<_>>().try_into().unwrap();
+        let payload = BASE64_URL_SAFE_NO_PAD.encode(r#"{"user_id":1,"scopes":["password"]}"#);
+
+        assert!(matches!(
+            Signed::<PasswordReset>::decode_serialized(
+                &format!("{header}.{payload}.{signature}"),
+                &key.verifying_key()
+            ),
+            Err(DecodeError::MacMismatch(_))
+        ));
+    }
+}
@@ -0,0 +1,24 @@
+CREATE TABLE kv_secrets(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    path TEXT NOT NULL UNIQUE,
+    current_version INTEGER NOT NULL DEFAULT 0,
+    -- older versions are pruned once a secret has more than this
+    max_versions INTEGER NOT NULL DEFAULT 10,
+    -- JSON object of unencrypted, caller-defined metadata
+    custom_metadata TEXT NOT NULL DEFAULT '{}',
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    CHECK (max_versions > 0)
+);
+
+CREATE TABLE kv_versions(
+    secret_id INTEGER NOT NULL,
+    version INTEGER NOT NULL,
+    -- nonce and XChaCha20-Poly1305 ciphertext of the JSON data
+    ciphertext BLOB NOT NULL,
+    created_at DATETIME NOT NULL,
+    -- soft deleted versions keep their ciphertext and can be undeleted
+    deleted_at DATETIME,
+    PRIMARY KEY (secret_id, version),
+    FOREIGN KEY (secret_id) REFERENCES kv_secrets (id) ON DELETE CASCADE
+);
@@ -0,0 +1,48 @@
+CREATE TABLE pki_issuers(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    -- NULL for root CAs
+    parent_id INTEGER,
+    certificate_pem TEXT NOT NULL,
+    -- nonce and XChaCha20-Poly1305 ciphertext of the PKCS#8 private key
+    key_ciphertext BLOB NOT NULL,
+    not_after DATETIME NOT NULL,
+    -- bumped on every revocation, published in the CRL
+    crl_number INTEGER NOT NULL DEFAULT 1,
+    created_at DATETIME NOT NULL,
+    FOREIGN KEY (parent_id) REFERENCES pki_issuers (id)
+);
+
+CREATE TABLE pki_roles(
+    name TEXT NOT NULL PRIMARY KEY,
+    issuer_id INTEGER NOT NULL,
+    -- JSON array of DNS names that may be requested
+    allowed_domains TEXT NOT NULL DEFAULT '[]',
+    allow_subdomains BOOLEAN NOT NULL DEFAULT 0,
+    allow_ip_sans BOOLEAN NOT NULL DEFAULT 0,
+    max_ttl_sec INTEGER NOT NULL,
+    server_auth BOOLEAN NOT NULL DEFAULT 1,
+    client_auth BOOLEAN NOT NULL DEFAULT 1,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    FOREIGN KEY (issuer_id) REFERENCES pki_issuers (id),
+    CHECK (max_ttl_sec > 0)
+);
+
+CREATE TABLE pki_certificates(
+    -- lowercase hex of the serial number
+    serial TEXT NOT NULL PRIMARY KEY,
+    issuer_id INTEGER NOT NULL,
+    role TEXT NOT NULL,
+    common_name TEXT,
+    certificate_pem TEXT NOT NULL,
+    not_before DATETIME NOT NULL,
+    not_after DATETIME NOT NULL,
+    revoked_at DATETIME,
+    -- RFC 5280 CRLReason code
+    revocation_reason INTEGER,
+    created_at DATETIME NOT NULL,
+    FOREIGN KEY (issuer_id) REFERENCES pki_issuers (id)
+);
+
+CREATE INDEX pki_certificates_issuer_revoked ON pki_certificates (issuer_id, revoked_at);
@@ -0,0 +1,24 @@
+CREATE TABLE transit_keys(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    -- `xchacha20-poly1305` or `ed25519`
+    key_type TEXT NOT NULL,
+    latest_version INTEGER NOT NULL DEFAULT 1,
+    -- ciphertexts and signatures of older versions are rejected
+    min_decryption_version INTEGER NOT NULL DEFAULT 1,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    CHECK (min_decryption_version BETWEEN 1 AND latest_version)
+);
+
+CREATE TABLE transit_key_versions(
+    key_id INTEGER NOT NULL,
+    version INTEGER NOT NULL,
+    -- nonce and XChaCha20-Poly1305 ciphertext of the key material
+    key_ciphertext BLOB NOT NULL,
+    -- of signing keys, served with the key's metadata
+    public_key BLOB,
+    created_at DATETIME NOT NULL,
+    PRIMARY KEY (key_id, version),
+    FOREIGN KEY (key_id) REFERENCES transit_keys (id) ON DELETE CASCADE
+);
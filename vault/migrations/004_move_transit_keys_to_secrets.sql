@@ -0,0 +1,2 @@
+-- the key material lives in the secrets directory, sealed under the master key
+ALTER TABLE transit_key_versions DROP COLUMN key_ciphertext;
@@ -0,0 +1,109 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{MethodRouter, delete},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    authorizer::AuthorizationError,
+    kv::{self, PathError},
+};
+
+pub const PATH: &str = "/kv/data/{*path}";
+
+pub fn method_router() -> MethodRouter<AppState> {
+    delete(handler)
+}
+
+/// Soft deletes the current version of the secret; see `/kv/undelete` to restore it.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%path), skip_all, ret))]
+pub async fn handler(
+    State(AppState {
+        pool, authorizer, ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Path(path): Path<String>,
+) -> Result<StatusCode, Error> {
+    kv::validate_path(&path)?;
+    authorizer
+        .require(&headers, "delete", &format!("/kv/data/{path}"))
+        .await?;
+
+    let now = OffsetDateTime::now_utc();
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE kv_versions
+        SET deleted_at = COALESCE(deleted_at, ?1)
+        WHERE (secret_id, version) IN (
+            SELECT id, current_version FROM kv_secrets WHERE path = ?2
+        )
+        "#,
+        now,
+        path
+    )
+    .execute(&pool)
+    .await
+    .context("soft delete current version")?;
+
+    if result.rows_affected() == 0 {
+        return Err(Error::NotFound(path));
+    }
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Path(#[from] PathError),
+
+    #[error("{0}")]
+    Authorization(#[from] AuthorizationError),
+
+    #[error("secret `{0}` not found")]
+    NotFound(String),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Path(err) => err.kind(),
+            Error::Authorization(err) => err.kind(),
+            Error::NotFound(_) => "kv.not-found",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Path(err) => err.into_response(),
+            Error::Authorization(err) => err.into_response(),
+            Error::NotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+pub mod delete;
+pub mod write;
+
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    routing::{MethodRouter, get},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    authorizer::AuthorizationError,
+    kv::{self, PathError, VersionMetadata},
+    sealing::{self, OpenError},
+};
+
+pub const PATH: &str = "/kv/data/{*path}";
+
+#[derive(Deserialize, Debug)]
+pub struct QueryParams {
+    /// defaults to the current version
+    pub version: Option<i64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResponseBody {
+    pub data: serde_json::Map<String, serde_json::Value>,
+    pub metadata: VersionMetadata,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    get(handler)
+}
+
+/// Reads a version of the secret, the current one unless `version` is given.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%path, ?params), skip_all))]
+pub async fn handler(
+    State(AppState {
+        pool,
+        master_key,
+        authorizer,
+        ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Path(path): Path<String>,
+    Query(params): Query<QueryParams>,
+) -> Result<Json<ResponseBody>, Error> {
+    kv::validate_path(&path)?;
+    authorizer
+        .require(&headers, "get", &format!("/kv/data/{path}"))
+        .await?;
+
+    let version = sqlx::query!(
+        r#"
+        SELECT
+            v.version,
+            v.ciphertext,
+            v.created_at as "created_at: OffsetDateTime",
+            v.deleted_at as "deleted_at: OffsetDateTime"
+        FROM kv_versions v
+        INNER JOIN kv_secrets s ON s.id = v.secret_id
+        WHERE s.path = ?1 AND v.version = COALESCE(?2, s.current_version)
+        "#,
+        path,
+        params.version
+    )
+    .fetch_optional(&pool)
+    .await
+    .context("select secret version")?
+    .ok_or_else(|| Error::NotFound(path.clone()))?;
+
+    if version.deleted_at.is_some() {
+        return Err(Error::Deleted(version.version));
+    }
+
+    let plaintext = sealing::open(&master_key, &path, version.version, &version.ciphertext)?;
+    let data = serde_json::from_slice(&plaintext)?;
+
+    Ok(Json(ResponseBody {
+        data,
+        metadata: VersionMetadata {
+            version: version.version,
+            created_at: version.created_at,
+            deleted_at: version.deleted_at,
+        },
+    }))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Path(#[from] PathError),
+
+    #[error("{0}")]
+    Authorization(#[from] AuthorizationError),
+
+    #[error("secret `{0}` or version not found")]
+    NotFound(String),
+
+    #[error("version {0} is deleted")]
+    Deleted(i64),
+
+    #[error("{0}")]
+    Open(#[from] OpenError),
+
+    #[error("stored secret data is not a JSON object :: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Path(err) => err.kind(),
+            Error::Authorization(err) => err.kind(),
+            Error::NotFound(_) => "kv.not-found",
+            Error::Deleted(_) => "kv.version.deleted",
+            Error::Open(_) => "kv.open",
+            Error::Json(_) => "kv.json",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Path(err) => err.into_response(),
+            Error::Authorization(err) => err.into_response(),
+            Error::NotFound(_) | Error::Deleted(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Open(_) | Error::Json(_) | Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
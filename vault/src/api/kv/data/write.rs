@@ -0,0 +1,194 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{MethodRouter, put},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+use serde::Deserialize;
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    authorizer::AuthorizationError,
+    kv::{self, PathError, VersionMetadata},
+    sealing,
+};
+
+pub const PATH: &str = "/kv/data/{*path}";
+
+#[derive(Deserialize, Debug)]
+pub struct RequestBody {
+    pub data: serde_json::Map<String, serde_json::Value>,
+
+    /// Check-and-set: the write only succeeds if this is the current version.
+    /// `0` only succeeds if the secret does not exist yet.
+    pub cas: Option<i64>,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    put(handler)
+}
+
+/// Writes a new version of the secret, creating the secret on its first write.
+/// Versions beyond the secret's `max_versions` are pruned.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%path, cas = ?request_body.cas), skip_all))]
+pub async fn handler(
+    State(AppState {
+        pool,
+        master_key,
+        authorizer,
+        ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Path(path): Path<String>,
+    Json(request_body): Json<RequestBody>,
+) -> Result<Json<VersionMetadata>, Error> {
+    kv::validate_path(&path)?;
+    authorizer
+        .require(&headers, "put", &format!("/kv/data/{path}"))
+        .await?;
+
+    let plaintext = zeroize::Zeroizing::new(
+        serde_json::to_vec(&request_body.data).expect("a JSON map always serializes"),
+    );
+    let now = OffsetDateTime::now_utc();
+
+    let mut tx = pool
+        .begin()
+        .await
+        .context("begin transaction :: write secret")?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO kv_secrets (path, created_at, updated_at)
+        VALUES (?1, ?2, ?2)
+        ON CONFLICT (path) DO NOTHING
+        "#,
+        path,
+        now
+    )
+    .execute(&mut *tx)
+    .await
+    .context("insert secret")?;
+
+    let secret = sqlx::query!(
+        r#"
+        SELECT id as "id!", current_version
+        FROM kv_secrets
+        WHERE path = ?
+        "#,
+        path
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .context("select secret")?;
+
+    if let Some(cas) = request_body.cas
+        && cas != secret.current_version
+    {
+        return Err(Error::CasMismatch {
+            expected: cas,
+            current: secret.current_version,
+        });
+    }
+
+    let version = secret.current_version + 1;
+    let ciphertext = sealing::seal(&master_key, &path, version, &plaintext);
+
+    sqlx::query!(
+        r#"
+        INSERT INTO kv_versions (secret_id, version, ciphertext, created_at)
+        VALUES (?, ?, ?, ?)
+        "#,
+        secret.id,
+        version,
+        ciphertext,
+        now
+    )
+    .execute(&mut *tx)
+    .await
+    .context("insert secret version")?;
+
+    sqlx::query!(
+        r#"
+        UPDATE kv_secrets
+        SET current_version = ?, updated_at = ?
+        WHERE id = ?
+        "#,
+        version,
+        now,
+        secret.id
+    )
+    .execute(&mut *tx)
+    .await
+    .context("update current version")?;
+
+    kv::prune(&mut *tx, secret.id)
+        .await
+        .context("prune secret versions")?;
+
+    tx.commit()
+        .await
+        .context("commit transaction :: write secret")?;
+
+    #[cfg(feature = "tracing")]
+    tracing::info!(version, "secret written");
+
+    Ok(Json(VersionMetadata {
+        version,
+        created_at: now,
+        deleted_at: None,
+    }))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Path(#[from] PathError),
+
+    #[error("{0}")]
+    Authorization(#[from] AuthorizationError),
+
+    #[error("check-and-set expected version {expected}, but the current version is {current}")]
+    CasMismatch { expected: i64, current: i64 },
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Path(err) => err.kind(),
+            Error::Authorization(err) => err.kind(),
+            Error::CasMismatch { .. } => "kv.cas.mismatch",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Path(err) => err.into_response(),
+            Error::Authorization(err) => err.into_response(),
+            Error::CasMismatch { .. } => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::CONFLICT, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
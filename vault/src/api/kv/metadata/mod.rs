@@ -0,0 +1,161 @@
+pub mod delete;
+pub mod update;
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{MethodRouter, get},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+use serde::Serialize;
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    authorizer::AuthorizationError,
+    kv::{self, PathError, VersionMetadata},
+};
+
+pub const PATH: &str = "/kv/metadata/{*path}";
+
+#[derive(Serialize, Debug)]
+pub struct Metadata {
+    pub path: String,
+
+    pub current_version: i64,
+
+    pub max_versions: i64,
+
+    /// caller-defined, stored unencrypted
+    pub custom_metadata: serde_json::Map<String, serde_json::Value>,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+
+    /// oldest first, without destroyed and pruned versions
+    pub versions: Vec<VersionMetadata>,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    get(handler)
+}
+
+/// Reads the secret's metadata and version history, never its data.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%path), skip_all))]
+pub async fn handler(
+    State(AppState {
+        pool, authorizer, ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Path(path): Path<String>,
+) -> Result<Json<Metadata>, Error> {
+    kv::validate_path(&path)?;
+    authorizer
+        .require(&headers, "get", &format!("/kv/metadata/{path}"))
+        .await?;
+
+    let secret = sqlx::query!(
+        r#"
+        SELECT
+            id as "id!",
+            current_version,
+            max_versions,
+            custom_metadata,
+            created_at as "created_at: OffsetDateTime",
+            updated_at as "updated_at: OffsetDateTime"
+        FROM kv_secrets
+        WHERE path = ?
+        "#,
+        path
+    )
+    .fetch_optional(&pool)
+    .await
+    .context("select secret")?
+    .ok_or_else(|| Error::NotFound(path.clone()))?;
+
+    let versions = sqlx::query_as!(
+        VersionMetadata,
+        r#"
+        SELECT
+            version,
+            created_at as "created_at: OffsetDateTime",
+            deleted_at as "deleted_at: OffsetDateTime"
+        FROM kv_versions
+        WHERE secret_id = ?
+        ORDER BY version
+        "#,
+        secret.id
+    )
+    .fetch_all(&pool)
+    .await
+    .context("select secret versions")?;
+
+    Ok(Json(Metadata {
+        path,
+        current_version: secret.current_version,
+        max_versions: secret.max_versions,
+        custom_metadata: serde_json::from_str(&secret.custom_metadata)?,
+        created_at: secret.created_at,
+        updated_at: secret.updated_at,
+        versions,
+    }))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Path(#[from] PathError),
+
+    #[error("{0}")]
+    Authorization(#[from] AuthorizationError),
+
+    #[error("secret `{0}` not found")]
+    NotFound(String),
+
+    #[error("stored custom metadata is not a JSON object :: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Path(err) => err.kind(),
+            Error::Authorization(err) => err.kind(),
+            Error::NotFound(_) => "kv.not-found",
+            Error::Json(_) => "kv.json",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Path(err) => err.into_response(),
+            Error::Authorization(err) => err.into_response(),
+            Error::NotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Json(_) | Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{MethodRouter, put},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+use serde::Deserialize;
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    authorizer::AuthorizationError,
+    kv::{self, PathError},
+};
+
+pub const PATH: &str = "/kv/metadata/{*path}";
+
+const MAX_MAX_VERSIONS: i64 = 1000;
+
+#[derive(Deserialize, Debug)]
+pub struct RequestBody {
+    /// how many versions are kept, between 1 and 1000; unchanged when absent
+    pub max_versions: Option<i64>,
+
+    /// replaces the custom metadata; unchanged when absent
+    pub custom_metadata: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    put(handler)
+}
+
+/// Updates the secret's settings, creating the secret (without versions) if it does not exist.
+/// Lowering `max_versions` prunes the versions beyond it.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%path, max_versions = ?request_body.max_versions), skip_all, ret))]
+pub async fn handler(
+    State(AppState {
+        pool, authorizer, ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Path(path): Path<String>,
+    Json(request_body): Json<RequestBody>,
+) -> Result<StatusCode, Error> {
+    kv::validate_path(&path)?;
+
+    if let Some(max_versions) = request_body.max_versions
+        && !(1..=MAX_MAX_VERSIONS).contains(&max_versions)
+    {
+        return Err(Error::InvalidMaxVersions(max_versions));
+    }
+
+    authorizer
+        .require(&headers, "put", &format!("/kv/metadata/{path}"))
+        .await?;
+
+    let custom_metadata = request_body
+        .custom_metadata
+        .map(|custom_metadata| serde_json::Value::Object(custom_metadata).to_string());
+    let now = OffsetDateTime::now_utc();
+
+    let mut tx = pool
+        .begin()
+        .await
+        .context("begin transaction :: update metadata")?;
+
+    let secret_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO kv_secrets (path, max_versions, custom_metadata, created_at, updated_at)
+        VALUES (?1, COALESCE(?2, 10), COALESCE(?3, '{}'), ?4, ?4)
+        ON CONFLICT (path) DO UPDATE SET
+            max_versions = COALESCE(?2, max_versions),
+            custom_metadata = COALESCE(?3, custom_metadata),
+            updated_at = ?4
+        RETURNING id as "id!"
+        "#,
+        path,
+        request_body.max_versions,
+        custom_metadata,
+        now
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .context("upsert secret metadata")?;
+
+    kv::prune(&mut *tx, secret_id)
+        .await
+        .context("prune secret versions")?;
+
+    tx.commit()
+        .await
+        .context("commit transaction :: update metadata")?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Path(#[from] PathError),
+
+    #[error("max_versions must be between 1 and {MAX_MAX_VERSIONS}, got {0}")]
+    InvalidMaxVersions(i64),
+
+    #[error("{0}")]
+    Authorization(#[from] AuthorizationError),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Path(err) => err.kind(),
+            Error::InvalidMaxVersions(_) => "kv.max-versions.invalid",
+            Error::Authorization(err) => err.kind(),
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Path(err) => err.into_response(),
+            Error::Authorization(err) => err.into_response(),
+            Error::InvalidMaxVersions(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub mod data;
+pub mod delete;
+pub mod destroy;
+pub mod metadata;
+pub mod undelete;
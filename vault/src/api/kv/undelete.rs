@@ -0,0 +1,125 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{MethodRouter, post},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+
+use crate::{
+    AppState,
+    authorizer::AuthorizationError,
+    kv::{self, PathError, VersionsBody, VersionsError},
+};
+
+pub const PATH: &str = "/kv/undelete/{*path}";
+
+pub fn method_router() -> MethodRouter<AppState> {
+    post(handler)
+}
+
+/// Restores soft deleted versions of the secret.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%path, ?request_body), skip_all, ret))]
+pub async fn handler(
+    State(AppState {
+        pool, authorizer, ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Path(path): Path<String>,
+    Json(request_body): Json<VersionsBody>,
+) -> Result<StatusCode, Error> {
+    kv::validate_path(&path)?;
+    request_body.validate()?;
+    authorizer
+        .require(&headers, "post", &format!("/kv/undelete/{path}"))
+        .await?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .context("begin transaction :: undelete versions")?;
+
+    let secret_id =
+        sqlx::query_scalar!(r#"SELECT id as "id!" FROM kv_secrets WHERE path = ?"#, path)
+            .fetch_optional(&mut *tx)
+            .await
+            .context("select secret")?
+            .ok_or_else(|| Error::NotFound(path.clone()))?;
+
+    for version in request_body.versions {
+        sqlx::query!(
+            r#"
+            UPDATE kv_versions
+            SET deleted_at = NULL
+            WHERE secret_id = ? AND version = ?
+            "#,
+            secret_id,
+            version
+        )
+        .execute(&mut *tx)
+        .await
+        .context("undelete version")?;
+    }
+
+    tx.commit()
+        .await
+        .context("commit transaction :: undelete versions")?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Path(#[from] PathError),
+
+    #[error("{0}")]
+    Versions(#[from] VersionsError),
+
+    #[error("{0}")]
+    Authorization(#[from] AuthorizationError),
+
+    #[error("secret `{0}` not found")]
+    NotFound(String),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Path(err) => err.kind(),
+            Error::Versions(err) => err.kind(),
+            Error::Authorization(err) => err.kind(),
+            Error::NotFound(_) => "kv.not-found",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Path(err) => err.into_response(),
+            Error::Versions(err) => err.into_response(),
+            Error::Authorization(err) => err.into_response(),
+            Error::NotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod kv;
+pub mod pki;
+pub mod transit;
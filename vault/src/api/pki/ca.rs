@@ -0,0 +1,88 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{MethodRouter, get},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::{StatusCode, header};
+
+use crate::{
+    AppState,
+    pki::{self, ParamsError},
+};
+
+pub const PATH: &str = "/pki/ca/{issuer}";
+
+pub fn method_router() -> MethodRouter<AppState> {
+    get(handler)
+}
+
+/// Serves the issuer's PEM certificate, the trust anchor services configure for mTLS.
+/// Public, as certificates hold nothing secret.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%issuer), skip_all))]
+pub async fn handler(
+    State(AppState { pool, .. }): State<AppState>,
+    Path(issuer): Path<String>,
+) -> Result<impl IntoResponse, Error> {
+    pki::validate_name(&issuer)?;
+
+    let certificate = sqlx::query_scalar!(
+        "SELECT certificate_pem FROM pki_issuers WHERE name = ?",
+        issuer
+    )
+    .fetch_optional(&pool)
+    .await
+    .context("select issuer certificate")?
+    .ok_or_else(|| Error::NotFound(issuer.clone()))?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/x-pem-file")],
+        certificate,
+    ))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Params(#[from] ParamsError),
+
+    #[error("issuer `{0}` not found")]
+    NotFound(String),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Params(err) => err.kind(),
+            Error::NotFound(_) => "pki.issuer.not-found",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Params(err) => err.into_response(),
+            Error::NotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
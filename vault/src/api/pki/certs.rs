@@ -0,0 +1,139 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{MethodRouter, get},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+use serde::Serialize;
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    authorizer::AuthorizationError,
+    pki::{self, ParamsError},
+};
+
+pub const PATH: &str = "/pki/certs/{serial}";
+
+#[derive(Serialize, Debug)]
+pub struct IssuedCertificate {
+    pub serial: String,
+
+    pub issuer: String,
+
+    pub role: String,
+
+    pub common_name: Option<String>,
+
+    /// PEM encoded
+    pub certificate: String,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub not_before: OffsetDateTime,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub not_after: OffsetDateTime,
+
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub revoked_at: Option<OffsetDateTime>,
+
+    /// RFC 5280 CRL reason code
+    pub revocation_reason: Option<i64>,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    get(handler)
+}
+
+/// Looks up an issued certificate and its revocation status by serial.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%serial), skip_all))]
+pub async fn handler(
+    State(AppState {
+        pool, authorizer, ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Path(serial): Path<String>,
+) -> Result<Json<IssuedCertificate>, Error> {
+    let serial = pki::normalize_serial(&serial)?;
+    authorizer
+        .require(&headers, "get", &format!("/pki/certs/{serial}"))
+        .await?;
+
+    let certificate = sqlx::query_as!(
+        IssuedCertificate,
+        r#"
+        SELECT
+            serial,
+            pki_issuers.name as "issuer!",
+            role,
+            common_name,
+            pki_certificates.certificate_pem as certificate,
+            not_before as "not_before: OffsetDateTime",
+            pki_certificates.not_after as "not_after: OffsetDateTime",
+            revoked_at as "revoked_at: OffsetDateTime",
+            revocation_reason
+        FROM pki_certificates
+        INNER JOIN pki_issuers ON pki_issuers.id = pki_certificates.issuer_id
+        WHERE serial = ?
+        "#,
+        serial
+    )
+    .fetch_optional(&pool)
+    .await
+    .context("select certificate")?
+    .ok_or_else(|| Error::NotFound(serial.clone()))?;
+
+    Ok(Json(certificate))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Params(#[from] ParamsError),
+
+    #[error("{0}")]
+    Authorization(#[from] AuthorizationError),
+
+    #[error("certificate `{0}` not found")]
+    NotFound(String),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Params(err) => err.kind(),
+            Error::Authorization(err) => err.kind(),
+            Error::NotFound(_) => "pki.certificate.not-found",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Params(err) => err.into_response(),
+            Error::Authorization(err) => err.into_response(),
+            Error::NotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{MethodRouter, get},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::{StatusCode, header};
+use rcgen::{CertificateRevocationListParams, RevocationReason, RevokedCertParams, SerialNumber};
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    pki::{self, ParamsError, StoreError},
+};
+
+pub const PATH: &str = "/pki/crl/{issuer}";
+
+pub fn method_router() -> MethodRouter<AppState> {
+    get(handler)
+}
+
+/// Serves the issuer's DER encoded CRL, signed on every request. Expired certificates are left
+/// out, they are rejected without it.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%issuer), skip_all))]
+pub async fn handler(
+    State(AppState {
+        pool, master_key, ..
+    }): State<AppState>,
+    Path(issuer): Path<String>,
+) -> Result<impl IntoResponse, Error> {
+    pki::validate_name(&issuer)?;
+
+    let issuer = pki::load_issuer(&pool, &master_key, &issuer)
+        .await?
+        .ok_or_else(|| Error::NotFound(issuer.clone()))?;
+    let now = pki::now();
+
+    let revoked = sqlx::query!(
+        r#"
+        SELECT
+            serial,
+            revoked_at as "revoked_at!: OffsetDateTime",
+            revocation_reason
+        FROM pki_certificates
+        WHERE issuer_id = ? AND revoked_at IS NOT NULL AND not_after > ?
+        ORDER BY revoked_at
+        "#,
+        issuer.id,
+        now
+    )
+    .fetch_all(&pool)
+    .await
+    .context("select revoked certificates")?;
+
+    let crl = CertificateRevocationListParams {
+        this_update: now,
+        next_update: now + pki::CRL_VALIDITY,
+        crl_number: SerialNumber::from(issuer.crl_number as u64),
+        issuing_distribution_point: None,
+        revoked_certs: revoked
+            .into_iter()
+            .map(|certificate| RevokedCertParams {
+                serial_number: SerialNumber::from(serial_bytes(&certificate.serial)),
+                revocation_time: certificate.revoked_at,
+                reason_code: certificate.revocation_reason.and_then(reason),
+                invalidity_date: None,
+            })
+            .collect(),
+        key_identifier_method: issuer.certificate.params().key_identifier_method.clone(),
+    }
+    .signed_by(&issuer.certificate, &issuer.key)?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/pkix-crl")],
+        crl.der().to_vec(),
+    ))
+}
+
+/// Serials are stored as the hex they were generated from.
+fn serial_bytes(serial: &str) -> Vec<u8> {
+    (0..serial.len())
+        .step_by(2)
+        .filter_map(|i| u8::from_str_radix(serial.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn reason(code: i64) -> Option<RevocationReason> {
+    match code {
+        0 => Some(RevocationReason::Unspecified),
+        1 => Some(RevocationReason::KeyCompromise),
+        3 => Some(RevocationReason::AffiliationChanged),
+        4 => Some(RevocationReason::Superseded),
+        5 => Some(RevocationReason::CessationOfOperation),
+        _ => None,
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Params(#[from] ParamsError),
+
+    #[error("issuer `{0}` not found")]
+    NotFound(String),
+
+    #[error("{0}")]
+    Store(#[from] StoreError),
+
+    #[error("unable to sign the CRL :: {0}")]
+    Certificate(#[from] rcgen::Error),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Params(err) => err.kind(),
+            Error::NotFound(_) => "pki.issuer.not-found",
+            Error::Store(err) => err.kind(),
+            Error::Certificate(_) => "pki.certificate",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Params(err) => err.into_response(),
+            Error::Store(err) => err.into_response(),
+            Error::NotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Certificate(_) | Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
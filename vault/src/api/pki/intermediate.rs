@@ -0,0 +1,206 @@
+use axum::{
+    Json,
+    extract::State,
+    response::IntoResponse,
+    routing::{MethodRouter, post},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+use rcgen::KeyPair;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    authorizer::AuthorizationError,
+    pki::{self, ParamsError, StoreError},
+};
+
+pub const PATH: &str = "/pki/intermediate";
+
+#[derive(Deserialize, Debug)]
+pub struct RequestBody {
+    pub name: String,
+
+    /// the root CA signing the intermediate
+    pub parent: String,
+
+    pub common_name: String,
+
+    pub ttl_sec: i64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResponseBody {
+    pub name: String,
+
+    /// PEM encoded
+    pub certificate: String,
+
+    /// PEM encoded, from the intermediate up to the root
+    pub ca_chain: Vec<String>,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub expiration: OffsetDateTime,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    post(handler)
+}
+
+/// Creates an intermediate CA signed by a root. Roles usually issue from intermediates, so the
+/// root's key is only needed again when an intermediate is replaced.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(name = %request_body.name, parent = %request_body.parent), skip_all))]
+pub async fn handler(
+    State(AppState {
+        pool,
+        master_key,
+        authorizer,
+        ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Json(request_body): Json<RequestBody>,
+) -> Result<(StatusCode, Json<ResponseBody>), Error> {
+    pki::validate_name(&request_body.name)?;
+    pki::validate_name(&request_body.parent)?;
+    pki::validate_common_name(&request_body.common_name)?;
+    let ttl = pki::validate_ttl(request_body.ttl_sec, pki::MAX_CA_TTL_SEC)?;
+    authorizer.require(&headers, "post", PATH).await?;
+
+    let parent = pki::load_issuer(&pool, &master_key, &request_body.parent)
+        .await?
+        .ok_or_else(|| Error::ParentNotFound(request_body.parent.clone()))?;
+
+    if parent.parent_id.is_some() {
+        return Err(Error::ParentNotRoot(parent.name));
+    }
+
+    let (not_before, not_after) = pki::validity(ttl);
+    if not_after > parent.not_after {
+        return Err(Error::OutlivesParent(parent.name));
+    }
+
+    let key = KeyPair::generate()?;
+    let certificate = pki::ca_params(&request_body.common_name, not_before, not_after, false)
+        .signed_by(&key, &parent.certificate, &parent.key)?
+        .pem();
+    let key_ciphertext = pki::seal_key(&master_key, &request_body.name, &key);
+    let now = OffsetDateTime::now_utc();
+
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO pki_issuers (name, parent_id, certificate_pem, key_ciphertext, not_after, created_at)
+        VALUES (?, ?, ?, ?, ?, ?)
+        ON CONFLICT (name) DO NOTHING
+        "#,
+        request_body.name,
+        parent.id,
+        certificate,
+        key_ciphertext,
+        not_after,
+        now
+    )
+    .execute(&pool)
+    .await
+    .context("insert intermediate issuer")?;
+
+    if result.rows_affected() == 0 {
+        return Err(Error::AlreadyExists(request_body.name));
+    }
+
+    #[cfg(feature = "tracing")]
+    tracing::info!("intermediate CA created");
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ResponseBody {
+            name: request_body.name,
+            ca_chain: vec![certificate.clone(), parent.certificate_pem],
+            certificate,
+            expiration: not_after,
+        }),
+    ))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Params(#[from] ParamsError),
+
+    #[error("{0}")]
+    Authorization(#[from] AuthorizationError),
+
+    #[error("issuer `{0}` not found")]
+    ParentNotFound(String),
+
+    #[error("issuer `{0}` is an intermediate, only root CAs can sign intermediates")]
+    ParentNotRoot(String),
+
+    #[error("the intermediate would outlive its parent `{0}`")]
+    OutlivesParent(String),
+
+    #[error("issuer `{0}` already exists")]
+    AlreadyExists(String),
+
+    #[error("{0}")]
+    Store(#[from] StoreError),
+
+    #[error("unable to create the certificate :: {0}")]
+    Certificate(#[from] rcgen::Error),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Params(err) => err.kind(),
+            Error::Authorization(err) => err.kind(),
+            Error::ParentNotFound(_) => "pki.issuer.not-found",
+            Error::ParentNotRoot(_) => "pki.issuer.not-root",
+            Error::OutlivesParent(_) => "pki.ttl.outlives-issuer",
+            Error::AlreadyExists(_) => "pki.issuer.already-exists",
+            Error::Store(err) => err.kind(),
+            Error::Certificate(_) => "pki.certificate",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Params(err) => err.into_response(),
+            Error::Authorization(err) => err.into_response(),
+            Error::Store(err) => err.into_response(),
+            Error::ParentNotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::ParentNotRoot(_) | Error::OutlivesParent(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::AlreadyExists(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::CONFLICT, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Certificate(_) | Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,9 @@
+pub mod ca;
+pub mod certs;
+pub mod crl;
+pub mod intermediate;
+pub mod ocsp;
+pub mod revoke;
+pub mod roles;
+pub mod root;
+pub mod sign;
@@ -0,0 +1,145 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{MethodRouter, post},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use http::header;
+use time::OffsetDateTime;
+use yasna::ASN1Error;
+
+use crate::{
+    AppState,
+    pki::{
+        self, StoreError,
+        ocsp::{self, CertStatus, ErrorStatus, SignError},
+    },
+};
+
+pub const PATH: &str = "/pki/ocsp/{issuer}";
+
+const CONTENT_TYPE: &str = "application/ocsp-response";
+
+pub fn method_router() -> MethodRouter<AppState> {
+    post(handler)
+}
+
+/// Answers a DER encoded OCSP request about certificates of the issuer. Public, like the CRL.
+/// Failures are reported as unsuccessful OCSP responses, which is what OCSP clients understand.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%issuer), skip_all))]
+pub async fn handler(
+    State(AppState {
+        pool, master_key, ..
+    }): State<AppState>,
+    Path(issuer): Path<String>,
+    body: Bytes,
+) -> Result<impl IntoResponse, Error> {
+    pki::validate_name(&issuer).map_err(|_| Error::UnknownIssuer(issuer.clone()))?;
+    let request = ocsp::parse_request(&body)?;
+
+    let issuer = pki::load_issuer(&pool, &master_key, &issuer)
+        .await?
+        .ok_or_else(|| Error::UnknownIssuer(issuer.clone()))?;
+    let public_key = issuer.key.public_key_raw();
+
+    let mut statuses = Vec::with_capacity(request.cert_ids.len());
+    for cert_id in &request.cert_ids {
+        if !cert_id.is_issued_by(&issuer.subject, public_key) {
+            statuses.push(CertStatus::Unknown);
+            continue;
+        }
+
+        let certificate = sqlx::query!(
+            r#"
+            SELECT revoked_at as "revoked_at: OffsetDateTime", revocation_reason
+            FROM pki_certificates
+            WHERE serial = ? AND issuer_id = ?
+            "#,
+            cert_id.serial,
+            issuer.id
+        )
+        .fetch_optional(&pool)
+        .await
+        .context("select certificate status")?;
+
+        statuses.push(match certificate {
+            None => CertStatus::Unknown,
+            Some(certificate) => match certificate.revoked_at {
+                None => CertStatus::Good,
+                Some(at) => CertStatus::Revoked {
+                    at,
+                    reason: certificate.revocation_reason,
+                },
+            },
+        });
+    }
+
+    let pkcs8 = zeroize::Zeroizing::new(issuer.key.serialize_der());
+    let response = ocsp::response(&pkcs8, public_key, &request, &statuses)?;
+
+    Ok(([(header::CONTENT_TYPE, CONTENT_TYPE)], response))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("malformed OCSP request :: {0}")]
+    Malformed(#[from] ASN1Error),
+
+    #[error("issuer `{0}` not found")]
+    UnknownIssuer(String),
+
+    #[error("{0}")]
+    Store(#[from] StoreError),
+
+    #[error("{0}")]
+    Sign(#[from] SignError),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Malformed(_) => "pki.ocsp.malformed",
+            Error::UnknownIssuer(_) => "pki.issuer.not-found",
+            Error::Store(err) => err.kind(),
+            Error::Sign(_) => "pki.ocsp.sign",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        let status = match self {
+            Error::Malformed(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                ErrorStatus::MalformedRequest
+            }
+            Error::UnknownIssuer(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                ErrorStatus::Unauthorized
+            }
+            Error::Store(_) | Error::Sign(_) | Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                ErrorStatus::InternalError
+            }
+        };
+
+        (
+            [(header::CONTENT_TYPE, CONTENT_TYPE)],
+            ocsp::error_response(status),
+        )
+            .into_response()
+    }
+}
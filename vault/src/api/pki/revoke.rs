@@ -0,0 +1,172 @@
+use axum::{
+    Json,
+    extract::State,
+    response::IntoResponse,
+    routing::{MethodRouter, post},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    authorizer::AuthorizationError,
+    pki::{self, ParamsError},
+};
+
+pub const PATH: &str = "/pki/revoke";
+
+#[derive(Deserialize, Debug)]
+pub struct RequestBody {
+    /// hex, with or without `:` separators
+    pub serial: String,
+
+    pub reason: Option<Reason>,
+}
+
+/// The RFC 5280 CRL reasons that apply to service certificates.
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    Unspecified = 0,
+    KeyCompromise = 1,
+    AffiliationChanged = 3,
+    Superseded = 4,
+    CessationOfOperation = 5,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResponseBody {
+    pub serial: String,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub revoked_at: OffsetDateTime,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    post(handler)
+}
+
+/// Revokes an issued certificate. It is listed in its issuer's next CRL and reported as revoked
+/// through OCSP right away. Revoking a revoked certificate again changes nothing.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(serial = %request_body.serial, reason = ?request_body.reason), skip_all, ret))]
+pub async fn handler(
+    State(AppState {
+        pool, authorizer, ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Json(request_body): Json<RequestBody>,
+) -> Result<Json<ResponseBody>, Error> {
+    let serial = pki::normalize_serial(&request_body.serial)?;
+    authorizer.require(&headers, "post", PATH).await?;
+
+    let reason = request_body.reason.map(|reason| reason as i64);
+    let now = pki::now();
+
+    let mut tx = pool
+        .begin()
+        .await
+        .context("begin transaction :: revoke certificate")?;
+
+    let certificate = sqlx::query!(
+        r#"
+        SELECT issuer_id, revoked_at as "revoked_at: OffsetDateTime"
+        FROM pki_certificates
+        WHERE serial = ?
+        "#,
+        serial
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .context("select certificate")?
+    .ok_or_else(|| Error::NotFound(serial.clone()))?;
+
+    if let Some(revoked_at) = certificate.revoked_at {
+        return Ok(Json(ResponseBody { serial, revoked_at }));
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE pki_certificates
+        SET revoked_at = ?, revocation_reason = ?
+        WHERE serial = ?
+        "#,
+        now,
+        reason,
+        serial
+    )
+    .execute(&mut *tx)
+    .await
+    .context("revoke certificate")?;
+
+    sqlx::query!(
+        "UPDATE pki_issuers SET crl_number = crl_number + 1 WHERE id = ?",
+        certificate.issuer_id
+    )
+    .execute(&mut *tx)
+    .await
+    .context("bump crl number")?;
+
+    tx.commit()
+        .await
+        .context("commit transaction :: revoke certificate")?;
+
+    #[cfg(feature = "tracing")]
+    tracing::info!("certificate revoked");
+
+    Ok(Json(ResponseBody {
+        serial,
+        revoked_at: now,
+    }))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Params(#[from] ParamsError),
+
+    #[error("{0}")]
+    Authorization(#[from] AuthorizationError),
+
+    #[error("certificate `{0}` not found")]
+    NotFound(String),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Params(err) => err.kind(),
+            Error::Authorization(err) => err.kind(),
+            Error::NotFound(_) => "pki.certificate.not-found",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Params(err) => err.into_response(),
+            Error::Authorization(err) => err.into_response(),
+            Error::NotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+pub mod update;
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{MethodRouter, get},
+};
+use axum_macros::debug_handler;
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+
+use crate::{
+    AppState,
+    authorizer::AuthorizationError,
+    pki::{self, ParamsError, Role, StoreError},
+};
+
+pub const PATH: &str = "/pki/roles/{name}";
+
+pub fn method_router() -> MethodRouter<AppState> {
+    get(handler)
+}
+
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%name), skip_all))]
+pub async fn handler(
+    State(AppState {
+        pool, authorizer, ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> Result<Json<Role>, Error> {
+    pki::validate_name(&name)?;
+    authorizer
+        .require(&headers, "get", &format!("/pki/roles/{name}"))
+        .await?;
+
+    let role = pki::load_role(&pool, &name)
+        .await?
+        .ok_or_else(|| Error::NotFound(name.clone()))?;
+
+    Ok(Json(role))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Params(#[from] ParamsError),
+
+    #[error("{0}")]
+    Authorization(#[from] AuthorizationError),
+
+    #[error("role `{0}` not found")]
+    NotFound(String),
+
+    #[error("{0}")]
+    Store(#[from] StoreError),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Params(err) => err.kind(),
+            Error::Authorization(err) => err.kind(),
+            Error::NotFound(_) => "pki.role.not-found",
+            Error::Store(err) => err.kind(),
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Params(err) => err.into_response(),
+            Error::Authorization(err) => err.into_response(),
+            Error::Store(err) => err.into_response(),
+            Error::NotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{MethodRouter, put},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+use serde::Deserialize;
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    authorizer::AuthorizationError,
+    pki::{self, ParamsError, Role, StoreError},
+};
+
+pub const PATH: &str = "/pki/roles/{name}";
+
+/// Upper bound of allowed domains of a single role.
+const MAX_ALLOWED_DOMAINS: usize = 64;
+
+#[derive(Deserialize, Debug)]
+pub struct RequestBody {
+    /// the issuer signing the role's certificates
+    pub issuer: String,
+
+    pub allowed_domains: Vec<String>,
+
+    #[serde(default)]
+    pub allow_subdomains: bool,
+
+    #[serde(default)]
+    pub allow_ip_sans: bool,
+
+    pub max_ttl_sec: i64,
+
+    #[serde(default = "default_true")]
+    pub server_auth: bool,
+
+    #[serde(default = "default_true")]
+    pub client_auth: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    put(handler)
+}
+
+/// Creates or replaces a role. Certificates issued before keep the constraints they were issued with.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%name, issuer = %request_body.issuer), skip_all))]
+pub async fn handler(
+    State(AppState {
+        pool, authorizer, ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    Json(request_body): Json<RequestBody>,
+) -> Result<Json<Role>, Error> {
+    pki::validate_name(&name)?;
+    pki::validate_name(&request_body.issuer)?;
+    pki::validate_ttl(request_body.max_ttl_sec, pki::MAX_TTL_SEC)?;
+
+    if request_body.allowed_domains.len() > MAX_ALLOWED_DOMAINS {
+        return Err(Error::TooManyDomains(request_body.allowed_domains.len()));
+    }
+    if let Some(domain) = request_body
+        .allowed_domains
+        .iter()
+        .find(|domain| !pki::is_dns_name(domain))
+    {
+        return Err(Error::InvalidDomain(domain.clone()));
+    }
+    if !request_body.server_auth && !request_body.client_auth {
+        return Err(Error::NoUsage);
+    }
+
+    authorizer
+        .require(&headers, "put", &format!("/pki/roles/{name}"))
+        .await?;
+
+    let allowed_domains = serde_json::Value::from(request_body.allowed_domains).to_string();
+    let now = OffsetDateTime::now_utc();
+
+    let mut tx = pool
+        .begin()
+        .await
+        .context("begin transaction :: update role")?;
+
+    let issuer_id = sqlx::query_scalar!(
+        r#"SELECT id as "id!" FROM pki_issuers WHERE name = ?"#,
+        request_body.issuer
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .context("select issuer")?
+    .ok_or_else(|| Error::IssuerNotFound(request_body.issuer.clone()))?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO pki_roles (
+            name, issuer_id, allowed_domains, allow_subdomains, allow_ip_sans,
+            max_ttl_sec, server_auth, client_auth, created_at, updated_at
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9)
+        ON CONFLICT (name) DO UPDATE SET
+            issuer_id = ?2,
+            allowed_domains = ?3,
+            allow_subdomains = ?4,
+            allow_ip_sans = ?5,
+            max_ttl_sec = ?6,
+            server_auth = ?7,
+            client_auth = ?8,
+            updated_at = ?9
+        "#,
+        name,
+        issuer_id,
+        allowed_domains,
+        request_body.allow_subdomains,
+        request_body.allow_ip_sans,
+        request_body.max_ttl_sec,
+        request_body.server_auth,
+        request_body.client_auth,
+        now
+    )
+    .execute(&mut *tx)
+    .await
+    .context("upsert role")?;
+
+    let role = pki::load_role(&mut *tx, &name)
+        .await?
+        .expect("the role was just written");
+
+    tx.commit()
+        .await
+        .context("commit transaction :: update role")?;
+
+    Ok(Json(role))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Params(#[from] ParamsError),
+
+    #[error("at most {MAX_ALLOWED_DOMAINS} allowed domains are supported, got {0}")]
+    TooManyDomains(usize),
+
+    #[error("invalid allowed domain `{0}`, expected a lowercase DNS name without wildcards")]
+    InvalidDomain(String),
+
+    #[error("at least one of server_auth and client_auth is required")]
+    NoUsage,
+
+    #[error("{0}")]
+    Authorization(#[from] AuthorizationError),
+
+    #[error("issuer `{0}` not found")]
+    IssuerNotFound(String),
+
+    #[error("{0}")]
+    Store(#[from] StoreError),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Params(err) => err.kind(),
+            Error::TooManyDomains(_) => "pki.role.too-many-domains",
+            Error::InvalidDomain(_) => "pki.role.invalid-domain",
+            Error::NoUsage => "pki.role.no-usage",
+            Error::Authorization(err) => err.kind(),
+            Error::IssuerNotFound(_) => "pki.issuer.not-found",
+            Error::Store(err) => err.kind(),
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Params(err) => err.into_response(),
+            Error::Authorization(err) => err.into_response(),
+            Error::Store(err) => err.into_response(),
+            Error::TooManyDomains(_) | Error::InvalidDomain(_) | Error::NoUsage => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::IssuerNotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use axum::{
+    Json,
+    extract::State,
+    response::IntoResponse,
+    routing::{MethodRouter, post},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+use rcgen::KeyPair;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    authorizer::AuthorizationError,
+    pki::{self, ParamsError},
+};
+
+pub const PATH: &str = "/pki/root";
+
+#[derive(Deserialize, Debug)]
+pub struct RequestBody {
+    /// how the issuer is addressed, e.g. in `/pki/ca/{issuer}`
+    pub name: String,
+
+    pub common_name: String,
+
+    pub ttl_sec: i64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResponseBody {
+    pub name: String,
+
+    /// PEM encoded
+    pub certificate: String,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub expiration: OffsetDateTime,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    post(handler)
+}
+
+/// Creates a self-signed root CA. Its private key never leaves the vault.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(name = %request_body.name), skip_all))]
+pub async fn handler(
+    State(AppState {
+        pool,
+        master_key,
+        authorizer,
+        ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Json(request_body): Json<RequestBody>,
+) -> Result<(StatusCode, Json<ResponseBody>), Error> {
+    pki::validate_name(&request_body.name)?;
+    pki::validate_common_name(&request_body.common_name)?;
+    let ttl = pki::validate_ttl(request_body.ttl_sec, pki::MAX_CA_TTL_SEC)?;
+    authorizer.require(&headers, "post", PATH).await?;
+
+    let (not_before, not_after) = pki::validity(ttl);
+    let key = KeyPair::generate()?;
+    let certificate = pki::ca_params(&request_body.common_name, not_before, not_after, true)
+        .self_signed(&key)?
+        .pem();
+    let key_ciphertext = pki::seal_key(&master_key, &request_body.name, &key);
+    let now = OffsetDateTime::now_utc();
+
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO pki_issuers (name, certificate_pem, key_ciphertext, not_after, created_at)
+        VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT (name) DO NOTHING
+        "#,
+        request_body.name,
+        certificate,
+        key_ciphertext,
+        not_after,
+        now
+    )
+    .execute(&pool)
+    .await
+    .context("insert root issuer")?;
+
+    if result.rows_affected() == 0 {
+        return Err(Error::AlreadyExists(request_body.name));
+    }
+
+    #[cfg(feature = "tracing")]
+    tracing::info!("root CA created");
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ResponseBody {
+            name: request_body.name,
+            certificate,
+            expiration: not_after,
+        }),
+    ))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Params(#[from] ParamsError),
+
+    #[error("{0}")]
+    Authorization(#[from] AuthorizationError),
+
+    #[error("issuer `{0}` already exists")]
+    AlreadyExists(String),
+
+    #[error("unable to create the certificate :: {0}")]
+    Certificate(#[from] rcgen::Error),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Params(err) => err.kind(),
+            Error::Authorization(err) => err.kind(),
+            Error::AlreadyExists(_) => "pki.issuer.already-exists",
+            Error::Certificate(_) => "pki.certificate",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Params(err) => err.into_response(),
+            Error::Authorization(err) => err.into_response(),
+            Error::AlreadyExists(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::CONFLICT, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Certificate(_) | Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
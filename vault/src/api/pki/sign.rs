@@ -0,0 +1,296 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{MethodRouter, post},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+use rcgen::{
+    CertificateSigningRequestParams, DistinguishedName, DnType, DnValue, ExtendedKeyUsagePurpose,
+    IsCa, KeyUsagePurpose, SanType,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    authorizer::AuthorizationError,
+    pki::{self, ParamsError, StoreError},
+};
+
+pub const PATH: &str = "/pki/sign/{role}";
+
+#[derive(Deserialize, Debug)]
+pub struct RequestBody {
+    /// PEM encoded PKCS#10 request; its common name and subject alternative names are certified
+    pub csr: String,
+
+    /// defaults to the role's `max_ttl_sec`
+    pub ttl_sec: Option<i64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResponseBody {
+    /// lowercase hex
+    pub serial: String,
+
+    /// PEM encoded
+    pub certificate: String,
+
+    /// PEM encoded
+    pub issuing_ca: String,
+
+    /// PEM encoded, from the issuing CA up to the root
+    pub ca_chain: Vec<String>,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub expiration: OffsetDateTime,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    post(handler)
+}
+
+/// Signs a CSR with the role's issuer, if the role allows every requested name and the validity.
+/// Everything but the names and the public key is decided by the role, not by the CSR.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%role), skip_all))]
+pub async fn handler(
+    State(AppState {
+        pool,
+        master_key,
+        authorizer,
+        ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Path(role): Path<String>,
+    Json(request_body): Json<RequestBody>,
+) -> Result<Json<ResponseBody>, Error> {
+    pki::validate_name(&role)?;
+    authorizer
+        .require(&headers, "post", &format!("/pki/sign/{role}"))
+        .await?;
+
+    let role = pki::load_role(&pool, &role)
+        .await?
+        .ok_or_else(|| Error::RoleNotFound(role.clone()))?;
+    let ttl = pki::validate_ttl(
+        request_body.ttl_sec.unwrap_or(role.max_ttl_sec),
+        role.max_ttl_sec,
+    )?;
+
+    let mut csr =
+        CertificateSigningRequestParams::from_pem(&request_body.csr).map_err(Error::InvalidCsr)?;
+
+    let common_name = match csr.params.distinguished_name.get(&DnType::CommonName) {
+        None => None,
+        Some(DnValue::Utf8String(common_name)) => Some(common_name.clone()),
+        Some(DnValue::PrintableString(common_name)) => Some(common_name.as_str().to_string()),
+        Some(_) => return Err(Error::UnsupportedName("common name encoding".to_string())),
+    };
+
+    let mut subject_alt_names = Vec::with_capacity(csr.params.subject_alt_names.len() + 1);
+    for name in &csr.params.subject_alt_names {
+        match name {
+            SanType::DnsName(dns_name) if role.allows_dns_name(dns_name.as_str()) => {}
+            SanType::DnsName(dns_name) => {
+                return Err(Error::NameNotAllowed(dns_name.as_str().to_string()));
+            }
+            SanType::IpAddress(_) if role.allow_ip_sans => {}
+            SanType::IpAddress(ip) => return Err(Error::NameNotAllowed(ip.to_string())),
+            _ => return Err(Error::UnsupportedName(format!("{name:?}"))),
+        }
+        subject_alt_names.push(name.clone());
+    }
+
+    if let Some(common_name) = &common_name {
+        if !role.allows_dns_name(common_name) {
+            return Err(Error::NameNotAllowed(common_name.clone()));
+        }
+        // clients only look at the subject alternative names
+        let dns_name = SanType::DnsName(
+            common_name
+                .clone()
+                .try_into()
+                .map_err(|_| Error::NameNotAllowed(common_name.clone()))?,
+        );
+        if !subject_alt_names.contains(&dns_name) {
+            subject_alt_names.push(dns_name);
+        }
+    }
+
+    if subject_alt_names.is_empty() {
+        return Err(Error::NoNames);
+    }
+
+    let issuer = pki::load_issuer(&pool, &master_key, &role.issuer)
+        .await?
+        .ok_or_else(|| Error::IssuerNotFound(role.issuer.clone()))?;
+
+    let (not_before, not_after) = pki::validity(ttl);
+    if not_after > issuer.not_after {
+        return Err(Error::OutlivesIssuer(issuer.name));
+    }
+
+    let serial = pki::generate_serial();
+    let params = &mut csr.params;
+    params.distinguished_name = DistinguishedName::new();
+    if let Some(common_name) = &common_name {
+        params
+            .distinguished_name
+            .push(DnType::CommonName, common_name.as_str());
+    }
+    params.subject_alt_names = subject_alt_names;
+    params.not_before = not_before;
+    params.not_after = not_after;
+    params.serial_number = Some(serial.clone());
+    params.is_ca = IsCa::ExplicitNoCa;
+    params.key_usages = vec![
+        KeyUsagePurpose::DigitalSignature,
+        KeyUsagePurpose::KeyEncipherment,
+    ];
+    params.extended_key_usages = [
+        (role.server_auth, ExtendedKeyUsagePurpose::ServerAuth),
+        (role.client_auth, ExtendedKeyUsagePurpose::ClientAuth),
+    ]
+    .into_iter()
+    .filter_map(|(enabled, usage)| enabled.then_some(usage))
+    .collect();
+    params.use_authority_key_identifier_extension = true;
+
+    let certificate = csr
+        .signed_by(&issuer.certificate, &issuer.key)
+        .map_err(Error::Certificate)?
+        .pem();
+    let serial = pki::hex(&serial.to_bytes());
+    let now = OffsetDateTime::now_utc();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO pki_certificates (
+            serial, issuer_id, role, common_name, certificate_pem, not_before, not_after, created_at
+        )
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+        serial,
+        issuer.id,
+        role.name,
+        common_name,
+        certificate,
+        not_before,
+        not_after,
+        now
+    )
+    .execute(&pool)
+    .await
+    .context("insert certificate")?;
+
+    #[cfg(feature = "tracing")]
+    tracing::info!(%serial, "certificate issued");
+
+    Ok(Json(ResponseBody {
+        serial,
+        certificate,
+        ca_chain: pki::ca_chain(&pool, &issuer).await?,
+        issuing_ca: issuer.certificate_pem,
+        expiration: not_after,
+    }))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Params(#[from] ParamsError),
+
+    #[error("{0}")]
+    Authorization(#[from] AuthorizationError),
+
+    #[error("role `{0}` not found")]
+    RoleNotFound(String),
+
+    #[error("invalid CSR :: {0}")]
+    InvalidCsr(rcgen::Error),
+
+    #[error("the role does not allow `{0}`")]
+    NameNotAllowed(String),
+
+    #[error("unsupported name in the CSR :: {0}")]
+    UnsupportedName(String),
+
+    #[error("the CSR contains neither a common name nor subject alternative names")]
+    NoNames,
+
+    #[error("issuer `{0}` not found")]
+    IssuerNotFound(String),
+
+    #[error("the certificate would outlive its issuer `{0}`")]
+    OutlivesIssuer(String),
+
+    #[error("{0}")]
+    Store(#[from] StoreError),
+
+    #[error("unable to sign the certificate :: {0}")]
+    Certificate(rcgen::Error),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Params(err) => err.kind(),
+            Error::Authorization(err) => err.kind(),
+            Error::RoleNotFound(_) => "pki.role.not-found",
+            Error::InvalidCsr(_) => "pki.csr.invalid",
+            Error::NameNotAllowed(_) => "pki.csr.name-not-allowed",
+            Error::UnsupportedName(_) => "pki.csr.unsupported-name",
+            Error::NoNames => "pki.csr.no-names",
+            Error::IssuerNotFound(_) => "pki.issuer.not-found",
+            Error::OutlivesIssuer(_) => "pki.ttl.outlives-issuer",
+            Error::Store(err) => err.kind(),
+            Error::Certificate(_) => "pki.certificate",
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Params(err) => err.into_response(),
+            Error::Authorization(err) => err.into_response(),
+            Error::Store(err) => err.into_response(),
+            Error::RoleNotFound(_) | Error::IssuerNotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::InvalidCsr(_)
+            | Error::UnsupportedName(_)
+            | Error::NoNames
+            | Error::OutlivesIssuer(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::NameNotAllowed(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::FORBIDDEN, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Certificate(_) | Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{MethodRouter, post},
+};
+use axum_macros::debug_handler;
+use base64::{Engine, prelude::BASE64_STANDARD};
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AppState,
+    authorizer::AuthorizationError,
+    transit::{self, KeyType, ParamsError, StoreError},
+};
+
+pub const PATH: &str = "/transit/decrypt/{name}";
+
+#[derive(Deserialize, Debug)]
+pub struct RequestBody {
+    /// `vault:v<version>:<base64>`, as returned by `/transit/encrypt`
+    pub ciphertext: String,
+
+    /// base64, the context given to encrypt
+    pub context: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResponseBody {
+    /// base64
+    pub plaintext: String,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    post(handler)
+}
+
+/// Decrypts with the version of the key named in the ciphertext, unless it is below the key's
+/// `min_decryption_version`.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%name), skip_all))]
+pub async fn handler(
+    State(AppState {
+        pool,
+        secrets,
+        authorizer,
+        ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    Json(request_body): Json<RequestBody>,
+) -> Result<Json<ResponseBody>, Error> {
+    transit::validate_name(&name)?;
+    authorizer
+        .require(&headers, "post", &format!("/transit/decrypt/{name}"))
+        .await?;
+
+    let (version, ciphertext) = transit::decode_versioned("ciphertext", &request_body.ciphertext)?;
+    let context = transit::decode_base64("context", request_body.context.as_deref().unwrap_or(""))?;
+
+    let key = transit::load_key(&pool, &name)
+        .await?
+        .ok_or_else(|| Error::NotFound(name.clone()))?;
+    key.require(KeyType::Xchacha20Poly1305, "decrypt")?;
+    key.check_version(version)?;
+
+    let material = transit::load_material(&secrets, &key, version)?;
+    let plaintext = transit::decrypt(&material, &ciphertext, &context)?;
+
+    Ok(Json(ResponseBody {
+        plaintext: BASE64_STANDARD.encode(plaintext),
+    }))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Params(#[from] ParamsError),
+
+    #[error("{0}")]
+    Authorization(#[from] AuthorizationError),
+
+    #[error("key `{0}` not found")]
+    NotFound(String),
+
+    #[error("{0}")]
+    Store(#[from] StoreError),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Params(err) => err.kind(),
+            Error::Authorization(err) => err.kind(),
+            Error::NotFound(_) => "transit.key.not-found",
+            Error::Store(err) => err.kind(),
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Params(err) => err.into_response(),
+            Error::Authorization(err) => err.into_response(),
+            Error::Store(err) => err.into_response(),
+            Error::NotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+        }
+    }
+}
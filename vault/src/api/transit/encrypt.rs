@@ -0,0 +1,118 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{MethodRouter, post},
+};
+use axum_macros::debug_handler;
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AppState,
+    authorizer::AuthorizationError,
+    transit::{self, KeyType, ParamsError, StoreError},
+};
+
+pub const PATH: &str = "/transit/encrypt/{name}";
+
+#[derive(Deserialize, Debug)]
+pub struct RequestBody {
+    /// base64
+    pub plaintext: String,
+
+    /// base64, authenticated but not encrypted; the same context is needed to decrypt, e.g. the
+    /// id of the row the ciphertext is stored in
+    pub context: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResponseBody {
+    /// `vault:v<version>:<base64>`
+    pub ciphertext: String,
+
+    pub key_version: i64,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    post(handler)
+}
+
+/// Encrypts with the latest version of the key.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%name), skip_all))]
+pub async fn handler(
+    State(AppState {
+        pool,
+        secrets,
+        authorizer,
+        ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    Json(request_body): Json<RequestBody>,
+) -> Result<Json<ResponseBody>, Error> {
+    transit::validate_name(&name)?;
+    authorizer
+        .require(&headers, "post", &format!("/transit/encrypt/{name}"))
+        .await?;
+
+    let plaintext = transit::decode_base64("plaintext", &request_body.plaintext)?;
+    let context = transit::decode_base64("context", request_body.context.as_deref().unwrap_or(""))?;
+
+    let key = transit::load_key(&pool, &name)
+        .await?
+        .ok_or_else(|| Error::NotFound(name.clone()))?;
+    key.require(KeyType::Xchacha20Poly1305, "encrypt")?;
+
+    let material = transit::load_material(&secrets, &key, key.latest_version)?;
+    let ciphertext = transit::encrypt(&material, &plaintext, &context);
+
+    Ok(Json(ResponseBody {
+        ciphertext: transit::encode_versioned(key.latest_version, &ciphertext),
+        key_version: key.latest_version,
+    }))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Params(#[from] ParamsError),
+
+    #[error("{0}")]
+    Authorization(#[from] AuthorizationError),
+
+    #[error("key `{0}` not found")]
+    NotFound(String),
+
+    #[error("{0}")]
+    Store(#[from] StoreError),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Params(err) => err.kind(),
+            Error::Authorization(err) => err.kind(),
+            Error::NotFound(_) => "transit.key.not-found",
+            Error::Store(err) => err.kind(),
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Params(err) => err.into_response(),
+            Error::Authorization(err) => err.into_response(),
+            Error::Store(err) => err.into_response(),
+            Error::NotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{MethodRouter, post},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+use serde::Deserialize;
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    authorizer::AuthorizationError,
+    transit::{self, KeyMetadata, KeyType, ParamsError, StoreError},
+};
+
+pub const PATH: &str = "/transit/keys/{name}";
+
+#[derive(Deserialize, Debug)]
+pub struct RequestBody {
+    /// `xchacha20-poly1305` (default) to encrypt, `ed25519` to sign
+    #[serde(rename = "type", default)]
+    pub key_type: KeyType,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    post(handler)
+}
+
+/// Creates a key with its first version.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%name, key_type = ?request_body.key_type), skip_all))]
+pub async fn handler(
+    State(AppState {
+        pool,
+        secrets,
+        authorizer,
+        ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    Json(request_body): Json<RequestBody>,
+) -> Result<(StatusCode, Json<KeyMetadata>), Error> {
+    transit::validate_name(&name)?;
+    authorizer
+        .require(&headers, "post", &format!("/transit/keys/{name}"))
+        .await?;
+
+    let key_type = request_body.key_type.as_str();
+    let now = OffsetDateTime::now_utc();
+
+    let mut tx = pool
+        .begin()
+        .await
+        .context("begin transaction :: create key")?;
+
+    let Some(key) = sqlx::query!(
+        r#"
+        INSERT INTO transit_keys (name, key_type, created_at, updated_at)
+        VALUES (?1, ?2, ?3, ?3)
+        ON CONFLICT (name) DO NOTHING
+        RETURNING id as "id!"
+        "#,
+        name,
+        key_type,
+        now
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .context("insert key")?
+    else {
+        return Err(Error::AlreadyExists(name));
+    };
+
+    transit::insert_version(
+        &mut *tx,
+        &secrets,
+        key.id,
+        &name,
+        request_body.key_type,
+        1,
+        now,
+    )
+    .await?;
+
+    let key = transit::load_key(&mut *tx, &name)
+        .await?
+        .expect("the key was just created");
+    let metadata = transit::key_metadata(&mut *tx, key).await?;
+
+    tx.commit()
+        .await
+        .context("commit transaction :: create key")?;
+
+    #[cfg(feature = "tracing")]
+    tracing::info!("key created");
+
+    Ok((StatusCode::CREATED, Json(metadata)))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Params(#[from] ParamsError),
+
+    #[error("{0}")]
+    Authorization(#[from] AuthorizationError),
+
+    #[error("key `{0}` already exists")]
+    AlreadyExists(String),
+
+    #[error("{0}")]
+    Store(#[from] StoreError),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Params(err) => err.kind(),
+            Error::Authorization(err) => err.kind(),
+            Error::AlreadyExists(_) => "transit.key.already-exists",
+            Error::Store(err) => err.kind(),
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Params(err) => err.into_response(),
+            Error::Authorization(err) => err.into_response(),
+            Error::Store(err) => err.into_response(),
+            Error::AlreadyExists(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::CONFLICT, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+pub mod create;
+pub mod rotate;
+pub mod update;
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{MethodRouter, get},
+};
+use axum_macros::debug_handler;
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+
+use crate::{
+    AppState,
+    authorizer::AuthorizationError,
+    transit::{self, KeyMetadata, ParamsError, StoreError},
+};
+
+pub const PATH: &str = "/transit/keys/{name}";
+
+pub fn method_router() -> MethodRouter<AppState> {
+    get(handler)
+}
+
+/// Reads the key's versions and, for signing keys, their public keys. Never the key material.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%name), skip_all))]
+pub async fn handler(
+    State(AppState {
+        pool, authorizer, ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> Result<Json<KeyMetadata>, Error> {
+    transit::validate_name(&name)?;
+    authorizer
+        .require(&headers, "get", &format!("/transit/keys/{name}"))
+        .await?;
+
+    let key = transit::load_key(&pool, &name)
+        .await?
+        .ok_or_else(|| Error::NotFound(name.clone()))?;
+
+    Ok(Json(transit::key_metadata(&pool, key).await?))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Params(#[from] ParamsError),
+
+    #[error("{0}")]
+    Authorization(#[from] AuthorizationError),
+
+    #[error("key `{0}` not found")]
+    NotFound(String),
+
+    #[error("{0}")]
+    Store(#[from] StoreError),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Params(err) => err.kind(),
+            Error::Authorization(err) => err.kind(),
+            Error::NotFound(_) => "transit.key.not-found",
+            Error::Store(err) => err.kind(),
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Params(err) => err.into_response(),
+            Error::Authorization(err) => err.into_response(),
+            Error::Store(err) => err.into_response(),
+            Error::NotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+        }
+    }
+}
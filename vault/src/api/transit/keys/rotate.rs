@@ -0,0 +1,143 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{MethodRouter, post},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    authorizer::AuthorizationError,
+    transit::{self, KeyMetadata, ParamsError, StoreError},
+};
+
+pub const PATH: &str = "/transit/keys/{name}/rotate";
+
+pub fn method_router() -> MethodRouter<AppState> {
+    post(handler)
+}
+
+/// Adds a version which encrypts and signs from now on. Older versions keep decrypting, see
+/// `min_decryption_version` and `/transit/rewrap`.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%name), skip_all))]
+pub async fn handler(
+    State(AppState {
+        pool,
+        secrets,
+        authorizer,
+        ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> Result<Json<KeyMetadata>, Error> {
+    transit::validate_name(&name)?;
+    authorizer
+        .require(&headers, "post", &format!("/transit/keys/{name}/rotate"))
+        .await?;
+
+    let now = OffsetDateTime::now_utc();
+
+    let mut tx = pool
+        .begin()
+        .await
+        .context("begin transaction :: rotate key")?;
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE transit_keys
+        SET latest_version = latest_version + 1, updated_at = ?
+        WHERE name = ?
+        "#,
+        now,
+        name
+    )
+    .execute(&mut *tx)
+    .await
+    .context("bump latest version")?;
+
+    if result.rows_affected() == 0 {
+        return Err(Error::NotFound(name));
+    }
+
+    let key = transit::load_key(&mut *tx, &name)
+        .await?
+        .expect("the key was just updated");
+    transit::insert_version(
+        &mut *tx,
+        &secrets,
+        key.id,
+        &name,
+        key.key_type,
+        key.latest_version,
+        now,
+    )
+    .await?;
+    let metadata = transit::key_metadata(&mut *tx, key).await?;
+
+    tx.commit()
+        .await
+        .context("commit transaction :: rotate key")?;
+
+    #[cfg(feature = "tracing")]
+    tracing::info!(version = metadata.latest_version, "key rotated");
+
+    Ok(Json(metadata))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Params(#[from] ParamsError),
+
+    #[error("{0}")]
+    Authorization(#[from] AuthorizationError),
+
+    #[error("key `{0}` not found")]
+    NotFound(String),
+
+    #[error("{0}")]
+    Store(#[from] StoreError),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Params(err) => err.kind(),
+            Error::Authorization(err) => err.kind(),
+            Error::NotFound(_) => "transit.key.not-found",
+            Error::Store(err) => err.kind(),
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Params(err) => err.into_response(),
+            Error::Authorization(err) => err.into_response(),
+            Error::Store(err) => err.into_response(),
+            Error::NotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{MethodRouter, put},
+};
+use axum_macros::debug_handler;
+use contextual::Context;
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+use serde::Deserialize;
+use time::OffsetDateTime;
+
+use crate::{
+    AppState,
+    authorizer::AuthorizationError,
+    transit::{self, KeyMetadata, ParamsError, StoreError},
+};
+
+pub const PATH: &str = "/transit/keys/{name}";
+
+#[derive(Deserialize, Debug)]
+pub struct RequestBody {
+    /// Ciphertexts and signatures of older versions are rejected. Can be lowered again, the
+    /// material of older versions is kept.
+    pub min_decryption_version: i64,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    put(handler)
+}
+
+/// Updates the key's configuration.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%name, min_decryption_version = request_body.min_decryption_version), skip_all))]
+pub async fn handler(
+    State(AppState {
+        pool, authorizer, ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    Json(request_body): Json<RequestBody>,
+) -> Result<Json<KeyMetadata>, Error> {
+    transit::validate_name(&name)?;
+    authorizer
+        .require(&headers, "put", &format!("/transit/keys/{name}"))
+        .await?;
+
+    let now = OffsetDateTime::now_utc();
+
+    let mut tx = pool
+        .begin()
+        .await
+        .context("begin transaction :: update key")?;
+
+    let key = transit::load_key(&mut *tx, &name)
+        .await?
+        .ok_or_else(|| Error::NotFound(name.clone()))?;
+
+    let min_decryption_version = request_body.min_decryption_version;
+    if !(1..=key.latest_version).contains(&min_decryption_version) {
+        return Err(Error::MinDecryptionVersion(
+            min_decryption_version,
+            key.latest_version,
+        ));
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE transit_keys
+        SET min_decryption_version = ?, updated_at = ?
+        WHERE id = ?
+        "#,
+        min_decryption_version,
+        now,
+        key.id
+    )
+    .execute(&mut *tx)
+    .await
+    .context("update key")?;
+
+    let metadata = transit::key_metadata(
+        &mut *tx,
+        transit::Key {
+            min_decryption_version,
+            updated_at: now,
+            ..key
+        },
+    )
+    .await?;
+
+    tx.commit()
+        .await
+        .context("commit transaction :: update key")?;
+
+    #[cfg(feature = "tracing")]
+    tracing::info!("key updated");
+
+    Ok(Json(metadata))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Params(#[from] ParamsError),
+
+    #[error("{0}")]
+    Authorization(#[from] AuthorizationError),
+
+    #[error("key `{0}` not found")]
+    NotFound(String),
+
+    #[error("min_decryption_version must be between 1 and the latest version {1}, got {0}")]
+    MinDecryptionVersion(i64, i64),
+
+    #[error("{0}")]
+    Store(#[from] StoreError),
+
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Params(err) => err.kind(),
+            Error::Authorization(err) => err.kind(),
+            Error::NotFound(_) => "transit.key.not-found",
+            Error::MinDecryptionVersion(..) => "transit.min-decryption-version.invalid",
+            Error::Store(err) => err.kind(),
+            Error::Sqlx(_) => "sqlx",
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Params(err) => err.into_response(),
+            Error::Authorization(err) => err.into_response(),
+            Error::Store(err) => err.into_response(),
+            Error::NotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::MinDecryptionVersion(..) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(self))).into_response()
+            }
+            Error::Sqlx(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,6 @@
+pub mod decrypt;
+pub mod encrypt;
+pub mod keys;
+pub mod rewrap;
+pub mod sign;
+pub mod verify;
@@ -0,0 +1,123 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{MethodRouter, post},
+};
+use axum_macros::debug_handler;
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AppState,
+    authorizer::AuthorizationError,
+    transit::{self, KeyType, ParamsError, StoreError},
+};
+
+pub const PATH: &str = "/transit/rewrap/{name}";
+
+#[derive(Deserialize, Debug)]
+pub struct RequestBody {
+    /// `vault:v<version>:<base64>`, as returned by `/transit/encrypt`
+    pub ciphertext: String,
+
+    /// base64, the context given to encrypt; kept for the new ciphertext
+    pub context: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResponseBody {
+    /// `vault:v<version>:<base64>`
+    pub ciphertext: String,
+
+    pub key_version: i64,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    post(handler)
+}
+
+/// Re-encrypts a ciphertext with the latest version of the key, without revealing the plaintext
+/// to the caller. Used to move stored ciphertexts off old versions before raising
+/// `min_decryption_version`.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%name), skip_all))]
+pub async fn handler(
+    State(AppState {
+        pool,
+        secrets,
+        authorizer,
+        ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    Json(request_body): Json<RequestBody>,
+) -> Result<Json<ResponseBody>, Error> {
+    transit::validate_name(&name)?;
+    authorizer
+        .require(&headers, "post", &format!("/transit/rewrap/{name}"))
+        .await?;
+
+    let (version, ciphertext) = transit::decode_versioned("ciphertext", &request_body.ciphertext)?;
+    let context = transit::decode_base64("context", request_body.context.as_deref().unwrap_or(""))?;
+
+    let key = transit::load_key(&pool, &name)
+        .await?
+        .ok_or_else(|| Error::NotFound(name.clone()))?;
+    key.require(KeyType::Xchacha20Poly1305, "rewrap")?;
+    key.check_version(version)?;
+
+    let material = transit::load_material(&secrets, &key, version)?;
+    let plaintext = transit::decrypt(&material, &ciphertext, &context)?;
+
+    let material = transit::load_material(&secrets, &key, key.latest_version)?;
+    let ciphertext = transit::encrypt(&material, &plaintext, &context);
+
+    Ok(Json(ResponseBody {
+        ciphertext: transit::encode_versioned(key.latest_version, &ciphertext),
+        key_version: key.latest_version,
+    }))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Params(#[from] ParamsError),
+
+    #[error("{0}")]
+    Authorization(#[from] AuthorizationError),
+
+    #[error("key `{0}` not found")]
+    NotFound(String),
+
+    #[error("{0}")]
+    Store(#[from] StoreError),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Params(err) => err.kind(),
+            Error::Authorization(err) => err.kind(),
+            Error::NotFound(_) => "transit.key.not-found",
+            Error::Store(err) => err.kind(),
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Params(err) => err.into_response(),
+            Error::Authorization(err) => err.into_response(),
+            Error::Store(err) => err.into_response(),
+            Error::NotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+    routing::{MethodRouter, post},
+};
+use axum_macros::debug_handler;
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AppState,
+    authorizer::AuthorizationError,
+    transit::{self, KeyType, ParamsError, StoreError},
+};
+
+pub const PATH: &str = "/transit/sign/{name}";
+
+#[derive(Deserialize, Debug)]
+pub struct RequestBody {
+    /// base64
+    pub input: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResponseBody {
+    /// `vault:v<version>:<base64>`
+    pub signature: String,
+
+    pub key_version: i64,
+}
+
+pub fn method_router() -> MethodRouter<AppState> {
+    post(handler)
+}
+
+/// Signs with the latest version of the key. The signature verifies with the version's public key
+/// from `/transit/keys/{name}` as well as through `/transit/verify`.
+#[debug_handler]
+#[cfg_attr(feature = "tracing", tracing::instrument(fields(%name), skip_all))]
+pub async fn handler(
+    State(AppState {
+        pool,
+        secrets,
+        authorizer,
+        ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    Json(request_body): Json<RequestBody>,
+) -> Result<Json<ResponseBody>, Error> {
+    transit::validate_name(&name)?;
+    authorizer
+        .require(&headers, "post", &format!("/transit/sign/{name}"))
+        .await?;
+
+    let input = transit::decode_base64("input", &request_body.input)?;
+
+    let key = transit::load_key(&pool, &name)
+        .await?
+        .ok_or_else(|| Error::NotFound(name.clone()))?;
+    key.require(KeyType::Ed25519, "sign")?;
+
+    let material = transit::load_material(&secrets, &key, key.latest_version)?;
+    let signature = transit::sign(&material, &input)?;
+
+    Ok(Json(ResponseBody {
+        signature: transit::encode_versioned(key.latest_version, &signature),
+        key_version: key.latest_version,
+    }))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Params(#[from] ParamsError),
+
+    #[error("{0}")]
+    Authorization(#[from] AuthorizationError),
+
+    #[error("key `{0}` not found")]
+    NotFound(String),
+
+    #[error("{0}")]
+    Store(#[from] StoreError),
+}
+
+impl extra::ErrorKind for Error {
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Params(err) => err.kind(),
+            Error::Authorization(err) => err.kind(),
+            Error::NotFound(_) => "transit.key.not-found",
+            Error::Store(err) => err.kind(),
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Error::Params(err) => err.into_response(),
+            Error::Authorization(err) => err.into_response(),
+            Error::Store(err) => err.into_response(),
+            Error::NotFound(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::NOT_FOUND, Json(ErrorResponse::from(self))).into_response()
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Authorization against the auth service.
+//!
+//! The caller's credentials are forwarded to auth's `/introspect`, which reports the permissions
+//! the caller holds. Permissions follow auth's `method:/path` model, where a trailing `/*` grants
+//! the method on everything below, e.g. `get:/kv/data/app/*` reads `app/db` and `app/db/password`.
+
+use std::collections::HashMap;
+
+use axum::{
+    Json,
+    response::{IntoResponse, Response},
+};
+use extra::ErrorResponse;
+use http::{HeaderMap, StatusCode, header};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone)]
+pub struct Authorizer {
+    client: reqwest::Client,
+    introspect_url: String,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum AuthorizationError {
+    #[error("no credentials provided")]
+    NoCredentialsProvided,
+
+    #[error("invalid credentials")]
+    Unauthenticated,
+
+    #[error("insufficient permissions, `{0}` is required")]
+    InsufficientPermissions(String),
+
+    #[error("introspection failed :: {0}")]
+    Introspection(#[from] reqwest::Error),
+
+    #[error("introspection responded with status {0}")]
+    IntrospectionStatus(u16),
+}
+
+#[derive(Serialize)]
+struct IntrospectionRequest<'a> {
+    permissions: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct IntrospectionResponse {
+    permissions: HashMap<String, bool>,
+}
+
+/// Headers carrying the caller's credentials.
+const FORWARDED_HEADERS: [header::HeaderName; 2] = [header::AUTHORIZATION, header::COOKIE];
+
+impl Authorizer {
+    pub fn new(client: reqwest::Client, introspect_url: String) -> Self {
+        Self {
+            client,
+            introspect_url,
+        }
+    }
+
+    /// Requires the caller to hold `method:path` or a wildcard permission above it.
+    pub async fn require(
+        &self,
+        headers: &HeaderMap,
+        method: &str,
+        path: &str,
+    ) -> Result<(), AuthorizationError> {
+        let mut request = self.client.post(&self.introspect_url);
+        let mut has_credentials = false;
+        for name in FORWARDED_HEADERS {
+            for value in headers.get_all(&name) {
+                request = request.header(&name, value);
+                has_credentials = true;
+            }
+        }
+
+        if !has_credentials {
+            return Err(AuthorizationError::NoCredentialsProvided);
+        }
+
+        let permissions = candidates(method, path);
+        let response = request
+            .json(&IntrospectionRequest {
+                permissions: &permissions,
+            })
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::OK => {}
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                return Err(AuthorizationError::Unauthenticated);
+            }
+            status => return Err(AuthorizationError::IntrospectionStatus(status.as_u16())),
+        }
+
+        let IntrospectionResponse { permissions: held } = response.json().await?;
+        match permissions
+            .iter()
+            .any(|permission| held.get(permission).copied().unwrap_or(false))
+        {
+            true => Ok(()),
+            false => Err(AuthorizationError::InsufficientPermissions(
+                permissions.into_iter().next().unwrap_or_default(),
+            )),
+        }
+    }
+}
+
+/// The exact permission first, followed by the wildcards of every ancestor,
+/// e.g. `get:/kv/data/a/b`, `get:/kv/data/a/*`, `get:/kv/data/*` and `get:/kv/*`.
+fn candidates(method: &str, path: &str) -> Vec<String> {
+    let mut permissions = vec![format!("{method}:{path}")];
+
+    let mut ancestor = path;
+    while let Some((parent, _)) = ancestor.rsplit_once('/') {
+        if parent.is_empty() {
+            break;
+        }
+        permissions.push(format!("{method}:{parent}/*"));
+        ancestor = parent;
+    }
+
+    permissions
+}
+
+impl extra::ErrorKind for AuthorizationError {
+    fn kind(&self) -> &'static str {
+        match self {
+            AuthorizationError::NoCredentialsProvided => "vault.no-credentials",
+            AuthorizationError::Unauthenticated => "vault.unauthenticated",
+            AuthorizationError::InsufficientPermissions(_) => "vault.insufficient-permissions",
+            AuthorizationError::Introspection(_) => "vault.introspection",
+            AuthorizationError::IntrospectionStatus(_) => "vault.introspection.status",
+        }
+    }
+}
+
+impl IntoResponse for AuthorizationError {
+    fn into_response(self) -> Response {
+        match self {
+            AuthorizationError::NoCredentialsProvided | AuthorizationError::Unauthenticated => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::UNAUTHORIZED, Json(ErrorResponse::from(self))).into_response()
+            }
+            AuthorizationError::InsufficientPermissions(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("{:?}", self);
+
+                (StatusCode::FORBIDDEN, Json(ErrorResponse::from(self))).into_response()
+            }
+            AuthorizationError::Introspection(_) | AuthorizationError::IntrospectionStatus(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("{:?}", self);
+
+                StatusCode::BAD_GATEWAY.into_response()
+            }
+        }
+    }
+}
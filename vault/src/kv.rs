@@ -0,0 +1,154 @@
+//! Shared pieces of the key/value secrets API.
+
+use axum::{
+    Json,
+    response::{IntoResponse, Response},
+};
+use extra::ErrorResponse;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Sqlite};
+use time::OffsetDateTime;
+
+const MAX_PATH_LEN: usize = 512;
+const MAX_PATH_SEGMENTS: usize = 32;
+
+/// Upper bound of versions addressed by a single delete, undelete or destroy.
+pub const MAX_VERSIONS: usize = 100;
+
+/// Versions addressed by `/kv/delete`, `/kv/undelete` and `/kv/destroy`.
+#[derive(Deserialize, Debug)]
+pub struct VersionsBody {
+    pub versions: Vec<i64>,
+}
+
+impl VersionsBody {
+    pub fn validate(&self) -> Result<(), VersionsError> {
+        match self.versions.len() {
+            0 => Err(VersionsError::Empty),
+            n if n > MAX_VERSIONS => Err(VersionsError::TooMany(n)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum VersionsError {
+    #[error("at least one version is required")]
+    Empty,
+
+    #[error("at most {MAX_VERSIONS} versions can be addressed at once, got {0}")]
+    TooMany(usize),
+}
+
+#[derive(Serialize, Debug)]
+pub struct VersionMetadata {
+    pub version: i64,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+
+    /// set while the version is soft deleted
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub deleted_at: Option<OffsetDateTime>,
+}
+
+/// Permanently removes the versions that fell out of the secret's `max_versions`.
+pub async fn prune<'a, E: Executor<'a, Database = Sqlite>>(
+    ex: E,
+    secret_id: i64,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM kv_versions
+        WHERE secret_id = ?1
+        AND version <= (SELECT current_version - max_versions FROM kv_secrets WHERE id = ?1)
+        "#,
+        secret_id
+    )
+    .execute(ex)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PathError {
+    #[error("secret path is empty")]
+    Empty,
+
+    #[error("secret path is longer than {MAX_PATH_LEN} characters")]
+    TooLong,
+
+    #[error("secret path has more than {MAX_PATH_SEGMENTS} segments")]
+    TooDeep,
+
+    #[error("invalid secret path segment `{0}`")]
+    InvalidSegment(String),
+}
+
+/// Checks a secret path such as `app/db/password`: `/` separated segments of
+/// ASCII alphanumerics, `-`, `_` and `.`, without empty, `.` or `..` segments.
+pub fn validate_path(path: &str) -> Result<(), PathError> {
+    if path.is_empty() {
+        return Err(PathError::Empty);
+    }
+
+    if path.len() > MAX_PATH_LEN {
+        return Err(PathError::TooLong);
+    }
+
+    if path.split('/').count() > MAX_PATH_SEGMENTS {
+        return Err(PathError::TooDeep);
+    }
+
+    match path.split('/').find(|segment| {
+        segment.is_empty()
+            || *segment == "."
+            || *segment == ".."
+            || !segment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    }) {
+        Some(segment) => Err(PathError::InvalidSegment(segment.to_string())),
+        None => Ok(()),
+    }
+}
+
+impl extra::ErrorKind for PathError {
+    fn kind(&self) -> &'static str {
+        match self {
+            PathError::Empty => "kv.path.empty",
+            PathError::TooLong => "kv.path.too-long",
+            PathError::TooDeep => "kv.path.too-deep",
+            PathError::InvalidSegment(_) => "kv.path.invalid-segment",
+        }
+    }
+}
+
+impl IntoResponse for PathError {
+    fn into_response(self) -> Response {
+        #[cfg(feature = "tracing")]
+        tracing::info!("{:?}", self);
+
+        (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(self))).into_response()
+    }
+}
+
+impl extra::ErrorKind for VersionsError {
+    fn kind(&self) -> &'static str {
+        match self {
+            VersionsError::Empty => "kv.versions.empty",
+            VersionsError::TooMany(_) => "kv.versions.too-many",
+        }
+    }
+}
+
+impl IntoResponse for VersionsError {
+    fn into_response(self) -> Response {
+        #[cfg(feature = "tracing")]
+        tracing::info!("{:?}", self);
+
+        (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(self))).into_response()
+    }
+}
@@ -0,0 +1,170 @@
+//! Path-addressed key/value secrets with version history, soft deletion and encryption at rest,
+//! an internal certificate authority for mTLS between services, see [`pki`], and encryption as a
+//! service with named keys that never leave the vault, see [`transit`].
+//! Access is governed by the auth service's `method:/path` permissions, see [`authorizer`].
+
+mod api;
+mod authorizer;
+mod kv;
+mod pki;
+mod sealing;
+mod transit;
+
+pub use sealing::{MasterKey, MasterKeyError};
+
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{Router, extract::FromRef};
+use contextual::Context;
+use http::HeaderName;
+use secrets::Secrets;
+use tokio::net::TcpListener;
+use tower::ServiceBuilder;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+
+use crate::authorizer::Authorizer;
+
+#[derive(Debug)]
+pub struct ServerOpts {
+    pub database: DatabaseConfig,
+    /// Encrypts the data of every secret version, and every key, at rest.
+    pub master_key: MasterKey,
+    /// Holds the material of the transit keys, see [`transit`].
+    pub secrets_dir: std::path::PathBuf,
+    pub auth: AuthConfig,
+}
+
+#[derive(Debug)]
+pub struct DatabaseConfig {
+    pub url: String,
+}
+
+#[derive(Debug)]
+pub struct AuthConfig {
+    /// The auth service's `/introspect` endpoint, e.g. `http://localhost:8080/introspect`.
+    pub introspect_url: String,
+    /// How long the auth service has to respond.
+    pub timeout: std::time::Duration,
+}
+
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: sqlx::Pool<sqlx::Sqlite>,
+    pub master_key: Arc<MasterKey>,
+    pub secrets: Secrets,
+    pub authorizer: Authorizer,
+}
+
+pub async fn router(opts: ServerOpts) -> Result<Router, ServerError> {
+    use crate::api::{
+        kv::{data, delete, destroy, metadata, undelete},
+        pki, transit,
+    };
+
+    let router = Router::new()
+        .route(data::PATH, data::method_router())
+        .route(data::write::PATH, data::write::method_router())
+        .route(data::delete::PATH, data::delete::method_router())
+        .route(delete::PATH, delete::method_router())
+        .route(undelete::PATH, undelete::method_router())
+        .route(destroy::PATH, destroy::method_router())
+        .route(metadata::PATH, metadata::method_router())
+        .route(metadata::update::PATH, metadata::update::method_router())
+        .route(metadata::delete::PATH, metadata::delete::method_router())
+        .route(pki::root::PATH, pki::root::method_router())
+        .route(pki::intermediate::PATH, pki::intermediate::method_router())
+        .route(pki::roles::PATH, pki::roles::method_router())
+        .route(pki::roles::update::PATH, pki::roles::update::method_router())
+        .route(pki::sign::PATH, pki::sign::method_router())
+        .route(pki::revoke::PATH, pki::revoke::method_router())
+        .route(pki::certs::PATH, pki::certs::method_router())
+        .route(pki::ca::PATH, pki::ca::method_router())
+        .route(pki::crl::PATH, pki::crl::method_router())
+        .route(pki::ocsp::PATH, pki::ocsp::method_router())
+        .route(transit::keys::PATH, transit::keys::method_router())
+        .route(transit::keys::create::PATH, transit::keys::create::method_router())
+        .route(transit::keys::update::PATH, transit::keys::update::method_router())
+        .route(transit::keys::rotate::PATH, transit::keys::rotate::method_router())
+        .route(transit::encrypt::PATH, transit::encrypt::method_router())
+        .route(transit::decrypt::PATH, transit::decrypt::method_router())
+        .route(transit::rewrap::PATH, transit::rewrap::method_router())
+        .route(transit::sign::PATH, transit::sign::method_router())
+        .route(transit::verify::PATH, transit::verify::method_router());
+
+    const X_TRACE_ID: HeaderName = HeaderName::from_static("x-trace-id");
+    let middleware = ServiceBuilder::new()
+        .layer(SetRequestIdLayer::new(X_TRACE_ID, MakeRequestUuid))
+        .layer(PropagateRequestIdLayer::new(X_TRACE_ID));
+
+    #[cfg(feature = "tracing")]
+    let middleware = middleware.layer(tower_http::trace::TraceLayer::new_for_http());
+
+    let router = router.layer(middleware);
+
+    let pool = opts
+        .database
+        .pool()
+        .await
+        .context(format!("connect database :: {}", opts.database.url))?;
+
+    let client = reqwest::Client::builder()
+        .timeout(opts.auth.timeout)
+        .build()
+        .context("build auth http client")?;
+
+    let master_key = Arc::new(opts.master_key);
+    // transit keys are versioned by the database, never retired
+    let secrets = Secrets::new(opts.secrets_dir, std::time::Duration::ZERO)
+        .with_master_key(master_key.clone());
+
+    let router = router.with_state(AppState {
+        pool,
+        master_key,
+        secrets,
+        authorizer: Authorizer::new(client, opts.auth.introspect_url),
+    });
+
+    Ok(router)
+}
+
+/// Returns the local address that the listener is bound to.
+/// This can be useful, for example, when binding to port 0 to figure out which port was actually bound.
+pub async fn serve(server: Router, port: u16) -> Result<SocketAddr, ServerError> {
+    let listener = TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], port)))
+        .await
+        .context("bind")?;
+
+    let local_addr = listener.local_addr().context("local_addr")?;
+
+    #[cfg(feature = "tracing")]
+    tracing::info!("listening on {}", local_addr);
+
+    axum::serve(listener, server.into_make_service())
+        .await
+        .context("axum::serve")?;
+    Ok(local_addr)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ServerError {
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+
+    #[error("{0}")]
+    Io(#[from] contextual::Error<std::io::Error>),
+
+    #[error("{0}")]
+    HttpClient(#[from] contextual::Error<reqwest::Error>),
+}
+
+impl FromRef<AppState> for sqlx::Pool<sqlx::Sqlite> {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.pool.clone()
+    }
+}
+
+impl DatabaseConfig {
+    pub async fn pool(&self) -> Result<sqlx::Pool<sqlx::Sqlite>, sqlx::Error> {
+        sqlx::Pool::<sqlx::Sqlite>::connect(&self.url).await
+    }
+}
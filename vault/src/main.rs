@@ -0,0 +1,71 @@
+use clap::Parser;
+
+#[derive(Debug, clap::Parser)]
+struct Serve {
+    /// The port number on which the server will listen for incoming connections.
+    /// Example: `8200`
+    #[arg(long, env("PORT"))]
+    #[cfg_attr(debug_assertions, arg(default_value_t = 0))]
+    port: u16,
+
+    /// The database connection URL used by the server.
+    /// Example: `sqlite:///tmp/data/vault.db` (or) `/tmp/data/vault.db` (or) `./vault.db`
+    #[arg(long, env("DATABASE_URL"))]
+    database_url: String,
+
+    /// Base64 encoded 32 byte key encrypting the secrets at rest.
+    /// Generate one with `openssl rand -base64 32`.
+    #[arg(long, env("VAULT_MASTER_KEY"), hide_env_values = true)]
+    master_key: String,
+
+    /// The directory holding the transit keys' material, sealed with the master key.
+    /// Example: `./secrets` or `/var/lib/vault/secrets`
+    #[arg(long, env("SECRETS_DIR"))]
+    secrets_dir: std::path::PathBuf,
+
+    /// The auth service's introspection endpoint, callers' credentials are checked against it.
+    /// Example: `http://localhost:8080/introspect`
+    #[arg(long, env("AUTH_INTROSPECT_URL"))]
+    auth_introspect_url: String,
+
+    /// How long (in seconds) the auth service has to respond.
+    /// Example: `5`
+    #[arg(long, env("AUTH_TIMEOUT_SEC"), default_value_t = 5)]
+    auth_timeout_sec: u64,
+}
+
+#[tokio::main]
+async fn main() {
+    #[cfg(feature = "tracing")]
+    {
+        use tracing_subscriber::{EnvFilter, fmt};
+
+        fmt()
+            .with_env_filter(EnvFilter::from_default_env(/* RUST_LOG env var sets logging level */))
+            .init()
+    };
+
+    let args = Serve::parse();
+
+    let port = args.port;
+    let opts = vault::ServerOpts {
+        database: vault::DatabaseConfig {
+            url: args.database_url,
+        },
+        master_key: vault::MasterKey::from_base64(&args.master_key).unwrap_or_else(|e| exit(e)),
+        secrets_dir: args.secrets_dir,
+        auth: vault::AuthConfig {
+            introspect_url: args.auth_introspect_url,
+            timeout: std::time::Duration::from_secs(args.auth_timeout_sec),
+        },
+    };
+
+    let router = vault::router(opts).await.unwrap_or_else(|e| exit(e));
+    vault::serve(router, port).await.unwrap_or_else(|e| exit(e));
+}
+
+#[inline(always)]
+fn exit(err: impl std::error::Error) -> ! {
+    eprintln!("{err}");
+    std::process::exit(1)
+}
@@ -0,0 +1,403 @@
+//! Internal certificate authority, issuing certificates for mTLS between services.
+//!
+//! Issuers are root or intermediate CAs whose private keys are sealed under the master key.
+//! Roles bind an issuer to the names it may certify and the longest validity it may grant, and
+//! CSRs are always signed against a role. Every issued certificate is recorded by its serial, and
+//! revocations are published per issuer through a CRL and an OCSP responder, see [`ocsp`].
+
+pub mod ocsp;
+
+use axum::{
+    Json,
+    response::{IntoResponse, Response},
+};
+use contextual::Context;
+use extra::ErrorResponse;
+use http::StatusCode;
+use rand::RngCore;
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType, IsCa, KeyPair,
+    KeyUsagePurpose, SerialNumber,
+};
+use serde::Serialize;
+use sqlx::{Executor, Sqlite};
+use time::{Duration, OffsetDateTime};
+
+use crate::sealing::{self, MasterKey, OpenError};
+
+const MAX_NAME_LEN: usize = 64;
+
+/// RFC 5280 upper bound of a common name.
+const MAX_COMMON_NAME_LEN: usize = 64;
+
+/// Longest validity of a CA certificate, 20 years.
+pub const MAX_CA_TTL_SEC: i64 = 20 * 365 * 24 * 60 * 60;
+
+/// Longest validity a role can grant to service certificates, one year.
+pub const MAX_TTL_SEC: i64 = 365 * 24 * 60 * 60;
+
+/// Certificates are valid slightly before they are issued, to tolerate clock skew between services.
+const BACKDATE: Duration = Duration::seconds(30);
+
+/// How long a published CRL stays authoritative.
+pub const CRL_VALIDITY: Duration = Duration::hours(24);
+
+/// An issuer with its unsealed key, ready to sign.
+pub struct Issuer {
+    pub id: i64,
+    pub name: String,
+    pub parent_id: Option<i64>,
+    pub certificate_pem: String,
+    pub not_after: OffsetDateTime,
+    pub crl_number: i64,
+    /// DER encoded subject, as hashed by OCSP clients
+    pub subject: Vec<u8>,
+    pub key: KeyPair,
+    /// re-created from `certificate_pem`, carries the subject and key identifier used when signing
+    pub certificate: Certificate,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Role {
+    pub name: String,
+
+    pub issuer: String,
+
+    /// DNS names that may be requested, see `allow_subdomains`
+    pub allowed_domains: Vec<String>,
+
+    /// also allow any name below the allowed domains, e.g. `api.svc.internal` for `svc.internal`
+    pub allow_subdomains: bool,
+
+    pub allow_ip_sans: bool,
+
+    pub max_ttl_sec: i64,
+
+    /// adds the TLS server authentication extended key usage
+    pub server_auth: bool,
+
+    /// adds the TLS client authentication extended key usage
+    pub client_auth: bool,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+impl Role {
+    pub fn allows_dns_name(&self, name: &str) -> bool {
+        let name = name.to_ascii_lowercase();
+        is_dns_name(&name)
+            && self.allowed_domains.iter().any(|domain| {
+                name == *domain
+                    || (self.allow_subdomains
+                        && name
+                            .strip_suffix(domain.as_str())
+                            .is_some_and(|prefix| prefix.ends_with('.')))
+            })
+    }
+}
+
+/// Failures of the stored PKI state, never caused by the request.
+#[derive(thiserror::Error, Debug)]
+pub enum StoreError {
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+
+    #[error("stored allowed domains are not a JSON array :: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("unable to decrypt the issuer key :: {0}")]
+    Open(#[from] OpenError),
+
+    #[error("stored issuer is not usable :: {0}")]
+    Certificate(#[from] rcgen::Error),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ParamsError {
+    #[error("invalid name `{0}`, expected up to {MAX_NAME_LEN} of `a-z`, `0-9`, `-` and `_`")]
+    Name(String),
+
+    #[error("common name must be between 1 and {MAX_COMMON_NAME_LEN} characters")]
+    CommonName,
+
+    #[error("ttl_sec must be between 1 and {1}, got {0}")]
+    Ttl(i64, i64),
+
+    #[error("invalid serial `{0}`, expected hex")]
+    Serial(String),
+}
+
+/// Issuer and role names are a single path segment, e.g. `services-2025`.
+pub fn validate_name(name: &str) -> Result<(), ParamsError> {
+    match !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_'))
+    {
+        true => Ok(()),
+        false => Err(ParamsError::Name(name.to_string())),
+    }
+}
+
+pub fn validate_common_name(common_name: &str) -> Result<(), ParamsError> {
+    match (1..=MAX_COMMON_NAME_LEN).contains(&common_name.chars().count()) {
+        true => Ok(()),
+        false => Err(ParamsError::CommonName),
+    }
+}
+
+pub fn validate_ttl(ttl_sec: i64, max_ttl_sec: i64) -> Result<Duration, ParamsError> {
+    match (1..=max_ttl_sec).contains(&ttl_sec) {
+        true => Ok(Duration::seconds(ttl_sec)),
+        false => Err(ParamsError::Ttl(ttl_sec, max_ttl_sec)),
+    }
+}
+
+/// Accepts `0a1b..` as well as the `0A:1B:..` notation of openssl, returns lowercase hex.
+pub fn normalize_serial(serial: &str) -> Result<String, ParamsError> {
+    let normalized = serial.replace(':', "").to_ascii_lowercase();
+    match !normalized.is_empty()
+        && normalized.len() <= 40
+        && normalized.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        true => Ok(normalized),
+        false => Err(ParamsError::Serial(serial.to_string())),
+    }
+}
+
+/// Lowercase DNS names of letters, digits and hyphens, without wildcards.
+pub fn is_dns_name(name: &str) -> bool {
+    name.len() <= 253
+        && name.split('.').all(|label| {
+            (1..=63).contains(&label.len())
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        })
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// A random positive 128 bit serial without leading zero byte, so its DER encoding is always
+/// 16 bytes and matches its hex form.
+pub fn generate_serial() -> SerialNumber {
+    let mut serial = [0u8; 16];
+    rand::rng().fill_bytes(&mut serial);
+    serial[0] = serial[0] & 0x7f | 0x40;
+    SerialNumber::from_slice(&serial)
+}
+
+/// Whole seconds, which is all X.509 time fields can hold.
+pub fn now() -> OffsetDateTime {
+    let now = OffsetDateTime::now_utc();
+    now.replace_nanosecond(0).unwrap_or(now)
+}
+
+/// Validity of a new certificate: backdated a little and ending `ttl` from now.
+pub fn validity(ttl: Duration) -> (OffsetDateTime, OffsetDateTime) {
+    let now = now();
+    (now - BACKDATE, now + ttl)
+}
+
+/// Parameters of a CA certificate. Only roots may sign intermediates, intermediates only sign
+/// service certificates.
+pub fn ca_params(
+    common_name: &str,
+    not_before: OffsetDateTime,
+    not_after: OffsetDateTime,
+    root: bool,
+) -> CertificateParams {
+    let mut distinguished_name = DistinguishedName::new();
+    distinguished_name.push(DnType::CommonName, common_name);
+
+    let mut params = CertificateParams::default();
+    params.distinguished_name = distinguished_name;
+    params.not_before = not_before;
+    params.not_after = not_after;
+    params.serial_number = Some(generate_serial());
+    params.is_ca = IsCa::Ca(match root {
+        true => BasicConstraints::Constrained(1),
+        false => BasicConstraints::Constrained(0),
+    });
+    params.key_usages = vec![
+        KeyUsagePurpose::KeyCertSign,
+        KeyUsagePurpose::CrlSign,
+        KeyUsagePurpose::DigitalSignature,
+    ];
+    params.use_authority_key_identifier_extension = !root;
+    params
+}
+
+fn key_path(name: &str) -> String {
+    format!("pki/issuers/{name}")
+}
+
+pub fn seal_key(master_key: &MasterKey, name: &str, key: &KeyPair) -> Vec<u8> {
+    let pkcs8 = zeroize::Zeroizing::new(key.serialize_der());
+    sealing::seal(master_key, &key_path(name), 0, &pkcs8)
+}
+
+pub async fn load_issuer<'a, E: Executor<'a, Database = Sqlite>>(
+    ex: E,
+    master_key: &MasterKey,
+    name: &str,
+) -> Result<Option<Issuer>, StoreError> {
+    let Some(row) = sqlx::query!(
+        r#"
+        SELECT
+            id as "id!",
+            parent_id,
+            certificate_pem,
+            key_ciphertext,
+            not_after as "not_after: OffsetDateTime",
+            crl_number
+        FROM pki_issuers
+        WHERE name = ?
+        "#,
+        name
+    )
+    .fetch_optional(ex)
+    .await
+    .context("select issuer")?
+    else {
+        return Ok(None);
+    };
+
+    let pkcs8 = sealing::open(master_key, &key_path(name), 0, &row.key_ciphertext)?;
+    let key = KeyPair::try_from(pkcs8.as_slice())?;
+    let certificate =
+        CertificateParams::from_ca_cert_pem(&row.certificate_pem)?.self_signed(&key)?;
+    let subject = x509_parser::pem::parse_x509_pem(row.certificate_pem.as_bytes())
+        .ok()
+        .and_then(|(_, pem)| Some(pem.parse_x509().ok()?.subject().as_raw().to_vec()))
+        .ok_or(rcgen::Error::CouldNotParseCertificate)?;
+
+    Ok(Some(Issuer {
+        id: row.id,
+        name: name.to_string(),
+        parent_id: row.parent_id,
+        certificate_pem: row.certificate_pem,
+        not_after: row.not_after,
+        crl_number: row.crl_number,
+        subject,
+        key,
+        certificate,
+    }))
+}
+
+pub async fn load_role<'a, E: Executor<'a, Database = Sqlite>>(
+    ex: E,
+    name: &str,
+) -> Result<Option<Role>, StoreError> {
+    let Some(row) = sqlx::query!(
+        r#"
+        SELECT
+            pki_roles.name,
+            pki_issuers.name as "issuer!",
+            allowed_domains,
+            allow_subdomains,
+            allow_ip_sans,
+            max_ttl_sec,
+            server_auth,
+            client_auth,
+            pki_roles.created_at as "created_at: OffsetDateTime",
+            updated_at as "updated_at: OffsetDateTime"
+        FROM pki_roles
+        INNER JOIN pki_issuers ON pki_issuers.id = pki_roles.issuer_id
+        WHERE pki_roles.name = ?
+        "#,
+        name
+    )
+    .fetch_optional(ex)
+    .await
+    .context("select role")?
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(Role {
+        name: row.name,
+        issuer: row.issuer,
+        allowed_domains: serde_json::from_str(&row.allowed_domains)?,
+        allow_subdomains: row.allow_subdomains,
+        allow_ip_sans: row.allow_ip_sans,
+        max_ttl_sec: row.max_ttl_sec,
+        server_auth: row.server_auth,
+        client_auth: row.client_auth,
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+    }))
+}
+
+/// PEM certificates from the issuer up to its root.
+pub async fn ca_chain(
+    pool: &sqlx::Pool<Sqlite>,
+    issuer: &Issuer,
+) -> Result<Vec<String>, StoreError> {
+    let mut chain = vec![issuer.certificate_pem.clone()];
+
+    let mut parent_id = issuer.parent_id;
+    while let Some(id) = parent_id {
+        let parent = sqlx::query!(
+            "SELECT parent_id, certificate_pem FROM pki_issuers WHERE id = ?",
+            id
+        )
+        .fetch_one(pool)
+        .await
+        .context("select parent issuer")?;
+
+        chain.push(parent.certificate_pem);
+        parent_id = parent.parent_id;
+    }
+
+    Ok(chain)
+}
+
+impl extra::ErrorKind for StoreError {
+    fn kind(&self) -> &'static str {
+        match self {
+            StoreError::Sqlx(_) => "sqlx",
+            StoreError::Json(_) => "pki.store.json",
+            StoreError::Open(_) => "pki.store.sealed-key",
+            StoreError::Certificate(_) => "pki.store.certificate",
+        }
+    }
+}
+
+impl IntoResponse for StoreError {
+    fn into_response(self) -> Response {
+        #[cfg(feature = "tracing")]
+        tracing::error!("{:?}", self);
+
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    }
+}
+
+impl extra::ErrorKind for ParamsError {
+    fn kind(&self) -> &'static str {
+        match self {
+            ParamsError::Name(_) => "pki.name.invalid",
+            ParamsError::CommonName => "pki.common-name.invalid",
+            ParamsError::Ttl(..) => "pki.ttl.invalid",
+            ParamsError::Serial(_) => "pki.serial.invalid",
+        }
+    }
+}
+
+impl IntoResponse for ParamsError {
+    fn into_response(self) -> Response {
+        #[cfg(feature = "tracing")]
+        tracing::info!("{:?}", self);
+
+        (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(self))).into_response()
+    }
+}
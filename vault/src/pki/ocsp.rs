@@ -0,0 +1,278 @@
+//! A minimal OCSP responder (RFC 6960) for the certificates of one issuer.
+//!
+//! Requests are answered by the issuer itself, identified by the hash of its key, so clients
+//! need nothing but the CA certificate to verify the responses. Only the `POST` binding is
+//! supported and request signatures are ignored; a request nonce is echoed back.
+
+use ring::{
+    digest,
+    rand::SystemRandom,
+    signature::{ECDSA_P256_SHA256_ASN1_SIGNING, EcdsaKeyPair},
+};
+use time::{Duration, OffsetDateTime};
+use yasna::{
+    ASN1Error, ASN1ErrorKind, Tag,
+    models::{GeneralizedTime, ObjectIdentifier},
+};
+
+/// How long a response may be cached by clients.
+const RESPONSE_VALIDITY: Duration = Duration::hours(1);
+
+/// Upper bound of certificates asked about in a single request.
+const MAX_CERT_IDS: usize = 16;
+
+const OID_SHA1: &[u64] = &[1, 3, 14, 3, 2, 26];
+const OID_SHA256: &[u64] = &[2, 16, 840, 1, 101, 3, 4, 2, 1];
+const OID_ECDSA_WITH_SHA256: &[u64] = &[1, 2, 840, 10045, 4, 3, 2];
+const OID_OCSP_BASIC: &[u64] = &[1, 3, 6, 1, 5, 5, 7, 48, 1, 1];
+const OID_OCSP_NONCE: &[u64] = &[1, 3, 6, 1, 5, 5, 7, 48, 1, 2];
+
+/// `OCSPResponseStatus` of responses without a signed body.
+#[derive(Debug, Clone, Copy)]
+pub enum ErrorStatus {
+    MalformedRequest = 1,
+    InternalError = 2,
+    Unauthorized = 6,
+}
+
+/// A certificate asked about, as identified by the client.
+#[derive(Debug)]
+pub struct CertId {
+    /// echoed back verbatim in the response
+    raw: Vec<u8>,
+    digest: &'static digest::Algorithm,
+    issuer_name_hash: Vec<u8>,
+    issuer_key_hash: Vec<u8>,
+    /// lowercase hex of the serial, as stored
+    pub serial: String,
+}
+
+#[derive(Debug)]
+pub struct Request {
+    pub cert_ids: Vec<CertId>,
+    /// the complete nonce extension
+    nonce: Option<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub enum CertStatus {
+    Good,
+    Revoked {
+        at: OffsetDateTime,
+        reason: Option<i64>,
+    },
+    Unknown,
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("unable to sign the OCSP response")]
+pub struct SignError;
+
+impl CertId {
+    /// Whether the certificate was asked about for the issuer with the given subject and key,
+    /// as found in its certificate.
+    pub fn is_issued_by(&self, issuer_subject: &[u8], issuer_public_key: &[u8]) -> bool {
+        digest::digest(self.digest, issuer_subject).as_ref() == self.issuer_name_hash
+            && digest::digest(self.digest, issuer_public_key).as_ref() == self.issuer_key_hash
+    }
+}
+
+pub fn parse_request(der: &[u8]) -> Result<Request, ASN1Error> {
+    let request = yasna::parse_der(der, |reader| {
+        reader.read_sequence(|reader| {
+            let request = reader.next().read_sequence(|reader| {
+                // version and requestorName
+                reader.read_optional(|reader| {
+                    reader.read_tagged(Tag::context(0), |r| r.read_der())
+                })?;
+                reader.read_optional(|reader| {
+                    reader.read_tagged(Tag::context(1), |r| r.read_der())
+                })?;
+
+                let cert_ids = reader.next().collect_sequence_of(|reader| {
+                    reader.read_sequence(|reader| {
+                        let cert_id = reader.next().read_der()?;
+                        // singleRequestExtensions
+                        reader.read_optional(|reader| {
+                            reader.read_tagged(Tag::context(0), |r| r.read_der())
+                        })?;
+                        Ok(cert_id)
+                    })
+                })?;
+
+                let extensions = reader.read_optional(|reader| {
+                    reader.read_tagged(Tag::context(2), |reader| {
+                        reader.collect_sequence_of(|reader| reader.read_der())
+                    })
+                })?;
+
+                Ok((cert_ids, extensions.unwrap_or_default()))
+            })?;
+
+            // optionalSignature
+            reader.read_optional(|reader| reader.read_tagged(Tag::context(0), |r| r.read_der()))?;
+            Ok(request)
+        })
+    })?;
+
+    let (cert_ids, extensions) = request;
+    if cert_ids.is_empty() || cert_ids.len() > MAX_CERT_IDS {
+        return Err(ASN1Error::new(ASN1ErrorKind::Invalid));
+    }
+
+    let mut nonce = None;
+    for extension in extensions {
+        let oid = yasna::parse_der(&extension, |reader| {
+            reader.read_sequence(|reader| {
+                let oid = reader.next().read_oid()?;
+                reader.read_optional(|reader| reader.read_bool())?;
+                reader.next().read_bytes()?;
+                Ok(oid)
+            })
+        })?;
+        if oid == ObjectIdentifier::from_slice(OID_OCSP_NONCE) {
+            nonce = Some(extension);
+        }
+    }
+
+    Ok(Request {
+        cert_ids: cert_ids
+            .into_iter()
+            .map(parse_cert_id)
+            .collect::<Result<_, _>>()?,
+        nonce,
+    })
+}
+
+fn parse_cert_id(raw: Vec<u8>) -> Result<CertId, ASN1Error> {
+    let (digest, issuer_name_hash, issuer_key_hash, serial) = yasna::parse_der(&raw, |reader| {
+        reader.read_sequence(|reader| {
+            let algorithm = reader.next().read_sequence(|reader| {
+                let oid = reader.next().read_oid()?;
+                reader.read_optional(|reader| reader.read_null())?;
+                Ok(oid)
+            })?;
+            let digest = match algorithm.components().as_slice() {
+                OID_SHA1 => &digest::SHA1_FOR_LEGACY_USE_ONLY,
+                OID_SHA256 => &digest::SHA256,
+                _ => return Err(ASN1Error::new(ASN1ErrorKind::Invalid)),
+            };
+
+            let issuer_name_hash = reader.next().read_bytes()?;
+            let issuer_key_hash = reader.next().read_bytes()?;
+            let (serial, _) = reader.next().read_bigint_bytes()?;
+            Ok((digest, issuer_name_hash, issuer_key_hash, serial))
+        })
+    })?;
+
+    let leading_zeros = serial.iter().take_while(|b| **b == 0).count();
+    Ok(CertId {
+        serial: super::hex(&serial[leading_zeros..]),
+        raw,
+        digest,
+        issuer_name_hash,
+        issuer_key_hash,
+    })
+}
+
+/// A successful response, signed by the issuer's P-256 key.
+pub fn response(
+    issuer_pkcs8: &[u8],
+    issuer_public_key: &[u8],
+    request: &Request,
+    statuses: &[CertStatus],
+) -> Result<Vec<u8>, SignError> {
+    let now = super::now();
+    let time = GeneralizedTime::from_datetime;
+
+    let response_data = yasna::construct_der(|writer| {
+        writer.write_sequence(|writer| {
+            // responderID, byKey
+            writer.next().write_tagged(Tag::context(2), |writer| {
+                writer.write_bytes(
+                    digest::digest(&digest::SHA1_FOR_LEGACY_USE_ONLY, issuer_public_key).as_ref(),
+                )
+            });
+            writer.next().write_generalized_time(&time(now));
+            writer.next().write_sequence_of(|writer| {
+                for (cert_id, status) in request.cert_ids.iter().zip(statuses) {
+                    writer.next().write_sequence(|writer| {
+                        writer.next().write_der(&cert_id.raw);
+                        match status {
+                            CertStatus::Good => writer
+                                .next()
+                                .write_tagged_implicit(Tag::context(0), |w| w.write_null()),
+                            CertStatus::Revoked { at, reason } => writer
+                                .next()
+                                .write_tagged_implicit(Tag::context(1), |writer| {
+                                    writer.write_sequence(|writer| {
+                                        writer.next().write_generalized_time(&time(*at));
+                                        if let Some(reason) = reason {
+                                            writer.next().write_tagged(Tag::context(0), |w| {
+                                                w.write_enum(*reason)
+                                            });
+                                        }
+                                    })
+                                }),
+                            CertStatus::Unknown => writer
+                                .next()
+                                .write_tagged_implicit(Tag::context(2), |w| w.write_null()),
+                        }
+                        writer.next().write_generalized_time(&time(now));
+                        writer.next().write_tagged(Tag::context(0), |writer| {
+                            writer.write_generalized_time(&time(now + RESPONSE_VALIDITY))
+                        });
+                    });
+                }
+            });
+            if let Some(nonce) = &request.nonce {
+                writer.next().write_tagged(Tag::context(1), |writer| {
+                    writer.write_sequence(|writer| writer.next().write_der(nonce))
+                });
+            }
+        })
+    });
+
+    let rng = SystemRandom::new();
+    let signature = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, issuer_pkcs8, &rng)
+        .map_err(|_| SignError)?
+        .sign(&rng, &response_data)
+        .map_err(|_| SignError)?;
+
+    let basic_response = yasna::construct_der(|writer| {
+        writer.write_sequence(|writer| {
+            writer.next().write_der(&response_data);
+            writer.next().write_sequence(|writer| {
+                writer
+                    .next()
+                    .write_oid(&ObjectIdentifier::from_slice(OID_ECDSA_WITH_SHA256))
+            });
+            let signature = signature.as_ref();
+            writer
+                .next()
+                .write_bitvec_bytes(signature, signature.len() * 8);
+        })
+    });
+
+    Ok(yasna::construct_der(|writer| {
+        writer.write_sequence(|writer| {
+            // successful
+            writer.next().write_enum(0);
+            writer.next().write_tagged(Tag::context(0), |writer| {
+                writer.write_sequence(|writer| {
+                    writer
+                        .next()
+                        .write_oid(&ObjectIdentifier::from_slice(OID_OCSP_BASIC));
+                    writer.next().write_bytes(&basic_response);
+                })
+            });
+        })
+    }))
+}
+
+/// An unsuccessful response, which carries nothing but its status.
+pub fn error_response(status: ErrorStatus) -> Vec<u8> {
+    yasna::construct_der(|writer| {
+        writer.write_sequence(|writer| writer.next().write_enum(status as i64))
+    })
+}
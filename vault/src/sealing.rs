@@ -0,0 +1,32 @@
+//! Encryption of secret data at rest, in the envelope format of [`secrets::sealing`].
+//!
+//! The secret's path and version are authenticated along with the data, so ciphertexts cannot be
+//! moved between rows. Private keys of the PKI issuers are sealed the same way, under
+//! `pki/issuers/<name>`. The material of the transit keys lives in the secrets directory instead,
+//! see [`crate::transit`].
+
+use secrets::sealing;
+use zeroize::Zeroizing;
+
+pub use secrets::sealing::{MasterKey, MasterKeyError};
+
+#[derive(thiserror::Error, Debug)]
+#[error("unable to decrypt secret data")]
+pub struct OpenError;
+
+fn aad(path: &str, version: i64) -> Vec<u8> {
+    format!("{path}#{version}").into_bytes()
+}
+
+pub fn seal(master_key: &MasterKey, path: &str, version: i64, plaintext: &[u8]) -> Vec<u8> {
+    sealing::seal(master_key, &aad(path, version), plaintext)
+}
+
+pub fn open(
+    master_key: &MasterKey,
+    path: &str,
+    version: i64,
+    sealed: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, OpenError> {
+    sealing::open(master_key, &aad(path, version), sealed).map_err(|_| OpenError)
+}
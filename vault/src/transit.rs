@@ -0,0 +1,444 @@
+//! Shared pieces of the transit API: encryption, decryption and signing with named keys that never
+//! leave the vault.
+//!
+//! Keys have numbered versions. New data is always encrypted or signed with the latest version,
+//! older versions keep decrypting and verifying until the key's `min_decryption_version` passes
+//! them. Ciphertexts and signatures carry the version that produced them: `vault:v<version>:<base64>`.
+//!
+//! The key material is stored like the auth service's keys: every transit key is a secret of the
+//! vault's [`Secrets`] directory, named after the key, with one version (`v<version>`) per key
+//! version, sealed under the master key. The database only tracks the versions.
+
+use axum::{
+    Json,
+    response::{IntoResponse, Response},
+};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use contextual::Context;
+use extra::ErrorResponse;
+use http::StatusCode;
+use rand::RngCore;
+use ring::{
+    rand::SystemRandom,
+    signature::{ED25519, Ed25519KeyPair, KeyPair, UnparsedPublicKey},
+};
+use secrets::{Secrets, sealing};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Sqlite};
+use time::OffsetDateTime;
+use zeroize::Zeroizing;
+
+const MAX_NAME_LEN: usize = 64;
+
+const PREFIX: &str = "vault:v";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyType {
+    /// encrypt, decrypt and rewrap
+    #[default]
+    Xchacha20Poly1305,
+
+    /// sign and verify
+    Ed25519,
+}
+
+pub struct Key {
+    pub id: i64,
+    pub name: String,
+    pub key_type: KeyType,
+    pub latest_version: i64,
+    pub min_decryption_version: i64,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Serialize, Debug)]
+pub struct KeyMetadata {
+    pub name: String,
+
+    #[serde(rename = "type")]
+    pub key_type: KeyType,
+
+    /// encrypts and signs new data
+    pub latest_version: i64,
+
+    /// ciphertexts and signatures of older versions are rejected
+    pub min_decryption_version: i64,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+
+    /// oldest first
+    pub versions: Vec<VersionMetadata>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct VersionMetadata {
+    pub version: i64,
+
+    /// base64, only for signing keys
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+/// Failures of the stored keys, never caused by the request.
+#[derive(thiserror::Error, Debug)]
+pub enum StoreError {
+    #[error("{0}")]
+    Sqlx(#[from] contextual::Error<sqlx::Error>),
+
+    #[error("{0}")]
+    Secrets(#[from] contextual::Error<std::io::Error>),
+
+    #[error("stored key type `{0}` is unknown")]
+    KeyType(String),
+
+    #[error("stored key material is not usable")]
+    Material,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ParamsError {
+    #[error("invalid key name `{0}`, expected up to {MAX_NAME_LEN} of `a-z`, `0-9`, `-` and `_`")]
+    Name(String),
+
+    #[error("`{0}` is not valid base64")]
+    Base64(&'static str),
+
+    #[error("`{0}` is not of the form `vault:v<version>:<base64>`")]
+    Versioned(&'static str),
+
+    #[error("a `{key_type}` key cannot {operation}")]
+    Operation {
+        key_type: &'static str,
+        operation: &'static str,
+    },
+
+    #[error("key version {0} does not exist")]
+    UnknownVersion(i64),
+
+    #[error("key version {version} is below the minimum decryption version {min}")]
+    DisabledVersion { version: i64, min: i64 },
+
+    #[error("unable to decrypt, the ciphertext or its context does not match")]
+    Decrypt,
+}
+
+impl KeyType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyType::Xchacha20Poly1305 => "xchacha20-poly1305",
+            KeyType::Ed25519 => "ed25519",
+        }
+    }
+
+    fn parse(key_type: &str) -> Option<Self> {
+        [KeyType::Xchacha20Poly1305, KeyType::Ed25519]
+            .into_iter()
+            .find(|candidate| candidate.as_str() == key_type)
+    }
+
+    /// Fresh key material and, for signing keys, its public key.
+    pub fn generate(&self) -> (Zeroizing<Vec<u8>>, Option<Vec<u8>>) {
+        match self {
+            KeyType::Xchacha20Poly1305 => {
+                let mut key = Zeroizing::new(vec![0u8; sealing::KEY_LEN]);
+                rand::rng().fill_bytes(&mut key);
+                (key, None)
+            }
+            KeyType::Ed25519 => {
+                let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+                    .expect("generating an Ed25519 key cannot fail");
+                let public_key = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref())
+                    .expect("a generated Ed25519 key is valid")
+                    .public_key()
+                    .as_ref()
+                    .to_vec();
+                (Zeroizing::new(pkcs8.as_ref().to_vec()), Some(public_key))
+            }
+        }
+    }
+}
+
+impl Key {
+    /// Fails unless the key is of the type `operation` needs.
+    pub fn require(&self, key_type: KeyType, operation: &'static str) -> Result<(), ParamsError> {
+        match self.key_type == key_type {
+            true => Ok(()),
+            false => Err(ParamsError::Operation {
+                key_type: self.key_type.as_str(),
+                operation,
+            }),
+        }
+    }
+
+    /// Fails unless the version exists and is not below the minimum decryption version.
+    pub fn check_version(&self, version: i64) -> Result<(), ParamsError> {
+        if version < 1 || version > self.latest_version {
+            return Err(ParamsError::UnknownVersion(version));
+        }
+        if version < self.min_decryption_version {
+            return Err(ParamsError::DisabledVersion {
+                version,
+                min: self.min_decryption_version,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Key names are a single path segment, e.g. `user-pii`.
+pub fn validate_name(name: &str) -> Result<(), ParamsError> {
+    match !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_'))
+    {
+        true => Ok(()),
+        false => Err(ParamsError::Name(name.to_string())),
+    }
+}
+
+pub fn decode_base64(
+    field: &'static str,
+    encoded: &str,
+) -> Result<Zeroizing<Vec<u8>>, ParamsError> {
+    BASE64_STANDARD
+        .decode(encoded)
+        .map(Zeroizing::new)
+        .map_err(|_| ParamsError::Base64(field))
+}
+
+pub fn encode_versioned(version: i64, bytes: &[u8]) -> String {
+    format!("{PREFIX}{version}:{}", BASE64_STANDARD.encode(bytes))
+}
+
+/// Splits `vault:v<version>:<base64>` into the version and the decoded bytes.
+pub fn decode_versioned(field: &'static str, encoded: &str) -> Result<(i64, Vec<u8>), ParamsError> {
+    encoded
+        .strip_prefix(PREFIX)
+        .and_then(|rest| rest.split_once(':'))
+        .and_then(|(version, bytes)| {
+            let version = version.parse().ok()?;
+            let bytes = BASE64_STANDARD.decode(bytes).ok()?;
+            Some((version, bytes))
+        })
+        .ok_or(ParamsError::Versioned(field))
+}
+
+/// The optional context is authenticated along with the plaintext and needed again to decrypt.
+pub fn encrypt(material: &[u8], plaintext: &[u8], context: &[u8]) -> Vec<u8> {
+    sealing::encrypt(material, context, plaintext)
+}
+
+pub fn decrypt(
+    material: &[u8],
+    ciphertext: &[u8],
+    context: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, ParamsError> {
+    sealing::decrypt(material, context, ciphertext).map_err(|_| ParamsError::Decrypt)
+}
+
+pub fn sign(material: &[u8], input: &[u8]) -> Result<Vec<u8>, StoreError> {
+    let key = Ed25519KeyPair::from_pkcs8(material).map_err(|_| StoreError::Material)?;
+    Ok(key.sign(input).as_ref().to_vec())
+}
+
+pub fn verify(public_key: &[u8], input: &[u8], signature: &[u8]) -> bool {
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(input, signature)
+        .is_ok()
+}
+
+/// kid of the key version in [`Secrets`]
+fn kid(version: i64) -> String {
+    format!("v{version}")
+}
+
+pub async fn load_key<'a, E: Executor<'a, Database = Sqlite>>(
+    ex: E,
+    name: &str,
+) -> Result<Option<Key>, StoreError> {
+    let Some(row) = sqlx::query!(
+        r#"
+        SELECT
+            id as "id!",
+            key_type,
+            latest_version,
+            min_decryption_version,
+            created_at as "created_at: OffsetDateTime",
+            updated_at as "updated_at: OffsetDateTime"
+        FROM transit_keys
+        WHERE name = ?
+        "#,
+        name
+    )
+    .fetch_optional(ex)
+    .await
+    .context("select key")?
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(Key {
+        id: row.id,
+        name: name.to_string(),
+        key_type: KeyType::parse(&row.key_type).ok_or(StoreError::KeyType(row.key_type))?,
+        latest_version: row.latest_version,
+        min_decryption_version: row.min_decryption_version,
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+    }))
+}
+
+/// Unsealed material of a version checked with [`Key::check_version`].
+pub fn load_material(
+    secrets: &Secrets,
+    key: &Key,
+    version: i64,
+) -> Result<Zeroizing<Vec<u8>>, StoreError> {
+    secrets
+        .get_version(&key.name, Some(&kid(version)))
+        .context(format!("read key material :: {} v{version}", key.name))?
+        .ok_or(StoreError::Material)
+}
+
+/// Public key of a signing key's version checked with [`Key::check_version`].
+pub async fn load_public_key<'a, E: Executor<'a, Database = Sqlite>>(
+    ex: E,
+    key: &Key,
+    version: i64,
+) -> Result<Vec<u8>, StoreError> {
+    sqlx::query!(
+        "SELECT public_key FROM transit_key_versions WHERE key_id = ? AND version = ?",
+        key.id,
+        version
+    )
+    .fetch_one(ex)
+    .await
+    .context("select key version")?
+    .public_key
+    .ok_or(StoreError::Material)
+}
+
+/// Generates and stores the material of a new version.
+/// The material is written first; a version left behind by a rolled back transaction is replaced.
+pub async fn insert_version<'a, E: Executor<'a, Database = Sqlite>>(
+    ex: E,
+    secrets: &Secrets,
+    key_id: i64,
+    name: &str,
+    key_type: KeyType,
+    version: i64,
+    now: OffsetDateTime,
+) -> Result<(), StoreError> {
+    let (material, public_key) = key_type.generate();
+    secrets
+        .put_version(name, &kid(version), &material)
+        .context(format!("write key material :: {name} v{version}"))?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO transit_key_versions (key_id, version, public_key, created_at)
+        VALUES (?, ?, ?, ?)
+        "#,
+        key_id,
+        version,
+        public_key,
+        now
+    )
+    .execute(ex)
+    .await
+    .context("insert key version")?;
+
+    Ok(())
+}
+
+pub async fn key_metadata<'a, E: Executor<'a, Database = Sqlite>>(
+    ex: E,
+    key: Key,
+) -> Result<KeyMetadata, StoreError> {
+    let versions = sqlx::query!(
+        r#"
+        SELECT
+            version,
+            public_key,
+            created_at as "created_at: OffsetDateTime"
+        FROM transit_key_versions
+        WHERE key_id = ?
+        ORDER BY version
+        "#,
+        key.id
+    )
+    .fetch_all(ex)
+    .await
+    .context("select key versions")?
+    .into_iter()
+    .map(|row| VersionMetadata {
+        version: row.version,
+        public_key: row.public_key.map(|key| BASE64_STANDARD.encode(key)),
+        created_at: row.created_at,
+    })
+    .collect();
+
+    Ok(KeyMetadata {
+        name: key.name,
+        key_type: key.key_type,
+        latest_version: key.latest_version,
+        min_decryption_version: key.min_decryption_version,
+        created_at: key.created_at,
+        updated_at: key.updated_at,
+        versions,
+    })
+}
+
+impl extra::ErrorKind for StoreError {
+    fn kind(&self) -> &'static str {
+        match self {
+            StoreError::Sqlx(_) => "sqlx",
+            StoreError::Secrets(_) => "transit.store.secrets",
+            StoreError::KeyType(_) => "transit.store.key-type",
+            StoreError::Material => "transit.store.material",
+        }
+    }
+}
+
+impl IntoResponse for StoreError {
+    fn into_response(self) -> Response {
+        #[cfg(feature = "tracing")]
+        tracing::error!("{:?}", self);
+
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    }
+}
+
+impl extra::ErrorKind for ParamsError {
+    fn kind(&self) -> &'static str {
+        match self {
+            ParamsError::Name(_) => "transit.name.invalid",
+            ParamsError::Base64(_) => "transit.base64.invalid",
+            ParamsError::Versioned(_) => "transit.versioned.invalid",
+            ParamsError::Operation { .. } => "transit.key.unsupported-operation",
+            ParamsError::UnknownVersion(_) => "transit.version.unknown",
+            ParamsError::DisabledVersion { .. } => "transit.version.disabled",
+            ParamsError::Decrypt => "transit.decrypt.failed",
+        }
+    }
+}
+
+impl IntoResponse for ParamsError {
+    fn into_response(self) -> Response {
+        #[cfg(feature = "tracing")]
+        tracing::info!("{:?}", self);
+
+        (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(self))).into_response()
+    }
+}
@@ -0,0 +1,216 @@
+mod shared;
+
+use serde_json::{Value, json};
+use shared::TestClient;
+
+#[tokio::test]
+async fn versions_can_be_written_read_deleted_and_undeleted() {
+    let mut client = TestClient::default().await;
+    client.grant(
+        "ops",
+        &[
+            "get:/kv/data/*",
+            "put:/kv/data/app/*",
+            "delete:/kv/data/app/db",
+            "post:/kv/undelete/app/*",
+            "get:/kv/metadata/app/db",
+        ],
+    );
+
+    let write = |data: Value, cas: Option<i64>| {
+        request!(
+            PUT "/kv/data/app/db";
+            "authorization" => "Bearer ops"
+            "content-type" => "application/json";
+            json!({ "data": data, "cas": cas }).to_string()
+        )
+    };
+
+    for (password, version) in [("hunter2", 1), ("correct horse", 2)] {
+        let metadata = client
+            .send(write(json!({ "password": password }), None))
+            .await
+            .status(200)
+            .into_deserialized_json_body::<Value>()
+            .await;
+        assert_eq!(metadata["version"], version);
+    }
+
+    // check-and-set against a stale version
+    client
+        .send(write(json!({ "password": "stale" }), Some(1)))
+        .await
+        .status(409);
+
+    let read = |version: Option<i64>| {
+        request!(
+            GET match version {
+                Some(version) => format!("/kv/data/app/db?version={version}"),
+                None => "/kv/data/app/db".to_string(),
+            };
+            "authorization" => "Bearer ops";
+        )
+    };
+
+    let secret = client
+        .send(read(None))
+        .await
+        .status(200)
+        .into_deserialized_json_body::<Value>()
+        .await;
+    assert_eq!(secret["data"]["password"], "correct horse");
+    assert_eq!(secret["metadata"]["version"], 2);
+
+    let secret = client
+        .send(read(Some(1)))
+        .await
+        .status(200)
+        .into_deserialized_json_body::<Value>()
+        .await;
+    assert_eq!(secret["data"]["password"], "hunter2");
+
+    client
+        .send(request!(
+            DELETE "/kv/data/app/db";
+            "authorization" => "Bearer ops";
+        ))
+        .await
+        .status(200);
+    client.send(read(None)).await.status(404);
+
+    let metadata = client
+        .send(request!(
+            GET "/kv/metadata/app/db";
+            "authorization" => "Bearer ops";
+        ))
+        .await
+        .status(200)
+        .into_deserialized_json_body::<Value>()
+        .await;
+    assert_eq!(metadata["current_version"], 2);
+    assert!(metadata["versions"][0]["deleted_at"].is_null());
+    assert!(metadata["versions"][1]["deleted_at"].is_string());
+
+    client
+        .send(request!(
+            POST "/kv/undelete/app/db";
+            "authorization" => "Bearer ops"
+            "content-type" => "application/json";
+            json!({ "versions": [2] }).to_string()
+        ))
+        .await
+        .status(200);
+    client.send(read(None)).await.status(200);
+}
+
+#[tokio::test]
+async fn permissions_are_checked_per_path() {
+    let mut client = TestClient::default().await;
+    client.grant("reader", &["get:/kv/data/team-a/*"]);
+
+    client
+        .send(request!(
+            GET "/kv/data/team-a/api-key";
+            ;
+        ))
+        .await
+        .status(401);
+
+    client
+        .send(request!(
+            GET "/kv/data/team-a/api-key";
+            "authorization" => "Bearer unknown";
+        ))
+        .await
+        .status(401);
+
+    // allowed to read, but the secret does not exist yet
+    client
+        .send(request!(
+            GET "/kv/data/team-a/api-key";
+            "authorization" => "Bearer reader";
+        ))
+        .await
+        .status(404);
+
+    client
+        .send(request!(
+            GET "/kv/data/team-b/api-key";
+            "authorization" => "Bearer reader";
+        ))
+        .await
+        .status(403);
+
+    client
+        .send(request!(
+            PUT "/kv/data/team-a/api-key";
+            "authorization" => "Bearer reader"
+            "content-type" => "application/json";
+            json!({ "data": { "key": "value" } }).to_string()
+        ))
+        .await
+        .status(403);
+
+    client
+        .send(request!(
+            GET "/kv/data/team-a/../team-b/api-key";
+            "authorization" => "Bearer reader";
+        ))
+        .await
+        .status(400);
+}
+
+#[tokio::test]
+async fn old_versions_are_pruned_and_data_is_encrypted_at_rest() {
+    let mut client = TestClient::default().await;
+    client.grant("ops", &["put:/kv/*", "get:/kv/*"]);
+
+    client
+        .send(request!(
+            PUT "/kv/metadata/app/token";
+            "authorization" => "Bearer ops"
+            "content-type" => "application/json";
+            json!({ "max_versions": 2, "custom_metadata": { "owner": "team-a" } }).to_string()
+        ))
+        .await
+        .status(200);
+
+    for n in 1..=3 {
+        client
+            .send(request!(
+                PUT "/kv/data/app/token";
+                "authorization" => "Bearer ops"
+                "content-type" => "application/json";
+                json!({ "data": { "token": format!("plaintext-token-{n}") } }).to_string()
+            ))
+            .await
+            .status(200);
+    }
+
+    let metadata = client
+        .send(request!(
+            GET "/kv/metadata/app/token";
+            "authorization" => "Bearer ops";
+        ))
+        .await
+        .status(200)
+        .into_deserialized_json_body::<Value>()
+        .await;
+    assert_eq!(metadata["custom_metadata"]["owner"], "team-a");
+    let versions = metadata["versions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|version| version["version"].as_i64().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(versions, [2, 3]);
+
+    let ciphertexts = sqlx::query_scalar::<_, Vec<u8>>("SELECT ciphertext FROM kv_versions")
+        .fetch_all(client.pool())
+        .await
+        .unwrap();
+    assert_eq!(ciphertexts.len(), 2);
+    for ciphertext in ciphertexts {
+        assert!(!ciphertext.windows(9).any(|window| window == b"plaintext"));
+    }
+}
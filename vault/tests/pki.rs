@@ -0,0 +1,480 @@
+mod shared;
+
+use axum::body::to_bytes;
+use rcgen::{CertificateParams, DistinguishedName, DnType, KeyPair};
+use ring::{
+    digest::{SHA1_FOR_LEGACY_USE_ONLY, digest},
+    signature::{ECDSA_P256_SHA256_ASN1, UnparsedPublicKey},
+};
+use serde_json::{Value, json};
+use shared::{Asserter, TestClient};
+use x509_parser::prelude::*;
+use yasna::{Tag, models::ObjectIdentifier};
+
+const DAY: i64 = 24 * 60 * 60;
+
+fn csr(common_name: &str, alt_names: &[&str]) -> String {
+    let key = KeyPair::generate().unwrap();
+    let mut params = CertificateParams::new(
+        alt_names
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>(),
+    )
+    .unwrap();
+    params.distinguished_name = DistinguishedName::new();
+    params
+        .distinguished_name
+        .push(DnType::CommonName, common_name);
+    params.serialize_request(&key).unwrap().pem().unwrap()
+}
+
+async fn body(asserter: Asserter) -> Vec<u8> {
+    to_bytes(asserter.into_response().into_body(), usize::MAX)
+        .await
+        .unwrap()
+        .to_vec()
+}
+
+/// Creates the `root` CA and a role `internal` on `issuer` for `svc.internal` and its subdomains.
+async fn setup(client: &mut TestClient, intermediate: bool) -> Value {
+    let root = client
+        .send(request!(
+            POST "/pki/root";
+            "authorization" => "Bearer ops"
+            "content-type" => "application/json";
+            json!({ "name": "root", "common_name": "Test Root CA", "ttl_sec": 3650 * DAY }).to_string()
+        ))
+        .await
+        .status(201)
+        .into_deserialized_json_body::<Value>()
+        .await;
+
+    let issuer = match intermediate {
+        true => {
+            client
+                .send(request!(
+                    POST "/pki/intermediate";
+                    "authorization" => "Bearer ops"
+                    "content-type" => "application/json";
+                    json!({
+                        "name": "services",
+                        "parent": "root",
+                        "common_name": "Test Services CA",
+                        "ttl_sec": 365 * DAY,
+                    })
+                    .to_string()
+                ))
+                .await
+                .status(201);
+            "services"
+        }
+        false => "root",
+    };
+
+    client
+        .send(request!(
+            PUT "/pki/roles/internal";
+            "authorization" => "Bearer ops"
+            "content-type" => "application/json";
+            json!({
+                "issuer": issuer,
+                "allowed_domains": ["svc.internal"],
+                "allow_subdomains": true,
+                "max_ttl_sec": DAY,
+            })
+            .to_string()
+        ))
+        .await
+        .status(200);
+
+    root
+}
+
+async fn sign(client: &mut TestClient, csr: String) -> Asserter {
+    client
+        .send(request!(
+            POST "/pki/sign/internal";
+            "authorization" => "Bearer ops"
+            "content-type" => "application/json";
+            json!({ "csr": csr }).to_string()
+        ))
+        .await
+}
+
+#[tokio::test]
+async fn csrs_are_signed_within_the_role_constraints() {
+    let mut client = TestClient::default().await;
+    client.grant("ops", &["post:/pki/*", "put:/pki/*", "get:/pki/*"]);
+    client.grant("reader", &["get:/pki/*"]);
+    setup(&mut client, true).await;
+
+    let issued = sign(
+        &mut client,
+        csr("api.svc.internal", &["api-internal.svc.internal"]),
+    )
+    .await
+    .status(200)
+    .into_deserialized_json_body::<Value>()
+    .await;
+    assert_eq!(issued["ca_chain"].as_array().unwrap().len(), 2);
+    assert_eq!(issued["issuing_ca"], issued["ca_chain"][0]);
+
+    let (_, certificate) =
+        parse_x509_pem(issued["certificate"].as_str().unwrap().as_bytes()).unwrap();
+    let certificate = certificate.parse_x509().unwrap();
+    let (_, issuing_ca) =
+        parse_x509_pem(issued["issuing_ca"].as_str().unwrap().as_bytes()).unwrap();
+    let issuing_ca = issuing_ca.parse_x509().unwrap();
+    let (_, root) = parse_x509_pem(issued["ca_chain"][1].as_str().unwrap().as_bytes()).unwrap();
+    let root = root.parse_x509().unwrap();
+
+    // the chain verifies up to the root
+    certificate
+        .verify_signature(Some(issuing_ca.public_key()))
+        .unwrap();
+    issuing_ca
+        .verify_signature(Some(root.public_key()))
+        .unwrap();
+    root.verify_signature(None).unwrap();
+    assert!(issuing_ca.is_ca());
+    assert!(!certificate.is_ca());
+    assert_eq!(certificate.issuer(), issuing_ca.subject());
+    assert_eq!(
+        certificate.raw_serial_as_string().replace(':', ""),
+        issued["serial"]
+    );
+
+    // the common name is added to the subject alternative names
+    let names = certificate
+        .subject_alternative_name()
+        .unwrap()
+        .unwrap()
+        .value
+        .general_names
+        .iter()
+        .map(|name| match name {
+            GeneralName::DNSName(name) => name.to_string(),
+            other => panic!("unexpected name {other:?}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["api-internal.svc.internal", "api.svc.internal"]);
+
+    let usage = certificate.extended_key_usage().unwrap().unwrap().value;
+    assert!(usage.server_auth && usage.client_auth);
+    let validity = certificate.validity().not_after - certificate.validity().not_before;
+    assert!(validity.unwrap() <= ::time::Duration::seconds(DAY + 30));
+
+    // issued serials are recorded
+    let recorded = client
+        .send(request!(
+            GET format!("/pki/certs/{}", issued["serial"].as_str().unwrap());
+            "authorization" => "Bearer reader";
+        ))
+        .await
+        .status(200)
+        .into_deserialized_json_body::<Value>()
+        .await;
+    assert_eq!(recorded["issuer"], "services");
+    assert_eq!(recorded["role"], "internal");
+    assert_eq!(recorded["certificate"], issued["certificate"]);
+    assert!(recorded["revoked_at"].is_null());
+
+    // names outside of the role
+    sign(&mut client, csr("api.example.com", &[]))
+        .await
+        .status(403);
+    sign(
+        &mut client,
+        csr("api.svc.internal", &["svc.internal.example.com"]),
+    )
+    .await
+    .status(403);
+    sign(&mut client, csr("api.svc.internal", &["10.0.0.1"]))
+        .await
+        .status(403);
+
+    // longer than the role allows
+    client
+        .send(request!(
+            POST "/pki/sign/internal";
+            "authorization" => "Bearer ops"
+            "content-type" => "application/json";
+            json!({ "csr": csr("api.svc.internal", &[]), "ttl_sec": 2 * DAY }).to_string()
+        ))
+        .await
+        .status(400);
+
+    client
+        .send(request!(
+            POST "/pki/sign/internal";
+            "authorization" => "Bearer ops"
+            "content-type" => "application/json";
+            json!({ "csr": "not a csr" }).to_string()
+        ))
+        .await
+        .status(400);
+
+    // intermediates only issue service certificates
+    client
+        .send(request!(
+            POST "/pki/intermediate";
+            "authorization" => "Bearer ops"
+            "content-type" => "application/json";
+            json!({
+                "name": "nested",
+                "parent": "services",
+                "common_name": "Nested CA",
+                "ttl_sec": DAY,
+            })
+            .to_string()
+        ))
+        .await
+        .status(400);
+
+    client
+        .send(request!(
+            POST "/pki/sign/internal";
+            "authorization" => "Bearer reader"
+            "content-type" => "application/json";
+            json!({ "csr": csr("api.svc.internal", &[]) }).to_string()
+        ))
+        .await
+        .status(403);
+
+    client
+        .send(request!(
+            POST "/pki/sign/internal";
+            "content-type" => "application/json";
+            json!({ "csr": csr("api.svc.internal", &[]) }).to_string()
+        ))
+        .await
+        .status(401);
+}
+
+#[tokio::test]
+async fn revocations_are_published_through_crl_and_ocsp() {
+    let mut client = TestClient::default().await;
+    client.grant("ops", &["post:/pki/*", "put:/pki/*"]);
+    let created = setup(&mut client, false).await;
+
+    let (_, root) = parse_x509_pem(created["certificate"].as_str().unwrap().as_bytes()).unwrap();
+    let root = root.parse_x509().unwrap();
+
+    let mut serials = Vec::new();
+    for name in ["a.svc.internal", "b.svc.internal"] {
+        let issued = sign(&mut client, csr(name, &[]))
+            .await
+            .status(200)
+            .into_deserialized_json_body::<Value>()
+            .await;
+        serials.push(issued["serial"].as_str().unwrap().to_string());
+    }
+
+    for _ in 0..2 {
+        client
+            .send(request!(
+                POST "/pki/revoke";
+                "authorization" => "Bearer ops"
+                "content-type" => "application/json";
+                json!({ "serial": serials[0], "reason": "key_compromise" }).to_string()
+            ))
+            .await
+            .status(200);
+    }
+
+    client
+        .send(request!(
+            POST "/pki/revoke";
+            "authorization" => "Bearer ops"
+            "content-type" => "application/json";
+            json!({ "serial": "00ff" }).to_string()
+        ))
+        .await
+        .status(404);
+
+    // the CRL is public
+    let crl = body(
+        client
+            .send(request!(GET "/pki/crl/root";;))
+            .await
+            .status(200),
+    )
+    .await;
+    let (_, crl) = parse_x509_crl(&crl).unwrap();
+    crl.verify_signature(root.public_key()).unwrap();
+    assert_eq!(crl.issuer(), root.subject());
+    assert_eq!(crl.crl_number().unwrap().to_string(), "2");
+    let revoked = crl.iter_revoked_certificates().collect::<Vec<_>>();
+    assert_eq!(revoked.len(), 1);
+    assert_eq!(
+        revoked[0].raw_serial_as_string().replace(':', ""),
+        serials[0]
+    );
+    assert_eq!(
+        revoked[0].reason_code().unwrap().1,
+        ReasonCode::KeyCompromise
+    );
+
+    let ca = body(
+        client
+            .send(request!(GET "/pki/ca/root";;))
+            .await
+            .status(200),
+    )
+    .await;
+    assert_eq!(String::from_utf8(ca).unwrap(), created["certificate"]);
+
+    // so is OCSP
+    for (serial, expected) in [
+        (serials[0].as_str(), 1 /* revoked */),
+        (serials[1].as_str(), 0 /* good */),
+        ("4f", 2 /* unknown */),
+    ] {
+        let response = body(
+            client
+                .send(request!(
+                    POST "/pki/ocsp/root";
+                    "content-type" => "application/ocsp-request";
+                    ocsp_request(&root, serial)
+                ))
+                .await
+                .status(200),
+        )
+        .await;
+        assert_eq!(ocsp_cert_status(&response, &root), Ok(expected));
+    }
+
+    let response = body(
+        client
+            .send(request!(
+                POST "/pki/ocsp/root";
+                "content-type" => "application/ocsp-request";
+                b"garbage".to_vec()
+            ))
+            .await
+            .status(200),
+    )
+    .await;
+    assert_eq!(
+        ocsp_cert_status(&response, &root),
+        Err(1 /* malformedRequest */)
+    );
+}
+
+fn ocsp_request(issuer: &X509Certificate, serial: &str) -> Vec<u8> {
+    let serial = (0..serial.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&serial[i..i + 2], 16).unwrap())
+        .collect::<Vec<_>>();
+
+    yasna::construct_der(|writer| {
+        writer.write_sequence(|writer| {
+            writer.next().write_sequence(|writer| {
+                writer.next().write_sequence_of(|writer| {
+                    writer.next().write_sequence(|writer| {
+                        writer.next().write_sequence(|writer| {
+                            writer.next().write_sequence(|writer| {
+                                writer.next().write_oid(&ObjectIdentifier::from_slice(&[
+                                    1, 3, 14, 3, 2, 26,
+                                ]));
+                                writer.next().write_null();
+                            });
+                            writer.next().write_bytes(
+                                digest(&SHA1_FOR_LEGACY_USE_ONLY, issuer.subject().as_raw())
+                                    .as_ref(),
+                            );
+                            writer.next().write_bytes(
+                                digest(
+                                    &SHA1_FOR_LEGACY_USE_ONLY,
+                                    &issuer.public_key().subject_public_key.data,
+                                )
+                                .as_ref(),
+                            );
+                            writer.next().write_bigint_bytes(&serial, true);
+                        });
+                    });
+                });
+                // nonce extension
+                writer.next().write_tagged(Tag::context(2), |writer| {
+                    writer.write_sequence(|writer| {
+                        writer.next().write_sequence(|writer| {
+                            writer.next().write_oid(&ObjectIdentifier::from_slice(&[
+                                1, 3, 6, 1, 5, 5, 7, 48, 1, 2,
+                            ]));
+                            writer
+                                .next()
+                                .write_bytes(&yasna::construct_der(|w| w.write_bytes(b"nonce")));
+                        });
+                    });
+                });
+            });
+        })
+    })
+}
+
+/// The certificate status (`0` good, `1` revoked, `2` unknown) of a successful response, after
+/// checking its signature, or the status of an unsuccessful response.
+fn ocsp_cert_status(response: &[u8], issuer: &X509Certificate) -> Result<u64, i64> {
+    let (status, basic) = yasna::parse_der(response, |reader| {
+        reader.read_sequence(|reader| {
+            let status = reader.next().read_enum()?;
+            let basic = reader.read_optional(|reader| {
+                reader.read_tagged(Tag::context(0), |reader| {
+                    reader.read_sequence(|reader| {
+                        reader.next().read_oid()?;
+                        reader.next().read_bytes()
+                    })
+                })
+            })?;
+            Ok((status, basic))
+        })
+    })
+    .unwrap();
+
+    let Some(basic) = basic else {
+        return Err(status);
+    };
+
+    let (response_data, signature) = yasna::parse_der(&basic, |reader| {
+        reader.read_sequence(|reader| {
+            let response_data = reader.next().read_der()?;
+            reader.next().read_der()?;
+            let (signature, _) = reader.next().read_bitvec_bytes()?;
+            Ok((response_data, signature))
+        })
+    })
+    .unwrap();
+
+    UnparsedPublicKey::new(
+        &ECDSA_P256_SHA256_ASN1,
+        &issuer.public_key().subject_public_key.data,
+    )
+    .verify(&response_data, &signature)
+    .expect("OCSP response signature does not verify");
+
+    let (cert_status, nonce) = yasna::parse_der(&response_data, |reader| {
+        reader.read_sequence(|reader| {
+            reader.next().read_der()?;
+            reader.next().read_generalized_time()?;
+            let cert_status = reader.next().read_sequence(|reader| {
+                reader.next().read_sequence(|reader| {
+                    reader.next().read_der()?;
+                    let cert_status = reader.next().read_tagged_der()?;
+                    reader.next().read_generalized_time()?;
+                    reader.read_optional(|reader| {
+                        reader.read_tagged(Tag::context(0), |r| r.read_generalized_time())
+                    })?;
+                    Ok(cert_status.tag().tag_number)
+                })
+            })?;
+            let nonce = reader
+                .read_optional(|reader| reader.read_tagged(Tag::context(1), |r| r.read_der()))?;
+            Ok((cert_status, nonce))
+        })
+    })
+    .unwrap();
+
+    assert!(nonce.is_some_and(|nonce| nonce.windows(5).any(|window| window == b"nonce")));
+    Ok(cert_status)
+}
@@ -0,0 +1,193 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    Json, Router,
+    body::{Body, to_bytes},
+    extract::State,
+    http::HeaderMap,
+    routing::post,
+};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use http::{Request, Response, StatusCode};
+use sqlx::{Pool, Sqlite, sqlite::SqliteConnectOptions};
+use tempfile::{TempDir, tempdir};
+use tower::Service;
+
+pub mod macros;
+
+/// Permissions held by every bearer token the fake auth service knows.
+type Grants = Arc<Mutex<HashMap<String, Vec<String>>>>;
+
+pub struct TestClient {
+    router: Router,
+    #[allow(dead_code)]
+    pool: Pool<Sqlite>,
+
+    grants: Grants,
+
+    // hold TempDir because the temporary directory will be deleted on Drop
+    _temp_dir: TempDir,
+}
+
+impl TestClient {
+    pub async fn default() -> Self {
+        let temp_dir = tempdir().expect("unable to create temp dir");
+
+        let database_config = vault::DatabaseConfig {
+            url: {
+                let path = temp_dir.path().join("test.db");
+                path.to_string_lossy().to_string()
+            },
+        };
+        let pool = Self::prepare_database(&database_config).await;
+
+        let grants = Grants::default();
+        let introspect_url = Self::spawn_auth(grants.clone()).await;
+
+        let router = vault::router(vault::ServerOpts {
+            database: database_config,
+            master_key: vault::MasterKey::from_base64(&BASE64_STANDARD.encode([7u8; 32]))
+                .expect("invalid master key"),
+            secrets_dir: temp_dir.path().join("secrets"),
+            auth: vault::AuthConfig {
+                introspect_url,
+                timeout: std::time::Duration::from_secs(5),
+            },
+        })
+        .await
+        .expect("unable to create router");
+
+        Self {
+            router,
+            pool,
+            grants,
+            _temp_dir: temp_dir,
+        }
+    }
+
+    pub async fn send(&mut self, request: Request<Body>) -> Asserter {
+        let response = self.router
+            .call(request)
+            .await
+            .unwrap(/* Infallible */);
+        Asserter::from(response)
+    }
+
+    #[allow(dead_code)]
+    pub fn pool(&self) -> &Pool<Sqlite> {
+        &self.pool
+    }
+
+    #[allow(dead_code)]
+    pub fn secrets_dir(&self) -> std::path::PathBuf {
+        self._temp_dir.path().join("secrets")
+    }
+
+    /// Lets the bearer `token` through the fake auth service with the given permissions.
+    pub fn grant(&self, token: &str, permissions: &[&str]) {
+        self.grants.lock().unwrap().insert(
+            token.to_string(),
+            permissions.iter().map(|p| p.to_string()).collect(),
+        );
+    }
+
+    /// Stands in for auth's `/introspect` and returns its URL.
+    async fn spawn_auth(grants: Grants) -> String {
+        #[derive(serde::Deserialize)]
+        struct RequestBody {
+            permissions: Vec<String>,
+        }
+
+        async fn introspect(
+            State(grants): State<Grants>,
+            headers: HeaderMap,
+            Json(request_body): Json<RequestBody>,
+        ) -> Result<Json<serde_json::Value>, StatusCode> {
+            let token = headers
+                .get("authorization")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .ok_or(StatusCode::UNAUTHORIZED)?;
+            let grants = grants.lock().unwrap();
+            let held = grants.get(token).ok_or(StatusCode::UNAUTHORIZED)?;
+
+            let permissions = request_body
+                .permissions
+                .into_iter()
+                .map(|permission| {
+                    let granted = held.contains(&permission);
+                    (permission, granted)
+                })
+                .collect::<BTreeMap<_, _>>();
+
+            Ok(Json(serde_json::json!({ "permissions": permissions })))
+        }
+
+        let router = Router::new()
+            .route("/introspect", post(introspect))
+            .with_state(grants);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        format!("http://{addr}/introspect")
+    }
+
+    async fn prepare_database(config: &vault::DatabaseConfig) -> Pool<Sqlite> {
+        let pool = Pool::<Sqlite>::connect_with(
+            SqliteConnectOptions::new()
+                .filename(&config.url)
+                .create_if_missing(true),
+        )
+        .await
+        .expect("unable to connect to test db");
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await
+            .expect("unable to run migrations");
+        pool
+    }
+}
+
+pub struct Asserter {
+    response: Response<Body>,
+}
+
+impl Asserter {
+    #[allow(dead_code)]
+    pub fn into_response(self) -> Response<Body> {
+        self.response
+    }
+
+    pub fn status(self, expected: u16) -> Self {
+        assert_eq!(
+            self.response.status().as_u16(),
+            expected,
+            "expected status {}, got {}",
+            expected,
+            self.response.status()
+        );
+        self
+    }
+
+    pub async fn into_deserialized_json_body<T>(self) -> T
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let body_bytes = to_bytes(self.response.into_body(), usize::MAX)
+            .await
+            .expect("unable to read response body");
+
+        serde_json::from_slice::<T>(&body_bytes).expect("unable to deserialize response body")
+    }
+}
+
+impl From<Response<Body>> for Asserter {
+    fn from(response: Response<Body>) -> Self {
+        Self { response }
+    }
+}
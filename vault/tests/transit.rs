@@ -0,0 +1,277 @@
+mod shared;
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use ring::signature::{ED25519, UnparsedPublicKey};
+use serde_json::{Value, json};
+use shared::TestClient;
+
+fn b64(data: &[u8]) -> String {
+    BASE64_STANDARD.encode(data)
+}
+
+#[tokio::test]
+async fn data_is_encrypted_with_the_latest_version_and_rewrapped() {
+    let mut client = TestClient::default().await;
+    client.grant(
+        "admin",
+        &["post:/transit/*", "put:/transit/*", "get:/transit/*"],
+    );
+    client.grant(
+        "app",
+        &["post:/transit/encrypt/pii", "post:/transit/decrypt/pii"],
+    );
+
+    client
+        .send(request!(
+            POST "/transit/keys/pii";
+            "authorization" => "Bearer admin"
+            "content-type" => "application/json";
+            json!({}).to_string()
+        ))
+        .await
+        .status(201);
+    client
+        .send(request!(
+            POST "/transit/keys/pii";
+            "authorization" => "Bearer admin"
+            "content-type" => "application/json";
+            json!({}).to_string()
+        ))
+        .await
+        .status(409);
+
+    let encrypt = |plaintext: &[u8], context: &[u8]| {
+        request!(
+            POST "/transit/encrypt/pii";
+            "authorization" => "Bearer app"
+            "content-type" => "application/json";
+            json!({ "plaintext": b64(plaintext), "context": b64(context) }).to_string()
+        )
+    };
+    let decrypt = |ciphertext: &Value, context: &[u8]| {
+        request!(
+            POST "/transit/decrypt/pii";
+            "authorization" => "Bearer app"
+            "content-type" => "application/json";
+            json!({ "ciphertext": ciphertext, "context": b64(context) }).to_string()
+        )
+    };
+
+    let v1 = client
+        .send(encrypt(b"jane@example.com", b"user:1"))
+        .await
+        .status(200)
+        .into_deserialized_json_body::<Value>()
+        .await;
+    assert_eq!(v1["key_version"], 1);
+    let ciphertext = v1["ciphertext"].as_str().unwrap();
+    assert!(ciphertext.starts_with("vault:v1:"));
+    assert!(!ciphertext.contains(&b64(b"jane@example.com")));
+
+    let decrypted = client
+        .send(decrypt(&v1["ciphertext"], b"user:1"))
+        .await
+        .status(200)
+        .into_deserialized_json_body::<Value>()
+        .await;
+    assert_eq!(decrypted["plaintext"], b64(b"jane@example.com"));
+
+    // the context is bound to the ciphertext
+    client
+        .send(decrypt(&v1["ciphertext"], b"user:2"))
+        .await
+        .status(400);
+
+    let metadata = client
+        .send(request!(
+            POST "/transit/keys/pii/rotate";
+            "authorization" => "Bearer admin";
+        ))
+        .await
+        .status(200)
+        .into_deserialized_json_body::<Value>()
+        .await;
+    assert_eq!(metadata["latest_version"], 2);
+    assert_eq!(metadata["versions"].as_array().unwrap().len(), 2);
+
+    // the material is kept in the secrets directory, sealed under the master key
+    for version in ["v1", "v2"] {
+        let material = std::fs::read(client.secrets_dir().join("pii").join(version)).unwrap();
+        assert!(material.starts_with(b"auth-sealed-v1\n"));
+    }
+
+    let v2 = client
+        .send(encrypt(b"jane@example.com", b"user:1"))
+        .await
+        .status(200)
+        .into_deserialized_json_body::<Value>()
+        .await;
+    assert_eq!(v2["key_version"], 2);
+
+    // old versions keep decrypting until they are rewrapped
+    client
+        .send(decrypt(&v1["ciphertext"], b"user:1"))
+        .await
+        .status(200);
+
+    let rewrapped = client
+        .send(request!(
+            POST "/transit/rewrap/pii";
+            "authorization" => "Bearer admin"
+            "content-type" => "application/json";
+            json!({ "ciphertext": v1["ciphertext"], "context": b64(b"user:1") }).to_string()
+        ))
+        .await
+        .status(200)
+        .into_deserialized_json_body::<Value>()
+        .await;
+    assert_eq!(rewrapped["key_version"], 2);
+
+    let metadata = client
+        .send(request!(
+            PUT "/transit/keys/pii";
+            "authorization" => "Bearer admin"
+            "content-type" => "application/json";
+            json!({ "min_decryption_version": 2 }).to_string()
+        ))
+        .await
+        .status(200)
+        .into_deserialized_json_body::<Value>()
+        .await;
+    assert_eq!(metadata["min_decryption_version"], 2);
+
+    client
+        .send(decrypt(&v1["ciphertext"], b"user:1"))
+        .await
+        .status(400);
+    let decrypted = client
+        .send(decrypt(&rewrapped["ciphertext"], b"user:1"))
+        .await
+        .status(200)
+        .into_deserialized_json_body::<Value>()
+        .await;
+    assert_eq!(decrypted["plaintext"], b64(b"jane@example.com"));
+
+    client
+        .send(request!(
+            PUT "/transit/keys/pii";
+            "authorization" => "Bearer admin"
+            "content-type" => "application/json";
+            json!({ "min_decryption_version": 3 }).to_string()
+        ))
+        .await
+        .status(400);
+
+    client
+        .send(decrypt(&json!("vault:v9:AAAA"), b"user:1"))
+        .await
+        .status(400);
+    client
+        .send(decrypt(&json!("not a ciphertext"), b"user:1"))
+        .await
+        .status(400);
+
+    // the app may use the key but not manage or read it
+    client
+        .send(request!(
+            POST "/transit/rewrap/pii";
+            "authorization" => "Bearer app"
+            "content-type" => "application/json";
+            json!({ "ciphertext": v2["ciphertext"] }).to_string()
+        ))
+        .await
+        .status(403);
+    client
+        .send(request!(
+            GET "/transit/keys/pii";
+            "authorization" => "Bearer app";
+        ))
+        .await
+        .status(403);
+
+    client
+        .send(request!(
+            POST "/transit/encrypt/missing";
+            "authorization" => "Bearer admin"
+            "content-type" => "application/json";
+            json!({ "plaintext": b64(b"data") }).to_string()
+        ))
+        .await
+        .status(404);
+}
+
+#[tokio::test]
+async fn signatures_verify_with_the_published_public_key() {
+    let mut client = TestClient::default().await;
+    client.grant("admin", &["post:/transit/*", "get:/transit/*"]);
+
+    let metadata = client
+        .send(request!(
+            POST "/transit/keys/release";
+            "authorization" => "Bearer admin"
+            "content-type" => "application/json";
+            json!({ "type": "ed25519" }).to_string()
+        ))
+        .await
+        .status(201)
+        .into_deserialized_json_body::<Value>()
+        .await;
+    assert_eq!(metadata["type"], "ed25519");
+    let public_key = BASE64_STANDARD
+        .decode(metadata["versions"][0]["public_key"].as_str().unwrap())
+        .unwrap();
+
+    let signed = client
+        .send(request!(
+            POST "/transit/sign/release";
+            "authorization" => "Bearer admin"
+            "content-type" => "application/json";
+            json!({ "input": b64(b"artifact digest") }).to_string()
+        ))
+        .await
+        .status(200)
+        .into_deserialized_json_body::<Value>()
+        .await;
+
+    let signature = signed["signature"].as_str().unwrap();
+    let raw = BASE64_STANDARD
+        .decode(signature.strip_prefix("vault:v1:").unwrap())
+        .unwrap();
+    UnparsedPublicKey::new(&ED25519, &public_key)
+        .verify(b"artifact digest", &raw)
+        .unwrap();
+
+    let verify = |input: &[u8]| {
+        request!(
+            POST "/transit/verify/release";
+            "authorization" => "Bearer admin"
+            "content-type" => "application/json";
+            json!({ "input": b64(input), "signature": signature }).to_string()
+        )
+    };
+    let verified = client
+        .send(verify(b"artifact digest"))
+        .await
+        .status(200)
+        .into_deserialized_json_body::<Value>()
+        .await;
+    assert_eq!(verified["valid"], true);
+    let verified = client
+        .send(verify(b"tampered digest"))
+        .await
+        .status(200)
+        .into_deserialized_json_body::<Value>()
+        .await;
+    assert_eq!(verified["valid"], false);
+
+    // signing keys do not encrypt
+    client
+        .send(request!(
+            POST "/transit/encrypt/release";
+            "authorization" => "Bearer admin"
+            "content-type" => "application/json";
+            json!({ "plaintext": b64(b"data") }).to_string()
+        ))
+        .await
+        .status(400);
+}